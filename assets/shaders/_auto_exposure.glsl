@@ -0,0 +1,48 @@
+// Shared between auto exposure compute passes and tonemapping.
+// Change `AutoExposurePass` consts in rust too.
+
+const uint AUTO_EXPOSURE_HISTOGRAM_BINS = 256;
+const uint AUTO_EXPOSURE_THREAD_GROUP_SIZE = 16; // 16x16 for histogram, 256 for average
+/** Luminance of 'middle gray' */
+const float AUTO_EXPOSURE_KEY_VALUE = 0.18;
+/** Darker pixels go to bin 0, which is ignored */
+const float AUTO_EXPOSURE_MIN_LUMINANCE = 0.0001;
+
+#ifdef BINDING_INDEX_HISTOGRAM
+layout(std430, binding=BINDING_INDEX_HISTOGRAM)
+buffer AutoExposureHistogramBuffer {
+  uint u_histogram[AUTO_EXPOSURE_HISTOGRAM_BINS];
+};
+#endif
+
+#ifdef BINDING_INDEX_EXPOSURE
+layout(std430, binding=BINDING_INDEX_EXPOSURE)
+buffer AutoExposureBuffer {
+  /** Final multiplier for HDR color, includes exposure compensation */
+  float u_autoExposureValue;
+  /** Temporally smoothed */
+  float u_adaptedLuminance;
+  /** Average luminance of the current frame */
+  float u_targetLuminance;
+  uint u_histogramPixelCount;
+  /** Copy of the last histogram, debug only */
+  uint u_histogramDebug[AUTO_EXPOSURE_HISTOGRAM_BINS];
+};
+#endif
+
+/** Histogram covers log2 luminance in [u_autoExposureMinEV, u_autoExposureMaxEV] */
+uint luminanceToHistogramBin(float luminance) {
+  if (luminance < AUTO_EXPOSURE_MIN_LUMINANCE) {
+    return 0;
+  }
+  float rangeEV = u_autoExposureMaxEV - u_autoExposureMinEV;
+  float logLuminance = (log2(luminance) - u_autoExposureMinEV) / rangeEV;
+  logLuminance = clamp(logLuminance, 0.0, 1.0);
+  return uint(logLuminance * float(AUTO_EXPOSURE_HISTOGRAM_BINS - 2) + 1.0);
+}
+
+float histogramBinToLuminance(float bin) {
+  float rangeEV = u_autoExposureMaxEV - u_autoExposureMinEV;
+  float logLuminance = (bin - 1.0) / float(AUTO_EXPOSURE_HISTOGRAM_BINS - 2);
+  return exp2(logLuminance * rangeEV + u_autoExposureMinEV);
+}
@@ -18,15 +18,13 @@ uniform GlobalConfigUniformBuffer {
   mat4 u_projectionMat;
   mat4 u_invProjectionMat; // inverse projection matrix
   mat4 u_viewProjectionMat;
-  // hair + simulation
-  vec4 u_tfxHairSettings; // [hairDisplayMode, u_tfxLinkedListPoolSize, g_GravityMagnitude, g_TimeStep]
-  vec4 u_tfxWind; // [windDir.xyz, windStrength]
-  vec4 u_tfxShape; // [Sim0.Verlet damping, Sim2.LSC local stiffness, Sim0.GSC global stiffness, Sim0.GSC global range.]
-  vec4 u_tfxConstraints; // [Sim3.Length Constraints iterations, Sim3.Length stiffness,-,-]
+  mat4 u_prevViewProjectionMat; // previous frame, no jitter. Used for motion vectors
+  // hair
+  vec4 u_tfxHairSettings; // [hairDisplayMode, u_tfxLinkedListPoolSize, u_tfxMinPixelWidth, g_TimeStep]
+  vec4 u_tfxWind; // [windDir.xyz, windStrength] - debug only, simulation uses per-object settings
   // AO + Shadow
-  mat4 u_directionalShadowMatrix_VP;
   vec4 u_shadowRadiusAndBias; // [u_shadowRadiusForwardShading, u_shadowBiasForwardShading, u_shadowRadiusTfx, u_shadowBiasTfx]
-  vec4 u_directionalShadowCasterPosition; // [position.xyz, u_maxShadowContribution]
+  vec4 u_shadowSettings; // [u_shadowsTechnique, u_vsmLightBleedingReduction, u_vsmMinVariance, -]. Shadow atlas slots are in `_lights.glsl`
   vec4 u_aoSettings; // (u_aoStrength, u_aoExp, showDebugPositions+u_maxShadowContribution, gamma)
   // sss
   vec4 u_sssSettings; // [u_sssPosition, u_sssFarPlane]
@@ -34,20 +32,26 @@ uniform GlobalConfigUniformBuffer {
   vec4 u_sssBlur; // [u_sssWidth, u_sssStrength, u_sssFovy+u_sssFollowSurface, -]
   // Lights
   vec4 u_lightAmbient;
-  vec3 u_light0_Position;
-  vec4 u_light0_Color;
-  vec3 u_light1_Position;
-  vec4 u_light1_Color;
-  vec3 u_light2_Position;
-  vec4 u_light2_Color;
+  vec4 u_lightsSettings; // [u_lightCount, -, -, -]. Lights themselves are in `_lights.glsl`
+  vec4 u_envSettings; // [u_envEnabled, u_envIntensity, u_envRotation, u_skyboxBlur]
+  vec4 u_envIrradianceSH[9]; // rgb only, see `sampleEnvIrradiance()`
   // SSAO
   vec4 u_ssao;
   vec4 u_ssao_and_misc;
   // FXAA
   vec4 u_fxaaSettings;
+  // TAA
+  vec4 u_taaSettings; // [u_taaJitter.xy, u_taaHistoryWeight, u_taaNeighbourhoodClamp]
+  vec4 u_renderScaleSettings; // [u_windowSize.xy, u_renderScale, -]
   // Color correction
   vec4 u_tonemapping;
   vec4 u_tonemapping2;
+  vec4 u_autoExposure; // [u_autoExposureEnabled, u_autoExposureMinEV, u_autoExposureMaxEV, u_autoExposureSpeed]
+  vec4 u_autoExposure2; // [u_exposureCompensation, u_deltaTime, -, -]
+  vec4 u_bloomSettings; // [u_bloomThreshold, u_bloomSoftKnee, u_bloomIntensity, -]
+  vec4 u_lensSettings; // [u_vignetteStrength, u_vignetteRadius, u_chromaticAberration, u_filmGrain]
+  vec4 u_lensSettings2; // [u_filmGrainSeed, -, -, -]
+  vec4 u_dofSettings; // [u_dofFocusDistance, u_dofCocScale, u_dofMaxCoc, -]
   // TONEMAPPING
   // vec2 u_colorCorrectionSettings;
   vec4 u_colorSaturation; // general
@@ -85,13 +89,16 @@ uniform GlobalConfigUniformBuffer {
 #define u_nearAndFar (u_viewportAndNearFar.zw)
 #define u_tfxDisplayMode (readConfigUint(u_tfxHairSettings.x))
 #define u_tfxLinkedListPoolSize (readConfigUint(u_tfxHairSettings.y))
+#define u_tfxMinPixelWidth (u_tfxHairSettings.z)
 
 // Shadows
 #define u_shadowRadiusForwardShading (readConfigInt(u_shadowRadiusAndBias.x))
 #define u_shadowBiasForwardShading (u_shadowRadiusAndBias.y)
 #define u_shadowRadiusTfx (readConfigInt(u_shadowRadiusAndBias.z))
 #define u_shadowBiasTfx (u_shadowRadiusAndBias.w)
-#define u_shadowsTechnique (readConfigUint(u_directionalShadowCasterPosition.w))
+#define u_shadowsTechnique (readConfigUint(u_shadowSettings.x))
+#define u_vsmLightBleedingReduction (u_shadowSettings.y)
+#define u_vsmMinVariance (u_shadowSettings.z)
 
 // AO + misc
 #define u_aoStrength (u_aoSettings.r)
@@ -100,6 +107,13 @@ uniform GlobalConfigUniformBuffer {
 #define u_maxShadowContribution (readConfigValueFromValueWithFlag(u_aoSettings.b))
 #define u_gamma (u_aoSettings.w)
 
+// Lights
+#define u_lightCount (readConfigUint(u_lightsSettings.x))
+#define u_envEnabled (u_envSettings.x > 0.0)
+#define u_envIntensity (u_envSettings.y)
+#define u_envRotation (u_envSettings.z)
+#define u_skyboxBlur (u_envSettings.w)
+
 // SSS (u_sssSettings)
 #define u_sssPosition (u_sssSettings.xyz)
 #define u_sssFarPlane (u_sssSettings.w)
@@ -113,6 +127,13 @@ uniform GlobalConfigUniformBuffer {
 #define u_edgeThreshold (u_fxaaSettings.y)
 #define u_edgeThresholdMin (u_fxaaSettings.z)
 #define u_fxaa_luma_gamma (u_fxaaSettings.w)
+// taa (u_taaSettings)
+#define u_taaJitter (u_taaSettings.xy) // in NDC, already applied to projection matrices
+#define u_taaHistoryWeight (u_taaSettings.z)
+#define u_taaNeighbourhoodClamp (u_taaSettings.w > 0.0)
+// render scale (u_renderScaleSettings). `u_viewport` is the size of render targets
+#define u_windowSize (u_renderScaleSettings.xy)
+#define u_renderScale (u_renderScaleSettings.z)
 
 // TONEMAPPING
 // u_tonemapping
@@ -125,6 +146,29 @@ uniform GlobalConfigUniformBuffer {
 #define u_tonemappingMode (readConfigUint(u_tonemapping2.y))
 #define u_colorCorrectionShadowsMax (u_tonemapping2.z)
 #define u_colorCorrectionHighlightsMin (u_tonemapping2.w)
+// u_autoExposure
+#define u_autoExposureEnabled (u_autoExposure.x > 0.0)
+#define u_autoExposureMinEV (u_autoExposure.y)
+#define u_autoExposureMaxEV (u_autoExposure.z)
+#define u_autoExposureSpeed (u_autoExposure.w)
+// u_autoExposure2
+#define u_exposureCompensation (u_autoExposure2.x)
+#define u_deltaTime (u_autoExposure2.y)
+// u_bloomSettings
+#define u_bloomThreshold (u_bloomSettings.x)
+#define u_bloomSoftKnee (u_bloomSettings.y)
+#define u_bloomIntensity (u_bloomSettings.z) // 0 if bloom is disabled
+// u_lensSettings. Effect is disabled if value is 0
+#define u_vignetteStrength (u_lensSettings.x)
+#define u_vignetteRadius (u_lensSettings.y)
+#define u_chromaticAberration (u_lensSettings.z)
+#define u_filmGrain (u_lensSettings.w)
+// u_lensSettings2
+#define u_filmGrainSeed (u_lensSettings2.x)
+// u_dofSettings
+#define u_dofFocusDistance (u_dofSettings.x)
+#define u_dofCocScale (u_dofSettings.y) // in pixels, see `PostFxCfg::dof_coc_scale()`
+#define u_dofMaxCoc (u_dofSettings.z)
 
 // SSAO
 // u_ssao
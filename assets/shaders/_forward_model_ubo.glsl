@@ -2,7 +2,7 @@ layout(binding = 1)
 uniform ForwardModelUBO {
   mat4 u_M; // model matrix
   mat4 u_MVP;
-  mat4 u_directionalShadowMatrix_MVP;
+  mat4 u_prevMVP; // previous frame, no jitter
   // material
   float u_specular;
   float u_specularMul;
@@ -0,0 +1,12 @@
+// Motion vectors for TAA. Requires `_config_ubo`.
+
+/**
+ * Screen space movement since the previous frame, in NDC.
+ * Current position is jittered (rendered with `u_viewProjectionMat`),
+ * previous one is not (`u_prevViewProjectionMat`).
+ */
+vec2 calculateVelocity(vec4 positionClip, vec4 prevPositionClip) {
+  vec2 current = positionClip.xy / positionClip.w - u_taaJitter;
+  vec2 previous = prevPositionClip.xy / prevPositionClip.w;
+  return current - previous;
+}
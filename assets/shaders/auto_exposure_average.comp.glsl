@@ -0,0 +1,59 @@
+#version 450
+
+precision highp float;
+precision highp int;
+
+#define BINDING_INDEX_HISTOGRAM 2
+#define BINDING_INDEX_EXPOSURE 3
+
+#pragma include ./_config_ubo;
+#pragma include ./_utils;
+#pragma include ./_auto_exposure;
+
+shared float s_weightedBins[AUTO_EXPOSURE_HISTOGRAM_BINS];
+
+
+// Average the histogram and adapt exposure towards it.
+// Single workgroup, one thread per bin. Clears the histogram for the next frame.
+//
+layout (local_size_x = AUTO_EXPOSURE_HISTOGRAM_BINS) in;
+void main() {
+  uint binIdx = gl_LocalInvocationIndex;
+  uint count = u_histogram[binIdx];
+  u_histogramDebug[binIdx] = count;
+  u_histogram[binIdx] = 0;
+
+  // bin 0 are (almost) black pixels, skip them
+  s_weightedBins[binIdx] = float(count) * float(binIdx);
+  uint blackPixels = binIdx == 0 ? count : 0;
+  barrier();
+
+  // parallel reduction
+  for (uint stride = AUTO_EXPOSURE_HISTOGRAM_BINS / 2; stride > 0; stride >>= 1) {
+    if (binIdx < stride) {
+      s_weightedBins[binIdx] += s_weightedBins[binIdx + stride];
+    }
+    barrier();
+  }
+
+  if (binIdx != 0) {
+    return;
+  }
+
+  ivec2 size = ivec2(u_viewport);
+  uint pixelCount = uint(size.x * size.y);
+  float litPixels = max(float(pixelCount - blackPixels), 1.0);
+  float averageBin = s_weightedBins[0] / litPixels;
+  float targetLuminance = histogramBinToLuminance(max(averageBin, 1.0));
+
+  // smooth over time. Previous value can be garbage on the first frame
+  float adapted = u_adaptedLuminance;
+  bool isFirstFrame = isnan(adapted) || isinf(adapted) || adapted <= 0.0;
+  float blend = 1.0 - exp(-u_deltaTime * u_autoExposureSpeed);
+  adapted = isFirstFrame ? targetLuminance : mix(adapted, targetLuminance, blend);
+
+  u_adaptedLuminance = adapted;
+  u_targetLuminance = targetLuminance;
+  u_histogramPixelCount = pixelCount;
+  u_autoExposureValue = AUTO_EXPOSURE_KEY_VALUE / adapted * exp2(u_exposureCompensation);
+}
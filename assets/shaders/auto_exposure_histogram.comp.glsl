@@ -0,0 +1,40 @@
+#version 450
+
+precision highp float;
+precision highp int;
+precision highp sampler2D;
+
+#define BINDING_INDEX_HISTOGRAM 2
+
+layout(binding = 1)
+uniform sampler2D u_sourceTex;
+
+#pragma include ./_config_ubo;
+#pragma include ./_utils;
+#pragma include ./_auto_exposure;
+
+shared uint s_histogram[AUTO_EXPOSURE_HISTOGRAM_BINS];
+
+
+// Build luminance histogram of the HDR image.
+// One thread reads one pixel.
+//
+layout (local_size_x = AUTO_EXPOSURE_THREAD_GROUP_SIZE, local_size_y = AUTO_EXPOSURE_THREAD_GROUP_SIZE) in;
+void main() {
+  s_histogram[gl_LocalInvocationIndex] = 0;
+  barrier();
+
+  ivec2 size = textureSize(u_sourceTex, 0);
+  ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+  if (pixel.x < size.x && pixel.y < size.y) {
+    vec3 color = texelFetch(u_sourceTex, pixel, 0).rgb;
+    uint bin = luminanceToHistogramBin(toLuma_fromLinear(color));
+    atomicAdd(s_histogram[bin], 1);
+  }
+  barrier();
+
+  uint count = s_histogram[gl_LocalInvocationIndex];
+  if (count > 0) {
+    atomicAdd(u_histogram[gl_LocalInvocationIndex], count);
+  }
+}
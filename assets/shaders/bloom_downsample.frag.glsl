@@ -0,0 +1,58 @@
+#version 450
+
+precision highp float;
+precision highp int;
+precision highp sampler2D;
+
+// Dual filtering - downsample step.
+// https://community.arm.com/cfs-file/__key/communityserver-blogs-components-weblogfiles/00-00-00-20-66/siggraph2015_2D00_mmg_2D00_marius_2D00_notes.pdf
+
+layout(push_constant) uniform Constants {
+  vec2 u_sourceTexelSize;
+  /** First step extracts bright pixels from the HDR image. Bool as float */
+  float u_isFirstPass;
+  float u_radius;
+};
+
+layout(binding = 1)
+uniform sampler2D u_sourceTex;
+
+layout(location = 0) in vec2 v_position;
+layout(location = 0) out vec4 outColor;
+
+#pragma include ./_config_ubo;
+#pragma include ./_utils;
+
+/** Max value of 16-bit float is 65504. Also removes fireflies */
+const float BLOOM_MAX_VALUE = 256.0;
+
+
+/** Soft threshold, see `u_bloomSoftKnee` */
+vec3 prefilter(vec3 color) {
+  color = min(color, vec3(BLOOM_MAX_VALUE));
+  float brightness = max3(color);
+  float knee = u_bloomThreshold * u_bloomSoftKnee;
+  float soft = clamp(brightness - u_bloomThreshold + knee, 0.0, 2.0 * knee);
+  soft = soft * soft / (4.0 * knee + 0.00001);
+  float contribution = max(soft, brightness - u_bloomThreshold) / max(brightness, 0.00001);
+  return color * contribution;
+}
+
+vec3 sampleSource(vec2 uv) {
+  vec3 color = texture(u_sourceTex, uv).rgb;
+  return u_isFirstPass > 0.0 ? prefilter(color) : color;
+}
+
+
+void main() {
+  vec2 uv = fixOpenGLTextureCoords_AxisY(v_position);
+  vec2 offset = u_sourceTexelSize * u_radius;
+
+  vec3 result = sampleSource(uv) * 4.0;
+  result += sampleSource(uv + vec2(-offset.x, -offset.y));
+  result += sampleSource(uv + vec2( offset.x, -offset.y));
+  result += sampleSource(uv + vec2(-offset.x,  offset.y));
+  result += sampleSource(uv + vec2( offset.x,  offset.y));
+
+  outColor = vec4(result / 8.0, 1.0);
+}
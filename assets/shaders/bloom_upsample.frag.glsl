@@ -0,0 +1,42 @@
+#version 450
+
+precision highp float;
+precision highp int;
+precision highp sampler2D;
+
+// Dual filtering - upsample step. Result is additively blended with the current mip.
+// https://community.arm.com/cfs-file/__key/communityserver-blogs-components-weblogfiles/00-00-00-20-66/siggraph2015_2D00_mmg_2D00_marius_2D00_notes.pdf
+
+layout(push_constant) uniform Constants {
+  vec2 u_sourceTexelSize;
+  float u_isFirstPass; // not used
+  float u_radius;
+};
+
+layout(binding = 1)
+uniform sampler2D u_sourceTex;
+
+layout(location = 0) in vec2 v_position;
+layout(location = 0) out vec4 outColor;
+
+#pragma include ./_config_ubo;
+#pragma include ./_utils;
+
+
+void main() {
+  vec2 uv = fixOpenGLTextureCoords_AxisY(v_position);
+  vec2 offset = u_sourceTexelSize * u_radius;
+  vec2 halfOffset = offset * 0.5;
+
+  vec3 result = vec3(0.0);
+  result += texture(u_sourceTex, uv + vec2(-offset.x, 0.0)).rgb;
+  result += texture(u_sourceTex, uv + vec2( offset.x, 0.0)).rgb;
+  result += texture(u_sourceTex, uv + vec2(0.0, -offset.y)).rgb;
+  result += texture(u_sourceTex, uv + vec2(0.0,  offset.y)).rgb;
+  result += texture(u_sourceTex, uv + vec2(-halfOffset.x, -halfOffset.y)).rgb * 2.0;
+  result += texture(u_sourceTex, uv + vec2( halfOffset.x, -halfOffset.y)).rgb * 2.0;
+  result += texture(u_sourceTex, uv + vec2(-halfOffset.x,  halfOffset.y)).rgb * 2.0;
+  result += texture(u_sourceTex, uv + vec2( halfOffset.x,  halfOffset.y)).rgb * 2.0;
+
+  outColor = vec4(result / 12.0, 1.0);
+}
@@ -0,0 +1,64 @@
+#version 450
+
+precision highp float;
+precision highp int;
+precision highp sampler2D;
+
+// HDR color
+layout(binding = 2)
+uniform sampler2D u_colorTex;
+// signed circle of confusion in pixels, see `dof_coc.frag.glsl`
+layout(binding = 3)
+uniform sampler2D u_cocTex;
+
+layout(location = 0) in vec2 v_position; // TexCoords
+layout(location = 0) out vec4 outColor;
+
+#pragma include ./_config_ubo;
+#pragma include ./_utils;
+
+const float GOLDEN_ANGLE = 2.39996323;
+/** Distance between samples on the spiral. Lower is smoother, but slower */
+const float RADIUS_STEP = 1.0;
+
+
+/**
+ * Scatter-as-gather on a golden angle spiral. Each sample contributes
+ * only if its own CoC reaches the current pixel. Samples behind the current
+ * pixel have CoC clamped, so blurry background does not bleed
+ * over the sharp foreground (e.g. hair strands in focus).
+ *
+ * http://blog.tuxedolabs.com/2018/05/04/bokeh-depth-of-field-in-single-pass.html
+ */
+vec3 depthOfField(vec2 uv, vec2 texelSize) {
+  float centerCoc = texture(u_cocTex, uv).r;
+  float centerSize = abs(centerCoc);
+  vec3 color = texture(u_colorTex, uv).rgb;
+  float totalWeight = 1.0;
+
+  float radius = RADIUS_STEP;
+  for (float angle = 0.0; radius < u_dofMaxCoc; angle += GOLDEN_ANGLE) {
+    vec2 sampleUv = uv + vec2(cos(angle), sin(angle)) * texelSize * radius;
+    vec3 sampleColor = texture(u_colorTex, sampleUv).rgb;
+    float sampleCoc = texture(u_cocTex, sampleUv).r;
+    float sampleSize = abs(sampleCoc);
+    if (sampleCoc > centerCoc) { // sample is further away
+      sampleSize = clamp(sampleSize, 0.0, centerSize * 2.0);
+    }
+
+    float m = smoothstep(radius - 0.5, radius + 0.5, sampleSize);
+    color += mix(color / totalWeight, sampleColor, m);
+    totalWeight += 1.0;
+    radius += RADIUS_STEP / radius;
+  }
+
+  return color / totalWeight;
+}
+
+
+void main() {
+  vec2 texelSize = 1.0 / vec2(textureSize(u_colorTex, 0));
+  vec2 uv = fixOpenGLTextureCoords_AxisY(v_position);
+  float alpha = texture(u_colorTex, uv).a;
+  outColor = vec4(depthOfField(uv, texelSize), alpha);
+}
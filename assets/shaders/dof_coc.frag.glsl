@@ -0,0 +1,50 @@
+#version 450
+
+precision highp float;
+precision highp int;
+precision highp sampler2D;
+
+// values are [-zNear, -zFar]
+layout(binding = 1)
+uniform sampler2D u_linearDepthTex;
+
+layout(location = 0) in vec2 v_position; // TexCoords
+// signed circle of confusion in pixels. Negative for foreground, positive for background
+layout(location = 0) out vec4 outCoc;
+
+#pragma include ./_config_ubo;
+#pragma include ./_utils;
+
+
+/**
+ * Hair strands are often thinner than a pixel. Partially covered pixels
+ * around the strand did not write depth, so they would get the background's
+ * CoC and smear the strand's color into the blur. Use depth of
+ * the closest pixel in 3x3 neighbourhood instead.
+ */
+float getClosestDepth(vec2 texelSize) {
+  float closestDepth = -1e20;
+  for (int dy = -1; dy <= 1; dy++) {
+    for (int dx = -1; dx <= 1; dx++) {
+      vec2 offset = vec2(dx, dy) * texelSize;
+      // linear depth is negative, so closest means highest
+      float depth = textureLinearDepth(u_linearDepthTex, v_position + offset);
+      closestDepth = max(closestDepth, depth);
+    }
+  }
+  return closestDepth;
+}
+
+/** See `PostFxCfg::dof_coc_scale()` */
+float getCircleOfConfusion(float linearDepth) {
+  float dist = max(-linearDepth, 0.0001);
+  float coc = u_dofCocScale * (1.0 - u_dofFocusDistance / dist);
+  return clamp(coc, -u_dofMaxCoc, u_dofMaxCoc);
+}
+
+
+void main() {
+  vec2 texelSize = 1.0 / vec2(textureSize(u_linearDepthTex, 0));
+  float depth = getClosestDepth(texelSize);
+  outCoc = vec4(getCircleOfConfusion(depth), 0.0, 0.0, 1.0);
+}
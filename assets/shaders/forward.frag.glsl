@@ -14,19 +14,22 @@ precision highp usampler2D;
 layout(binding = 2) uniform sampler2D u_albedoTexture;
 layout(binding = 3) uniform usampler2D u_specularTexture;
 layout(binding = 4) uniform usampler2D u_hairShadowTexture;
-layout(binding = 5) uniform sampler2D u_directionalShadowDepthTex;
+layout(binding = 5) uniform sampler2D u_shadowAtlasTex;
 layout(binding = 6) uniform sampler2D u_sssDepthTex;
 layout(binding = 7) uniform sampler2D u_aoTex;
+layout(binding = 9) uniform sampler2D u_environmentTex;
 
 
 // input-output variables
 layout(location = 0) in vec3 v_Position;
 layout(location = 1) in vec3 v_Normal;
 layout(location = 2) in vec2 v_UV;
-layout(location = 3) in vec4 v_PositionLightShadowSpace;
+layout(location = 3) in vec4 v_PositionClip;
+layout(location = 4) in vec4 v_PrevPositionClip;
 
 layout(location = 0) out vec4 outColor1;
 layout(location = 1) out uvec4 outColor2;
+layout(location = 2) out vec2 outVelocity;
 
 
 // required by SSSSS import, but not used here (used in SSS blur)
@@ -37,9 +40,12 @@ float SSSSS_sampleDepthLinear (sampler2D depthTex, vec2 texcoord) {
 
 
 #pragma include ./_utils;
+#pragma include ./_velocity;
 #pragma include ./materials/_material;
+#pragma include ./materials/_lights; // binding 8
 #pragma include ./materials/_pbr;
 #pragma include ./materials/_shadows;
+#pragma include ./materials/_ibl;
 #define SSSS_GLSL_3 1
 #define SSSS_QUALITY 2
 #pragma include ./materials/_separableSSSSS;
@@ -87,13 +93,6 @@ Material createMaterial() {
   // convert specular/smoothness -> roughness
   material.roughness = 1.0 - readSpecular();
 
-  vec3 toCaster = normalize(u_directionalShadowCasterPosition.xyz - v_Position);
-  material.shadow = 1.0 - calculateDirectionalShadow(
-    u_directionalShadowDepthTex,
-    v_PositionLightShadowSpace, material.normal, toCaster,
-    u_shadowBiasForwardShading,
-    u_shadowRadiusForwardShading
-  );
   material.hairShadow = readHairShadow();
 
   return material;
@@ -114,12 +113,34 @@ vec4 calculateSSSForwardScattering(Material material) {
   );
 }
 
-vec3 doShading(Material material, Light lights[3]) {
-  vec3 ambient = u_lightAmbient.rgb * u_lightAmbient.a * material.ao;
-  vec3 radianceSum = vec3(0.0);
+/** Image based lighting if environment map is provided, constant color otherwise */
+vec3 calculateAmbient(Material material) {
+  if (!u_envEnabled) {
+    return u_lightAmbient.rgb * u_lightAmbient.a * material.ao;
+  }
+
+  vec3 N = material.normal;
+  vec3 V = material.toEye;
+  float NdotV = dotMax0(N, V);
+  vec3 F0 = mix(DIELECTRIC_FRESNEL, material.albedo, material.isMetallic);
 
-  for (uint i = 0u; i < 3u; i++) {
-    Light light = lights[i];
+  vec3 irradiance = sampleEnvIrradiance(N);
+  vec3 diffuse = pbr_LambertDiffuse(material) * irradiance * (1.0 - material.isMetallic);
+
+  vec3 prefiltered = sampleEnvRadiance(u_environmentTex, reflect(-V, N), material.roughness);
+  vec3 specular = prefiltered * envBRDFApprox(F0, material.roughness, NdotV);
+  specular *= material.specularMul; // same as for lights
+
+  return (diffuse + specular) * u_envIntensity * material.ao;
+}
+
+vec3 doShading(Material material) {
+  vec3 ambient = calculateAmbient(material);
+  vec3 radianceSum = vec3(0.0); // without shadows, SSS needs it
+  vec3 radianceSumShadowed = vec3(0.0);
+
+  for (uint i = 0u; i < u_lightCount; i++) {
+    Light light = getLight(i);
 
     vec3 contrib = pbr(material, light);
 
@@ -131,19 +152,27 @@ vec3 doShading(Material material, Light lights[3]) {
     */
 
     radianceSum += contrib;
+
+    float lightShadow = calculateLightShadow(
+      u_shadowAtlasTex, light,
+      material.positionWS, material.normal,
+      u_shadowBiasForwardShading,
+      u_shadowRadiusForwardShading
+    );
+    float shadow = max(lightShadow, material.hairShadow);
+    float shadowContrib = clamp(shadow, 0.0, u_maxShadowContribution);
+    radianceSumShadowed += contrib * (1.0 - shadowContrib);
   }
 
   // not PBR, but we need this to highlight some details like collarbones etc.
   float aoRadianceFactor = getCustom_AO(material.ao, u_aoStrength, u_aoExp);
   radianceSum *= aoRadianceFactor;
+  radianceSumShadowed *= aoRadianceFactor;
 
   vec4 contribSSS = calculateSSSForwardScattering(material);
   vec3 sssForwardScattering = contribSSS.rgb * radianceSum * u_sssStrength;
 
-  float shadow = max(material.shadow, material.hairShadow);
-  float shadowContrib = clamp(shadow, 0.0, u_maxShadowContribution);
-  radianceSum = radianceSum * (1.0 - shadowContrib);
-  return ambient + radianceSum + sssForwardScattering;
+  return ambient + radianceSumShadowed + sssForwardScattering;
 }
 
 vec4 debugModeOverride(Material material, vec3 shadingResult){
@@ -151,7 +180,13 @@ vec4 debugModeOverride(Material material, vec3 shadingResult){
 
   switch(u_displayMode) {
     case DISPLAY_MODE_SHADOW_MAP: {
-      vec3 c = mix(shadingResult, vec3(1 - material.shadow), 0.8);
+      float shadow = calculateMaxLightShadow(
+        u_shadowAtlasTex,
+        material.positionWS, material.normal,
+        u_shadowBiasForwardShading,
+        u_shadowRadiusForwardShading
+      );
+      vec3 c = mix(shadingResult, vec3(1 - shadow), 0.8);
       result = vec4(c, 1);
       break;
     }
@@ -171,29 +206,26 @@ vec4 debugModeOverride(Material material, vec3 shadingResult){
 }
 
 void main() {
-  Light lights[3];
-  lights[0] = unpackLight(u_light0_Position, u_light0_Color);
-  lights[1] = unpackLight(u_light1_Position, u_light1_Color);
-  lights[2] = unpackLight(u_light2_Position, u_light2_Color);
-
   vec3 color;
   Material material = createMaterial();
   // SkinParams skinParams = createSkinParams();
   // material.skin = skinShader(material, skinParams);
-  color = doShading(material, lights);
+  color = doShading(material);
 
   vec4 colorDebug = debugModeOverride(material, color);
   color = mix(color, colorDebug.rgb, colorDebug.a);
   outColor1 = vec4(color, 1.0);
   // outColor2 = vec4(packNormal(material.normal), 1.0);
   outColor2 = uvec4(packNormal(material.normal), 255);
+  outVelocity = calculateVelocity(v_PositionClip, v_PrevPositionClip);
   
   /* DEBUG:
   // vec3 n = vec3(0.0, 0.5, 1.0);
   // vec3 n = material.normal;
-  vec3 toCaster = normalize(u_directionalShadowCasterPosition.xyz - v_Position);
-  vec4 positionShadowSpace = u_directionalShadowMatrix_MVP * vec4(v_Position, 1);
-  float shadowSim = shadowTestSimple(positionShadowSpace, material.normal, toCaster);
+  float shadowSim = 1.0 - calculateLightShadow(
+    u_shadowAtlasTex, getLight(0), v_Position, material.normal,
+    u_shadowBiasForwardShading, 0
+  );
   color = mix(
     material.albedo,
     vec3(shadowSim),
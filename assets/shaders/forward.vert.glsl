@@ -15,14 +15,16 @@ layout(location = 2) in vec2 in_UV;
 layout(location = 0) out vec3 v_Position; // global-space
 layout(location = 1) out vec3 v_Normal;
 layout(location = 2) out vec2 v_UV;
-layout(location = 3) out vec4 v_PositionLightShadowSpace;
+layout(location = 3) out vec4 v_PositionClip;
+layout(location = 4) out vec4 v_PrevPositionClip;
 
 
 void main() {
   vec4 pos = vec4(in_Position.xyz, 1.0);
   gl_Position = u_MVP * pos;
   v_Position = (u_M * pos).xyz;
-  v_PositionLightShadowSpace = u_directionalShadowMatrix_MVP * pos;
   v_Normal = in_Normal; // TODO [IGNORE] technically we should have rotation matrix here, but not needed for app as simple as this
   v_UV = in_UV;
+  v_PositionClip = gl_Position;
+  v_PrevPositionClip = u_prevMVP * pos;
 }
\ No newline at end of file
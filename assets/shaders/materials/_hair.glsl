@@ -1,19 +1,33 @@
-#pragma include ./_material; // for light struct
+#pragma include ./_material;
+#pragma include ./_lights;
 #pragma include ./_shadows;
 #pragma include ./_kajiyakay;
+#pragma include ./_ibl;
 
 float calculateHairShadow (
-  sampler2D directionalShadowDepthTex,
+  sampler2D shadowAtlasTex,
+  Light light,
   vec3 positionWorld,
-  vec3 normal,
-  vec4 positionShadowProjected
+  vec3 normal
 ) {
-  vec3 positionShadowCaster = u_directionalShadowCasterPosition.xyz;
-  vec3 toCaster = normalize(positionShadowCaster - positionWorld);
   vec3 normal2 = normalize(normal); // TODO [IGNORE] use tangent per http://developer.amd.com/wordpress/media/2012/10/Scheuermann_HairRendering.pdf s7?
-  return 1.0 - calculateDirectionalShadow(
-    directionalShadowDepthTex,
-    positionShadowProjected, normal2, toCaster,
+  return calculateLightShadow(
+    shadowAtlasTex, light,
+    positionWorld, normal2,
+    u_shadowBiasTfx,
+    u_shadowRadiusTfx
+  );
+}
+
+/** Debug only: strongest shadow from all lights */
+float calculateHairShadowDebug (
+  sampler2D shadowAtlasTex,
+  vec3 positionWorld,
+  vec3 normal
+) {
+  return calculateMaxLightShadow(
+    shadowAtlasTex,
+    positionWorld, normalize(normal),
     u_shadowBiasTfx,
     u_shadowRadiusTfx
   );
@@ -45,25 +59,31 @@ KajiyaKayParams createKajiyakayParams(
 
 
 vec3 doHairShading(
-  Light lights[3],
+  sampler2D shadowAtlasTex,
   float ao,
-  float shadow,
   vec3 positionWorld,
   vec3 normal, // assumed normalized
   vec3 tangent // assumed normalized
 ) {
   vec3 ambient = u_lightAmbient.rgb * u_lightAmbient.a;
+  if (u_envEnabled) {
+    // diffuse only, prefiltered specular does not fit Kajiya-Kay
+    ambient = TfxParamsUbo.u_albedo.rgb * sampleEnvIrradiance(normal) * u_envIntensity;
+  }
   vec3 radianceSum = vec3(0.0);
   KajiyaKayParams params = createKajiyakayParams(
     positionWorld, normal, tangent
   );
 
-  for (uint i = 0u; i < 3u; i++) {
-    Light light = lights[i];
-    vec3 L = normalize(light.position - positionWorld); // wi in integral
+  for (uint i = 0u; i < u_lightCount; i++) {
+    Light light = getLight(i);
+    float attenuation;
+    vec3 L = getLightVector(light, positionWorld, attenuation); // wi in integral
     // float NdotL = dotMax0(normalize(normal), L); // no, cause it's hair
     float NdotL = dotMax0(tangent, L);
-    vec3 radiance = light.color * light.intensity; // incoming color from light
+    vec3 radiance = light.color * attenuation * light.intensity; // incoming color from light
+    float shadow = calculateHairShadow(shadowAtlasTex, light, positionWorld, normal);
+    radiance *= 1.0 - clamp(shadow, 0.0, u_maxShadowContribution);
 
     // specular
     params.L = L;
@@ -91,8 +111,6 @@ vec3 doHairShading(
   radianceSum *= aoRadianceFactor;
   ambient *= aoRadianceFactor;
 
-  float shadowContrib = clamp(shadow, 0.0, u_maxShadowContribution);
-  radianceSum = radianceSum * (1.0 - shadowContrib);
   return ambient + radianceSum;
 }
 
@@ -0,0 +1,57 @@
+// Image based lighting. See `environment_map.rs`.
+// Environment map is equirectangular, mip n is prefiltered for `roughness = n / (mipCount - 1)`.
+
+/** Apply `u_envRotation` (around the vertical axis) to the direction before environment lookup */
+vec3 rotateEnvDirection(vec3 dir) {
+  float c = cos(u_envRotation);
+  float s = sin(u_envRotation);
+  return vec3(c * dir.x + s * dir.z, dir.y, -s * dir.x + c * dir.z);
+}
+
+/** Must match `direction_to_uv()` in `environment_map.rs` */
+vec2 envDirectionToUv(vec3 dir) {
+  return vec2(
+    0.5 + atan(dir.z, dir.x) / (2.0 * PI),
+    acos(clamp(dir.y, -1.0, 1.0)) / PI
+  );
+}
+
+/**
+ * Diffuse irradiance from 2nd order spherical harmonics.
+ * Already divided by PI, so multiply by albedo and done.
+ * Basis order must match `sh9_basis()` in `environment_map.rs`.
+ */
+vec3 sampleEnvIrradiance(vec3 normal) {
+  vec3 n = rotateEnvDirection(normal);
+  vec3 result = u_envIrradianceSH[0].rgb * 0.282095;
+  result += u_envIrradianceSH[1].rgb * 0.488603 * n.y;
+  result += u_envIrradianceSH[2].rgb * 0.488603 * n.z;
+  result += u_envIrradianceSH[3].rgb * 0.488603 * n.x;
+  result += u_envIrradianceSH[4].rgb * 1.092548 * n.x * n.y;
+  result += u_envIrradianceSH[5].rgb * 1.092548 * n.y * n.z;
+  result += u_envIrradianceSH[6].rgb * 0.315392 * (3.0 * n.z * n.z - 1.0);
+  result += u_envIrradianceSH[7].rgb * 1.092548 * n.x * n.z;
+  result += u_envIrradianceSH[8].rgb * 0.546274 * (n.x * n.x - n.y * n.y);
+  return max(result, vec3(0.0));
+}
+
+/** @param float roughness01 - 0 for mirror-like, 1 for the most blurred mip */
+vec3 sampleEnvRadiance(sampler2D envTex, vec3 dir, float roughness01) {
+  float maxLod = float(textureQueryLevels(envTex) - 1);
+  vec2 uv = envDirectionToUv(rotateEnvDirection(dir));
+  // explicit lod, derivatives are broken at the equirectangular seam
+  return textureLod(envTex, uv, roughness01 * maxLod).rgb;
+}
+
+/**
+ * Split sum's environment BRDF, analytical approximation instead of LUT.
+ * https://www.unrealengine.com/en-US/blog/physically-based-shading-on-mobile
+ */
+vec3 envBRDFApprox(vec3 F0, float roughness, float NdotV) {
+  const vec4 c0 = vec4(-1.0, -0.0275, -0.572, 0.022);
+  const vec4 c1 = vec4(1.0, 0.0425, 1.04, -0.04);
+  vec4 r = roughness * c0 + c1;
+  float a004 = min(r.x * r.x, exp2(-9.28 * NdotV)) * r.x + r.y;
+  vec2 AB = vec2(-1.04, 1.04) * a004 + r.zw;
+  return F0 * AB.x + AB.y;
+}
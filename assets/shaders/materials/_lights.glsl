@@ -0,0 +1,117 @@
+// Must match `LightType`
+const uint LIGHT_TYPE_DIRECTIONAL = 0u;
+const uint LIGHT_TYPE_POINT = 1u;
+const uint LIGHT_TYPE_SPOT = 2u;
+
+#ifndef BINDING_INDEX_LIGHTS_SSBO
+#define BINDING_INDEX_LIGHTS_SSBO 8
+#endif
+
+// Must match `Config::MAX_LIGHTS` and `ShadowAtlas::MAX_SLOTS`
+#define MAX_LIGHTS 16
+#define MAX_SHADOW_SLOTS 16
+
+// Must match `LightGPUData`. Only first `u_lightCount` entries are valid.
+struct LightGPUData {
+  vec4 positionAndType; // [position.xyz, type]
+  vec4 directionAndRadius; // [direction.xyz, radius]
+  vec4 color; // [color.rgb, intensity]
+  vec4 spotAndFalloff; // [cos(innerAngle), cos(outerAngle), falloff, shadowSlot or -1]
+};
+
+// Must match `ShadowSlotGPUData`. Region of the shadow atlas.
+struct ShadowSlotGPUData {
+  mat4 viewProjection;
+  vec4 atlasRect; // [uvOffset.xy, uvScale.xy]
+};
+
+// Must match `LightsSSBO`
+layout(std430, binding=BINDING_INDEX_LIGHTS_SSBO)
+readonly buffer LightsBuffer {
+  LightGPUData u_lights[MAX_LIGHTS];
+  ShadowSlotGPUData u_shadowSlots[MAX_SHADOW_SLOTS];
+};
+
+struct Light {
+  uint type;
+  vec3 position;
+  vec3 direction; // direction the light shines in. Directional and spot lights only
+  vec3 color;
+  float intensity;
+  float radius; // point and spot lights only
+  float falloff; // point and spot lights only
+  float cosInnerAngle; // spot lights only
+  float cosOuterAngle; // spot lights only
+  int shadowSlot; // first slot in `u_shadowSlots`, -1 if the light does not cast shadows
+};
+
+Light getLight(uint idx) {
+  LightGPUData data = u_lights[idx];
+  Light light;
+  light.type = readConfigUint(data.positionAndType.w);
+  light.position = data.positionAndType.xyz;
+  light.direction = data.directionAndRadius.xyz;
+  light.color = data.color.rgb;
+  light.intensity = data.color.a;
+  light.radius = data.directionAndRadius.w;
+  light.falloff = data.spotAndFalloff.z;
+  light.cosInnerAngle = data.spotAndFalloff.x;
+  light.cosOuterAngle = data.spotAndFalloff.y;
+  light.shadowSlot = int(round(data.spotAndFalloff.w)); // can be negative, do not use `readConfigInt()`
+  return light;
+}
+
+/**
+ * Attenuation based only on the distance. Light does not reach
+ * beyond the radius. Falloff of 0 means no attenuation inside the radius.
+ */
+float lightDistanceAttenuation(float dist, float radius, float falloff) {
+  if (dist >= radius) { return 0.0; }
+  return pow(1.0 - dist / radius, falloff);
+}
+
+/**
+ * @param vec3 positionWorld - shaded point
+ * @param out float attenuation - combined distance and spot cone attenuation
+ * @return vec3 normalized direction from the shaded point to the light (wi in integral)
+ */
+vec3 getLightVector(Light light, vec3 positionWorld, out float attenuation) {
+  if (light.type == LIGHT_TYPE_DIRECTIONAL) {
+    attenuation = 1.0;
+    return -light.direction;
+  }
+
+  vec3 L = light.position - positionWorld;
+  float dist = length(L);
+  L = L / max(dist, 0.0001);
+  attenuation = lightDistanceAttenuation(dist, light.radius, light.falloff);
+
+  if (light.type == LIGHT_TYPE_SPOT) {
+    float cosAngle = dot(-L, light.direction);
+    attenuation *= smoothstep(light.cosOuterAngle, light.cosInnerAngle, cosAngle);
+  }
+  return L;
+}
+
+/**
+ * Point lights have 6 consecutive slots (cube map faces).
+ * Order must match `CUBE_FACES` in `shadow_atlas.rs`.
+ * @return index into `u_shadowSlots` or -1 if the light does not cast shadows
+ */
+int getShadowSlotIdx(Light light, vec3 positionWorld) {
+  if (light.shadowSlot < 0 || light.type != LIGHT_TYPE_POINT) {
+    return light.shadowSlot;
+  }
+
+  vec3 d = positionWorld - light.position;
+  vec3 a = abs(d);
+  int face;
+  if (a.x >= a.y && a.x >= a.z) {
+    face = d.x > 0.0 ? 0 : 1;
+  } else if (a.y >= a.z) {
+    face = d.y > 0.0 ? 2 : 3;
+  } else {
+    face = d.z > 0.0 ? 4 : 5;
+  }
+  return light.shadowSlot + face;
+}
@@ -9,24 +9,9 @@ struct Material {
   float isMetallic;
   float ao;
   // shadow
-  float shadow; // 0.0 - in shadow, 1.0 - in light
   float hairShadow; // 0.0 - in shadow, 1.0 - in light. This is special Sintel texture!!!
 };
 
-struct Light {
-  vec3 position;
-  vec3 color;
-  float intensity;
-};
-
-Light unpackLight(vec3 pos, vec4 color) {
-  Light light;
-  light.position = pos;
-  light.color = color.rgb;
-  light.intensity = color.a;
-  return light;
-}
-
 
 /// utils:
 
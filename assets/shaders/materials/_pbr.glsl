@@ -111,10 +111,8 @@ vec3 pbr_mixDiffuseAndSpecular (const Material material, vec3 diffuse, vec3 spec
 vec3 pbr (const Material material, const Light light) {
   vec3 N = material.normal; // normal at fragment
   vec3 V = normalize(u_cameraPosition - material.positionWS); // viewDir
-  vec3 L = light.position - material.positionWS; // wi in integral
-  // float attenuation = lightAttenuation(length(L), light.radius);
-  float attenuation = 1.0; // hardcoded for this demo
-  L = normalize(L);
+  float attenuation;
+  vec3 L = getLightVector(light, material.positionWS, attenuation); // wi in integral
 
   // diffuse
   vec3 lambert = pbr_LambertDiffuse(material);
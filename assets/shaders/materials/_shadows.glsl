@@ -27,9 +27,12 @@ float calculateDirectionalShadow(vec4 lightPosInterp, vec3 normal, vec3 toShadow
 }
 */
 
+#pragma include ./_lights;
+
 const uint SHADOW_TECHNIQUE_BINARY_DEBUG = 0;
 const uint SHADOW_TECHNIQUE_PFC = 1;
 const uint SHADOW_TECHNIQUE_PCSS = 2;
+const uint SHADOW_TECHNIQUE_VSM = 3;
 
 const float IN_SHADOW = 1.0f;
 const float NOT_IN_SHADOW = 0.0f;
@@ -38,9 +41,14 @@ const float NOT_IN_SHADOW = 0.0f;
 const float PCSS_PENUMBRA_WIDTH = 10.0;
 const int PCSS_PENUMBRA_BASE = 1; // we want at least some blur
 
+/** Convert uv inside the atlas slot into uv of the whole atlas texture */
+vec2 toShadowAtlasUv(vec4 atlasRect, vec2 uv) {
+  return atlasRect.xy + uv * atlasRect.zw;
+}
+
 /** Simplest possible shadow implementation - just a binary in shadow or not */
 float shadowTestSimple(
-  sampler2D directionalShadowDepthTex,
+  sampler2D shadowAtlasTex, vec4 atlasRect,
   vec4 lightPosInterp, vec3 normal, vec3 toShadowCaster, float shadowBiasFromUi
 ) {
   // position of fragment as rendered from light POV
@@ -65,7 +73,7 @@ float shadowTestSimple(
   // GDC_Poster_NormalOffset.png
   float bias = max(shadowBiasFromUi * (1.0 - dot(normal, toShadowCaster)), 0.005);
 
-  float shadowMapDepth = texture(directionalShadowDepthTex, uv).r;
+  float shadowMapDepth = texture(shadowAtlasTex, toShadowAtlasUv(atlasRect, uv)).r;
   float shadow = fragmentDepth - bias > shadowMapDepth  ? IN_SHADOW : NOT_IN_SHADOW;
   // return vec3(0, 0, shadow); // debug: blue
   return 1.0 - shadow;
@@ -75,7 +83,7 @@ float shadowTestSimple(
 ////////////////
 
 float sampleShadowMap (
-  sampler2D directionalShadowDepthTex,
+  sampler2D shadowAtlasTex, vec4 atlasRect,
   int sampleRadius, vec3 lightPosProj, float bias
 ) {
   // depth of current fragment (we multiplied by light-shadow matrix
@@ -83,15 +91,17 @@ float sampleShadowMap (
   float fragmentDepth = lightPosProj.z;
 
   float shadow = 0.0;
-  vec2 texelSize = 1.0 / vec2(textureSize(directionalShadowDepthTex, 0));
+  vec2 texelSize = 1.0 / vec2(textureSize(shadowAtlasTex, 0));
+  // do not sample the neighbouring slots of the atlas
+  vec2 uvMin = atlasRect.xy + texelSize * 0.5;
+  vec2 uvMax = atlasRect.xy + atlasRect.zw - texelSize * 0.5;
+  vec2 uvCenter = toShadowAtlasUv(atlasRect, lightPosProj.xy);
 
   for (int x = -sampleRadius; x <= sampleRadius; ++x) {
     for (int y = -sampleRadius; y <= sampleRadius; ++y) {
       // depth from shadow map
-      float shadowMapDepth = texture(
-        directionalShadowDepthTex,
-        lightPosProj.xy + vec2(x, y) * texelSize
-      ).r;
+      vec2 uv = clamp(uvCenter + vec2(x, y) * texelSize, uvMin, uvMax);
+      float shadowMapDepth = texture(shadowAtlasTex, uv).r;
 
       // There are following cases:
       //  * fragmentDepth > shadowMapDepth
@@ -111,14 +121,49 @@ float sampleShadowMap (
   return shadow /= pcfTmp * pcfTmp;
 }
 
-float calculateDirectionalShadow(
-  sampler2D directionalShadowDepthTex,
+/**
+ * Variance shadow maps. Texture contains blurred moments (depth, depth^2),
+ * see `shadow_moments.frag.glsl`. Does not need the bias.
+ * @return notInShadow(1.0) or inShadow(0.0)
+ */
+float shadowVarianceTest(sampler2D shadowMomentsTex, vec4 atlasRect, vec3 lightPosProj) {
+  vec2 texelSize = 1.0 / vec2(textureSize(shadowMomentsTex, 0));
+  // do not sample the neighbouring slots of the atlas
+  vec2 uvMin = atlasRect.xy + texelSize * 0.5;
+  vec2 uvMax = atlasRect.xy + atlasRect.zw - texelSize * 0.5;
+  vec2 uv = clamp(toShadowAtlasUv(atlasRect, lightPosProj.xy), uvMin, uvMax);
+  vec2 moments = texture(shadowMomentsTex, uv).rg;
+
+  float fragmentDepth = lightPosProj.z;
+  if (fragmentDepth <= moments.x) {
+    return 1.0 - NOT_IN_SHADOW;
+  }
+
+  // Chebyshev's inequality - upper bound on the fraction of lit samples
+  float variance = max(moments.y - moments.x * moments.x, u_vsmMinVariance);
+  float d = fragmentDepth - moments.x;
+  float pMax = variance / (variance + d * d);
+
+  // light bleeding reduction - treat lowest values as fully in shadow
+  float lbr = u_vsmLightBleedingReduction;
+  return clamp((pMax - lbr) / (1.0 - lbr), 0.0, 1.0);
+}
+
+/** returns notInShadow(1.0) or inShadow(0.0) */
+float calculateShadow(
+  sampler2D shadowAtlasTex,
+  vec4 atlasRect,
   vec4 lightPosInterp, 
   vec3 normal, 
   vec3 toShadowCaster,
   float shadowBiasFromUi,
   int sampleRadius
 ) {
+  // behind the perspective (spot/point light) camera
+  if (lightPosInterp.w <= 0.0) {
+    return 1.0 - NOT_IN_SHADOW;
+  }
+
   // position of fragment as rendered from light POV
   vec3 lightPosProj = lightPosInterp.xyz / lightPosInterp.w; // Useless for ORTHO, only PERSP.
   lightPosProj = vec3(to_0_1(lightPosProj.xy), lightPosProj.z); // from opengl [-1, 1] to depth-texture-like [0..1]
@@ -128,11 +173,11 @@ float calculateDirectionalShadow(
   // Mark no shadow, cause it's better than dark region
   // far away (or whatever relative light-camera postion is)
   if (lightPosProj.z > 1.0) {
-    return NOT_IN_SHADOW;
+    return 1.0 - NOT_IN_SHADOW;
   }
   // would cause 'invalid' sampling, mark as no shadow too.
   if (outOfScreen(lightPosProj.xy)) {
-    return NOT_IN_SHADOW;
+    return 1.0 - NOT_IN_SHADOW;
   }
 
   // GDC_Poster_NormalOffset.png
@@ -142,17 +187,20 @@ float calculateDirectionalShadow(
     case SHADOW_TECHNIQUE_BINARY_DEBUG: {
       // discard variables above and use simplest possible way
       return shadowTestSimple(
-        directionalShadowDepthTex,
+        shadowAtlasTex, atlasRect,
         lightPosInterp, normal, toShadowCaster, shadowBiasFromUi
       );
     }
+    case SHADOW_TECHNIQUE_VSM: {
+      return shadowVarianceTest(shadowAtlasTex, atlasRect, lightPosProj);
+    }
     case SHADOW_TECHNIQUE_PFC: {
-      return 1.0 - sampleShadowMap(directionalShadowDepthTex, sampleRadius, lightPosProj, bias);
+      return 1.0 - sampleShadowMap(shadowAtlasTex, atlasRect, sampleRadius, lightPosProj, bias);
     }
     default:
     case SHADOW_TECHNIQUE_PCSS: {
       float fragmentDepth = lightPosProj.z; // current fragment - depth buffer from light position
-      float shadowMapDepth = texture(directionalShadowDepthTex, lightPosProj.xy).r; // sample center
+      float shadowMapDepth = texture(shadowAtlasTex, toShadowAtlasUv(atlasRect, lightPosProj.xy)).r; // sample center
 
       // Subtraction is reversed for RH coordinate system.
       // Fine for ortho projection.
@@ -160,8 +208,54 @@ float calculateDirectionalShadow(
 
       int sampleRadius = PCSS_PENUMBRA_BASE + int(depthDiff / shadowMapDepth * PCSS_PENUMBRA_WIDTH);
       return 1.0 - sampleShadowMap(
-        directionalShadowDepthTex, sampleRadius, lightPosProj, bias
+        shadowAtlasTex, atlasRect, sampleRadius, lightPosProj, bias
       );
     }
   }
 }
+
+/**
+ * Shadow from a single light. Finds the light's region in the shadow atlas.
+ * @return inShadow(1.0) or notInShadow(0.0)
+ */
+float calculateLightShadow(
+  sampler2D shadowAtlasTex,
+  Light light,
+  vec3 positionWorld,
+  vec3 normal,
+  float shadowBiasFromUi,
+  int sampleRadius
+) {
+  int slotIdx = getShadowSlotIdx(light, positionWorld);
+  if (slotIdx < 0) {
+    return NOT_IN_SHADOW;
+  }
+
+  ShadowSlotGPUData slot = u_shadowSlots[slotIdx];
+  vec4 positionShadowSpace = slot.viewProjection * vec4(positionWorld, 1.0);
+  float attenuation;
+  vec3 toShadowCaster = getLightVector(light, positionWorld, attenuation);
+  return 1.0 - calculateShadow(
+    shadowAtlasTex, slot.atlasRect,
+    positionShadowSpace, normal, toShadowCaster,
+    shadowBiasFromUi, sampleRadius
+  );
+}
+
+/** Debug only: strongest shadow from all lights */
+float calculateMaxLightShadow(
+  sampler2D shadowAtlasTex,
+  vec3 positionWorld,
+  vec3 normal,
+  float shadowBiasFromUi,
+  int sampleRadius
+) {
+  float shadow = NOT_IN_SHADOW;
+  for (uint i = 0u; i < u_lightCount; i++) {
+    Light light = getLight(i);
+    shadow = max(shadow, calculateLightShadow(
+      shadowAtlasTex, light, positionWorld, normal, shadowBiasFromUi, sampleRadius
+    ));
+  }
+  return shadow;
+}
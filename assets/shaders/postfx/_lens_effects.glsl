@@ -0,0 +1,38 @@
+// Vignette, chromatic aberration, film grain. All are disabled if strength is 0.
+
+/** Offset red and blue channels towards/away from the screen center */
+vec3 sampleWithChromaticAberration(sampler2D tex, vec2 uv) {
+  vec3 color = texture(tex, uv).rgb;
+  if (u_chromaticAberration <= 0.0) {
+    return color;
+  }
+  vec2 offset = (uv - vec2(0.5)) * u_chromaticAberration * 0.01;
+  color.r = texture(tex, uv + offset).r;
+  color.b = texture(tex, uv - offset).b;
+  return color;
+}
+
+/** `uv` in [0, 1] */
+vec3 applyVignette(vec3 color, vec2 uv) {
+  if (u_vignetteStrength <= 0.0) {
+    return color;
+  }
+  // 0 in the center, 1 in the corners
+  float dist = length(uv - vec2(0.5)) * sqrt(2.0);
+  float vignette = smoothstep(u_vignetteRadius, 1.0, dist);
+  return color * (1.0 - u_vignetteStrength * vignette);
+}
+
+float filmGrainNoise(vec2 pixel, float seed) {
+  return fract(sin(dot(pixel + seed, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+/** Applied to LDR color. Darker areas receive more grain */
+vec3 applyFilmGrain(vec3 color) {
+  if (u_filmGrain <= 0.0) {
+    return color;
+  }
+  float noise = filmGrainNoise(gl_FragCoord.xy, u_filmGrainSeed) - 0.5;
+  float luma = toLuma_fromLinear(color);
+  return color + noise * u_filmGrain * (1.0 - luma);
+}
@@ -1,9 +1,10 @@
+// NOTE: exposure is applied before color grading, see `tonemapping.frag.glsl`
+
 vec3 tonemapLinear(vec3 hdrColor) {
-  return u_exposure * hdrColor;
+  return hdrColor;
 }
 
 vec3 tonemapReinhard(vec3 hdrColor) {
-  hdrColor *= u_exposure;
   return hdrColor / (hdrColor + vec3(1.0));
 }
 
@@ -22,7 +23,6 @@ vec3 Uncharted2Tonemap(vec3 x) {
 }
 
 vec3 tonemapUncharted2(vec3 hdrColor) {
-	hdrColor *= u_exposure;
   vec3 denom = Uncharted2Tonemap(vec3(u_whitePoint));
 	return Uncharted2Tonemap(hdrColor) / denom;
 }
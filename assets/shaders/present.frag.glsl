@@ -14,7 +14,7 @@ uniform sampler2D u_ssaoTex;
 layout(binding = 4)
 uniform sampler2D u_depthTex;
 layout(binding = 5)
-uniform sampler2D u_directionalShadowDepthTex;
+uniform sampler2D u_shadowAtlasTex;
 layout(binding = 6)
 uniform sampler2D u_rawForwardPassResult;
 layout(binding = 7)
@@ -23,15 +23,55 @@ uniform sampler2D u_linearDepthTex;
 
 #pragma include _config_ubo;
 #pragma include _utils;
+#pragma include materials/_lights; // binding 8
 #pragma include postfx/_fxaa;
 
 
 layout(location = 0) in vec2 v_position;
 layout(location = 0) out vec4 color1;
 
+vec3 fetchTonemapped(ivec2 texel, ivec2 size) {
+  texel = clamp(texel, ivec2(0), size - 1);
+  return vec3(texelFetch(u_tonemappedTex, texel, 0).rgb) / 255.0;
+}
+
+/**
+ * Tonemapped result has the size of render targets (`u_viewport`), which
+ * differs from the window size if render scale is not 1.0.
+ * - supersampling - average all texels covered by the window pixel (box filter),
+ * - upscaling - bilinear filter. Texture is uint, so we cannot use the sampler for this.
+ */
+vec3 sampleTonemappedResampled(vec2 uv) {
+  if (u_renderScale > 1.0) {
+    int tapsPerAxis = int(ceil(u_renderScale));
+    vec2 windowPixelSize = 1.0 / u_windowSize;
+    vec3 result = vec3(0.0);
+    for (int y = 0; y < tapsPerAxis; y++) {
+      for (int x = 0; x < tapsPerAxis; x++) {
+        vec2 offset = ((vec2(x, y) + 0.5) / float(tapsPerAxis) - 0.5) * windowPixelSize;
+        result += FxaaSampleCol(u_tonemappedTex, uv + offset, 0.0).rgb;
+      }
+    }
+    return result / float(tapsPerAxis * tapsPerAxis);
+  }
+
+  ivec2 size = textureSize(u_tonemappedTex, 0);
+  vec2 texelPos = uv * vec2(size) - 0.5;
+  ivec2 p = ivec2(floor(texelPos));
+  vec2 t = texelPos - vec2(p);
+  vec3 top = mix(fetchTonemapped(p, size), fetchTonemapped(p + ivec2(1, 0), size), t.x);
+  vec3 bottom = mix(fetchTonemapped(p + ivec2(0, 1), size), fetchTonemapped(p + ivec2(1, 1), size), t.x);
+  return mix(top, bottom, t.y);
+}
+
 vec3 doFxaa (vec2 uv) {
   vec4 color;
 
+  if (u_renderScale != 1.0) {
+    // FXAA operates on render target pixels, there is no point after resampling
+    return sampleTonemappedResampled(uv);
+  }
+
   if (u_edgeThreshold == 0.0) {
     // FXAA off
     color = FxaaSampleCol(u_tonemappedTex, uv, 0.0);
@@ -103,10 +143,10 @@ vec4 drawDebugSpheres(){
   float r = 1.5; // debug sphere radius
 
   if (u_showDebugPositions) {
-    DRAW_DEBUG_SPHERE(u_directionalShadowCasterPosition.xyz, vec3(0.2), r);
-    DRAW_DEBUG_SPHERE(u_light0_Position, u_light0_Color.rgb, r);
-    DRAW_DEBUG_SPHERE(u_light1_Position, u_light1_Color.rgb, r);
-    DRAW_DEBUG_SPHERE(u_light2_Position, u_light2_Color.rgb, r);
+    for (uint i = 0u; i < u_lightCount; i++) {
+      Light light = getLight(i);
+      DRAW_DEBUG_SPHERE(light.position, light.color, r);
+    }
     DRAW_DEBUG_SPHERE(u_sssPosition, vec3(0.87, 0.53, 0.36), r); // #de875d
     // wind
     vec3 windPosition = -u_tfxWind.xyz * 10; // reverse cause if wind blows to the left, we draw source on right
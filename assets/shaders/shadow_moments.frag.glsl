@@ -0,0 +1,24 @@
+#version 450
+
+precision highp float;
+precision highp int;
+precision highp sampler2D;
+
+// depth rendered from lights POV, all atlas slots at once
+layout(binding = 1)
+uniform sampler2D u_shadowAtlasTex;
+
+
+layout(location = 0) in vec2 v_position; // TexCoords
+// Variance shadow maps: (depth, depth^2). Blurred afterwards.
+layout(location = 0) out vec4 outMoments;
+
+#pragma include ./_config_ubo;
+#pragma include ./_utils;
+
+
+void main() {
+  // same texel as the atlas. `textureDepth()` would flip Y and mirror the moments
+  float depth = texelFetch(u_shadowAtlasTex, ivec2(gl_FragCoord.xy), 0).r;
+  outMoments = vec4(depth, depth * depth, 0.0, 1.0);
+}
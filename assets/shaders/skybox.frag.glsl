@@ -0,0 +1,36 @@
+#version 450
+
+precision highp float;
+precision highp int;
+precision highp sampler2D;
+
+// prefiltered equirectangular environment map
+layout(binding = 9)
+uniform sampler2D u_environmentTex;
+
+
+layout(location = 0) in vec2 v_position; // TexCoords
+layout(location = 0) out vec4 outColor1;
+layout(location = 1) out uvec4 outColor2;
+layout(location = 2) out vec2 outVelocity;
+
+#pragma include ./_config_ubo;
+#pragma include ./_utils;
+#pragma include ./_velocity;
+#pragma include ./materials/_ibl;
+
+
+void main() {
+  // direction from camera through the pixel
+  vec4 positionClip = vec4(v_position * 2.0 - 1.0, 1.0, 1.0);
+  vec4 positionView = u_invProjectionMat * positionClip;
+  vec3 dirView = positionView.xyz / positionView.w;
+  vec3 dirWorld = normalize(transpose(mat3(u_viewMat)) * dirView);
+
+  vec3 color = sampleEnvRadiance(u_environmentTex, dirWorld, u_skyboxBlur);
+  outColor1 = vec4(color * u_envIntensity, 1.0);
+  outColor2 = uvec4(0); // same as `Config::clear_normal`
+  // skybox is infinitely far away, only camera rotation matters
+  vec4 prevPositionClip = u_prevViewProjectionMat * vec4(dirWorld, 0.0);
+  outVelocity = calculateVelocity(positionClip, prevPositionClip);
+}
@@ -0,0 +1,10 @@
+#version 450
+
+layout(location = 0) out vec2 outUV;
+
+/// Same as `fullscreen_quad.vert.glsl`, but placed at the far plane.
+/// With `LESS_OR_EQUAL` depth test only the background is rendered.
+void main() {
+  outUV = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+  gl_Position = vec4(outUV * 2.0f + -1.0f, 1.0f, 1.0f);
+}
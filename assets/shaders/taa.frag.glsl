@@ -0,0 +1,105 @@
+#version 450
+
+precision highp float;
+precision highp int;
+precision highp sampler2D;
+
+layout(binding = 1)
+uniform sampler2D u_colorTex;
+layout(binding = 2)
+uniform sampler2D u_historyTex;
+layout(binding = 3)
+uniform sampler2D u_velocityTex;
+// values are [-zNear, -zFar]
+layout(binding = 4)
+uniform sampler2D u_linearDepthTex;
+
+layout(location = 0) in vec2 v_position; // TexCoords
+layout(location = 0) out vec4 outColor;
+
+#pragma include ./_config_ubo;
+#pragma include ./_utils;
+
+
+vec3 rgbToYCoCg(vec3 c) {
+  return vec3(
+     0.25 * c.r + 0.5 * c.g + 0.25 * c.b,
+     0.5  * c.r             - 0.5  * c.b,
+    -0.25 * c.r + 0.5 * c.g - 0.25 * c.b
+  );
+}
+
+vec3 yCoCgToRgb(vec3 c) {
+  return vec3(
+    c.x + c.y - c.z,
+    c.x       + c.z,
+    c.x - c.y - c.z
+  );
+}
+
+/**
+ * Use velocity of the closest pixel in 3x3 neighbourhood.
+ * Otherwise object edges would reproject the background.
+ */
+vec2 getDilatedVelocity(vec2 texelSize) {
+  vec2 closestOffset = vec2(0.0);
+  float closestDepth = -1e20;
+  for (int dy = -1; dy <= 1; dy++) {
+    for (int dx = -1; dx <= 1; dx++) {
+      vec2 offset = vec2(dx, dy) * texelSize;
+      // linear depth is negative, so closest means highest
+      float depth = textureLinearDepth(u_linearDepthTex, v_position + offset);
+      if (depth > closestDepth) {
+        closestDepth = depth;
+        closestOffset = offset;
+      }
+    }
+  }
+  vec2 uv = fixOpenGLTextureCoords_AxisY(v_position + closestOffset);
+  return texture(u_velocityTex, uv).xy;
+}
+
+/** Clamp history to the color range of current pixel's neighbourhood */
+vec3 clampToNeighbourhood(vec3 historyColor, vec2 texelSize) {
+  vec3 minColor = vec3(1e20);
+  vec3 maxColor = vec3(-1e20);
+  for (int dy = -1; dy <= 1; dy++) {
+    for (int dx = -1; dx <= 1; dx++) {
+      vec2 uv = fixOpenGLTextureCoords_AxisY(v_position + vec2(dx, dy) * texelSize);
+      vec3 c = rgbToYCoCg(texture(u_colorTex, uv).rgb);
+      minColor = min(minColor, c);
+      maxColor = max(maxColor, c);
+    }
+  }
+  vec3 history = clamp(rgbToYCoCg(historyColor), minColor, maxColor);
+  return yCoCgToRgb(history);
+}
+
+
+void main() {
+  vec2 texelSize = 1.0 / vec2(textureSize(u_colorTex, 0));
+  vec2 uv = fixOpenGLTextureCoords_AxisY(v_position);
+  vec4 currentColor = texture(u_colorTex, uv);
+
+  // velocity is in NDC, texture coordinates are half of that
+  vec2 velocity = getDilatedVelocity(texelSize);
+  vec2 historyPosition = v_position - velocity * 0.5;
+  if (outOfScreen(historyPosition)) {
+    outColor = currentColor;
+    return;
+  }
+  vec2 historyUv = fixOpenGLTextureCoords_AxisY(historyPosition);
+  vec3 historyColor = texture(u_historyTex, historyUv).rgb;
+
+  if (u_taaNeighbourhoodClamp) {
+    historyColor = clampToNeighbourhood(historyColor, texelSize);
+  }
+
+  // weight by inverse luma to reduce flickering of bright pixels
+  float currentWeight = (1.0 - u_taaHistoryWeight) / (1.0 + toLuma_fromLinear(currentColor.rgb));
+  float historyWeight = u_taaHistoryWeight / (1.0 + toLuma_fromLinear(historyColor));
+  vec3 result = (currentColor.rgb * currentWeight + historyColor * historyWeight) /
+                max(currentWeight + historyWeight, 0.0001);
+
+  outColor = vec4(result, currentColor.a);
+}
@@ -1,17 +1,29 @@
 // NOTE: rust packing has problems with raw floats (packing?) - use vec4
 
-layout(binding = 3) 
+#ifndef BINDING_INDEX_TFX_PARAMS_UBO
+#define BINDING_INDEX_TFX_PARAMS_UBO 3
+#endif
+
+layout(binding = BINDING_INDEX_TFX_PARAMS_UBO)
 uniform TfxParamsUniformBuffer {
   mat4 u_modelMatrix;
   vec4 u_generalSettings;
   // geometry
   vec4 u_geometry;
   vec4 u_centerOfGravity;
+  vec4 u_followHairs; // [uint followHairMode, clumpStrength, clumpShape, -]
+  vec4 u_strandModifiers; // [curlRadius, curlFrequency, frizz, lengthVariation]
+  vec4 u_selectedStrand; // [uint strandId, uint instanceId, bool isSelected, -]
+  vec4 u_lod; // [lodFollowHairs, -, -, -]
   // material
   vec4 u_albedo;
   vec4 u_specular1;
   vec4 u_specular2;
   vec4 u_material;
+  // simulation
+  vec4 u_simWind; // [windDir.xyz, windStrength]
+  vec4 u_simShape; // [Sim0.Verlet damping, Sim2.LSC local stiffness, Sim0.GSC global stiffness, Sim0.GSC global range.]
+  vec4 u_simConstraints; // [Sim3.Length Constraints iterations, Sim3.Length stiffness, g_GravityMagnitude, -]
 } TfxParamsUbo;
 
 // u_specular1, u_specular2
@@ -24,6 +36,21 @@ uniform TfxParamsUniformBuffer {
 #define u_fiberRadius (TfxParamsUbo.u_geometry.y)
 #define u_followHairSpreadRoot (TfxParamsUbo.u_geometry.z)
 #define u_followHairSpreadTip (TfxParamsUbo.u_geometry.w)
+// u_followHairs
+#define u_followHairMode (readConfigUint(TfxParamsUbo.u_followHairs.x))
+#define u_followHairClumpStrength (TfxParamsUbo.u_followHairs.y)
+#define u_followHairClumpShape (TfxParamsUbo.u_followHairs.z)
+// u_strandModifiers
+#define u_curlRadius (TfxParamsUbo.u_strandModifiers.x)
+#define u_curlFrequency (TfxParamsUbo.u_strandModifiers.y)
+#define u_frizz (TfxParamsUbo.u_strandModifiers.z)
+#define u_lengthVariation (TfxParamsUbo.u_strandModifiers.w)
+// u_selectedStrand
+#define u_selectedStrandId (readConfigUint(TfxParamsUbo.u_selectedStrand.x))
+#define u_selectedStrandInstanceId (readConfigUint(TfxParamsUbo.u_selectedStrand.y))
+#define u_hasSelectedStrand (TfxParamsUbo.u_selectedStrand.z > 0.5)
+// u_lod
+#define u_lodFollowHairs (TfxParamsUbo.u_lod.x)
 // u_material
 #define u_primaryShift (TfxParamsUbo.u_material.x)
 #define u_secondaryShift (TfxParamsUbo.u_material.y)
@@ -39,3 +66,12 @@ uniform TfxParamsUniformBuffer {
 vec3 calculateHairNormal(vec3 positionWorldSpace) {
   return normalize(positionWorldSpace - TfxParamsUbo.u_centerOfGravity.xyz);
 }
+
+/** Strand picked in the strand inspector. Highlighted in every hair technique. */
+bool isSelectedStrand(int instanceId, uint strandId) {
+  return u_hasSelectedStrand &&
+    uint(instanceId) == u_selectedStrandInstanceId &&
+    strandId == u_selectedStrandId;
+}
+
+const vec3 SELECTED_STRAND_COLOR = vec3(1.0, 0.0, 1.0);
@@ -4,10 +4,17 @@
 #define DEPTH_IS_FURTHER(DEPTH_AAA, DEPTH_BBB) ((DEPTH_AAA) < (DEPTH_BBB))
 #define DEPTH_RESET_TO_CLOSE (0)
 
+// Must match `HairPPLLTailMode` in Rust
+const int PPLL_TAIL_MODE_SIMPLE_SHADING = 0;
+const int PPLL_TAIL_MODE_IGNORE = 1;
+const int PPLL_TAIL_MODE_FULL_SHADING = 2;
+
 
 void ClearKBuffer(inout PPLLFragmentData kBuffer[KBUFFER_SIZE]) {
   for (int t = 0; t < KBUFFER_SIZE; ++t) {
     kBuffer[t].depth = 100000.0;
+    kBuffer[t].flags = 0u;
+    kBuffer[t].widthCoverage = 1.0;
     kBuffer[t].tangentAndCoverage = vec4(0); // coverage 0.0 means ignored. Though we will use FRAGMENT_LIST_NULL to detect end of the list
   }
 }
@@ -17,6 +24,8 @@ PPLLFragmentData unpackPPLLFragment(uint pointer) {
   frag.tangentAndCoverage = parseTangentAndCoverage(NODE_TANGENT_COV(pointer));
   frag.positionWorldSpace = NODE_POSITION(pointer);
   frag.depth = NODE_DEPTH(pointer);
+  frag.flags = NODE_FLAGS(pointer);
+  frag.widthCoverage = NODE_WIDTH_COVERAGE(pointer);
   return frag;
 }
 
@@ -24,12 +33,18 @@ void swapPPLLFragmentsData(inout PPLLFragmentData fragA, inout PPLLFragmentData
   vec4 tangentAndCoverage = fragA.tangentAndCoverage;
   vec3 positionWorldSpace = fragA.positionWorldSpace;
   float depth = fragA.depth;
+  uint flags = fragA.flags;
+  float widthCoverage = fragA.widthCoverage;
   fragA.tangentAndCoverage = fragB.tangentAndCoverage;
   fragA.positionWorldSpace = fragB.positionWorldSpace;
   fragA.depth = fragB.depth;
+  fragA.flags = fragB.flags;
+  fragA.widthCoverage = fragB.widthCoverage;
   fragB.tangentAndCoverage = tangentAndCoverage;
   fragB.positionWorldSpace = positionWorldSpace;
   fragB.depth = depth;
+  fragB.flags = flags;
+  fragB.widthCoverage = widthCoverage;
 }
 
 uint FillFirstKBuffferElements (inout PPLLFragmentData kBuffer[KBUFFER_SIZE], uint pointer) {
@@ -102,8 +117,14 @@ vec4 GatherLinkedList(vec2 vfScreenAddress, inout PPLLFragmentData closestFragme
     }
 
     // add the element to accumulating value
-    vec4 fragmentColor = TFX_SHADING_FAR_FN(vfScreenAddress, furthestFragment);
-    fcolor = combineFragmentColorWithAcc(fcolor, fragmentColor);
+    // (`PPLL_TAIL_MODE` is specialization constant, so the branches are free)
+    if (PPLL_TAIL_MODE == PPLL_TAIL_MODE_SIMPLE_SHADING) {
+      vec4 fragmentColor = TFX_SHADING_FAR_FN(vfScreenAddress, furthestFragment);
+      fcolor = combineFragmentColorWithAcc(fcolor, fragmentColor);
+    } else if (PPLL_TAIL_MODE == PPLL_TAIL_MODE_FULL_SHADING) {
+      vec4 fragmentColor = TFX_SHADING_CLOSE_FN(vfScreenAddress, furthestFragment);
+      fcolor = combineFragmentColorWithAcc(fcolor, fragmentColor);
+    }
 
     pointer = NODE_NEXT(pointer);
   }
@@ -4,12 +4,14 @@
 struct PerPixelListEntryData {
   uint uNext; // pointer to next data
   uint tangentAndCoverage; // tangent.xyz and coverage
-  uint _padding;
-  uint _padding2;
+  uint flags; // PPLL_FLAG_*
+  float widthCoverage; // < 1.0 if strand was widened to min. pixel width
   vec4 positionWorldSpace; // [.xyz, depth]
 };
 
 #define FRAGMENT_LIST_NULL (0xffffffff)
+// fragment belongs to the strand selected in the strand inspector
+#define PPLL_FLAG_SELECTED_STRAND (1u)
 
 uint PackFloat4IntoUint(vec4 vValue) {
   return ((uint(vValue.x * 255)) << 24) |
@@ -66,11 +68,15 @@ void writeFragmentAttributes(
   float fDepth,
   vec3 tangent,
   float coverage,
-  vec3 positionWorldSpace
+  float widthCoverage,
+  vec3 positionWorldSpace,
+  uint flags
 ) {
     u_linkedListDataBuffer[nAddress].tangentAndCoverage  = PackFloat4IntoUint(vec4(to_0_1(tangent.xyz), coverage));
     // u_linkedListDataBuffer[nAddress].depth = uint(fDepth * 255.0); //uint(saturate(fDepth)); or gl_FragCoord.z; ?
     u_linkedListDataBuffer[nAddress].uNext = nPreviousLink;
+    u_linkedListDataBuffer[nAddress].flags = flags;
+    u_linkedListDataBuffer[nAddress].widthCoverage = widthCoverage;
     u_linkedListDataBuffer[nAddress].positionWorldSpace = vec4(positionWorldSpace, fDepth);
 }
 
@@ -85,6 +91,8 @@ uint getListHeadPointer(vec2 vfScreenAddress) {
 #define NODE_NEXT(x)  (u_linkedListDataBuffer[x].uNext)
 #define NODE_DEPTH(x) (u_linkedListDataBuffer[x].positionWorldSpace.w)
 #define NODE_POSITION(x) (u_linkedListDataBuffer[x].positionWorldSpace.xyz)
+#define NODE_FLAGS(x) (u_linkedListDataBuffer[x].flags)
+#define NODE_WIDTH_COVERAGE(x) (u_linkedListDataBuffer[x].widthCoverage)
 
 vec4 parseTangentAndCoverage(uint tangentAndCoverage) {
   vec4 value = UnpackUintIntoFloat4(tangentAndCoverage);
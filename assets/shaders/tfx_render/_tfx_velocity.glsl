@@ -0,0 +1,45 @@
+// Motion vectors for hair. Include after `_tfx_vertex_resolve`.
+
+// Simulation result from the previous frame. Must match `TfxForwardPass::BINDING_INDEX_PREV_POSITIONS_SSBO`
+layout(std430, binding=9)
+buffer TfxVertexPrevPositionsBuffer {
+  vec4 u_vertexPrevPositionsBuffer[];
+};
+
+/** Simulation movement of the vertex since the previous frame. Model space */
+vec3 getPositionDelta(uint index) {
+  return getPosition(index).xyz - u_vertexPrevPositionsBuffer[index].xyz;
+}
+
+/**
+ * World space movement of the vertex since the previous frame. Only the simulation
+ * is taken into account - follow hair offsets and strand modifiers do not change between frames.
+ */
+vec3 getSimulationMovement(TressFXParams params) {
+  uint index = params.vertexId / 2u;
+  index = applyLengthVariation(params, index);
+  vec3 delta = getPositionDelta(index);
+
+  // same as `getInterpolatedFollowHair()`
+  if (params.followHairMode == FOLLOW_HAIR_MODE_INTERPOLATED && params.instanceId > 0u) {
+    uint vertexInStrandId = index % uint(u_numVerticesPerStrand);
+    uvec4 neighbours = u_followHairNeighbours[params.strandId];
+    uint n0 = neighbours[params.instanceId % 4u];
+    uint n1 = neighbours[(params.instanceId + 1u) % 4u];
+    uint index0 = n0 * uint(u_numVerticesPerStrand) + vertexInStrandId;
+    uint index1 = n1 * uint(u_numVerticesPerStrand) + vertexInStrandId;
+
+    vec3 w = getInterpolationWeights(params.instanceId, params.strandId);
+    delta = w.x * delta +
+            w.y * getPositionDelta(index0) +
+            w.z * getPositionDelta(index1);
+  }
+
+  return (params.modelMat * vec4(delta, 0.0)).xyz;
+}
+
+/** Clip space position in the previous frame. Use with `calculateVelocity()` */
+vec4 getPrevPositionClip(TressFXParams params, TressFXVertex vertex) {
+  vec3 prevPosition = vertex.positionWorldSpace.xyz - getSimulationMovement(params);
+  return u_prevViewProjectionMat * vec4(prevPosition, 1.0);
+}
@@ -5,6 +5,11 @@
 
 const float EXPAND_PIXELS_FACTOR = 0.71;
 
+// Must match `TfxFollowHairMode`
+const uint FOLLOW_HAIR_MODE_RANDOM = 0u;
+const uint FOLLOW_HAIR_MODE_INTERPOLATED = 1u;
+const uint FOLLOW_HAIR_MODE_CLUMPED = 2u;
+
 
 layout(std430, binding=1)
 buffer TfxVertexPositionsBuffer {
@@ -15,6 +20,11 @@ buffer TfxVertexTangentsBuffer {
   vec4 u_vertexTangentsBuffer[];
 };
 
+layout(std430, binding=7)
+buffer TfxFollowHairNeighboursBuffer {
+  uvec4 u_followHairNeighbours[]; // nearest guide strands for each guide strand
+};
+
 vec4  getPosition (uint index) { return u_vertexPositionsBuffer[index]; }
 vec4  getTangent  (uint index) { return u_vertexTangentsBuffer[index]; }
 
@@ -44,6 +54,7 @@ struct TressFXVertex {
   vec3 normal;
   vec3 tangent;
   float vertexRootToTipFactor; // 1 := root, 0: = tip
+  float widthCoverage; // < 1.0 if strand was widened to `minPixelWidth`
 };
 
 struct TressFXParams {
@@ -60,6 +71,17 @@ struct TressFXParams {
   float fiberRadius;
   float followHairSpreadRoot;
   float followHairSpreadTip;
+  uint followHairMode;
+  float followHairClumpStrength;
+  float followHairClumpShape;
+
+  float curlRadius;
+  float curlFrequency;
+  float frizz;
+  float lengthVariation;
+
+  float lodFollowHairs;
+  float minPixelWidth; // 0 to disable
 };
 
 TressFXParams createTfxParams() {
@@ -77,6 +99,17 @@ TressFXParams createTfxParams() {
   params.fiberRadius = u_fiberRadius;
   params.followHairSpreadRoot = u_followHairSpreadRoot;
   params.followHairSpreadTip = u_followHairSpreadTip;
+  params.followHairMode = u_followHairMode;
+  params.followHairClumpStrength = u_followHairClumpStrength;
+  params.followHairClumpShape = u_followHairClumpShape;
+
+  params.curlRadius = u_curlRadius;
+  params.curlFrequency = u_curlFrequency;
+  params.frizz = u_frizz;
+  params.lengthVariation = u_lengthVariation;
+
+  params.lodFollowHairs = u_lodFollowHairs;
+  params.minPixelWidth = u_tfxMinPixelWidth;
 
   return params;
 }
@@ -95,14 +128,20 @@ vec3 randomizeStrandPos(uint instanceId, uint strandId, uint rngFac) {
 vec3 getFollowHairDisplacement (
   TressFXParams params, float fractionOfStrand, vec3 tangent
 ) {
-  if (params.instanceId == 0u) {
+  if (params.instanceId == 0u || params.followHairMode == FOLLOW_HAIR_MODE_INTERPOLATED) {
     // not required, but why not? It should stick in the middle of follow-hair group
     return vec3(0.0);
   }
 
   vec3 rootOffset = randomizeStrandPos(params.instanceId, params.strandId, 1u);
-  vec3 tipOffset = randomizeStrandPos(params.instanceId, params.strandId, 2u);
   rootOffset *= params.followHairSpreadRoot;
+  if (params.followHairMode == FOLLOW_HAIR_MODE_CLUMPED) {
+    // 0 at root, `clumpStrength` at tip
+    float clump = pow(1.0 - fractionOfStrand, params.followHairClumpShape) * params.followHairClumpStrength;
+    return rootOffset * (1.0 - clump);
+  }
+
+  vec3 tipOffset = randomizeStrandPos(params.instanceId, params.strandId, 2u);
   tipOffset *= params.followHairSpreadTip;
   return mix(tipOffset, rootOffset, fractionOfStrand);
 
@@ -120,6 +159,93 @@ vec3 getFollowHairDisplacement (
 }
 
 
+/** Random barycentric weights for guide strand and 2 of its neighbours */
+vec3 getInterpolationWeights(uint instanceId, uint strandId) {
+  vec3 w = hash(vec3(float(instanceId), float(strandId), 3.0)) + 0.05;
+  return w / (w.x + w.y + w.z);
+}
+
+/**
+ * Follow hair placed between guide strand and 2 of its nearest guide strands.
+ * Each follow hair instance uses different pair of neighbours.
+ * Both position and tangent are in model space.
+ */
+vec3 getInterpolatedFollowHair(TressFXParams params, uint index, inout vec3 tangent) {
+  uint vertexInStrandId = index % uint(u_numVerticesPerStrand);
+  uvec4 neighbours = u_followHairNeighbours[params.strandId];
+  uint n0 = neighbours[params.instanceId % 4u];
+  uint n1 = neighbours[(params.instanceId + 1u) % 4u];
+  uint index0 = n0 * uint(u_numVerticesPerStrand) + vertexInStrandId;
+  uint index1 = n1 * uint(u_numVerticesPerStrand) + vertexInStrandId;
+
+  vec3 w = getInterpolationWeights(params.instanceId, params.strandId);
+  tangent = w.x * tangent + w.y * getTangent(index0).xyz + w.z * getTangent(index1).xyz;
+  return w.x * getPosition(index).xyz +
+         w.y * getPosition(index0).xyz +
+         w.z * getPosition(index1).xyz;
+}
+
+
+/**
+ * Randomly shorten the strand. Vertices past the cut collapse into the last kept vertex,
+ * which produces degenerate (invisible) triangles.
+ */
+uint applyLengthVariation(TressFXParams params, uint index) {
+  if (params.lengthVariation <= 0.0) {
+    return index;
+  }
+
+  uint vertsPerStrand = uint(u_numVerticesPerStrand);
+  uint vertexInStrandId = index % vertsPerStrand;
+  float rng = hash(vec3(float(params.instanceId), float(params.strandId), 4.0)).x;
+  float keepFraction = 1.0 - rng * params.lengthVariation;
+  uint lastVertex = max(1u, uint(float(vertsPerStrand - 1u) * keepFraction));
+  return index - vertexInStrandId + min(vertexInStrandId, lastVertex);
+}
+
+/** Curl (helix around the tangent) and frizz (per-vertex noise). Both are 0 at the root. */
+vec3 getStrandModifiersOffset(
+  TressFXParams params, uint index, float fractionOfStrand, vec3 tangent
+) {
+  float alongStrand = 1.0 - fractionOfStrand; // 0 := root, 1 := tip
+  vec3 offset = vec3(0.0);
+
+  if (params.curlRadius > 0.0) {
+    vec3 up = abs(tangent.y) < 0.99 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+    vec3 n = safeNormalize(cross(tangent, up));
+    vec3 b = cross(tangent, n);
+    float phaseOffset = hash(vec3(float(params.instanceId), float(params.strandId), 5.0)).x;
+    float phase = 2.0 * PI * (params.curlFrequency * alongStrand + phaseOffset);
+    float rootFadeIn = smoothstep(0.0, 0.1, alongStrand); // keep the root in place
+    offset += (cos(phase) * n + sin(phase) * b) * params.curlRadius * rootFadeIn;
+  }
+
+  if (params.frizz > 0.0) {
+    uint vertexInStrandId = index % uint(u_numVerticesPerStrand);
+    vec3 seed = vec3(float(params.instanceId), float(params.strandId), float(vertexInStrandId) + 6.0);
+    offset += to_neg1_1(hash(seed)) * params.frizz * alongStrand * alongStrand;
+  }
+
+  return offset;
+}
+
+
+/**
+ * LOD draws `ceil(lodFollowHairs)` instances. Last instance is thinned
+ * by the fractional part, so hairs fade in/out instead of popping.
+ */
+float getLodInstanceFade(TressFXParams params) {
+  return clamp(params.lodFollowHairs - float(params.instanceId), 0.0, 1.0);
+}
+
+/** Width of the strand on screen. Does not include `EXPAND_PIXELS_FACTOR` */
+float getProjectedWidthPixels(TressFXParams params, vec3 v, vec3 thicknessVector) {
+  vec4 p0 = params.viewProjMat * vec4(v - thicknessVector, 1.0);
+  vec4 p1 = params.viewProjMat * vec4(v + thicknessVector, 1.0);
+  vec2 ndcDelta = p1.xy / p1.w - p0.xy / p0.w;
+  return length(ndcDelta * 0.5 * params.viewportSize);
+}
+
 // https://github.com/GPUOpen-Effects/TressFX/blob/ba0bdacdfb964e38522fda812bf23169bc5fa603/src/Shaders/TressFXStrands.hlsl#L82
 TressFXVertex getExpandedTressFXVert(TressFXParams params) {
   // Access the current line segment
@@ -129,18 +255,23 @@ TressFXVertex getExpandedTressFXVert(TressFXParams params) {
   // And by 'left' and 'right' we mean according to normal&tangent.
   // And by normal we mean (hair_pos - camera_pos)
   uint index = params.vertexId / 2u;  // vertexId is actually the indexed vertex id when indexed triangles are used
+  index = applyLengthVariation(params, index);
 
   // Get updated positions and tangents from simulation result
   vec3 v = getPosition(index).xyz;
   vec3 t = getTangent(index).xyz;
+  if (params.followHairMode == FOLLOW_HAIR_MODE_INTERPOLATED && params.instanceId > 0u) {
+    v = getInterpolatedFollowHair(params, index, t);
+  }
   v = (params.modelMat * vec4(v, 1.0)).xyz; // transform to world space
   t = normalize(t); // not needed for cross, but useful for debugging
 
   // Get hair strand thickness
   float fractionOfStrand = getVertexInStrandPercentage(index); // 1 := root, 0 := tip
-  float ratio = mix(params.thinTip, 1.0, fractionOfStrand);
+  float ratio = mix(params.thinTip, 1.0, fractionOfStrand) * getLodInstanceFade(params);
 
   v += getFollowHairDisplacement(params, fractionOfStrand, t);
+  v += getStrandModifiersOffset(params, index, fractionOfStrand, t);
 
   // Calculate right and projected right vectors
   vec3 towardsCamera = safeNormalize(v - params.eye);
@@ -154,6 +285,16 @@ TressFXVertex getExpandedTressFXVert(TressFXParams params) {
   // Calculate the negative and positive offset world space positions
   vec4 hairEdgePositions[2]; // 0 is for odd vertexId, 1 is positive even vertexId
   vec3 thicknessVector = right * ratio * params.fiberRadius;
+
+  // Sub-pixel strands shimmer. Widen them and lower coverage, so apparent density stays the same
+  float widthCoverage = 1.0;
+  if (params.minPixelWidth > 0.0) {
+    float widthPx = getProjectedWidthPixels(params, v, thicknessVector);
+    if (widthPx > 0.0 && widthPx < params.minPixelWidth) {
+      widthCoverage = widthPx / params.minPixelWidth;
+      thicknessVector /= widthCoverage;
+    }
+  }
   hairEdgePositions[0] = vec4(v - thicknessVector, 1.0); // position 'left'
   hairEdgePositions[1] = vec4(v + thicknessVector, 1.0); // position 'right'
 
@@ -164,6 +305,7 @@ TressFXVertex getExpandedTressFXVert(TressFXParams params) {
   result.position = params.viewProjMat * result.positionWorldSpace;
   result.tangent = t;
   result.vertexRootToTipFactor = fractionOfStrand;
+  result.widthCoverage = widthCoverage;
   result.normal = calculateHairNormal(result.positionWorldSpace.xyz);
 
   // some additional fixing
@@ -0,0 +1,17 @@
+#version 450
+precision highp float;
+precision highp int;
+
+layout(location = 0) in vec4 v_positionClip;
+layout(location = 1) in vec4 v_prevPositionClip;
+
+layout(location = 0) out vec2 outVelocity;
+
+#pragma include ../_config_ubo;
+#pragma include ../_velocity;
+
+
+void main() {
+  // depth is written automatically
+  outVelocity = calculateVelocity(v_positionClip, v_prevPositionClip);
+}
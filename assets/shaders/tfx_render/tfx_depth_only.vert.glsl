@@ -6,10 +6,18 @@ precision highp int;
 #pragma include ../_utils;
 #pragma include ./_tfx_params_ubo;
 #pragma include ./_tfx_vertex_resolve;
+#pragma include ./_tfx_velocity;
+
+
+layout(location = 0) out vec4 v_positionClip;
+layout(location = 1) out vec4 v_prevPositionClip;
 
 
 void main() {
   TressFXParams tfxParams = createTfxParams();
   TressFXVertex tressfxVert = getExpandedTressFXVert(tfxParams);
   gl_Position = tressfxVert.position;
+
+  v_positionClip = tfxParams.viewProjMat * tressfxVert.positionWorldSpace;
+  v_prevPositionClip = getPrevPositionClip(tfxParams, tressfxVert);
 }
@@ -5,7 +5,7 @@ precision highp usampler2D;
 // precision highp sampler2D;
 
 
-layout(binding = 4) uniform sampler2D u_directionalShadowDepthTex;
+layout(binding = 4) uniform sampler2D u_shadowAtlasTex;
 layout(binding = 5) uniform sampler2D u_aoTex;
 
 
@@ -14,17 +14,21 @@ layout(location = 1) in float v_vertexRootToTipFactor;
 layout(location = 2) in vec3 v_position;
 layout(location = 3) in vec3 v_normal;
 layout(location = 4) in vec3 v_tangent;
-layout(location = 5) in vec4 v_positionLightShadowSpace;
-layout(location = 6) flat in uint v_strandId;
+layout(location = 5) flat in uint v_strandId;
+layout(location = 6) in float v_widthCoverage;
+layout(location = 7) in vec4 v_positionClip;
+layout(location = 8) in vec4 v_prevPositionClip;
 
 layout(location = 0) out vec4 outColor1;
 layout(location = 1) out uvec4 outColor2;
+layout(location = 2) out vec2 outVelocity;
 
 
 #pragma include ../_config_ubo;
 #pragma include ../_utils;
+#pragma include ../_velocity;
 #pragma include ./_tfx_params_ubo;
-#pragma include ../materials/_hair;
+#pragma include ../materials/_hair; // lights at binding 8
 
 const int TFX_DISPLAY_MODE_FINAL = 0;
 const int TFX_DISPLAY_MODE_FLAT = 1;
@@ -56,13 +60,14 @@ vec3 getColorFromInstance (int instanceId) {
 
 
 
-vec4 debugModeOverride(vec3 shadingResult, float shadow){
+vec4 debugModeOverride(vec3 shadingResult){
   vec3 result = vec3(0);
   float mixFac = 1;
 
   // global debug mode
   switch (u_displayMode) {
     case DISPLAY_MODE_SHADOW_MAP: {
+      float shadow = calculateHairShadowDebug(u_shadowAtlasTex, v_position, v_normal);
       float shadow2 = 1.0 - shadow;
       return vec4(shadow2,shadow2,shadow2, 1);
     }
@@ -96,25 +101,26 @@ vec4 debugModeOverride(vec3 shadingResult, float shadow){
 }
 
 void main() {
-  Light lights[3];
-  lights[0] = unpackLight(u_light0_Position, u_light0_Color);
-  lights[1] = unpackLight(u_light1_Position, u_light1_Color);
-  lights[2] = unpackLight(u_light2_Position, u_light2_Color);
+  // Strand was widened to min. pixel width. No transparency here, so dither instead
+  if (v_widthCoverage < 1.0) {
+    float rng = hash(vec3(gl_FragCoord.xy, float(v_strandId))).x;
+    if (rng > v_widthCoverage) {
+      discard;
+    }
+  }
 
   float ao = calculateHairAO(u_aoTex);
-  float shadow = calculateHairShadow(
-    u_directionalShadowDepthTex,
-    v_position.xyz,
-    normalize(v_normal),
-    v_positionLightShadowSpace
-  );
   vec3 result = doHairShading(
-    lights, ao, shadow,
+    u_shadowAtlasTex, ao,
     v_position, normalize(v_normal), normalize(v_tangent)
   );
 
-  vec4 colorDebug = debugModeOverride(result, shadow);
+  vec4 colorDebug = debugModeOverride(result);
   result = mix(result, colorDebug.rgb, colorDebug.a);
+  if (isSelectedStrand(v_hairInstanceId, v_strandId)) {
+    result = SELECTED_STRAND_COLOR;
+  }
   outColor1 = vec4(result, 1.0);
   outColor2 = uvec4(packNormal(v_normal), 255);
+  outVelocity = calculateVelocity(v_positionClip, v_prevPositionClip);
 }
\ No newline at end of file
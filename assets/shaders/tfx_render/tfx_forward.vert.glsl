@@ -6,6 +6,7 @@ precision highp int;
 #pragma include ../_utils;
 #pragma include ./_tfx_params_ubo;
 #pragma include ./_tfx_vertex_resolve;
+#pragma include ./_tfx_velocity;
 
 
 layout(location = 0) flat out int v_hairInstanceId;
@@ -13,8 +14,10 @@ layout(location = 1) out float v_vertexRootToTipFactor;
 layout(location = 2) out vec3 v_position;
 layout(location = 3) out vec3 v_normal;
 layout(location = 4) out vec3 v_tangent;
-layout(location = 5) out vec4 v_positionLightShadowSpace;
-layout(location = 6) flat out uint v_strandId;
+layout(location = 5) flat out uint v_strandId;
+layout(location = 6) out float v_widthCoverage;
+layout(location = 7) out vec4 v_positionClip;
+layout(location = 8) out vec4 v_prevPositionClip;
 
 
 void main() {
@@ -25,9 +28,11 @@ void main() {
 
   v_hairInstanceId = gl_InstanceIndex;
   v_vertexRootToTipFactor = tressfxVert.vertexRootToTipFactor;
-  v_positionLightShadowSpace = u_directionalShadowMatrix_VP * tressfxVert.positionWorldSpace;
   v_position = tressfxVert.positionWorldSpace.xyz;
   v_normal = tressfxVert.normal;
   v_tangent = tressfxVert.tangent;
   v_strandId = tfxParams.strandId;
+  v_widthCoverage = tressfxVert.widthCoverage;
+  v_positionClip = tfxParams.viewProjMat * tressfxVert.positionWorldSpace;
+  v_prevPositionClip = getPrevPositionClip(tfxParams, tressfxVert);
 }
@@ -6,6 +6,7 @@
 
 #pragma include ../_config_ubo;
 #pragma include ../_utils;
+#pragma include ./_tfx_params_ubo;
 #pragma include _tfx_ppll_shared;
 // #pragma include TressFXRendering.coverage;
 
@@ -15,6 +16,8 @@ layout(location = 2) in vec3 v_position;
 layout(location = 3) in vec3 v_normal;
 layout(location = 4) in vec3 v_tangent;
 layout(location = 5) in vec4 v_p0p1;
+layout(location = 6) flat in uint v_strandId;
+layout(location = 7) in float v_widthCoverage;
 
 // NOTE: very important
 // Force early depth tests
@@ -94,7 +97,9 @@ void main () {
 			v_position.z, // depth
 			v_tangent.xyz, // tangent
 			coverage, // coverage
-			v_position.xyz // positionWorldSpace
+			v_widthCoverage, // widthCoverage
+			v_position.xyz, // positionWorldSpace
+			isSelectedStrand(v_hairInstanceId, v_strandId) ? PPLL_FLAG_SELECTED_STRAND : 0u
 		);
 	}
 }
@@ -14,6 +14,8 @@ layout(location = 2) out vec3 v_position;
 layout(location = 3) out vec3 v_normal;
 layout(location = 4) out vec3 v_tangent;
 layout(location = 5) out vec4 v_p0p1;
+layout(location = 6) flat out uint v_strandId;
+layout(location = 7) out float v_widthCoverage;
 
 // TBH this shader is moslty same as 'tfx_forward.vert.glsl'
 void main() {
@@ -28,4 +30,6 @@ void main() {
   v_normal = tressfxVert.normal;
   v_tangent = tressfxVert.tangent;
   v_p0p1 = tressfxVert.p0p1;
+  v_strandId = tfxParams.strandId;
+  v_widthCoverage = tressfxVert.widthCoverage;
 }
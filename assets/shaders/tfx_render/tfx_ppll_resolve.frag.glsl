@@ -1,7 +1,10 @@
 #version 450
 
-// Closest pixels are put in special buffer and have larger weight on the outcome
-#define KBUFFER_SIZE 4
+// Closest pixels are put in special buffer and have larger weight on the outcome.
+// Set from `TfxPpllResolvePass`, see `Config::PPLL_KBUFFER_SIZES`
+layout(constant_id = 0) const int KBUFFER_SIZE = 4;
+// How to shade fragments that did not fit into the KBUFFER. See `PPLL_TAIL_MODE_*`
+layout(constant_id = 1) const int PPLL_TAIL_MODE = 0;
 // Max entries per pixel in ppll data list
 #define MAX_FRAGMENTS 512
 const int PPLL_DISPLAY_MODE_FINAL = 0;
@@ -9,6 +12,7 @@ const int PPLL_DISPLAY_MODE_FLAT = 1;
 const int PPLL_DISPLAY_MODE_OVERLAP = 2;
 const int PPLL_DISPLAY_MODE_TANGENTS = 3;
 const int PPLL_DISPLAY_MODE_COVERAGE = 4;
+const int PPLL_DISPLAY_MODE_LIST_LENGTH = 5;
 
 
 // includes
@@ -19,6 +23,7 @@ const int PPLL_DISPLAY_MODE_COVERAGE = 4;
 #define PPLL_DATA_BUFFER_BINDING 2
 #pragma include _tfx_ppll_shared;
 #pragma include ./_tfx_params_ubo; // binding 3
+#define BINDING_INDEX_LIGHTS_SSBO 6
 #pragma include ../materials/_hair;
 
 // intra-shader stuff
@@ -26,7 +31,7 @@ layout(location = 0) out vec4 outColor1;
 layout(location = 1) out uvec4 outColor2;
 
 layout(binding = 4) uniform sampler2D u_aoTex;
-layout(binding = 5) uniform sampler2D u_directionalShadowDepthTex;
+layout(binding = 5) uniform sampler2D u_shadowAtlasTex;
 
 
 layout(early_fragment_tests) in; // [earlydepthstencil]
@@ -35,54 +40,50 @@ layout(early_fragment_tests) in; // [earlydepthstencil]
 
 ///////////////////////
 // TressFX Shading
-#pragma include ../materials/_material; // for light struct
 
 float PrecalcAmbientOcclusion;
-Light GlobalLightsArray[3];
 
 struct PPLLFragmentData {
   vec4 tangentAndCoverage;
   vec3 positionWorldSpace;
   float depth;
+  uint flags; // PPLL_FLAG_*
+  float widthCoverage; // < 1.0 if strand was widened to min. pixel width
 };
 
-float calculateShadowForPPLLFragment(inout PPLLFragmentData frag, vec3 normal) {
-  vec4 positionShadowProjected = u_directionalShadowMatrix_VP * vec4(frag.positionWorldSpace, 1);
-  return calculateHairShadow (
-    u_directionalShadowDepthTex,
-    frag.positionWorldSpace,
-    normal,
-    positionShadowProjected
-  );
-}
-
 vec4 tfxCalculateFarFragmentsColor(vec2 pixelCoord, inout PPLLFragmentData frag) {
-  float coverage = frag.tangentAndCoverage.w;
+  if ((frag.flags & PPLL_FLAG_SELECTED_STRAND) != 0u) {
+    return vec4(SELECTED_STRAND_COLOR, 1);
+  }
+  float coverage = frag.tangentAndCoverage.w * frag.widthCoverage;
   if (u_tfxDisplayMode == PPLL_DISPLAY_MODE_COVERAGE) {
     return vec4(coverage,coverage,coverage, 1);
   }
-  return vec4(TfxParamsUbo.u_albedo.rgb, u_tfxOpacity);
+  return vec4(TfxParamsUbo.u_albedo.rgb, u_tfxOpacity * frag.widthCoverage);
 }
 
 vec4 tfxCalculateCloseFragmentsColor(vec2 pixelCoord, inout PPLLFragmentData frag) {
   // return vec4(TfxParamsUbo.u_albedo.rgb, u_tfxOpacity);
   vec3 positionWorld = frag.positionWorldSpace;
-  float coverage = frag.tangentAndCoverage.w;
+  float coverage = frag.tangentAndCoverage.w * frag.widthCoverage;
   vec3 tangent = frag.tangentAndCoverage.xyz;
   vec3 normal = calculateHairNormal(positionWorld.xyz);
 
   if (u_tfxDisplayMode == PPLL_DISPLAY_MODE_COVERAGE) {
     return vec4(coverage,coverage,coverage, 1);
   }
+  if ((frag.flags & PPLL_FLAG_SELECTED_STRAND) != 0u) {
+    return vec4(SELECTED_STRAND_COLOR, 1);
+  }
 
   float ao = PrecalcAmbientOcclusion;
-  float shadow = calculateShadowForPPLLFragment(frag, normal); // TODO [LOW] can be expensive, though only for `KBUFFER_SIZE`, so not *that* bad?
+  // TODO [LOW] shadows can be expensive, though only for `KBUFFER_SIZE`, so not *that* bad?
   vec3 result = doHairShading(
-    GlobalLightsArray, ao, shadow,
+    u_shadowAtlasTex, ao,
     positionWorld, normal, tangent
   );
 
-  return vec4(result, u_tfxOpacity);
+  return vec4(result, u_tfxOpacity * frag.widthCoverage);
 }
 
 #define TFX_SHADING_FAR_FN tfxCalculateFarFragmentsColor
@@ -95,13 +96,11 @@ vec4 tfxCalculateCloseFragmentsColor(vec2 pixelCoord, inout PPLLFragmentData fra
 ///////////////////////
 // fwd decl.
 vec3 getDebugColorForPpllDepth();
+vec3 getDebugColorForListLength();
 vec4 debugModeOverride(vec3 shadingResult, inout PPLLFragmentData closestFragment, vec3 normal);
 
 
 void main () {
-  GlobalLightsArray[0] = unpackLight(u_light0_Position, u_light0_Color);
-  GlobalLightsArray[1] = unpackLight(u_light1_Position, u_light1_Color);
-  GlobalLightsArray[2] = unpackLight(u_light2_Position, u_light2_Color);
   // shared value based on last-frame's closest fragment
   PrecalcAmbientOcclusion = calculateHairAO(u_aoTex);
 
@@ -128,7 +127,9 @@ vec4 debugModeOverride(vec3 shadingResult, inout PPLLFragmentData closestFragmen
   // global debug mode
   switch (u_displayMode) {
     case DISPLAY_MODE_SHADOW_MAP: {
-      float shadow = 1 - calculateShadowForPPLLFragment(closestFragment, normal);
+      float shadow = 1 - calculateHairShadowDebug(
+        u_shadowAtlasTex, closestFragment.positionWorldSpace, normal
+      );
       return vec4(shadow,shadow,shadow, 1);
     }
   }
@@ -139,6 +140,10 @@ vec4 debugModeOverride(vec3 shadingResult, inout PPLLFragmentData closestFragmen
       result = getDebugColorForPpllDepth();
       break;
     }
+    case PPLL_DISPLAY_MODE_LIST_LENGTH: {
+      result = getDebugColorForListLength();
+      break;
+    }
     case PPLL_DISPLAY_MODE_FLAT: {
       result = debugHairFlatColor();
       break;
@@ -181,4 +186,25 @@ vec3 getDebugColorForPpllDepth() {
   uint depth = countListNodesForPixel(gl_FragCoord.xy);
   float fac = clamp(float(depth) / MAX_DEBUG_LIST_DEPTH, 0, 1);
   return mix(ColorFragmentsZero, ColorFragmentsFull, fac);
-}
\ No newline at end of file
+}
+
+/**
+ * Green - all fragments fit into KBUFFER (full quality).
+ * Yellow to red - fragments that are shaded according to `PPLL_TAIL_MODE`.
+ * Red when the list is 4x longer than KBUFFER.
+ */
+vec3 getDebugColorForListLength() {
+  const vec3 ColorFitsKBuffer = vec3(0,1,0);
+  const vec3 ColorTailShort = vec3(1,1,0);
+  const vec3 ColorTailLong = vec3(1,0,0);
+
+  uint depth = countListNodesForPixel(gl_FragCoord.xy);
+  uint kBufferSize = uint(KBUFFER_SIZE);
+  if (depth <= kBufferSize) {
+    float fac = float(depth) / float(KBUFFER_SIZE);
+    return ColorFitsKBuffer * mix(0.3, 1.0, fac);
+  }
+  float tailLength = float(depth - kBufferSize);
+  float fac = clamp(tailLength / float(3 * KBUFFER_SIZE), 0, 1);
+  return mix(ColorTailShort, ColorTailLong, fac);
+}
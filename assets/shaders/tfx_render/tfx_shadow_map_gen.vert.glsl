@@ -8,7 +8,7 @@ precision highp int;
 #pragma include ./_tfx_vertex_resolve;
 
 layout(push_constant) uniform Constants {
-  mat4 u_MVP;
+  mat4 u_MVP; // hair is in world space, so this is only view-projection of the atlas slot
   vec4 u_shadowCameraPosition; // [cameraPosition.xyz, u_fiberRadius]
   vec4 u_shadowViewport;
 };
@@ -16,9 +16,10 @@ layout(push_constant) uniform Constants {
 void main() {
   TressFXParams tfxParams = createTfxParams();
   tfxParams.eye = u_shadowCameraPosition.xyz;
-  tfxParams.viewProjMat = u_directionalShadowMatrix_VP;
+  tfxParams.viewProjMat = u_MVP;
   tfxParams.viewportSize = u_shadowViewport.xy;
   tfxParams.fiberRadius = u_fiberRadius * u_shadowCameraPosition.w;
+  tfxParams.minPixelWidth = 0.0; // shadow map has its own resolution
   
   TressFXVertex tressfxVert = getExpandedTressFXVert(tfxParams);
 
@@ -0,0 +1,78 @@
+#version 450
+precision highp float;
+precision highp int;
+precision highp usampler2D;
+
+
+layout(binding = 4) uniform sampler2D u_shadowAtlasTex;
+layout(binding = 5) uniform sampler2D u_aoTex;
+
+
+layout(location = 0) flat in int v_hairInstanceId;
+layout(location = 1) in float v_vertexRootToTipFactor;
+layout(location = 2) in vec3 v_position;
+layout(location = 3) in vec3 v_normal;
+layout(location = 4) in vec3 v_tangent;
+layout(location = 5) flat in uint v_strandId;
+layout(location = 6) in float v_widthCoverage;
+
+layout(location = 0) out vec4 outAccumulation; // [premultiplied color * weight, alpha * weight]
+layout(location = 1) out vec4 outRevealage; // .r only
+layout(location = 2) out uvec4 outNormals;
+
+
+#pragma include ../_config_ubo;
+#pragma include ../_utils;
+#pragma include ./_tfx_params_ubo;
+#pragma include ../materials/_hair; // lights at binding 8
+
+const int WBOIT_DISPLAY_MODE_FLAT = 1;
+
+
+/**
+ * Weighted Blended Order-Independent Transparency, eq. 9.
+ * Closer fragments get larger weight.
+ * http://jcgt.org/published/0002/02/09/
+ */
+float getWboitWeight(float depthViewSpace, float alpha) {
+  float z = abs(depthViewSpace);
+  float depthFalloff = 10.0 / (1e-5 + pow(z / 5.0, 2.0) + pow(z / 200.0, 6.0));
+  return alpha * clamp(depthFalloff, 1e-2, 3e3);
+}
+
+vec3 shadeHairFragment() {
+  float ao = calculateHairAO(u_aoTex);
+
+  // global debug mode
+  if (u_displayMode == DISPLAY_MODE_SHADOW_MAP) {
+    float shadow = calculateHairShadowDebug(u_shadowAtlasTex, v_position, v_normal);
+    float shadow2 = 1.0 - shadow;
+    return vec3(shadow2, shadow2, shadow2);
+  }
+  // hair debug mode
+  if (u_tfxDisplayMode == WBOIT_DISPLAY_MODE_FLAT) {
+    return debugHairFlatColor();
+  }
+
+  return doHairShading(
+    u_shadowAtlasTex, ao,
+    v_position, normalize(v_normal), normalize(v_tangent)
+  );
+}
+
+void main() {
+  vec3 color = shadeHairFragment();
+  if (isSelectedStrand(v_hairInstanceId, v_strandId)) {
+    color = SELECTED_STRAND_COLOR;
+  }
+  // Strand was widened to min. pixel width - lower opacity to compensate
+  float alpha = clamp(u_tfxOpacity * v_widthCoverage, 0.0, 1.0);
+
+  vec4 positionViewSpace = u_viewMat * vec4(v_position, 1.0);
+  float weight = getWboitWeight(positionViewSpace.z, alpha);
+
+  outAccumulation = vec4(color * alpha, alpha) * weight;
+  outRevealage = vec4(alpha);
+  // Depth write is disabled, so this is the last drawn fragment, not the closest one
+  outNormals = uvec4(packNormal(v_normal), 255);
+}
@@ -0,0 +1,63 @@
+#version 450
+precision highp float;
+precision highp int;
+
+const int WBOIT_DISPLAY_MODE_FINAL = 0;
+const int WBOIT_DISPLAY_MODE_FLAT = 1;
+const int WBOIT_DISPLAY_MODE_REVEALAGE = 2;
+const int WBOIT_DISPLAY_MODE_WEIGHTS = 3;
+
+
+#pragma include ../_config_ubo;
+#pragma include ../_utils;
+
+layout(binding = 1) uniform sampler2D u_accumulationTex;
+layout(binding = 2) uniform sampler2D u_revealageTex;
+
+layout(location = 0) out vec4 outColor1;
+layout(location = 1) out uvec4 outColor2; // normals - write mask disabled
+
+
+// discard pixels that do not pass stencil test
+layout(early_fragment_tests) in;
+
+
+vec3 getDebugColorForWeights(float weightSum) {
+  // weights are in [0.01, 3000] range per fragment, see `getWboitWeight()`
+  const float MAX_DEBUG_WEIGHT = 3000.0;
+  const vec3 ColorWeightZero = vec3(0,0,1);
+  const vec3 ColorWeightFull = vec3(1,0,0);
+
+  float fac = clamp(log(1.0 + weightSum) / log(1.0 + MAX_DEBUG_WEIGHT), 0, 1);
+  return mix(ColorWeightZero, ColorWeightFull, fac);
+}
+
+void main() {
+  ivec2 pixelCoord = ivec2(gl_FragCoord.xy);
+  vec4 accumulation = texelFetch(u_accumulationTex, pixelCoord, 0);
+  float revealage = texelFetch(u_revealageTex, pixelCoord, 0).r;
+  float alpha = 1.0 - revealage;
+
+  // Prevent overflow of the 16 bit float
+  if (isinf(max3(abs(accumulation.rgb)))) {
+    accumulation.rgb = vec3(accumulation.a);
+  }
+  vec3 averageColor = accumulation.rgb / max(accumulation.a, 1e-5);
+
+  switch (u_tfxDisplayMode) {
+    case WBOIT_DISPLAY_MODE_REVEALAGE: {
+      outColor1 = vec4(alpha, alpha, alpha, 1.0);
+      break;
+    }
+    case WBOIT_DISPLAY_MODE_WEIGHTS: {
+      outColor1 = vec4(getDebugColorForWeights(accumulation.a), 1.0);
+      break;
+    }
+    default: {
+      // Blend mode: `src * src.a + dst * (1 - src.a)`
+      outColor1 = vec4(averageColor, alpha);
+      break;
+    }
+  }
+  outColor2 = uvec4(0);
+}
@@ -43,3 +43,27 @@ readonly buffer g_InitialHairPositionsBuffer { // StructuredBuffer<vec4> g_Initi
   vec4 g_InitialHairPositions[];
 };
 #endif
+
+#ifdef BINDING_INDEX_VERTEX_WEIGHTS
+layout(std430, binding=BINDING_INDEX_VERTEX_WEIGHTS)
+readonly buffer g_HairVertexWeightsBuffer {
+  vec4 g_HairVertexWeights[]; // [global stiffness multiplier, local stiffness multiplier, -, pinned]
+};
+
+float GetGlobalStiffnessMultiplier(uint vertexId_global) {
+  return g_HairVertexWeights[vertexId_global].x;
+}
+
+float GetLocalStiffnessMultiplier(uint vertexId_global) {
+  return g_HairVertexWeights[vertexId_global].y;
+}
+
+// Pinned vertices keep their initial position e.g. hair ties
+bool IsPinned(uint vertexId_global) {
+  return g_HairVertexWeights[vertexId_global].w > 0.5; // it can be only 0.0 or 1.0, so 0.5 just in case
+}
+
+bool IsMovable(uint vertexInStrandId, uint vertexId_global) {
+  return IsMovable(vertexInStrandId) && !IsPinned(vertexId_global);
+}
+#endif
@@ -1,4 +1,5 @@
 #pragma include ../_config_ubo;
+#pragma include ../tfx_render/_tfx_params_ubo;
 
 //
 // Constants
@@ -14,10 +15,11 @@ const int NUM_COLLISION_CAPSULES = 4;
 //
 // Uniforms
 
-#define g_GravityMagnitude (u_tfxHairSettings.z)
 #define g_TimeStep (u_tfxHairSettings.w)
-#define g_Wind (u_tfxWind)
-#define g_LengthStiffness (u_tfxConstraints.y)
+// per-object simulation settings
+#define g_GravityMagnitude (TfxParamsUbo.u_simConstraints.z)
+#define g_Wind (TfxParamsUbo.u_simWind)
+#define g_LengthStiffness (TfxParamsUbo.u_simConstraints.y)
 
 
 
@@ -30,7 +32,7 @@ const int NUM_COLLISION_CAPSULES = 4;
 //   * if damping == 0, then delta affects outcome (seemingly another acceleration)
 //   * if damping == 1, then delta is nullified and verlet only calculates
 //       basing on forces/gravity
-float GetDamping() { return u_tfxShape.x; }
+float GetDamping() { return TfxParamsUbo.u_simShape.x; }
 
 
 //
@@ -41,7 +43,7 @@ float GetDamping() { return u_tfxShape.x; }
 // this, the strands will not move.
 //
 // TL;DR: 'Nudge' final position toward initial position by 'that much'.
-float GetGlobalStiffness() { return u_tfxShape.z; }
+float GetGlobalStiffness() { return TfxParamsUbo.u_simShape.z; }
 
 // AMD:
 // By default, Global Shape Constraints affect only `global_range * vertices_in_strand`
@@ -54,7 +56,7 @@ float GetGlobalStiffness() { return u_tfxShape.z; }
 //
 // TL;DR: If the number is small, the tips will be very 'bouncy' (it will affect only near root).
 // If it's high, the hair will be 'static'.
-float GetGlobalRange() { return u_tfxShape.w; }
+float GetGlobalRange() { return TfxParamsUbo.u_simShape.w; }
 
 
 
@@ -63,7 +65,7 @@ float GetGlobalRange() { return u_tfxShape.w; }
 
 // * stiffness == 0, then no local shape preservation
 // * stiffness == 1, then ignore forces/gravity VSP etc.
-float GetLocalStiffness() { return u_tfxShape.y; }
+float GetLocalStiffness() { return TfxParamsUbo.u_simShape.y; }
 
 
 
@@ -71,5 +73,5 @@ float GetLocalStiffness() { return u_tfxShape.y; }
 // Length Constraints
 
 int GetLengthConstraintIterations() {
-  return max(0, readConfigInt(u_tfxConstraints.x));
+  return max(0, readConfigInt(TfxParamsUbo.u_simConstraints.x));
 }
@@ -5,6 +5,8 @@
 #define BINDING_INDEX_POSITIONS_PREV 2      // START: last (frame-1) positions
 #define BINDING_INDEX_POSITIONS_PREV_PREV 3 // START: before last (frame-2) positions
 #define BINDING_INDEX_POSITIONS_INITIAL 4
+#define BINDING_INDEX_TFX_PARAMS_UBO 5
+#define BINDING_INDEX_VERTEX_WEIGHTS 6
 
 #pragma include ./_sim_params;
 #pragma include ./_sim_common;
@@ -56,7 +58,7 @@ void main() {
 
   // Integrate
   vec4 force = vec4(0, 0, 0, 0);
-  bool isMoveable = IsMovable(vertData.vertexId);
+  bool isMoveable = IsMovable(vertData.vertexId, vertData.vertexId_global);
   if (isMoveable){
     float damping = GetDamping(); // 1.0f;
     nextPosition = Integrate(
@@ -66,7 +68,8 @@ void main() {
 
   
   // Global Shape Constraints
-  float stiffnessForGlobalShapeMatching = GetGlobalStiffness();
+  float stiffnessForGlobalShapeMatching = GetGlobalStiffness() * GetGlobalStiffnessMultiplier(vertData.vertexId_global);
+  stiffnessForGlobalShapeMatching = min(stiffnessForGlobalShapeMatching, 1.0f);
   float globalShapeMatchingEffectiveRange = GetGlobalRange();
   bool closeToRoot = float(vertData.vertexId) < globalShapeMatchingEffectiveRange * float(numVerticesInTheStrand);
   // float linearDecr = 1.0 - float(vertData.vertexId) / float(numVerticesInTheStrand); // some kind of smoothstep?
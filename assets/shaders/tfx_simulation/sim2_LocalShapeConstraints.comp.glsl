@@ -3,6 +3,8 @@
 
 #define BINDING_INDEX_POSITIONS 1
 #define BINDING_INDEX_POSITIONS_INITIAL 2
+#define BINDING_INDEX_TFX_PARAMS_UBO 3
+#define BINDING_INDEX_VERTEX_WEIGHTS 4
 
 #pragma include ./_sim_params;
 #pragma include ./_sim_common;
@@ -29,8 +31,6 @@ void main() {
 
   // stiffness for local shape constraints
   float stiffnessForLocalShapeMatching = GetLocalStiffness();
-  // 1.0 for stiffness makes things unstable sometimes.
-  stiffnessForLocalShapeMatching = min(stiffnessForLocalShapeMatching, 0.95f);
 
   // Local shape constraint for bending/twisting
   vec4 pos_prev = g_HairVertexPositions[globalRootVertexIndex];
@@ -43,17 +43,20 @@ void main() {
     // pos of previous vertex in strand
     vec4 pos = g_HairVertexPositions[globalVertexIndex];
     vec4 pos_init = g_InitialHairPositions[globalVertexIndex];
+    // per-vertex stiffness. 1.0 for stiffness makes things unstable sometimes.
+    float stiffness = stiffnessForLocalShapeMatching * GetLocalStiffnessMultiplier(globalVertexIndex);
+    stiffness = min(stiffness, 0.95f);
 
     // delta from current_vert -> prev_vert - expected local shape (think curly hair)
     vec4 delta_init = pos_init - pos_init_prev;
     vec4 delta_now = pos - pos_prev;
     vec4 delta_diff = delta_init - delta_now; // delta from now->expected 
     // 0.5 cause we move both current and prev vert
-    delta_diff = stiffnessForLocalShapeMatching * delta_diff * 0.5;
+    delta_diff = stiffness * delta_diff * 0.5;
     
     // apply to go back to local shape
-    if (IsMovable(i)){ pos.xyz += delta_diff.xyz; }
-    if (IsMovable(i - 1)){ pos_prev.xyz -= delta_diff.xyz; }
+    if (IsMovable(i, globalVertexIndex)){ pos.xyz += delta_diff.xyz; }
+    if (IsMovable(i - 1, globalVertexIndex - 1)){ pos_prev.xyz -= delta_diff.xyz; }
 
     // write back
     g_HairVertexPositions[globalVertexIndex].xyz = pos.xyz;
@@ -5,6 +5,8 @@
 #define BINDING_INDEX_POSITIONS_PREV 2
 #define BINDING_INDEX_POSITIONS_INITIAL 3
 #define BINDING_INDEX_TANGENTS 4
+#define BINDING_INDEX_TFX_PARAMS_UBO 5
+#define BINDING_INDEX_VERTEX_WEIGHTS 6
 
 layout(push_constant) uniform Constants {
   vec4 collisionCapsule0;
@@ -14,7 +16,7 @@ layout(push_constant) uniform Constants {
 } u_PushConstants;
 vec4 g_Capsules[4];
 
-// @return true if vec4 from `sharedPos` is movable.
+// @return true if vec4 from `sharedPos` is movable. Includes per-vertex 'pinned' flag.
 bool sharedPosIsMovable(vec4 particle0) {
   return particle0.w > 0.5; // it can be only 0.0 or 1.0, so 0.5 just in case 
 }
@@ -46,8 +48,8 @@ float GetInitalLength(inout PerVertexData vertData, uint numVerticesInTheStrand)
 }
 
 vec2 ConstraintMultiplier(vec4 particle0, vec4 particle1) {
-  bool can_move0 = particle0.w > 0.5; // it can be only 0.0 or 1.0, so 0.5 just in case
-  bool can_move1 = particle1.w > 0.5; // it can be only 0.0 or 1.0, so 0.5 just in case
+  bool can_move0 = sharedPosIsMovable(particle0);
+  bool can_move1 = sharedPosIsMovable(particle1);
   if ( can_move0 &&  can_move1) { return vec2(0.5, 0.5); } // move equally
   if ( can_move0 && !can_move1) { return vec2(1.0, 0.0); } // move only 1st
   if (!can_move0 &&  can_move1) { return vec2(0.0, 1.0); } // move only 2nd
@@ -102,14 +104,14 @@ void main() {
   );
 
   // Copy data into shared memory
-  float isMovable = IsMovable(vertData.vertexId) ? 1.0 : 0.0;
+  float isMovable = IsMovable(vertData.vertexId, vertData.vertexId_global) ? 1.0 : 0.0;
   sharedPos[vertData.localId] = vec4(g_HairVertexPositions[vertData.vertexId_global].xyz, isMovable);
   sharedLength[vertData.localId] = GetInitalLength(vertData, numVerticesInTheStrand);
   GroupMemoryBarrierWithGroupSync();
 
 
   // Wind (proportional to length of the edge between this and next vertex)
-  if (IsMovable(vertData.vertexId)) {
+  if (sharedPosIsMovable(sharedPos[vertData.localId])) {
     uint sharedIndex      = getSharedIndex(vertData.vertexId);
     uint sharedIndex_next = getSharedIndex(vertData.vertexId + 1);
     // vector(next_vertex -> this_vertex), NOT NORMALIZED
@@ -4,8 +4,12 @@ precision highp float;
 precision highp int;
 precision highp usampler2D;
 
+#define BINDING_INDEX_EXPOSURE 2
+
 layout(binding = 1)
 uniform sampler2D u_source;
+layout(binding = 3)
+uniform sampler2D u_bloomTex;
 
 layout(location = 0) in vec2 v_position; // TexCoords
 layout(location = 0) out uvec4 outColor;
@@ -13,9 +17,11 @@ layout(location = 0) out uvec4 outColor;
 
 #pragma include ./_config_ubo;
 #pragma include ./_utils;
+#pragma include ./_auto_exposure;
 #pragma include ./postfx/_dither;
 #pragma include ./postfx/_tonemappers;
 #pragma include ./postfx/_color_grading;
+#pragma include ./postfx/_lens_effects;
 
 const uint TONEMAP_LINEAR = 0;
 const uint TONEMAP_REINHARD = 1;
@@ -36,9 +42,18 @@ vec3 doTonemapping(uint tonemapMode, vec3 hdrColor) {
 }
 
 
+float getExposure() {
+  return u_autoExposureEnabled ? u_autoExposureValue : u_exposure;
+}
+
+
 void main() {
   vec2 pixelTS = fixOpenGLTextureCoords_AxisY(v_position);
-  vec3 colorHDR = texture(u_source, pixelTS).rgb;
+  vec3 colorHDR = sampleWithChromaticAberration(u_source, pixelTS);
+  if (u_bloomIntensity > 0.0) { // bloom texture content is undefined if disabled
+    colorHDR += texture(u_bloomTex, pixelTS).rgb * u_bloomIntensity;
+  }
+  colorHDR *= getExposure();
 
   // do dithering to break up banding
   colorHDR = doDither(colorHDR, u_ditherStrength);
@@ -50,6 +65,8 @@ void main() {
   vec3 colorAfterColorGrading = colorCorrectAll(colorHDR);
 
   vec3 result = doTonemapping(u_tonemappingMode, colorAfterColorGrading);
+  result = applyVignette(result, v_position);
+  result = applyFilmGrain(result);
   result = saturate(result);
 
   float luma = toLuma_fromLinear(result);
@@ -0,0 +1,166 @@
+use glam::{vec3, vec4, Vec3, Vec4};
+use log::warn;
+use std::convert::TryInto;
+
+use crate::scene::TfxShortStrandPolicy;
+
+/// Command line arguments:
+///
+/// - `--hair-asset <file>` - load different hair asset instead of Sintel's. Supports `.tfx`,
+///   `.hair` and `.obj` (polylines). Sintel's collision capsules are still used.
+/// - `--hair-group <file>` - add another simulated hair group (e.g. beard) to the character.
+///   Can be repeated. Has no collision capsules unless provided with `--collision-capsule`.
+/// - `--static-hair-group <file>` - same as `--hair-group`, but without simulation.
+///   Use for short strands like eyebrows or eyelashes.
+/// - `--collision-capsule <x,y,z,radius>` - add collision capsule (model space) to the last
+///   hair group. Up to 4 per group.
+/// - `--center-of-gravity <x,y,z>` - model space point used to calculate normals of the last
+///   hair group. Default: center of the group's bounding sphere.
+/// - `--drop-short-strands` - when importing `.hair` or `.obj`, skip strands with less than
+///   32 points instead of upsampling them.
+/// - `--export-hair <file>` - export hair strands after `--export-after-frames` and close the app.
+///   Format is based on extension: `.tfx`, `.obj` or `.hair`. If there is more than one
+///   TressFX object, the object's name is added to the file name.
+/// - `--export-follow-hairs` - export also procedurally generated follow hairs.
+/// - `--export-after-frames <n>` - let the simulation run for `n` frames before export. Default: 1.
+/// - `--environment-map <file>` - equirectangular Radiance `.hdr` image used for image based lighting
+///   and as a skybox. Replaces the constant ambient light. `.exr` is not supported.
+///   If the file cannot be loaded, the constant ambient light is used.
+pub struct AppCli {
+  pub hair_asset: Option<String>,
+  pub hair_groups: Vec<AppCliHairGroup>,
+  pub drop_short_strands: bool,
+  pub export_hair: Option<String>,
+  pub export_follow_hairs: bool,
+  pub export_after_frames: u64,
+  pub environment_map: Option<String>,
+}
+
+/// Additional hair group from `--hair-group` or `--static-hair-group`
+pub struct AppCliHairGroup {
+  pub file_path: String,
+  pub simulated: bool,
+  /// Model space, `[center.xyz, radius]`
+  pub collision_capsules: Vec<Vec4>,
+  pub center_of_gravity: Option<Vec3>,
+}
+
+impl AppCliHairGroup {
+  /// Same as the simulation shader
+  pub const MAX_COLLISION_CAPSULES: usize = 4;
+}
+
+impl AppCli {
+  pub fn parse() -> Self {
+    let mut result = Self {
+      hair_asset: None,
+      hair_groups: Vec::new(),
+      drop_short_strands: false,
+      export_hair: None,
+      export_follow_hairs: false,
+      export_after_frames: 1,
+      environment_map: None,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "--hair-asset" => result.hair_asset = args.next(),
+        "--hair-group" | "--static-hair-group" => {
+          let file_path = args
+            .next()
+            .unwrap_or_else(|| panic!("Expected file path after {}", arg));
+          result.hair_groups.push(AppCliHairGroup {
+            file_path,
+            simulated: arg == "--hair-group",
+            collision_capsules: Vec::new(),
+            center_of_gravity: None,
+          });
+        }
+        "--collision-capsule" => {
+          let [x, y, z, radius] = parse_floats(&arg, args.next());
+          let group = last_hair_group(&mut result, &arg);
+          assert!(
+            group.collision_capsules.len() < AppCliHairGroup::MAX_COLLISION_CAPSULES,
+            "Hair group '{}' can have at most {} collision capsules",
+            group.file_path,
+            AppCliHairGroup::MAX_COLLISION_CAPSULES
+          );
+          group.collision_capsules.push(vec4(x, y, z, radius));
+        }
+        "--center-of-gravity" => {
+          let [x, y, z] = parse_floats(&arg, args.next());
+          last_hair_group(&mut result, &arg).center_of_gravity = Some(vec3(x, y, z));
+        }
+        "--drop-short-strands" => result.drop_short_strands = true,
+        "--export-hair" => result.export_hair = args.next(),
+        "--export-follow-hairs" => result.export_follow_hairs = true,
+        "--export-after-frames" => {
+          result.export_after_frames = args
+            .next()
+            .and_then(|v| v.parse().ok())
+            .expect("Expected frame count after --export-after-frames")
+        }
+        "--environment-map" => {
+          let file_path = args
+            .next()
+            .unwrap_or_else(|| panic!("Expected file path after {}", arg));
+          assert!(
+            file_path.to_lowercase().ends_with(".hdr"),
+            "Environment map '{}' is not a Radiance .hdr file. OpenEXR is not supported, convert it first",
+            file_path
+          );
+          result.environment_map = Some(file_path);
+        }
+        _ => warn!("Unknown command line argument: '{}'", arg),
+      }
+    }
+
+    result
+  }
+
+  pub fn short_strand_policy(&self) -> TfxShortStrandPolicy {
+    if self.drop_short_strands {
+      TfxShortStrandPolicy::Drop
+    } else {
+      TfxShortStrandPolicy::Upsample
+    }
+  }
+
+  /// Export path for TressFX object. Adds object name if there are many objects.
+  pub fn get_export_path(
+    &self,
+    object_name: &str,
+    object_count: usize,
+  ) -> Option<std::path::PathBuf> {
+    let path = std::path::Path::new(self.export_hair.as_ref()?);
+    if object_count <= 1 {
+      return Some(path.to_path_buf());
+    }
+
+    let stem = path.file_stem()?.to_string_lossy();
+    let ext = path.extension()?.to_string_lossy();
+    Some(path.with_file_name(format!("{}.{}.{}", stem, object_name, ext)))
+  }
+}
+
+fn last_hair_group<'a>(cli: &'a mut AppCli, arg: &str) -> &'a mut AppCliHairGroup {
+  cli
+    .hair_groups
+    .last_mut()
+    .unwrap_or_else(|| panic!("{} requires preceding --hair-group", arg))
+}
+
+/// Comma separated list of exactly `N` floats, e.g. `0,1.5,-2`
+fn parse_floats<const N: usize>(arg: &str, value: Option<String>) -> [f32; N] {
+  let value =
+    value.unwrap_or_else(|| panic!("Expected {} comma separated numbers after {}", N, arg));
+  let numbers: Vec<f32> = value
+    .split(',')
+    .map(|v| v.trim().parse().ok())
+    .collect::<Option<_>>()
+    .unwrap_or_else(|| panic!("Invalid number in '{}' after {}", value, arg));
+  numbers
+    .try_into()
+    .unwrap_or_else(|_| panic!("Expected {} comma separated numbers after {}", N, arg))
+}
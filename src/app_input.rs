@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use glam::Vec3;
+use glam::{vec2, Vec2, Vec3};
 use log::info;
 use winit::event::{
   ElementState, Event, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent,
@@ -14,6 +14,11 @@ pub struct AppInput {
   pub close_requested: bool,
   pub key_held: HashSet<VirtualKeyCode>,
   pub mouse_buttons_held: HashSet<MouseButton>,
+  /// pressed since last frame
+  pub mouse_buttons_just_pressed: HashSet<MouseButton>,
+  /// in pixels, from the top left corner
+  pub cursor_position: Vec2,
+  pub cursor_position_last_frame: Vec2,
   pub scroll_delta_y: f32,
   pub is_minimized: bool,
   /// handle losing focus, cursor moving out of window etc.
@@ -26,6 +31,9 @@ impl AppInput {
       close_requested: false,
       key_held: HashSet::new(),
       mouse_buttons_held: HashSet::new(),
+      mouse_buttons_just_pressed: HashSet::new(),
+      cursor_position: Vec2::ZERO,
+      cursor_position_last_frame: Vec2::ZERO,
       scroll_delta_y: 0.0,
       is_minimized: false,
       can_intercept_mouse_events: false, // wait to make sure we REALLY have mouse focus
@@ -34,6 +42,8 @@ impl AppInput {
 
   pub fn reset_transient_state(&mut self) {
     self.scroll_delta_y = 0.0;
+    self.mouse_buttons_just_pressed.clear();
+    self.cursor_position_last_frame = self.cursor_position;
   }
 
   pub fn handle_event<T>(&mut self, event: &Event<T>, imgui_intercepted: bool) {
@@ -86,6 +96,7 @@ impl AppInput {
         match *state {
           ElementState::Pressed if !imgui_intercepted => {
             self.mouse_buttons_held.insert(*button);
+            self.mouse_buttons_just_pressed.insert(*button);
           }
           ElementState::Released => {
             // always handle, regardless of imgui
@@ -94,6 +105,9 @@ impl AppInput {
           _ => (),
         }
       }
+      WindowEvent::CursorMoved { position, .. } => {
+        self.cursor_position = vec2(position.x as f32, position.y as f32);
+      }
       // window focus
       WindowEvent::Focused(is_focused) => {
         info!("Window focus change. Are we in focus: {:?}", is_focused);
@@ -123,6 +137,10 @@ impl AppInput {
     self.can_intercept_mouse_events && self.mouse_buttons_held.contains(&btn)
   }
 
+  pub fn is_mouse_button_just_pressed(&self, btn: MouseButton) -> bool {
+    self.can_intercept_mouse_events && self.mouse_buttons_just_pressed.contains(&btn)
+  }
+
   /// Rust's `Winit` has problem with keyboard keys:
   /// "When user holds the key, winit emits `KEY_PRESS`, waits 0.5s and then
   /// starts emitting subsequent `KEY_PRESS` events".
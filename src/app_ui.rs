@@ -1,27 +1,32 @@
 use ash;
 use ash::vk;
-use glam::{vec4, Vec4};
+use glam::{vec4, Vec3, Vec4};
 use imgui::{
   internal::DataTypeKind, ColorEditFlags, Condition, Context, StyleColor, TreeNodeFlags, Ui,
 };
 use imgui_rs_vulkan_renderer::{Options, Renderer};
 use imgui_winit_support::{HiDpiMode, WinitPlatform};
 use lazy_static::lazy_static;
+use log::error;
 use std::borrow::Cow;
 use winit::event::Event;
 
 use crate::{
   app_timer::AppTimer,
   config::{
-    tfx_simulation::TfxSimulation, ColorGradingPerRangeSettings, ColorGradingProp, Config,
-    DisplayMode, HairPPLLDisplayMode, HairSolidDisplayMode, HairTechnique, LightAmbient, LightCfg,
-    PostFxCfg, SSAOConfig, SSSBlurPassCfg, SSSForwardScatterPassCfg, ShadowTechnique,
-    ShadowsConfig, TonemappingMode,
+    AntiAliasing, ColorGradingPerRangeSettings, ColorGradingProp, Config, DisplayMode,
+    EnvironmentCfg, HairPPLLDisplayMode, HairPPLLTailMode, HairSolidDisplayMode, HairTechnique,
+    HairWboitDisplayMode, LightAmbient, LightCfg, LightType, PostFxCfg, SSAOConfig, SSSBlurPassCfg,
+    SSSForwardScatterPassCfg, ShadowTechnique, ShadowsConfig, TonemappingMode,
   },
   either,
   gpu_profiler::{GpuProfiler, GpuProfilerReport},
-  render_graph::PassExecContext,
-  scene::{TfxObject, WorldEntity},
+  render_graph::{PassExecContext, ShadowAtlas},
+  scene::{
+    EnvironmentMap, TfxFollowHairMode, TfxGroomSettings, TfxGroomTool, TfxLod, TfxLodSettings,
+    TfxObject, TfxSimulation, TfxSimulationCache, TfxSimulationCacheMode, TfxStrandInspector,
+    TfxStrandModifiers, WorldEntity,
+  },
   utils::{first_letters, vec3_to_pretty_str},
   vk_ctx::VkCtx,
 };
@@ -126,9 +131,8 @@ impl AppUI {
             .iter_mut()
             .for_each(|entity| Self::draw_tfx_object(ui, config, entity));
           Self::draw_ambient_light(ui, &mut config.light_ambient);
-          Self::draw_light(ui, "Light 0", &mut config.light0);
-          Self::draw_light(ui, "Light 1", &mut config.light1);
-          Self::draw_light(ui, "Light 2", &mut config.light2);
+          Self::draw_environment(ui, &mut config.environment, &scene.environment);
+          Self::draw_lights(ui, config, exec_ctx.shadow_atlas);
           Self::draw_shadows(ui, &mut config.shadows);
           Self::draw_sss_forward_pass(ui, &mut config.sss_forward_scatter);
           Self::draw_sss_blur(ui, &mut config.sss_blur);
@@ -144,7 +148,7 @@ impl AppUI {
             Self::draw_color_grading(ui, "midtones", &mut cg.midtones, None);
             Self::draw_color_grading(ui, "highlights", &mut cg.highlights, hl);
           }
-          Self::draw_fxaa_ui(ui, config);
+          Self::draw_anti_aliasing_ui(ui, config);
           Self::draw_gpu_profiler(ui, config, profiler);
         });
       // UI END
@@ -213,7 +217,7 @@ impl AppUI {
     }
 
     ui.checkbox("Show positions", &mut config.show_debug_positions);
-    add_tooltip_to_previous_widget(ui, "Show positions of lights, shadow source, wind etc.");
+    add_tooltip_to_previous_widget(ui, "Show positions of lights, SSS source, wind etc.");
 
     push_token.end();
   }
@@ -262,19 +266,26 @@ impl AppUI {
     ui.combo(
       "Hair technique",
       &mut config.hair_technique,
-      &[HairTechnique::PPLL, HairTechnique::Solid],
+      &[
+        HairTechnique::PPLL,
+        HairTechnique::Solid,
+        HairTechnique::WBOIT,
+      ],
       |idx| match *idx {
         HairTechnique::Solid => Cow::Borrowed("Solid"),
+        HairTechnique::WBOIT => Cow::Borrowed("WBOIT"),
         _ => Cow::Borrowed("PPLL"),
       },
     );
     add_tooltip_to_previous_widget(ui,
-      "PPLL - Order-Independent Transparency using Per-Pixel Linked List (TressFX)\nSolid - closest fragment wins, no alpha"
+      "PPLL - Order-Independent Transparency using Per-Pixel Linked List (TressFX)\nSolid - closest fragment wins, no alpha\nWBOIT - Weighted Blended Order-Independent Transparency, cheaper approximation of PPLL"
     );
 
     if config.display_mode == (DisplayMode::Final as _) {
       if config.hair_technique == HairTechnique::PPLL as _ {
         Self::draw_hair_settings_ppll(ui, config);
+      } else if config.hair_technique == HairTechnique::WBOIT as _ {
+        Self::draw_hair_settings_wboit(ui, config);
       } else {
         Self::draw_hair_settings_solid(ui, config);
       }
@@ -294,15 +305,63 @@ impl AppUI {
         HairPPLLDisplayMode::PpllOverlap,
         HairPPLLDisplayMode::Tangents,
         HairPPLLDisplayMode::Coverage,
+        HairPPLLDisplayMode::ListLength,
       ],
       |idx| match *idx {
         HairPPLLDisplayMode::Flat => Cow::Borrowed("Flat"),
         HairPPLLDisplayMode::PpllOverlap => Cow::Borrowed("PPLL overlap"),
         HairPPLLDisplayMode::Tangents => Cow::Borrowed("Tangents"),
         HairPPLLDisplayMode::Coverage => Cow::Borrowed("Coverage"),
+        HairPPLLDisplayMode::ListLength => Cow::Borrowed("List length vs K"),
         _ => Cow::Borrowed("Final"),
       },
     );
+    add_tooltip_to_previous_widget(
+      ui,
+      "'List length vs K': green if all fragments fit into K-buffer, yellow to red where tail mode is used",
+    );
+
+    next_widget_small(ui);
+    ui.combo(
+      "K-buffer size",
+      &mut config.hair_ppll_kbuffer_size_idx,
+      &Config::PPLL_KBUFFER_SIZES,
+      |size| Cow::Owned(format!("{}", size)),
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "Number of closest fragments per pixel that are sorted and fully shaded",
+    );
+    next_widget_small(ui);
+    ui.combo(
+      "Tail mode",
+      &mut config.hair_ppll_tail_mode,
+      &[
+        HairPPLLTailMode::SimpleShading,
+        HairPPLLTailMode::Ignore,
+        HairPPLLTailMode::FullShading,
+      ],
+      |idx| match *idx {
+        HairPPLLTailMode::Ignore => Cow::Borrowed("Ignore"),
+        HairPPLLTailMode::FullShading => Cow::Borrowed("Full shading"),
+        _ => Cow::Borrowed("Simple shading"),
+      },
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "How to shade fragments that did not fit into the K-buffer",
+    );
+    slider_small(
+      ui,
+      "Min. width (px)##ppll",
+      0.0,
+      3.0,
+      &mut config.hair_ppll_min_pixel_width,
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "Widen sub-pixel strands and lower their opacity to reduce shimmering. 0 to disable. Use 'Coverage' display mode to preview",
+    );
   }
 
   fn draw_hair_settings_solid(ui: &Ui, config: &mut Config) {
@@ -325,79 +384,60 @@ impl AppUI {
         _ => Cow::Borrowed("Final"),
       },
     );
+    slider_small(
+      ui,
+      "Min. width (px)##solid",
+      0.0,
+      3.0,
+      &mut config.hair_solid_min_pixel_width,
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "Widen sub-pixel strands and dither their pixels to reduce shimmering. 0 to disable",
+    );
+  }
+
+  fn draw_hair_settings_wboit(ui: &Ui, config: &mut Config) {
+    next_widget_small(ui);
+    ui.combo(
+      "Hair display mode##wboit",
+      &mut config.hair_wboit_display_mode,
+      &[
+        HairWboitDisplayMode::Final,
+        HairWboitDisplayMode::Flat,
+        HairWboitDisplayMode::Revealage,
+        HairWboitDisplayMode::Weights,
+      ],
+      |idx| match *idx {
+        HairWboitDisplayMode::Flat => Cow::Borrowed("Flat"),
+        HairWboitDisplayMode::Revealage => Cow::Borrowed("Opacity"),
+        HairWboitDisplayMode::Weights => Cow::Borrowed("Weights"),
+        _ => Cow::Borrowed("Final"),
+      },
+    );
+    slider_small(
+      ui,
+      "Min. width (px)##wboit",
+      0.0,
+      3.0,
+      &mut config.hair_wboit_min_pixel_width,
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "Widen sub-pixel strands and lower their opacity to reduce shimmering. 0 to disable",
+    );
   }
 
   fn draw_hair_simulation_settings(ui: &Ui, config: &mut Config) {
-    // TODO [LOW] flag to turn off collision? It can just set radii to 0.
     let push_token = ui.push_id("tressfx_sim");
 
     if ui.collapsing_header("Hair simulation", *HEADER_FLAGS) {
       if ui.button("Reset hair state") {
         config.reset_tfx_simulation_next_frame = true;
       }
-
-      let sim: &mut TfxSimulation = &mut config.tfx_simulation;
-      slider_small(ui, "Gravity", 0.0, 300.0, &mut sim.gravity);
-
-      // Verlet integration
-      slider_small(ui, "Damping", 0.0, 1.0, &mut sim.verlet_integration_damping);
-      add_tooltip_to_previous_widget(ui, "Damping for verlet integration.\n0 - continue movement from previous frame\n1 - use only gravity and wind");
-
-      // Wind
-      // TODO [MEDIUM] add option to jitter direction/strength? Can be CPU only
-      ui.text_disabled("Wind");
-      slider_small(ui, "Wind strength", 0.0, 300.0, &mut sim.wind_strength);
-      slider_position_phi(ui, "Wind position phi", &mut sim.wind_pos_phi);
-      slider_position_theta(ui, "Wind position th", &mut sim.wind_pos_theta);
-
-      // Global Shape Constraint
-      ui.text_disabled("Global Shape Constraint");
-      add_tooltip_to_previous_widget(
-        ui,
-        "Preserve initial shape of the hair.\nHappens every frame so this effect is VERY strong.",
-      );
-      slider_small(ui, "Stiffness##gsc", 0.0, 0.2, &mut sim.global_stiffness);
-      slider_small(
-        ui,
-        "Strand range",
-        0.0,
-        1.0,
-        &mut sim.global_stiffness_range,
-      );
-      add_tooltip_to_previous_widget(ui, "Which part of strand (whole or only near root) are affected by GSC.\n0 - only root is affected by GSC, so the tips will be 'bouncy'\n1 - whole strand is affected by GSC (less movement)",);
-
-      // Local Shape Constraint
-      ui.text_disabled("Local Shape Constraint");
-      add_tooltip_to_previous_widget(
-        ui,
-        "(Local Shape Constraint)\nPreserve local shape of the hair (direction between consecutive vertices).\nUsed with e.g. curly hair.",
-      );
-      slider_small(ui, "Stiffness##lsc", 0.0, 1.0, &mut sim.local_stiffness);
-      add_tooltip_to_previous_widget(
-        ui,
-        "0 - no local shape preservation (affected by gravity/wind more)\n1 - preserve relative vectors between vertices (less affected by forces)",
-      );
-      slider_small(
-        ui,
-        "Iterations##lsc",
-        0,
-        5,
-        &mut sim.local_stiffness_iterations,
-      );
-
-      // length constraints
-      ui.text_disabled("Length Constraint");
-      add_tooltip_to_previous_widget(
-        ui,
-        "(Length Constraint)\nPreserve initial distance between strand vertices. Fix hair segments that are too long/short.",
-      );
-      slider_small(ui, "Stiffness##length", 0.0, 1.0, &mut sim.length_stiffness);
-      slider_small(
+      text_disabled_multiline(
         ui,
-        "Iterations##length",
-        0,
-        5,
-        &mut sim.length_constraint_iterations,
+        "Simulation parameters are set separately for each TressFX object.",
       );
     }
 
@@ -458,7 +498,6 @@ impl AppUI {
 
   fn draw_tfx_object(ui: &Ui, config: &mut Config, entity: &mut TfxObject) {
     let push_token = ui.push_id(entity.name.clone());
-    let mat = &mut entity.material;
 
     let label = format!("TressFX: {}", entity.name);
     if ui.collapsing_header(label, *HEADER_FLAGS) {
@@ -468,29 +507,27 @@ impl AppUI {
         entity.num_vertices_per_strand
       ));
 
+      let is_showing_collision_meshes = config.debug_collision_sphere0.w != 0.0
+        || config.debug_collision_sphere1.w != 0.0
+        || config.debug_collision_sphere2.w != 0.0
+        || config.debug_collision_sphere3.w != 0.0;
       if ui.button("Toggle show collision meshes") {
-        let is_currently_showing = config.debug_collision_sphere0.w != 0.0
-          || config.debug_collision_sphere1.w != 0.0
-          || config.debug_collision_sphere2.w != 0.0
-          || config.debug_collision_sphere3.w != 0.0;
-        if is_currently_showing {
+        if is_showing_collision_meshes {
           config.debug_collision_sphere0.w = 0.0;
           config.debug_collision_sphere1.w = 0.0;
           config.debug_collision_sphere2.w = 0.0;
           config.debug_collision_sphere3.w = 0.0;
         } else {
-          let model_mat = entity.model_matrix.clone();
-          let scale = entity.scale_debug_use_only;
-          let cc_fn = |cc: Vec4| {
-            let a = model_mat * cc;
-            vec4(a.x, a.y, a.z, cc.w * scale)
-          };
-          config.debug_collision_sphere0 = cc_fn(entity.collision_capsule0);
-          config.debug_collision_sphere1 = cc_fn(entity.collision_capsule1);
-          config.debug_collision_sphere2 = cc_fn(entity.collision_capsule2);
-          config.debug_collision_sphere3 = cc_fn(entity.collision_capsule3);
+          Self::show_collision_meshes(config, entity);
         }
       }
+      if let Some(_token) = ui.tree_node("Colliders") {
+        let changed = Self::draw_tfx_colliders(ui, entity);
+        if changed && is_showing_collision_meshes {
+          Self::show_collision_meshes(config, entity);
+        }
+      }
+      let mat = &mut entity.material;
 
       slider_small(ui, "Radius", 0.001, 0.025, &mut entity.fiber_radius);
       add_tooltip_to_previous_widget(ui, "Radius of each strand");
@@ -504,22 +541,70 @@ impl AppUI {
         &mut entity.follow_hairs,
       );
       add_tooltip_to_previous_widget(ui, "Artificial strands around main, simulated strand");
-      slider_small(
-        ui,
-        "Spread root",
-        0.0,
-        0.6,
-        &mut entity.follow_hair_spread_root,
+      next_widget_small(ui);
+      ui.combo(
+        "Follow hair mode",
+        &mut entity.follow_hair_mode,
+        &[
+          TfxFollowHairMode::Random,
+          TfxFollowHairMode::Interpolated,
+          TfxFollowHairMode::Clumped,
+        ],
+        |idx| match *idx {
+          TfxFollowHairMode::Interpolated => Cow::Borrowed("Interpolated"),
+          TfxFollowHairMode::Clumped => Cow::Borrowed("Clumped"),
+          _ => Cow::Borrowed("Random"),
+        },
       );
-      add_tooltip_to_previous_widget(ui, "Scatter follow strands at root");
-      slider_small(
+      add_tooltip_to_previous_widget(
         ui,
-        "Spread tip",
-        0.0,
-        0.6,
-        &mut entity.follow_hair_spread_tip,
+        "Random: scatter around the guide strand. Interpolated: blend between nearest guide strands. Clumped: scatter at root, gather near the tip",
       );
-      add_tooltip_to_previous_widget(ui, "Scatter follow strands at tip");
+      let mode = entity.follow_hair_mode;
+      if mode != TfxFollowHairMode::Interpolated as usize {
+        slider_small(
+          ui,
+          "Spread root",
+          0.0,
+          0.6,
+          &mut entity.follow_hair_spread_root,
+        );
+        add_tooltip_to_previous_widget(ui, "Scatter follow strands at root");
+      }
+      if mode == TfxFollowHairMode::Random as usize {
+        slider_small(
+          ui,
+          "Spread tip",
+          0.0,
+          0.6,
+          &mut entity.follow_hair_spread_tip,
+        );
+        add_tooltip_to_previous_widget(ui, "Scatter follow strands at tip");
+      }
+      if mode == TfxFollowHairMode::Clumped as usize {
+        slider_small(
+          ui,
+          "Clump strength",
+          0.0,
+          1.0,
+          &mut entity.follow_hair_clump_strength,
+        );
+        add_tooltip_to_previous_widget(ui, "How close to the guide strand the tip is");
+        slider_small(
+          ui,
+          "Clump shape",
+          0.1,
+          5.0,
+          &mut entity.follow_hair_clump_shape,
+        );
+        add_tooltip_to_previous_widget(
+          ui,
+          "Exponent of the clump curve. Higher values gather strands only near the tip",
+        );
+      }
+
+      text_disabled_multiline(ui, "Strand modifiers");
+      Self::draw_tfx_strand_modifiers(ui, &mut entity.strand_modifiers);
 
       // material
       let max_spec = 500.0;
@@ -554,11 +639,435 @@ impl AppUI {
       text_disabled_multiline(ui, "Ambient occlusion");
       slider_small(ui, "AO strength", 0.0, 1.0, &mut mat.ao_strength); // delta 0.01
       slider_small(ui, "AO exp", 0.0, 5.0, &mut mat.ao_exp); // delta 0.1
+
+      text_disabled_multiline(ui, "Simulation");
+      Self::draw_tfx_simulation(ui, config, &mut entity.simulation);
+
+      text_disabled_multiline(ui, "Export");
+      let export = &mut entity.export_settings;
+      next_widget_small(ui);
+      ui.input_text("Export file", &mut export.file_path).build();
+      add_tooltip_to_previous_widget(
+        ui,
+        "Format is based on extension: .tfx, .obj (polylines) or .hair (Cem Yuksel)",
+      );
+      ui.checkbox("Export follow hairs", &mut export.include_follow_hairs);
+      if ui.button("Export strands") {
+        export.export_next_frame = true;
+      }
+
+      text_disabled_multiline(ui, "Level of detail");
+      Self::draw_tfx_lod(
+        ui,
+        &mut entity.lod_settings,
+        &entity.lod,
+        entity.follow_hairs,
+      );
+
+      text_disabled_multiline(ui, "Strand inspector");
+      Self::draw_tfx_strand_inspector(ui, &mut entity.strand_inspector, &entity.lod);
+
+      text_disabled_multiline(ui, "Grooming");
+      Self::draw_tfx_groom(ui, &mut entity.groom);
+      if ui.button("Save groomed .tfx") {
+        if let Err(err) = entity.save_rest_pose() {
+          error!("Failed to save '{}': {}", entity.groom.file_path, err);
+        }
+      }
+
+      text_disabled_multiline(ui, "Simulation cache");
+      Self::draw_tfx_simulation_cache(
+        ui,
+        &mut entity.simulation_cache,
+        entity.num_hair_strands,
+        entity.num_vertices_per_strand,
+      );
     }
 
     push_token.end();
   }
 
+  fn show_collision_meshes(config: &mut Config, entity: &TfxObject) {
+    let model_mat = entity.model_matrix;
+    let (model_scale, _, _) = model_mat.to_scale_rotation_translation();
+    let scale = model_scale.max_element();
+    let cc_fn = |cc: Vec4| {
+      let a = model_mat * cc;
+      vec4(a.x, a.y, a.z, cc.w * scale)
+    };
+    config.debug_collision_sphere0 = cc_fn(entity.collision_capsule0);
+    config.debug_collision_sphere1 = cc_fn(entity.collision_capsule1);
+    config.debug_collision_sphere2 = cc_fn(entity.collision_capsule2);
+    config.debug_collision_sphere3 = cc_fn(entity.collision_capsule3);
+  }
+
+  /// @return true if anything was changed
+  fn draw_tfx_colliders(ui: &Ui, entity: &mut TfxObject) -> bool {
+    let mut changed = false;
+
+    let mut center_of_gravity = entity.center_of_gravity.to_array();
+    if ui
+      .input_float3("Center of gravity", &mut center_of_gravity)
+      .build()
+    {
+      entity.center_of_gravity = Vec3::from_array(center_of_gravity);
+      changed = true;
+    }
+    add_tooltip_to_previous_widget(ui, "Model space. Hair normals point away from this point");
+
+    let mut capsules = [
+      &mut entity.collision_capsule0,
+      &mut entity.collision_capsule1,
+      &mut entity.collision_capsule2,
+      &mut entity.collision_capsule3,
+    ];
+    for (i, capsule) in capsules.iter_mut().enumerate() {
+      let mut value = capsule.to_array();
+      if ui
+        .input_float4(format!("Capsule {}", i), &mut value)
+        .build()
+      {
+        **capsule = Vec4::from_array(value);
+        changed = true;
+      }
+      add_tooltip_to_previous_widget(
+        ui,
+        "Model space center (xyz) and radius (w). Radius 0 disables the capsule",
+      );
+    }
+
+    changed
+  }
+
+  fn draw_tfx_lod(ui: &Ui, settings: &mut TfxLodSettings, lod: &TfxLod, follow_hairs: u32) {
+    ui.checkbox("Automatic LOD", &mut settings.enabled);
+    add_tooltip_to_previous_widget(
+      ui,
+      "Draw less follow hairs when the object is small on screen. Remaining strands get wider and more opaque",
+    );
+    if !settings.enabled {
+      return;
+    }
+    slider_small(
+      ui,
+      "Full detail at",
+      0.0,
+      1.0,
+      &mut settings.full_detail_coverage,
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "Fraction of the screen height above which all follow hairs are drawn",
+    );
+    slider_small(
+      ui,
+      "Min detail at",
+      0.0,
+      1.0,
+      &mut settings.min_detail_coverage,
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "Fraction of the screen height below which only min. follow hairs are drawn",
+    );
+    slider_small(
+      ui,
+      "Min follow hairs",
+      1,
+      follow_hairs.max(1),
+      &mut settings.min_follow_hairs,
+    );
+    slider_small(
+      ui,
+      "Max width scale",
+      1.0,
+      5.0,
+      &mut settings.max_width_scale,
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "How much strands can widen to preserve coverage. Rest goes into opacity",
+    );
+    ui.text_disabled(format!(
+      "Coverage: {:.1}%, follow hairs: {:.1}/{}",
+      lod.screen_coverage * 100.0,
+      lod.follow_hairs,
+      follow_hairs
+    ));
+    ui.text_disabled(format!(
+      "Width: x{:.2}, opacity: x{:.2}",
+      lod.width_scale, lod.opacity_scale
+    ));
+  }
+
+  fn draw_tfx_strand_inspector(ui: &Ui, inspector: &mut TfxStrandInspector, lod: &TfxLod) {
+    let selection = match inspector.selection {
+      Some(s) => s,
+      None => {
+        ui.text_disabled("Right click on hair to select a strand");
+        return;
+      }
+    };
+
+    if selection.is_guide() {
+      ui.text(format!("Guide strand {}", selection.strand_id));
+    } else {
+      ui.text(format!(
+        "Follow hair {} of guide strand {}",
+        selection.instance_id, selection.strand_id
+      ));
+      text_disabled_multiline(ui, "Simulation state below is for the guide strand");
+    }
+    if selection.instance_id >= lod.instance_count() {
+      text_disabled_multiline(ui, "Hidden by LOD, highlight is not visible");
+    }
+    ui.checkbox("Live update", &mut inspector.live_update);
+    add_tooltip_to_previous_widget(
+      ui,
+      "Read simulation state every frame. Waits for the GPU, so expect lower framerate",
+    );
+    if ui.button("Clear selection") {
+      inspector.clear();
+      return;
+    }
+
+    let rest_length = inspector.rest_length();
+    let current_length = inspector.current_length();
+    ui.text_disabled(format!(
+      "Length: {:.3} (rest {:.3})",
+      current_length, rest_length
+    ));
+    ui.text_disabled(format!(
+      "Max segment stretch: {:.1}%",
+      (inspector.max_stretch() - 1.0) * 100.0
+    ));
+    ui.text_disabled(format!(
+      "Max velocity: {:.4} / frame",
+      inspector.max_velocity()
+    ));
+
+    if let Some(_token) = ui.tree_node("Vertices") {
+      for (i, v) in inspector.vertices.iter().enumerate() {
+        let state = match (v.is_movable, v.is_pinned) {
+          (false, _) => "fixed",
+          (true, true) => "pinned",
+          (true, false) => "sim",
+        };
+        ui.text_disabled(format!(
+          "{:2} [{}] len {:.3}/{:.3} tan {}",
+          i,
+          state,
+          v.current_length,
+          v.rest_length,
+          vec3_to_pretty_str(v.tangent)
+        ));
+        if ui.is_item_hovered() {
+          ui.tooltip_text(format!(
+            "Position: {}\nVelocity: {:.4} / frame",
+            vec3_to_pretty_str(v.position),
+            v.velocity
+          ));
+        }
+      }
+    }
+  }
+
+  fn draw_tfx_groom(ui: &Ui, groom: &mut TfxGroomSettings) {
+    ui.checkbox("Grooming mode", &mut groom.enabled);
+    add_tooltip_to_previous_widget(
+      ui,
+      "Left mouse button applies the tool instead of rotating the camera. Changes are uploaded and simulation is reset when the button is released",
+    );
+    next_widget_small(ui);
+    ui.combo(
+      "Tool",
+      &mut groom.tool,
+      &[
+        TfxGroomTool::Comb,
+        TfxGroomTool::Cut,
+        TfxGroomTool::ScaleLength,
+      ],
+      |idx| match *idx {
+        TfxGroomTool::Cut => Cow::Borrowed("Cut"),
+        TfxGroomTool::ScaleLength => Cow::Borrowed("Scale length"),
+        _ => Cow::Borrowed("Comb"),
+      },
+    );
+    slider_small(ui, "Brush radius", 0.1, 5.0, &mut groom.brush_radius);
+    if groom.tool == TfxGroomTool::Comb as usize {
+      slider_small(ui, "Comb strength", 0.0, 1.0, &mut groom.comb_strength);
+    }
+    if groom.tool == TfxGroomTool::ScaleLength as usize {
+      slider_small(ui, "Length scale", 0.5, 2.0, &mut groom.length_scale);
+      add_tooltip_to_previous_widget(ui, "Applied to strands under the cursor on each click");
+    }
+    next_widget_small(ui);
+    ui.input_text("Groom file", &mut groom.file_path).build();
+  }
+
+  fn draw_tfx_strand_modifiers(ui: &Ui, modifiers: &mut TfxStrandModifiers) {
+    slider_small(ui, "Curl radius", 0.0, 0.5, &mut modifiers.curl_radius);
+    add_tooltip_to_previous_widget(ui, "Radius of the helix around each strand. 0 to disable");
+    slider_small(
+      ui,
+      "Curl frequency",
+      0.0,
+      20.0,
+      &mut modifiers.curl_frequency,
+    );
+    add_tooltip_to_previous_widget(ui, "Helix turns along the whole strand");
+    slider_small(ui, "Frizz", 0.0, 0.3, &mut modifiers.frizz);
+    add_tooltip_to_previous_widget(ui, "Random offset for each vertex. Grows toward the tip");
+    slider_small(
+      ui,
+      "Length variation",
+      0.0,
+      1.0,
+      &mut modifiers.length_variation,
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "Randomly shorten each strand up to this fraction of its length",
+    );
+  }
+
+  fn draw_tfx_simulation(ui: &Ui, config: &mut Config, sim: &mut TfxSimulation) {
+    if ui.checkbox("Simulate", &mut sim.enabled) {
+      // Position buffers rotate every frame. Without simulation they would
+      // show the last 3 frames in a loop, so go back to the rest pose.
+      config.reset_tfx_simulation_next_frame = true;
+    }
+    add_tooltip_to_previous_widget(
+      ui,
+      "Disable for short strands (eyelashes, eyebrows) that do not need to move",
+    );
+    if !sim.enabled {
+      return;
+    }
+
+    // TODO [LOW] flag to turn off collision? It can just set radii to 0.
+    slider_small(ui, "Gravity", 0.0, 300.0, &mut sim.gravity);
+    slider_small(ui, "Bounds scale", 1.0, 3.0, &mut sim.bounds_scale);
+    add_tooltip_to_previous_widget(
+      ui,
+      "Shadow and SSS depth bounds are NOT computed from the simulated strands.\nThey are the rest pose bounds scaled by this value (approximation).\nIncrease if the hair moves far away and gets clipped by the shadows",
+    );
+
+    // Verlet integration
+    slider_small(ui, "Damping", 0.0, 1.0, &mut sim.verlet_integration_damping);
+    add_tooltip_to_previous_widget(ui, "Damping for verlet integration.\n0 - continue movement from previous frame\n1 - use only gravity and wind");
+
+    // Wind
+    // TODO [MEDIUM] add option to jitter direction/strength? Can be CPU only
+    ui.text_disabled("Wind");
+    slider_small(ui, "Wind strength", 0.0, 300.0, &mut sim.wind_strength);
+    slider_position_phi(ui, "Wind position phi", &mut sim.wind_pos_phi);
+    slider_position_theta(ui, "Wind position th", &mut sim.wind_pos_theta);
+
+    // Global Shape Constraint
+    ui.text_disabled("Global Shape Constraint");
+    add_tooltip_to_previous_widget(
+      ui,
+      "Preserve initial shape of the hair.\nHappens every frame so this effect is VERY strong.",
+    );
+    slider_small(ui, "Stiffness##gsc", 0.0, 0.2, &mut sim.global_stiffness);
+    slider_small(
+      ui,
+      "Strand range",
+      0.0,
+      1.0,
+      &mut sim.global_stiffness_range,
+    );
+    add_tooltip_to_previous_widget(ui, "Which part of strand (whole or only near root) are affected by GSC.\n0 - only root is affected by GSC, so the tips will be 'bouncy'\n1 - whole strand is affected by GSC (less movement)",);
+
+    // Local Shape Constraint
+    ui.text_disabled("Local Shape Constraint");
+    add_tooltip_to_previous_widget(
+      ui,
+      "(Local Shape Constraint)\nPreserve local shape of the hair (direction between consecutive vertices).\nUsed with e.g. curly hair.",
+    );
+    slider_small(ui, "Stiffness##lsc", 0.0, 1.0, &mut sim.local_stiffness);
+    add_tooltip_to_previous_widget(
+      ui,
+      "0 - no local shape preservation (affected by gravity/wind more)\n1 - preserve relative vectors between vertices (less affected by forces)",
+    );
+    slider_small(
+      ui,
+      "Iterations##lsc",
+      0,
+      5,
+      &mut sim.local_stiffness_iterations,
+    );
+
+    // length constraints
+    ui.text_disabled("Length Constraint");
+    add_tooltip_to_previous_widget(
+      ui,
+      "(Length Constraint)\nPreserve initial distance between strand vertices. Fix hair segments that are too long/short.",
+    );
+    slider_small(ui, "Stiffness##length", 0.0, 1.0, &mut sim.length_stiffness);
+    slider_small(
+      ui,
+      "Iterations##length",
+      0,
+      5,
+      &mut sim.length_constraint_iterations,
+    );
+  }
+
+  fn draw_tfx_simulation_cache(
+    ui: &Ui,
+    cache: &mut TfxSimulationCache,
+    num_hair_strands: u32,
+    num_vertices_per_strand: u32,
+  ) {
+    next_widget_small(ui);
+    ui.combo(
+      "Cache mode",
+      &mut cache.mode,
+      &[
+        TfxSimulationCacheMode::Off,
+        TfxSimulationCacheMode::Record,
+        TfxSimulationCacheMode::Playback,
+      ],
+      |idx| match *idx {
+        TfxSimulationCacheMode::Record => Cow::Borrowed("Record"),
+        TfxSimulationCacheMode::Playback => Cow::Borrowed("Playback"),
+        _ => Cow::Borrowed("Off"),
+      },
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "Record - store simulation result every frame (slow, waits for GPU)\nPlayback - replace the simulation with recorded frames",
+    );
+    ui.text_disabled(format!("Recorded frames: {}", cache.frame_count()));
+
+    if cache.frame_count() > 0 {
+      let last_frame = cache.frame_count() - 1;
+      slider_small(ui, "Frame", 0, last_frame, &mut cache.playback_frame);
+      add_tooltip_to_previous_widget(ui, "Timeline scrubber. Used in playback mode");
+      ui.checkbox("Pause playback", &mut cache.playback_paused);
+    }
+
+    next_widget_small(ui);
+    ui.input_text("Cache file", &mut cache.file_path).build();
+    if ui.button("Save##sim_cache") {
+      if let Err(e) = cache.save_to_file(num_hair_strands, num_vertices_per_strand) {
+        error!("Failed to save simulation cache: {}", e);
+      }
+    }
+    ui.same_line();
+    if ui.button("Load##sim_cache") {
+      if let Err(e) = cache.load_from_file(num_hair_strands, num_vertices_per_strand) {
+        error!("Failed to load simulation cache: {}", e);
+      }
+    }
+    ui.same_line();
+    if ui.button("Clear##sim_cache") {
+      cache.clear();
+    }
+  }
+
   fn draw_ambient_light(ui: &Ui, light: &mut LightAmbient) {
     let push_token = ui.push_id("ambient_light");
 
@@ -570,18 +1079,131 @@ impl AppUI {
     push_token.end();
   }
 
-  fn draw_light(ui: &Ui, name: &str, light: &mut LightCfg) {
+  fn draw_environment(ui: &Ui, env: &mut EnvironmentCfg, env_map: &Option<EnvironmentMap>) {
+    let push_token = ui.push_id("environment");
+
+    if ui.collapsing_header("Environment map", *HEADER_FLAGS) {
+      match env_map {
+        Some(env_map) => {
+          ui.text_disabled(format!("Loaded: {}", env_map.name));
+          ui.checkbox("Enabled", &mut env.enabled);
+          slider_small(ui, "Intensity", 0.0, 4.0, &mut env.intensity);
+          slider_small(ui, "Rotation", 0.0, 360.0, &mut env.rotation);
+          ui.checkbox("Show skybox", &mut env.show_skybox);
+          slider_small(ui, "Skybox blur", 0.0, 1.0, &mut env.skybox_blur);
+        }
+        None => {
+          text_disabled_multiline(ui, "No environment map. Use '--environment-map <file.hdr>'");
+        }
+      }
+    }
+
+    push_token.end();
+  }
+
+  fn draw_lights(ui: &Ui, config: &mut Config, shadow_atlas: &ShadowAtlas) {
+    let push_token = ui.push_id("lights");
+
+    if ui.collapsing_header("Lights", *HEADER_FLAGS) {
+      let lights = &mut config.lights;
+      let mut removed_light: Option<usize> = None;
+      for (idx, light) in lights.iter_mut().enumerate() {
+        let name = format!("Light {}", idx);
+        let has_shadow_slot = shadow_atlas
+          .light_slots
+          .get(idx)
+          .copied()
+          .flatten()
+          .is_some();
+        if Self::draw_light(ui, &name, light, has_shadow_slot) {
+          removed_light = Some(idx);
+        }
+      }
+      if let Some(idx) = removed_light {
+        lights.remove(idx);
+      }
+
+      let can_add = lights.len() < Config::MAX_LIGHTS;
+      ui.disabled(!can_add, || {
+        if ui.button("Add light") {
+          lights.push(LightCfg::default());
+        }
+      });
+      add_tooltip_to_previous_widget(
+        ui,
+        &format!("At most {} lights are supported", Config::MAX_LIGHTS),
+      );
+    }
+
+    push_token.end();
+  }
+
+  /// Returns true if the light should be removed
+  fn draw_light(ui: &Ui, name: &str, light: &mut LightCfg, has_shadow_slot: bool) -> bool {
     let push_token = ui.push_id(name);
+    let mut remove = false;
 
-    if ui.collapsing_header(name, *HEADER_FLAGS) {
+    if let Some(_token) = ui.tree_node(name) {
+      ui.combo(
+        "Type",
+        &mut light.light_type,
+        &[LightType::Directional, LightType::Point, LightType::Spot],
+        |idx| match *idx {
+          LightType::Directional => Cow::Borrowed("Directional"),
+          LightType::Point => Cow::Borrowed("Point"),
+          _ => Cow::Borrowed("Spot"),
+        },
+      );
       color_rgb(ui, "Color", &mut light.color);
       slider_small(ui, "Energy", 0.0, 5.0, &mut light.energy);
       slider_position_phi(ui, "Position phi", &mut light.pos_phi);
       slider_position_theta(ui, "Position th", &mut light.pos_theta);
       slider_small(ui, "Distance", 1.0, 20.0, &mut light.pos_distance);
+      if light.is_directional() {
+        add_tooltip_to_previous_widget(ui, "Only affects debug position");
+      }
+
+      if !light.is_directional() {
+        slider_small(ui, "Radius", 1.0, 50.0, &mut light.radius);
+        add_tooltip_to_previous_widget(ui, "Light does not reach further than this");
+        slider_small(ui, "Falloff", 0.0, 4.0, &mut light.falloff);
+        add_tooltip_to_previous_widget(
+          ui,
+          "How fast light attenuates with distance. 0 means no attenuation inside the radius",
+        );
+      }
+      if light.is_directional() || light.is_spot() {
+        slider_small(ui, "Target height", 0.0, 10.0, &mut light.look_at_target.y);
+        add_tooltip_to_previous_widget(
+          ui,
+          either!(
+            light.is_directional(),
+            "Shadow map is centered at (0, height, 0). Ignored if shadows are fitted to scene",
+            "Light shines towards (0, height, 0)"
+          ),
+        );
+      }
+      if light.is_spot() {
+        slider_small(ui, "Inner angle", 0.0, 90.0, &mut light.spot_inner_angle);
+        add_tooltip_to_previous_widget(ui, "Full intensity inside this angle [dgr]");
+        slider_small(ui, "Outer angle", 0.0, 90.0, &mut light.spot_outer_angle);
+        add_tooltip_to_previous_widget(ui, "No light outside this angle [dgr]");
+      }
+
+      ui.checkbox("Casts shadow", &mut light.casts_shadow);
+      add_tooltip_to_previous_widget(
+        ui,
+        "Directional light takes 4 tiles of the shadow atlas, spot light 1, point light 6",
+      );
+      if light.casts_shadow && !has_shadow_slot {
+        ui.text_disabled("Shadow atlas is full");
+      }
+
+      remove = ui.button("Remove");
     }
 
     push_token.end();
+    remove
   }
 
   fn draw_shadows(ui: &Ui, shadows: &mut ShadowsConfig) {
@@ -596,16 +1218,18 @@ impl AppUI {
           ShadowTechnique::BinaryDebug,
           ShadowTechnique::PFC,
           ShadowTechnique::PCSS,
+          ShadowTechnique::VSM,
         ],
         |idx| match *idx {
           ShadowTechnique::BinaryDebug => Cow::Borrowed("Binary debug"),
           ShadowTechnique::PFC => Cow::Borrowed("PFC"),
+          ShadowTechnique::VSM => Cow::Borrowed("VSM"),
           _ => Cow::Borrowed("PCSS"),
         },
       );
       add_tooltip_to_previous_widget(
         ui,
-        "Use Percentage-Closer Soft Shadows or Percentage-closer Filtering or simplest possible binary debug check",
+        "Use Percentage-Closer Soft Shadows, Percentage-closer Filtering, Variance Shadow Maps or simplest possible binary debug check",
       );
       if shadows.shadow_technique == (ShadowTechnique::PFC as _) {
         slider_small(ui, "Blur radius", 0, 4, &mut shadows.blur_radius);
@@ -614,10 +1238,33 @@ impl AppUI {
       ui.slider("Strength", 0.0, 1.0, &mut shadows.strength);
       add_tooltip_to_previous_widget(ui, "Artificialy set maximal shadows strength");
 
-      ui.slider("Bias", 0.001, 0.1, &mut shadows.bias);
-      add_tooltip_to_previous_widget(ui, "Prevent shadow acne");
-      ui.slider("Hair bias", 0.001, 0.1, &mut shadows.bias_hair_tfx);
-      add_tooltip_to_previous_widget(ui, "Prevent shadow acne");
+      if shadows.is_vsm() {
+        slider_small(ui, "Blur radius", 0, 16, &mut shadows.vsm_blur_radius);
+        slider_small(
+          ui,
+          "Blur gauss sigma",
+          1.0,
+          10.0,
+          &mut shadows.vsm_blur_gauss_sigma,
+        );
+        ui.slider(
+          "Light bleeding",
+          0.0,
+          0.9,
+          &mut shadows.vsm_light_bleeding_reduction,
+        );
+        add_tooltip_to_previous_widget(
+          ui,
+          "Remove light bleeding where shadows overlap. Higher values make shadows sharper",
+        );
+        ui.slider("Min variance", 0.0, 0.0005, &mut shadows.vsm_min_variance);
+        add_tooltip_to_previous_widget(ui, "Prevent shadow acne");
+      } else {
+        ui.slider("Bias", 0.001, 0.1, &mut shadows.bias);
+        add_tooltip_to_previous_widget(ui, "Prevent shadow acne");
+        ui.slider("Hair bias", 0.001, 0.1, &mut shadows.bias_hair_tfx);
+        add_tooltip_to_previous_widget(ui, "Prevent shadow acne");
+      }
       slider_small(
         ui,
         "Hair radius mul",
@@ -626,10 +1273,12 @@ impl AppUI {
         &mut shadows.hair_tfx_radius_multipler,
       );
       add_tooltip_to_previous_widget(ui, "Make hair strands thicker to cast bigger shadow");
-
-      slider_position_phi(ui, "Position phi", &mut shadows.shadow_source.pos_phi);
-      slider_position_theta(ui, "Position th", &mut shadows.shadow_source.pos_theta);
-      // dir.add(this.cfg.shadows.directionalLight, 'posRadius', 1, 10).step(0.1).name('Position r');
+      ui.checkbox("Fit to scene", &mut shadows.fit_to_scene);
+      add_tooltip_to_previous_widget(
+        ui,
+        "Fit directional lights' and SSS shadow frustums to the meshes and hair",
+      );
+      // Shadow-casting lights are configured in the 'Lights' section
     }
 
     push_token.end();
@@ -775,70 +1424,254 @@ impl AppUI {
         ui.slider("AcesC", 0.5, 1.5, &mut postfx.aces_c);
         ui.slider("AcesS", 0.0, 2.0, &mut postfx.aces_s);
       }
-      if postfx.tonemapping_op == TonemappingMode::Linear as _
-        || postfx.tonemapping_op == TonemappingMode::Reinhard as _
-        || postfx.tonemapping_op == TonemappingMode::Uncharted2 as _
-      {
-        ui.slider("Exposure", 0.5, 2.0, &mut postfx.exposure);
-      }
       if postfx.tonemapping_op == TonemappingMode::Uncharted2 as _
         || postfx.tonemapping_op == TonemappingMode::Photographic as _
       {
         ui.slider("White point", 0.5, 2.0, &mut postfx.white_point);
       }
+
+      ui.checkbox("Auto exposure", &mut postfx.auto_exposure);
+      if postfx.auto_exposure {
+        Self::draw_auto_exposure(ui, postfx);
+      } else {
+        ui.slider("Exposure", 0.5, 2.0, &mut postfx.exposure);
+      }
+
+      ui.checkbox("Bloom", &mut postfx.use_bloom);
+      if postfx.use_bloom {
+        ui.slider("Threshold", 0.0, 5.0, &mut postfx.bloom_threshold);
+        add_tooltip_to_previous_widget(ui, "Luminance above which pixels start to glow");
+        ui.slider("Soft knee", 0.0, 1.0, &mut postfx.bloom_soft_knee);
+        ui.slider("Intensity", 0.0, 2.0, &mut postfx.bloom_intensity);
+        ui.slider("Radius", 0.5, 3.0, &mut postfx.bloom_radius);
+      }
+
+      ui.checkbox("Depth of field", &mut postfx.use_dof);
+      if postfx.use_dof {
+        Self::draw_dof(ui, postfx);
+      }
+
+      Self::draw_lens_effects(ui, postfx);
     }
 
     push_token.end();
   }
 
-  fn draw_fxaa_ui(ui: &Ui, config: &mut Config) {
-    let push_token = ui.push_id("fxaa");
+  fn draw_dof(ui: &Ui, postfx: &mut PostFxCfg) {
+    ui.slider("Focus distance", 0.1, 50.0, &mut postfx.dof_focus_distance);
+    ui.slider("f-stop", 1.0, 22.0, &mut postfx.dof_f_stop);
+    add_tooltip_to_previous_widget(ui, "Lower values have shallower depth of field");
+    ui.slider("Focal length", 18.0, 200.0, &mut postfx.dof_focal_length);
+    add_tooltip_to_previous_widget(
+      ui,
+      "In millimeters. Only affects the blur, field of view is set separately",
+    );
+    ui.slider("Max blur", 1.0, 32.0, &mut postfx.dof_max_coc);
+    add_tooltip_to_previous_widget(ui, "In pixels. Larger values are slower");
+    ui.checkbox("Autofocus", &mut postfx.dof_autofocus);
+    if postfx.dof_autofocus {
+      ui.text_disabled("Middle click to focus on the object under the cursor");
+    }
+  }
 
-    if ui.collapsing_header("Fxaa", *HEADER_FLAGS) {
-      ui.checkbox("Use FXAA", &mut config.postfx.use_fxaa);
+  fn draw_lens_effects(ui: &Ui, postfx: &mut PostFxCfg) {
+    ui.checkbox("Vignette", &mut postfx.use_vignette);
+    if postfx.use_vignette {
+      ui.slider("Vignette strength", 0.0, 1.0, &mut postfx.vignette_strength);
+      ui.slider("Vignette radius", 0.0, 1.0, &mut postfx.vignette_radius);
+    }
 
-      slider_small(ui, "Subpixel aa", 0.0, 2.0, &mut config.postfx.subpixel);
-      add_tooltip_to_previous_widget(ui, "0.0 - off, 1.0 - 'soft' result, >1.0 - nonsense");
+    ui.checkbox("Chromatic aberration", &mut postfx.use_chromatic_aberration);
+    if postfx.use_chromatic_aberration {
+      ui.slider("CA strength", 0.0, 2.0, &mut postfx.chromatic_aberration);
+    }
 
-      slider_small(
+    ui.checkbox("Film grain", &mut postfx.use_film_grain);
+    if postfx.use_film_grain {
+      ui.slider("Grain strength", 0.0, 0.3, &mut postfx.film_grain);
+    }
+  }
+
+  fn draw_auto_exposure(ui: &Ui, postfx: &mut PostFxCfg) {
+    ui.slider("Compensation", -4.0, 4.0, &mut postfx.exposure_compensation);
+    add_tooltip_to_previous_widget(ui, "In stops (EV)");
+    ui.slider(
+      "Adaptation speed",
+      0.1,
+      10.0,
+      &mut postfx.auto_exposure_speed,
+    );
+    ui.slider(
+      "Min EV",
+      -16.0,
+      postfx.auto_exposure_max_ev - 1.0,
+      &mut postfx.auto_exposure_min_ev,
+    );
+    ui.slider(
+      "Max EV",
+      postfx.auto_exposure_min_ev + 1.0,
+      16.0,
+      &mut postfx.auto_exposure_max_ev,
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "Log2 of luminance. Histogram covers only this range, exposure is clamped to it",
+    );
+
+    let stats = &postfx.auto_exposure_stats;
+    ui.text(format!(
+      "Exposure: {:.3}, luminance: {:.3} (target {:.3})",
+      stats.exposure, stats.adapted_luminance, stats.target_luminance
+    ));
+    // skip the first bin - (almost) black pixels
+    if stats.histogram.len() > 1 {
+      let max_value = stats.histogram[1..].iter().cloned().fold(0.0, f32::max);
+      ui.plot_histogram("##luminance_histogram", &stats.histogram[1..])
+        .scale_min(0.0)
+        .scale_max(max_value.max(0.0001))
+        .graph_size([0.0, 60.0])
+        .build();
+      add_tooltip_to_previous_widget(
         ui,
-        "Relative Edge Treshold",
-        0.063,
-        0.333,
-        &mut config.postfx.edge_threshold,
+        "Luminance histogram, from Min EV (left) to Max EV (right)",
       );
+    }
+  }
+
+  fn draw_anti_aliasing_ui(ui: &Ui, config: &mut Config) {
+    let push_token = ui.push_id("anti_aliasing");
+
+    if ui.collapsing_header("Anti-aliasing", *HEADER_FLAGS) {
+      // FXAA runs in the present pass and expects render targets to match the window
+      let is_fxaa_supported = config.render_scale() == 1.0;
+      if !is_fxaa_supported && config.postfx.use_fxaa() {
+        config.postfx.anti_aliasing = AntiAliasing::None as _;
+      }
+      let techniques: &[AntiAliasing] = if is_fxaa_supported {
+        &[AntiAliasing::None, AntiAliasing::FXAA, AntiAliasing::TAA]
+      } else {
+        &[AntiAliasing::None, AntiAliasing::TAA]
+      };
+      let mut technique_idx = techniques
+        .iter()
+        .position(|t| *t as usize == config.postfx.anti_aliasing)
+        .unwrap_or(0);
+      next_widget_small(ui);
+      if ui.combo("Technique", &mut technique_idx, techniques, |t| match t {
+        AntiAliasing::FXAA => Cow::Borrowed("FXAA"),
+        AntiAliasing::TAA => Cow::Borrowed("TAA"),
+        AntiAliasing::None => Cow::Borrowed("None"),
+      }) {
+        config.postfx.anti_aliasing = techniques[technique_idx] as _;
+      }
       add_tooltip_to_previous_widget(
         ui,
-        "The minimum amount of local contrast required to apply algorithm",
+        "FXAA - fast approximate anti-aliasing, single frame. Requires 1x render scale
+TAA - temporal anti-aliasing, accumulates jittered frames",
       );
 
-      slider_small(
-        ui,
-        "Absolute Edge Treshold",
-        0.0,
-        0.0833,
-        &mut config.postfx.edge_threshold_min,
+      if config.postfx.use_fxaa() {
+        Self::draw_fxaa_ui(ui, config);
+      } else if config.postfx.use_taa() {
+        Self::draw_taa_ui(ui, config);
+      }
+
+      ui.separator();
+      next_widget_small(ui);
+      ui.combo(
+        "Render scale",
+        &mut config.render_scale_idx,
+        &[0, 1, 2, 3, 4],
+        |idx| Cow::Owned(format!("{}x", Config::RENDER_SCALES[*idx])),
       );
       add_tooltip_to_previous_widget(
         ui,
-        "The minimum amount of contrast required to apply algorithm (mostly for dark areas)",
+        "Resolution of render targets relative to the window. Values above 1x are supersampling.
+FXAA is not available if render scale is not 1x.",
       );
-
-      slider_small(
-        ui,
-        "Luma gamma",
-        1.0,
-        3.0,
-        &mut config.postfx.fxaa_luma_gamma,
+      next_widget_small(ui);
+      ui.combo(
+        "MSAA",
+        &mut config.msaa_samples_idx,
+        &[0, 1, 2, 3],
+        |idx| match Config::MSAA_SAMPLES[*idx] {
+          1 => Cow::Borrowed("Off"),
+          samples => Cow::Owned(format!("{}x", samples)),
+        },
       );
       add_tooltip_to_previous_widget(
         ui,
-        "FXAA uses luma to detect edges, which includes gamma correction to perceptual space",
+        "Multisampling of the meshes forward pass. Hair is not affected.
+Clamped to the max sample count supported by the GPU.",
       );
     }
 
     push_token.end();
   }
+
+  fn draw_taa_ui(ui: &Ui, config: &mut Config) {
+    slider_small(
+      ui,
+      "History weight",
+      0.5,
+      0.98,
+      &mut config.postfx.taa_history_weight,
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "How much of the previous frames is kept. Higher is smoother, but ghosts more",
+    );
+
+    ui.checkbox(
+      "Neighbourhood clamp",
+      &mut config.postfx.taa_neighbourhood_clamp,
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "Clamp history to colors around the current pixel. Reduces ghosting",
+    );
+  }
+
+  fn draw_fxaa_ui(ui: &Ui, config: &mut Config) {
+    slider_small(ui, "Subpixel aa", 0.0, 2.0, &mut config.postfx.subpixel);
+    add_tooltip_to_previous_widget(ui, "0.0 - off, 1.0 - 'soft' result, >1.0 - nonsense");
+
+    slider_small(
+      ui,
+      "Relative Edge Treshold",
+      0.063,
+      0.333,
+      &mut config.postfx.edge_threshold,
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "The minimum amount of local contrast required to apply algorithm",
+    );
+
+    slider_small(
+      ui,
+      "Absolute Edge Treshold",
+      0.0,
+      0.0833,
+      &mut config.postfx.edge_threshold_min,
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "The minimum amount of contrast required to apply algorithm (mostly for dark areas)",
+    );
+
+    slider_small(
+      ui,
+      "Luma gamma",
+      1.0,
+      3.0,
+      &mut config.postfx.fxaa_luma_gamma,
+    );
+    add_tooltip_to_previous_widget(
+      ui,
+      "FXAA uses luma to detect edges, which includes gamma correction to perceptual space",
+    );
+  }
 }
 
 fn next_widget_small(ui: &Ui) {
@@ -0,0 +1,421 @@
+//! Offline TressFX asset toolkit. Runs entirely on the CPU, no Vulkan required.
+//!
+//! ```
+//! tfx-tool info <file>
+//! tfx-tool validate <file.tfx>
+//! tfx-tool convert <in> <out.tfx> [--vertices <n>] [--decimate <ratio>] [--drop-short-strands]
+//! ```
+//!
+//! Input for `info` and `convert` can be `.tfx`, `.hair` or `.obj` (polylines).
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use glam::Vec3;
+use log::{error, info, warn};
+
+// Shared with the viewer, not every helper is used here
+#[allow(dead_code)]
+#[path = "../scene/tressfx/tfx_file_data.rs"]
+mod tfx_file_data;
+#[path = "../scene/tressfx/tfx_file_import.rs"]
+mod tfx_file_import;
+#[path = "../scene/tressfx/tfx_file_load.rs"]
+mod tfx_file_load;
+#[path = "../scene/tressfx/tfx_file_save.rs"]
+mod tfx_file_save;
+
+use tfx_file_data::TfxFileData;
+use tfx_file_import::{resample_strand, try_load_hair_file, TfxShortStrandPolicy};
+use tfx_file_load::{try_read_float, try_read_float_array, try_read_uint};
+use tfx_file_save::save_tressfx_file;
+
+/// Same as `IMPORT_VERTICES_PER_STRAND`. Simulation work scheduling depends on it.
+const VIEWER_VERTICES_PER_STRAND: u64 = tfx_file_import::IMPORT_VERTICES_PER_STRAND as _;
+const HISTOGRAM_BUCKETS: usize = 10;
+const HISTOGRAM_BAR_WIDTH: usize = 40;
+
+fn main() {
+  simple_logger::SimpleLogger::new().init().unwrap();
+  log::set_max_level(log::LevelFilter::Info);
+
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  let ok = match args.first().map(|a| a.as_str()) {
+    Some("info") if args.len() == 2 => cmd_info(Path::new(&args[1])),
+    Some("validate") if args.len() == 2 => cmd_validate(Path::new(&args[1])),
+    Some("convert") if args.len() >= 3 => cmd_convert(&args[1], &args[2], &args[3..]),
+    _ => {
+      print_usage();
+      false
+    }
+  };
+
+  if !ok {
+    std::process::exit(1);
+  }
+}
+
+fn print_usage() {
+  println!("Usage:");
+  println!("  tfx-tool info <file>");
+  println!("    Print header, bounding box, strand and segment length histograms");
+  println!("  tfx-tool validate <file.tfx>");
+  println!("    Check header offsets and look for NaN/infinite positions");
+  println!("  tfx-tool convert <in> <out.tfx> [options]");
+  println!("    --vertices <n>          resample every strand to n vertices (power of 2, 4-64, viewer needs 32)");
+  println!("    --decimate <ratio>      keep only ratio (0-1] of strands");
+  println!("    --drop-short-strands    when importing .hair/.obj, skip strands with < 32 points");
+}
+
+//////////////////////////
+// info
+
+fn cmd_info(path: &Path) -> bool {
+  let data = match try_load_hair_file(path, TfxShortStrandPolicy::Upsample) {
+    Ok(data) => data,
+    Err(e) => {
+      error!("Failed to load '{}': {}", path.to_string_lossy(), e);
+      return false;
+    }
+  };
+  let verts_per_strand = data.num_vertices_per_strand as usize;
+
+  println!("{}", data);
+  println!(
+    "Strands: {}, vertices per strand: {}, total vertices: {}",
+    data.num_hair_strands,
+    data.num_vertices_per_strand,
+    data.total_vertices()
+  );
+
+  let (bbox_min, bbox_max) = get_bounding_box(&data);
+  println!(
+    "Bounding box: min {}, max {}, size {}",
+    bbox_min,
+    bbox_max,
+    bbox_max - bbox_min
+  );
+
+  let mut strand_lengths = Vec::with_capacity(data.num_hair_strands as _);
+  let mut segment_lengths = Vec::with_capacity(data.total_vertices() as _);
+  for strand_id in 0..(data.num_hair_strands as usize) {
+    let mut strand_length = 0.0;
+    for vertex_id in 1..verts_per_strand {
+      let idx = strand_id * verts_per_strand + vertex_id;
+      let segment_length = data
+        .get_vertex_pos(idx)
+        .distance(data.get_vertex_pos(idx - 1));
+      segment_lengths.push(segment_length);
+      strand_length += segment_length;
+    }
+    strand_lengths.push(strand_length);
+  }
+
+  print_histogram("Strand length", &strand_lengths);
+  print_histogram("Segment length", &segment_lengths);
+  true
+}
+
+fn get_bounding_box(data: &TfxFileData) -> (Vec3, Vec3) {
+  (0..(data.total_vertices() as usize))
+    .map(|idx| data.get_vertex_pos(idx))
+    .fold(
+      (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+      |(min, max), p| (min.min(p), max.max(p)),
+    )
+}
+
+fn print_histogram(name: &str, values: &[f32]) {
+  if values.is_empty() {
+    println!("{}: no values", name);
+    return;
+  }
+
+  let min = values.iter().cloned().fold(f32::MAX, f32::min);
+  let max = values.iter().cloned().fold(f32::MIN, f32::max);
+  let avg = values.iter().sum::<f32>() / values.len() as f32;
+  println!("{}: min {:.4}, max {:.4}, avg {:.4}", name, min, max, avg);
+
+  let bucket_size = (max - min) / HISTOGRAM_BUCKETS as f32;
+  let mut buckets = [0usize; HISTOGRAM_BUCKETS];
+  for v in values {
+    let bucket = if bucket_size > 0.0 {
+      ((v - min) / bucket_size) as usize
+    } else {
+      0
+    };
+    buckets[bucket.min(HISTOGRAM_BUCKETS - 1)] += 1;
+  }
+
+  let max_count = *buckets.iter().max().unwrap();
+  for (i, count) in buckets.iter().enumerate() {
+    let bar_len = count * HISTOGRAM_BAR_WIDTH / max_count;
+    println!(
+      "  [{:>10.4} - {:>10.4}) {:>8} {}",
+      min + bucket_size * i as f32,
+      min + bucket_size * (i + 1) as f32,
+      count,
+      "#".repeat(bar_len)
+    );
+  }
+}
+
+//////////////////////////
+// validate
+
+fn cmd_validate(path: &Path) -> bool {
+  validate_tressfx_file(path).unwrap_or_else(|e| {
+    error!("Failed to read '{}': {}", path.to_string_lossy(), e);
+    false
+  })
+}
+
+/// Reads the header on its own, `try_load_tressfx_file()` stops at the first problem.
+fn validate_tressfx_file(path: &Path) -> std::io::Result<bool> {
+  let file = File::open(path)?;
+  let file_size = file.metadata()?.len();
+  if file_size < TfxFileData::HEADER_SIZE as u64 {
+    error!(
+      "File has {} bytes, header alone is {} bytes",
+      file_size,
+      TfxFileData::HEADER_SIZE
+    );
+    return Ok(false);
+  }
+
+  let mut r = BufReader::new(file);
+  let version = try_read_float(&mut r)?;
+  let num_hair_strands = try_read_uint(&mut r)? as u64;
+  let num_vertices_per_strand = try_read_uint(&mut r)? as u64;
+  let offsets = [
+    ("offset_vertex_position", try_read_uint(&mut r)?, 4 * 4),
+    ("offset_strand_uv", try_read_uint(&mut r)?, 2 * 4),
+    ("offset_vertex_uv", try_read_uint(&mut r)?, 2 * 4),
+    ("offset_strand_thickness", try_read_uint(&mut r)?, 4),
+    ("offset_vertex_color", try_read_uint(&mut r)?, 4 * 4),
+  ];
+  let total_vertices = num_hair_strands * num_vertices_per_strand;
+  info!(
+    "Version: {}, strands: {}, vertices per strand: {}, file size: {} bytes",
+    version, num_hair_strands, num_vertices_per_strand, file_size
+  );
+
+  let mut ok = true;
+  if num_hair_strands == 0 {
+    error!("File has no strands");
+    ok = false;
+  }
+  if !is_valid_vertices_per_strand(num_vertices_per_strand) {
+    error!(
+      "Vertices per strand should be power of 2 from 4 to 64, got {}",
+      num_vertices_per_strand
+    );
+    ok = false;
+  } else if num_vertices_per_strand != VIEWER_VERTICES_PER_STRAND {
+    warn!(
+      "Viewer requires {} vertices per strand, use 'convert --vertices {}'",
+      VIEWER_VERTICES_PER_STRAND, VIEWER_VERTICES_PER_STRAND
+    );
+  }
+
+  for (name, offset, element_size) in offsets {
+    let is_per_strand = name == "offset_strand_uv" || name == "offset_strand_thickness";
+    if offset == 0 {
+      if name == "offset_vertex_position" {
+        error!("{} is 0, file has no positions", name);
+        ok = false;
+      }
+      continue;
+    }
+
+    let element_count = if is_per_strand {
+      num_hair_strands
+    } else {
+      total_vertices
+    };
+    let end = offset as u64 + element_count * element_size;
+    if offset < TfxFileData::HEADER_SIZE {
+      error!("{} ({}) points into the header", name, offset);
+      ok = false;
+    } else if end > file_size {
+      error!(
+        "{} ({}) + array size goes past the end of file ({} > {})",
+        name, offset, end, file_size
+      );
+      ok = false;
+    } else if offset % 8 != 0 {
+      warn!("{} ({}) is not aligned to 8 bytes", name, offset);
+    }
+  }
+
+  let (_, offset_vertex_position, _) = offsets[0];
+  if ok {
+    let mut positions = Vec::with_capacity((total_vertices * 4) as _);
+    try_read_float_array(
+      &mut r,
+      &mut positions,
+      offset_vertex_position as _,
+      (total_vertices * 4) as _,
+    )?;
+    let invalid_count = positions.iter().filter(|v| !v.is_finite()).count();
+    if invalid_count > 0 {
+      error!("Found {} NaN or infinite position values", invalid_count);
+      ok = false;
+    }
+  }
+
+  if ok {
+    info!("File is valid");
+  }
+  Ok(ok)
+}
+
+/// TressFX requires power of 2 from 4 to 64 (inclusive)
+fn is_valid_vertices_per_strand(vertex_count: u64) -> bool {
+  (4..=64).contains(&vertex_count) && vertex_count.is_power_of_two()
+}
+
+//////////////////////////
+// convert
+
+fn cmd_convert(in_path: &str, out_path: &str, options: &[String]) -> bool {
+  let mut vertices_per_strand: Option<u32> = None;
+  let mut decimate_ratio: Option<f32> = None;
+  let mut short_strands = TfxShortStrandPolicy::Upsample;
+
+  let mut opts = options.iter();
+  while let Some(opt) = opts.next() {
+    match opt.as_str() {
+      "--vertices" => match parse_option_value(opt, opts.next()) {
+        Some(v) => vertices_per_strand = Some(v),
+        None => return false,
+      },
+      "--decimate" => match parse_option_value(opt, opts.next()) {
+        Some(v) => decimate_ratio = Some(v),
+        None => return false,
+      },
+      "--drop-short-strands" => short_strands = TfxShortStrandPolicy::Drop,
+      _ => {
+        error!("Unknown option '{}'", opt);
+        return false;
+      }
+    }
+  }
+
+  // validate options before the (possibly slow) import
+  if let Some(ratio) = decimate_ratio {
+    if ratio <= 0.0 || ratio > 1.0 {
+      error!("Decimate ratio should be in (0, 1], got {}", ratio);
+      return false;
+    }
+  }
+  if let Some(vertex_count) = vertices_per_strand {
+    if !is_valid_vertices_per_strand(vertex_count as _) {
+      error!(
+        "Vertices per strand should be power of 2 from 4 to 64, got {}",
+        vertex_count
+      );
+      return false;
+    }
+    if vertex_count as u64 != VIEWER_VERTICES_PER_STRAND {
+      warn!(
+        "Viewer requires {} vertices per strand, output will not be loadable in the viewer",
+        VIEWER_VERTICES_PER_STRAND
+      );
+    }
+  }
+
+  let mut data = match try_load_hair_file(Path::new(in_path), short_strands) {
+    Ok(data) => data,
+    Err(e) => {
+      error!("Failed to load '{}': {}", in_path, e);
+      return false;
+    }
+  };
+  if let Some(ratio) = decimate_ratio {
+    decimate_strands(&mut data, ratio);
+  }
+  if let Some(vertex_count) = vertices_per_strand {
+    resample_strands(&mut data, vertex_count);
+  }
+
+  match save_tressfx_file(Path::new(out_path), &data) {
+    Ok(_) => {
+      info!(
+        "Written {} strands with {} vertices each",
+        data.num_hair_strands, data.num_vertices_per_strand
+      );
+      true
+    }
+    Err(e) => {
+      error!("Failed to write '{}': {}", out_path, e);
+      false
+    }
+  }
+}
+
+/// Logs an error and returns `None` if the value is missing or invalid.
+fn parse_option_value<T: std::str::FromStr>(opt: &str, value: Option<&String>) -> Option<T> {
+  match value {
+    None => {
+      error!("Option '{}' requires a value", opt);
+      None
+    }
+    Some(value) => {
+      let parsed = value.parse().ok();
+      if parsed.is_none() {
+        error!("Invalid value '{}' for option '{}'", value, opt);
+      }
+      parsed
+    }
+  }
+}
+
+/// Keep evenly distributed subset of strands.
+fn decimate_strands(data: &mut TfxFileData, ratio: f32) {
+  let floats_per_strand = (data.num_vertices_per_strand * 4) as usize;
+  let strand_count = data.num_hair_strands as usize;
+  let keep_count = ((strand_count as f32 * ratio).round() as usize).max(1);
+
+  let mut positions = Vec::with_capacity(keep_count * floats_per_strand);
+  let mut weights = Vec::with_capacity(keep_count * floats_per_strand);
+  for i in 0..keep_count {
+    let strand_id = i * strand_count / keep_count;
+    let range = (strand_id * floats_per_strand)..((strand_id + 1) * floats_per_strand);
+    positions.extend_from_slice(&data.raw_vertex_positions[range.clone()]);
+    weights.extend_from_slice(&data.raw_vertex_weights[range]);
+  }
+
+  info!("Decimated {} strands to {}", strand_count, keep_count);
+  data.num_hair_strands = keep_count as _;
+  data.raw_vertex_positions = positions;
+  data.raw_vertex_weights = weights;
+}
+
+/// Simulation weights can not be resampled in a meaningful way, so they are reset.
+fn resample_strands(data: &mut TfxFileData, vertices_per_strand: u32) {
+  let old_verts_per_strand = data.num_vertices_per_strand as usize;
+  let mut positions = Vec::with_capacity((data.num_hair_strands * vertices_per_strand * 4) as _);
+
+  for strand_id in 0..(data.num_hair_strands as usize) {
+    let first_vertex = strand_id * old_verts_per_strand;
+    let strand: Vec<Vec3> = (0..old_verts_per_strand)
+      .map(|i| data.get_vertex_pos(first_vertex + i))
+      .collect();
+    let resampled = resample_strand(&strand, vertices_per_strand as _);
+    for (i, p) in resampled.iter().enumerate() {
+      // root is not movable
+      let w = if i == 0 { 0.0 } else { 1.0 };
+      positions.extend_from_slice(&[p.x, p.y, p.z, w]);
+    }
+  }
+
+  info!(
+    "Resampled strands from {} to {} vertices each",
+    old_verts_per_strand, vertices_per_strand
+  );
+  data.num_vertices_per_strand = vertices_per_strand;
+  data.raw_vertex_positions = positions;
+  data.fill_default_vertex_weights();
+}
@@ -4,7 +4,6 @@ use glam::{vec2, vec4, Vec2, Vec3, Vec4};
 
 use crate::utils::color_hex_to_vec;
 
-use self::tfx_simulation::TfxSimulation;
 pub use self::{camera::*, color_grading::*, light::*, postfx::*, shadows::*, ssao::*, sss::*};
 
 pub mod camera;
@@ -14,7 +13,6 @@ pub mod postfx;
 pub mod shadows;
 pub mod ssao;
 pub mod sss;
-pub mod tfx_simulation;
 
 // Must match consts in `present.frag.glsl`.
 pub enum DisplayMode {
@@ -31,6 +29,7 @@ pub enum DisplayMode {
 pub enum HairTechnique {
   PPLL = 0,
   Solid = 1,
+  WBOIT = 2,
 }
 
 /// Must match consts in `tfx_ppll_resolve.frag.glsl`
@@ -40,6 +39,18 @@ pub enum HairPPLLDisplayMode {
   PpllOverlap = 2,
   Tangents = 3,
   Coverage = 4,
+  ListLength = 5,
+}
+
+/// How to shade PPLL fragments that did not fit into the K-buffer.
+/// Must match consts in `_tfx_ppll_resolve_impl.glsl`.
+pub enum HairPPLLTailMode {
+  /// Flat albedo color
+  SimpleShading = 0,
+  /// Only K closest fragments are visible
+  Ignore = 1,
+  /// Same shading as K closest fragments. Slow
+  FullShading = 2,
 }
 
 /// Must match consts in `tfx_forward.frag.glsl`.
@@ -51,6 +62,14 @@ pub enum HairSolidDisplayMode {
   RootTipPercentage = 4,
 }
 
+/// Must match consts in `tfx_wboit_resolve.frag.glsl`.
+pub enum HairWboitDisplayMode {
+  Final = 0,
+  Flat = 1,
+  Revealage = 2,
+  Weights = 3,
+}
+
 /// https://github.com/Scthe/WebFX/blob/master/src/Config.ts
 pub struct Config {
   /// crash program after first frame to read init errors
@@ -71,6 +90,11 @@ pub struct Config {
   pub window_width: f64,
   pub window_height: f64,
   vsync: bool,
+  /// Index into `Config::RENDER_SCALES`. Size of the render targets relative to the window.
+  /// Result is resampled to the window size in `PresentPass`
+  pub render_scale_idx: usize,
+  /// Index into `Config::MSAA_SAMPLES`. Only for the meshes forward pass
+  pub msaa_samples_idx: usize,
   // clear colors
   pub clear_color: Vec3,
   pub clear_normal: [u32; 4],
@@ -82,12 +106,21 @@ pub struct Config {
   pub hair_technique: usize,
   pub hair_ppll_display_mode: usize,
   pub hair_solid_display_mode: usize,
-  pub tfx_simulation: TfxSimulation,
+  pub hair_wboit_display_mode: usize,
+  /// Index into `Config::PPLL_KBUFFER_SIZES`. Closest fragments are sorted and shaded with full quality
+  pub hair_ppll_kbuffer_size_idx: usize,
+  pub hair_ppll_tail_mode: usize,
+  /// Clamp projected strand width to this many pixels and lower coverage to compensate. 0 to disable
+  pub hair_ppll_min_pixel_width: f32,
+  /// Same as `hair_ppll_min_pixel_width`. Solid technique has no transparency, so pixels are dithered instead
+  pub hair_solid_min_pixel_width: f32,
+  /// Same as `hair_ppll_min_pixel_width`
+  pub hair_wboit_min_pixel_width: f32,
   // lights
   pub light_ambient: LightAmbient,
-  pub light0: LightCfg,
-  pub light1: LightCfg,
-  pub light2: LightCfg,
+  pub environment: EnvironmentCfg,
+  /// At most `Config::MAX_LIGHTS`
+  pub lights: Vec<LightCfg>,
   // shadows
   pub shadows: ShadowsConfig,
   // sss
@@ -99,7 +132,6 @@ pub struct Config {
   pub postfx: PostFxCfg,
   // misc
   // showDebugPositions = false;
-  // useMSAA = true; // ok, technically it's brute force supersampling, but who cares?
   // center_of_gravity: vec3(0, 3.0, 0), // used for calulating hair normals (remember, no cards!)
 
   // TressFX collision spheres
@@ -121,6 +153,18 @@ impl Config {
   pub const STENCIL_BIT_SKIN: u32 = 1 << 0;
   pub const STENCIL_BIT_HAIR: u32 = 1 << 1;
 
+  /// PPLL resolve pipeline is precompiled for each of these (specialization constants)
+  pub const PPLL_KBUFFER_SIZES: [u32; 3] = [4, 8, 16];
+
+  /// Size of the lights SSBO
+  pub const MAX_LIGHTS: usize = 16;
+
+  /// Above 1.0 it's brute force supersampling
+  pub const RENDER_SCALES: [f32; 5] = [0.5, 0.75, 1.0, 1.5, 2.0];
+
+  /// 1 means no MSAA. Clamped to what the GPU supports
+  pub const MSAA_SAMPLES: [u32; 4] = [1, 2, 4, 8];
+
   pub fn new() -> Config {
     let clear_col: u8 = 93;
 
@@ -137,6 +181,8 @@ impl Config {
       window_width: 1280f64,
       window_height: 720f64,
       vsync: true,
+      render_scale_idx: 2,
+      msaa_samples_idx: 0,
       // clear colors
       clear_color: color_hex_to_vec(clear_col, clear_col, clear_col),
       clear_normal: [0, 0, 0, 0], // or [1,1,1,1] for performance reasons
@@ -148,12 +194,16 @@ impl Config {
       hair_technique: HairTechnique::PPLL as _,
       hair_ppll_display_mode: HairPPLLDisplayMode::Final as _,
       hair_solid_display_mode: HairSolidDisplayMode::Final as _,
-      tfx_simulation: TfxSimulation::default(),
+      hair_wboit_display_mode: HairWboitDisplayMode::Final as _,
+      hair_ppll_kbuffer_size_idx: 0,
+      hair_ppll_tail_mode: HairPPLLTailMode::SimpleShading as _,
+      hair_ppll_min_pixel_width: 0.0,
+      hair_solid_min_pixel_width: 0.0,
+      hair_wboit_min_pixel_width: 0.0,
       // lights
       light_ambient: LightAmbient::default(),
-      light0: LightCfg::light0(),
-      light1: LightCfg::light1(),
-      light2: LightCfg::light2(),
+      environment: EnvironmentCfg::default(),
+      lights: vec![LightCfg::light0(), LightCfg::light1(), LightCfg::light2()],
       // material + lights
       ssao: SSAOConfig::default(),
       shadows: ShadowsConfig::default(),
@@ -169,10 +219,20 @@ impl Config {
     }
   }
 
-  pub fn get_viewport_size(&self) -> vk::Extent2D {
+  pub fn render_scale(&self) -> f32 {
+    Self::RENDER_SCALES[self.render_scale_idx]
+  }
+
+  pub fn msaa_samples(&self) -> u32 {
+    Self::MSAA_SAMPLES[self.msaa_samples_idx]
+  }
+
+  /// Size of the render targets. Depends on `render_scale_idx`
+  pub fn get_render_size(&self, window_size: vk::Extent2D) -> vk::Extent2D {
+    let scale = self.render_scale();
     vk::Extent2D {
-      width: self.window_width as u32,
-      height: self.window_height as u32,
+      width: ((window_size.width as f32 * scale) as u32).max(1),
+      height: ((window_size.height as f32 * scale) as u32).max(1),
     }
   }
 
@@ -184,10 +244,10 @@ impl Config {
     self.is_release
   }
 
-  pub fn get_ssao_viewport_size(&self) -> vk::Extent2D {
+  pub fn get_ssao_viewport_size(&self, render_size: vk::Extent2D) -> vk::Extent2D {
     vk::Extent2D {
-      width: (self.window_width as u32) / self.ssao.texture_size_div,
-      height: (self.window_height as u32) / self.ssao.texture_size_div,
+      width: render_size.width / self.ssao.texture_size_div,
+      height: render_size.height / self.ssao.texture_size_div,
     }
   }
 
@@ -208,6 +268,14 @@ impl Config {
     }
   }
 
+  pub fn clear_velocity(&self) -> vk::ClearValue {
+    vk::ClearValue {
+      color: vk::ClearColorValue {
+        float32: [0f32, 0f32, 0f32, 0f32],
+      },
+    }
+  }
+
   pub fn clear_depth_stencil(&self) -> vk::ClearValue {
     vk::ClearValue {
       depth_stencil: vk::ClearDepthStencilValue {
@@ -237,7 +305,7 @@ impl Config {
   }
 
   pub fn fxaa_edge_threshold(&self) -> f32 {
-    if self.postfx.use_fxaa {
+    if self.postfx.use_fxaa() {
       self.postfx.edge_threshold
     } else {
       0.0
@@ -248,8 +316,27 @@ impl Config {
     (self.camera.fov_dgr / self.window_width as f32) * self.window_height as f32
   }
 
+  /// Number of lights uploaded to the GPU
+  pub fn get_light_count(&self) -> usize {
+    self.lights.len().min(Self::MAX_LIGHTS)
+  }
+
   pub fn is_hair_using_ppll(&self) -> bool {
-    self.hair_technique != (HairTechnique::Solid as _)
+    self.hair_technique == (HairTechnique::PPLL as _)
+  }
+
+  pub fn is_hair_using_wboit(&self) -> bool {
+    self.hair_technique == (HairTechnique::WBOIT as _)
+  }
+
+  pub fn get_hair_min_pixel_width(&self) -> f32 {
+    if self.is_hair_using_ppll() {
+      self.hair_ppll_min_pixel_width
+    } else if self.is_hair_using_wboit() {
+      self.hair_wboit_min_pixel_width
+    } else {
+      self.hair_solid_min_pixel_width
+    }
   }
 
   pub fn get_hair_display_mode(&self) -> usize {
@@ -257,6 +344,8 @@ impl Config {
     if allow_debug_mode {
       if self.is_hair_using_ppll() {
         return self.hair_ppll_display_mode;
+      } else if self.is_hair_using_wboit() {
+        return self.hair_wboit_display_mode;
       } else {
         return self.hair_solid_display_mode;
       }
@@ -266,6 +355,8 @@ impl Config {
     // This includes e.g. luma (takes render final output and greyscales it) etc.
     if self.is_hair_using_ppll() {
       return HairPPLLDisplayMode::Final as _;
+    } else if self.is_hair_using_wboit() {
+      return HairWboitDisplayMode::Final as _;
     } else {
       return HairSolidDisplayMode::Final as _;
     }
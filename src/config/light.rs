@@ -1,6 +1,7 @@
+use glam::{vec3, Vec3};
 use mint::Vector3;
 
-use crate::utils::{color_hex_to_vec, vec3_to_mint};
+use crate::utils::{color_hex_to_vec, spherical_to_cartesian_dgr, vec3_to_mint};
 
 pub struct LightAmbient {
   pub color: Vector3<f32>,
@@ -16,24 +17,94 @@ impl Default for LightAmbient {
   }
 }
 
+/// Image based lighting. Only used if the environment map was provided (`--environment-map`).
+pub struct EnvironmentCfg {
+  /// Use the environment map instead of `LightAmbient`
+  pub enabled: bool,
+  pub intensity: f32,
+  /// Rotation around the vertical axis [dgr]
+  pub rotation: f32,
+  /// Render the environment map as background instead of `Config::clear_color`
+  pub show_skybox: bool,
+  /// Which prefiltered mip to show as background. 0 - sharp, 1 - most blurred
+  pub skybox_blur: f32,
+}
+
+impl Default for EnvironmentCfg {
+  fn default() -> Self {
+    Self {
+      enabled: true,
+      intensity: 1.0,
+      rotation: 0.0,
+      show_skybox: true,
+      skybox_blur: 0.0,
+    }
+  }
+}
+
+/// Must match consts in `_lights.glsl`
+pub enum LightType {
+  Directional = 0,
+  Point = 1,
+  Spot = 2,
+}
+
 pub struct LightCfg {
+  pub light_type: usize,
   /// horizontal [dgr]
   pub pos_phi: f32,
   /// verical [dgr]
   pub pos_theta: f32,
   pub pos_distance: f32,
+  /// Directional and spot lights shine towards this point
+  pub look_at_target: Vec3,
   pub color: Vector3<f32>,
   pub energy: f32,
+  /// Point and spot lights do not reach further than this
+  pub radius: f32,
+  /// Exponent of the distance attenuation. 0 means no attenuation inside the `radius`
+  pub falloff: f32,
+  /// Spot light has full intensity inside this angle [dgr]
+  pub spot_inner_angle: f32,
+  /// Spot light has no effect outside this angle [dgr]
+  pub spot_outer_angle: f32,
+  /// Request a slot in the shadow atlas. See `ShadowAtlas`
+  pub casts_shadow: bool,
+}
+
+impl Default for LightCfg {
+  fn default() -> Self {
+    Self {
+      light_type: LightType::Point as _,
+      pos_phi: 0.0,
+      pos_theta: 45.0,
+      pos_distance: 8.0,
+      look_at_target: vec3(0.0, 5.0, 0.0),
+      color: vec3_to_mint(Vec3::ONE),
+      energy: 1.0,
+      radius: 15.0,
+      falloff: 2.0,
+      spot_inner_angle: 20.0,
+      spot_outer_angle: 30.0,
+      casts_shadow: false,
+    }
+  }
 }
 
 impl LightCfg {
+  /// Default lights do not attenuate with distance. Key light is directional and casts shadows
   pub fn light0() -> Self {
     LightCfg {
+      light_type: LightType::Directional as _,
+      casts_shadow: true,
       pos_phi: 125.0,  // horizontal [dgr]
       pos_theta: 45.0, // verical [dgr]
       pos_distance: 10.0,
       color: vec3_to_mint(color_hex_to_vec(214, 197, 208)),
       energy: 0.6,
+      radius: 50.0,
+      falloff: 0.0,
+      ..Default::default()
     }
   }
 
@@ -44,6 +115,9 @@ impl LightCfg {
       pos_distance: 10.0,
       color: vec3_to_mint(color_hex_to_vec(214, 166, 166)),
       energy: 1.25,
+      radius: 50.0,
+      falloff: 0.0,
+      ..Default::default()
     }
   }
 
@@ -54,6 +128,35 @@ impl LightCfg {
       pos_distance: 10.0,
       color: vec3_to_mint(color_hex_to_vec(154, 138, 112)),
       energy: 0.7,
+      radius: 50.0,
+      falloff: 0.0,
+      ..Default::default()
+    }
+  }
+
+  pub fn position(&self) -> Vec3 {
+    spherical_to_cartesian_dgr(self.pos_phi, self.pos_theta, self.pos_distance)
+  }
+
+  /// Direction the light shines in. Only for directional and spot lights.
+  /// Directional lights depend only on the angles, like the sun.
+  pub fn direction(&self) -> Vec3 {
+    if self.is_directional() {
+      -spherical_to_cartesian_dgr(self.pos_phi, self.pos_theta, 1.0)
+    } else {
+      (self.look_at_target - self.position()).normalize_or_zero()
     }
   }
+
+  pub fn is_directional(&self) -> bool {
+    self.light_type == (LightType::Directional as _)
+  }
+
+  pub fn is_spot(&self) -> bool {
+    self.light_type == (LightType::Spot as _)
+  }
+
+  pub fn is_point(&self) -> bool {
+    self.light_type == (LightType::Point as _)
+  }
 }
@@ -8,24 +8,91 @@ pub enum TonemappingMode {
   AcesUe4 = 4,
 }
 
+/// FXAA runs in the present pass, TAA before tonemapping
+#[derive(Clone, Copy)]
+pub enum AntiAliasing {
+  None = 0,
+  FXAA = 1,
+  /// Temporal anti-aliasing. Jitters the camera and accumulates samples from previous frames.
+  TAA = 2,
+}
+
+/// Result of the auto exposure pass, read back from GPU. Debug only, displayed in the UI.
+/// Lags a few frames behind (frames in flight).
+#[derive(Default)]
+pub struct AutoExposureStats {
+  pub exposure: f32,
+  pub adapted_luminance: f32,
+  pub target_luminance: f32,
+  pub histogram: Vec<f32>,
+}
+
 pub struct PostFxCfg {
   pub gamma: f32,
   pub dither_strength: f32,
   // tonemapping
   pub tonemapping_op: usize,
-  pub exposure: f32, // or calc automatically?
+  /// Used only if `auto_exposure` is off
+  pub exposure: f32,
   pub white_point: f32,
   pub aces_c: f32,
   pub aces_s: f32,
+  // auto exposure
+  /// Calculate exposure from the luminance histogram of the HDR image
+  pub auto_exposure: bool,
+  /// Log2 of the luminance. Histogram covers only `[min_ev, max_ev]` range
+  pub auto_exposure_min_ev: f32,
+  pub auto_exposure_max_ev: f32,
+  /// How fast the exposure adapts to the new luminance. Higher is faster
+  pub auto_exposure_speed: f32,
+  /// In stops (EV). Applied on top of auto exposure
+  pub exposure_compensation: f32,
+  pub auto_exposure_stats: AutoExposureStats,
+  // bloom
+  pub use_bloom: bool,
+  /// Only pixels brighter than this contribute to bloom. Before exposure
+  pub bloom_threshold: f32,
+  /// Fraction of `bloom_threshold`. Smooth transition instead of a hard cutoff
+  pub bloom_soft_knee: f32,
+  pub bloom_intensity: f32,
+  /// Scales sample offsets of the blur
+  pub bloom_radius: f32,
+  // lens effects
+  pub use_vignette: bool,
+  pub vignette_strength: f32,
+  /// Distance from the center where vignette starts, `[0, 1]`
+  pub vignette_radius: f32,
+  pub use_chromatic_aberration: bool,
+  pub chromatic_aberration: f32,
+  pub use_film_grain: bool,
+  pub film_grain: f32,
+  // depth of field
+  pub use_dof: bool,
+  /// Distance to the object in focus. In scene units, treated as meters
+  pub dof_focus_distance: f32,
+  /// Aperture as f-number. Lower values have shallower depth of field
+  pub dof_f_stop: f32,
+  /// In millimeters. Only affects depth of field, field of view is set separately
+  pub dof_focal_length: f32,
+  /// Max radius of the blur in pixels. Also limits the sample count
+  pub dof_max_coc: f32,
+  /// Middle mouse button click sets focus distance to the depth under the cursor
+  pub dof_autofocus: bool,
   // color grading
   // @see https://docs.unrealengine.com/en-us/Engine/Rendering/PostProcessEffects/ColorGrading
   pub color_grading: ColorGradingCfg,
+  /// See `AntiAliasing`
+  pub anti_aliasing: usize,
   // fxaa
-  pub use_fxaa: bool,
   pub fxaa_luma_gamma: f32,
   pub subpixel: f32,
   pub edge_threshold: f32,
   pub edge_threshold_min: f32,
+  // taa
+  /// How much of the previous frames to keep. Higher values are smoother, but ghost more
+  pub taa_history_weight: f32,
+  /// Reject history that does not match current frame's neighbourhood colors
+  pub taa_neighbourhood_clamp: bool,
 }
 
 impl Default for PostFxCfg {
@@ -38,10 +105,38 @@ impl Default for PostFxCfg {
       dither_strength: 1.5,
       // tonemapping
       tonemapping_op: TonemappingMode::AcesUe4 as _,
-      exposure: 1.0, // or calc automatically?
+      exposure: 1.0,
       white_point: 1.0,
       aces_c: 0.8,
       aces_s: 1.0,
+      // auto exposure
+      auto_exposure: false,
+      auto_exposure_min_ev: -8.0,
+      auto_exposure_max_ev: 4.0,
+      auto_exposure_speed: 1.5,
+      exposure_compensation: 0.0,
+      auto_exposure_stats: AutoExposureStats::default(),
+      // bloom
+      use_bloom: true,
+      bloom_threshold: 1.0,
+      bloom_soft_knee: 0.5,
+      bloom_intensity: 0.3,
+      bloom_radius: 1.0,
+      // lens effects
+      use_vignette: false,
+      vignette_strength: 0.4,
+      vignette_radius: 0.4,
+      use_chromatic_aberration: false,
+      chromatic_aberration: 0.5,
+      use_film_grain: false,
+      film_grain: 0.05,
+      // depth of field
+      use_dof: false,
+      dof_focus_distance: 10.0,
+      dof_f_stop: 1.8,
+      dof_focal_length: 85.0,
+      dof_max_coc: 12.0,
+      dof_autofocus: true,
       // color grading
       // @see https://docs.unrealengine.com/en-us/Engine/Rendering/PostProcessEffects/ColorGrading
       color_grading: ColorGradingCfg {
@@ -52,12 +147,39 @@ impl Default for PostFxCfg {
         midtones: ColorGradingPerRangeSettings::default(),
         highlights: ColorGradingPerRangeSettings::default(),
       },
+      anti_aliasing: AntiAliasing::FXAA as _,
       // fxaa
-      use_fxaa: true,
       subpixel: 0.75,
       edge_threshold: 0.125,
       edge_threshold_min: 0.0625,
       fxaa_luma_gamma: 2.2,
+      // taa
+      taa_history_weight: 0.9,
+      taa_neighbourhood_clamp: true,
     }
   }
 }
+
+impl PostFxCfg {
+  /// Full frame 35mm sensor
+  const DOF_SENSOR_HEIGHT_MM: f32 = 24.0;
+
+  pub fn use_fxaa(&self) -> bool {
+    self.anti_aliasing == (AntiAliasing::FXAA as _)
+  }
+
+  pub fn use_taa(&self) -> bool {
+    self.anti_aliasing == (AntiAliasing::TAA as _)
+  }
+
+  /// Thin lens circle of confusion is `A * f * |S - D| / (D * (S - f))`, where
+  /// `A = f / N` is the aperture diameter, `S` focus distance and `D` object distance.
+  /// Rewritten as `scale * |1 - S / D|`, this returns the `scale` in pixels.
+  pub fn dof_coc_scale(&self, render_height: u32) -> f32 {
+    let focal_length = self.dof_focal_length;
+    let aperture = focal_length / self.dof_f_stop;
+    let focus_distance_mm = (self.dof_focus_distance * 1000.0).max(focal_length + 1.0);
+    let coc_mm = aperture * focal_length / (focus_distance_mm - focal_length);
+    coc_mm / Self::DOF_SENSOR_HEIGHT_MM * render_height as f32
+  }
+}
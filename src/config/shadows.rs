@@ -16,11 +16,26 @@ pub struct ShadowSourceCfg {
   pub pos_phi: f32,
   /// verical [dgr]
   pub pos_theta: f32,
-  pub pos_distance: u32, // verify with projection box below!!!
+  pub pos_distance: u32, // verify with projection box below!!! Ignored if `ShadowsConfig::fit_to_scene`
   pub look_at_target: Vec3,
   pub projection: ShadowLightProjection,
 }
 
+impl Default for ShadowLightProjection {
+  /// Orthographic box for directional lights, all rays are parallel
+  fn default() -> Self {
+    let proj_box_side = ShadowsConfig::SHADOWS_ORTHO_SIZE as f32;
+    Self {
+      left: -proj_box_side,
+      right: proj_box_side,
+      top: proj_box_side,
+      bottom: -proj_box_side,
+      near: 0.1,
+      far: 40.0,
+    }
+  }
+}
+
 impl ShadowSourceCfg {
   pub fn position(&self) -> Vec3 {
     spherical_to_cartesian_dgr(self.pos_phi, self.pos_theta, self.pos_distance as f32)
@@ -29,20 +44,12 @@ impl ShadowSourceCfg {
 
 impl Default for ShadowSourceCfg {
   fn default() -> Self {
-    let proj_box_side = ShadowsConfig::SHADOWS_ORTHO_SIZE as f32;
     Self {
       pos_phi: 51.5,
       pos_theta: 45.0,
       pos_distance: 20,
       look_at_target: vec3(0.0, 5.0, 0.0),
-      projection: ShadowLightProjection {
-        left: -proj_box_side,
-        right: proj_box_side,
-        top: proj_box_side,
-        bottom: -proj_box_side,
-        near: 0.1,
-        far: 40.0,
-      },
+      projection: ShadowLightProjection::default(),
     }
   }
 }
@@ -51,14 +58,24 @@ pub enum ShadowTechnique {
   BinaryDebug = 0,
   PFC = 1,
   PCSS = 2,
+  /// Variance shadow maps. Uses blurred moments instead of depth comparison
+  VSM = 3,
 }
 
 pub struct ShadowsConfig {
   // TODO [MEDIUM] pub show_debug_view: bool, // overlay shadow and sss depth map
-  pub shadowmap_size: u32,
+  /// Size of the shadow atlas texture. See `ShadowAtlas`
+  pub atlas_size: u32,
   pub shadow_technique: usize,
   pub strength: f32,
-  pub shadow_source: ShadowSourceCfg,
+  /// Fit orthographic frustums (directional lights and SSS depth) to the scene bounds.
+  /// If false, use `directional_projection` and `ShadowSourceCfg::projection`.
+  pub fit_to_scene: bool,
+  /// Projection used by all directional lights
+  pub directional_projection: ShadowLightProjection,
+  /// Distance from `look_at_target` to directional light's shadow camera.
+  /// Verify with projection box above!!!
+  pub directional_distance: f32,
 
   /// in pixels
   pub blur_radius: u32,
@@ -69,17 +86,32 @@ pub struct ShadowsConfig {
 
   // hair-only:
   pub hair_tfx_radius_multipler: f32,
+
+  // VSM-only:
+  /// in pixels of the shadow atlas
+  pub vsm_blur_radius: usize,
+  pub vsm_blur_gauss_sigma: f32,
+  /// Cut off lowest values of Chebyshev's upper bound to remove light bleeding
+  pub vsm_light_bleeding_reduction: f32,
+  /// Prevents numerical issues on flat surfaces. Replaces bias used by other techniques
+  pub vsm_min_variance: f32,
 }
 
 impl ShadowsConfig {
   pub const SHADOWS_ORTHO_SIZE: u32 = 10;
+  /// Shadow atlas is a grid of `ATLAS_TILES_PER_ROW^2` square tiles
+  pub const ATLAS_TILES_PER_ROW: u32 = 4;
+
+  pub fn is_vsm(&self) -> bool {
+    self.shadow_technique == (ShadowTechnique::VSM as _)
+  }
 }
 
 impl Default for ShadowsConfig {
   fn default() -> Self {
     Self {
       // show_debug_view: false,
-      shadowmap_size: 1024 * 2,
+      atlas_size: 1024 * 4,
       shadow_technique: ShadowTechnique::PCSS as _,
       blur_radius: 4,
       blur_radius_tfx: 1,
@@ -87,7 +119,13 @@ impl Default for ShadowsConfig {
       bias_hair_tfx: 0.020,
       hair_tfx_radius_multipler: 1.1,
       strength: 0.7,
-      shadow_source: ShadowSourceCfg::default(),
+      fit_to_scene: true,
+      directional_projection: ShadowLightProjection::default(),
+      directional_distance: 20.0,
+      vsm_blur_radius: 4,
+      vsm_blur_gauss_sigma: 2.5,
+      vsm_light_bleeding_reduction: 0.2,
+      vsm_min_variance: 0.00002,
     }
   }
 }
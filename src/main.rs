@@ -1,4 +1,4 @@
-use log::{info, trace, warn};
+use log::{error, info, trace, warn};
 use winit::{
   dpi::LogicalSize,
   event::{DeviceEvent, Event, MouseButton},
@@ -7,11 +7,18 @@ use winit::{
 };
 
 use crate::{
-  app_input::AppInput, app_timer::AppTimer, app_ui::AppUI, config::Config,
-  gpu_profiler::GpuProfiler, render_graph::RenderGraph, scene::load_scene,
+  app_cli::AppCli,
+  app_input::AppInput,
+  app_timer::AppTimer,
+  app_ui::AppUI,
+  config::Config,
+  gpu_profiler::GpuProfiler,
+  render_graph::RenderGraph,
+  scene::{load_scene, TfxGroomRay},
   vk_ctx::vk_ctx_initialize,
 };
 
+mod app_cli;
 mod app_input;
 mod app_timer;
 mod app_ui;
@@ -32,6 +39,7 @@ fn main() {
   info!("-- Start --");
 
   // config
+  let app_cli = AppCli::parse();
   let mut config = Config::new();
   let mut timer = AppTimer::new();
 
@@ -55,7 +63,7 @@ fn main() {
   let mut profiler = GpuProfiler::new(&vk_app);
 
   // scene
-  let mut scene = load_scene(&vk_app, &config);
+  let mut scene = load_scene(&vk_app, &config, &app_cli);
   info!("Scene init: OK!");
 
   // render graph
@@ -86,7 +94,7 @@ fn main() {
         event: DeviceEvent::MouseMotion { delta, .. },
         ..
       } if !imgui_intercepted => {
-        if app_input.is_mouse_button_pressed(MouseButton::Left) {
+        if app_input.is_mouse_button_pressed(MouseButton::Left) && !scene.is_grooming() {
           // info!("Mouse delta {:?}", delta);
           scene
             .camera
@@ -108,6 +116,29 @@ fn main() {
           }
         }
         config.reset_tfx_simulation_next_frame = false;
+        if app_input.is_mouse_button_pressed(MouseButton::Left) && scene.is_grooming() {
+          let window_size = vk_app.window_size();
+          let camera = &scene.camera;
+          let (origin, dir) = camera.get_cursor_ray(app_input.cursor_position, window_size);
+          let ray = TfxGroomRay { origin, dir };
+          let (origin, dir) =
+            camera.get_cursor_ray(app_input.cursor_position_last_frame, window_size);
+          let prev_ray = TfxGroomRay { origin, dir };
+          let is_stroke_start = app_input.is_mouse_button_just_pressed(MouseButton::Left);
+          for tfx_entity in &mut scene.tressfx_objects {
+            if tfx_entity.groom.enabled {
+              tfx_entity.apply_groom_tool(&prev_ray, &ray, is_stroke_start);
+            }
+          }
+        } else {
+          // stroke ended (or grooming was disabled mid-stroke)
+          for tfx_entity in &mut scene.tressfx_objects {
+            tfx_entity.upload_groomed_rest_pose(&vk_app);
+          }
+        }
+        for tfx_entity in &mut scene.tressfx_objects {
+          tfx_entity.update_simulation_cache_playback(&vk_app);
+        }
 
         render_graph.execute_render_graph(
           &window,
@@ -118,6 +149,66 @@ fn main() {
           &timer,
           &mut profiler,
         );
+        for tfx_entity in &mut scene.tressfx_objects {
+          tfx_entity.update_simulation_cache_recording(&vk_app, timer.frame_idx());
+        }
+
+        // depth of field autofocus
+        let postfx = &config.postfx;
+        if postfx.use_dof
+          && postfx.dof_autofocus
+          && app_input.is_mouse_button_just_pressed(MouseButton::Middle)
+        {
+          let distance = render_graph.read_linear_depth(&vk_app, app_input.cursor_position);
+          // cleared depth buffer (nothing under the cursor) is at the far plane
+          if distance < config.camera.z_far * 0.99 {
+            info!("DoF autofocus distance: {}", distance);
+            config.postfx.dof_focus_distance = distance;
+          }
+        }
+
+        // strand picking + inspector
+        let frame_idx = timer.frame_idx();
+        if app_input.is_mouse_button_just_pressed(MouseButton::Right) {
+          let (origin, dir) = scene
+            .camera
+            .get_cursor_ray(app_input.cursor_position, vk_app.window_size());
+          scene.pick_strand(&vk_app, frame_idx, origin, dir);
+        }
+        for tfx_entity in &mut scene.tressfx_objects {
+          if tfx_entity.strand_inspector.live_update {
+            tfx_entity.update_strand_inspector(&vk_app, frame_idx);
+          }
+        }
+
+        // export hair strands (requested from UI or CLI)
+        let is_cli_export_frame = timer.frame_idx() + 1 == app_cli.export_after_frames;
+        let tfx_count = scene.tressfx_objects.len();
+        for tfx_entity in &mut scene.tressfx_objects {
+          let cli_export_path = app_cli
+            .get_export_path(&tfx_entity.name, tfx_count)
+            .filter(|_| is_cli_export_frame);
+          let export = &mut tfx_entity.export_settings;
+          let (path, include_follow_hairs) = match cli_export_path {
+            Some(path) => (path, app_cli.export_follow_hairs),
+            None if export.export_next_frame => (
+              std::path::PathBuf::from(&export.file_path),
+              export.include_follow_hairs,
+            ),
+            None => continue,
+          };
+          export.export_next_frame = false;
+
+          let frame_idx = timer.frame_idx();
+          if let Err(e) = tfx_entity.export_strands(&vk_app, frame_idx, &path, include_follow_hairs)
+          {
+            error!("Failed to export '{}': {}", tfx_entity.name, e);
+          }
+        }
+        if app_cli.export_hair.is_some() && is_cli_export_frame {
+          info!("Closing app after exporting hair from command line");
+          *control_flow = ControlFlow::Exit;
+        }
 
         // clear input events after processed
         app_input.reset_transient_state();
@@ -4,38 +4,51 @@ use std::mem::size_of;
 use ash;
 use ash::vk;
 use bytemuck;
+use glam::{vec2, Vec2};
 
 use crate::app_timer::AppTimer;
 use crate::app_ui::AppUI;
 use crate::config::Config;
+use crate::either;
 use crate::gpu_profiler::GpuProfiler;
 use crate::scene::World;
 use crate::vk_ctx::{VkCtx, VkCtxSwapchainImage};
 use crate::vk_utils::*;
 
 mod _shared;
+mod auto_exposure_pass;
+mod bloom_pass;
 mod blur_pass;
+mod dof_pass;
 mod forward_pass;
 mod linear_depth_pass;
 mod present_pass;
 mod shadow_map_pass;
+mod shadow_moments_pass;
 mod ssao_pass;
 mod sss_blur_pass;
 mod sss_depth_pass;
+mod taa_pass;
 mod tfx_render;
 mod tfx_simulation;
 mod tonemapping_pass;
 
 pub use self::_shared::*;
+use self::auto_exposure_pass::AutoExposurePass;
+use self::bloom_pass::BloomPass;
 use self::blur_pass::BlurPass;
+use self::dof_pass::DofPass;
 use self::forward_pass::ForwardPass;
 use self::linear_depth_pass::LinearDepthPass;
 use self::shadow_map_pass::ShadowMapPass;
+use self::shadow_moments_pass::ShadowMomentsPass;
 use self::ssao_pass::SSAOPass;
 use self::sss_blur_pass::SSSBlurPass;
 use self::sss_depth_pass::SSSDepthPass;
+use self::taa_pass::TaaPass;
 use self::tfx_render::{
-  execute_tfx_ppll, TfxDepthOnlyPass, TfxForwardPass, TfxPpllBuildPass, TfxPpllResolvePass,
+  execute_tfx_ppll, execute_tfx_wboit, TfxDepthOnlyPass, TfxForwardPass, TfxPpllBuildPass,
+  TfxPpllResolvePass, TfxRenderTargets, TfxWboitAccumulatePass, TfxWboitResolvePass,
 };
 use self::tfx_simulation::{execute_tfx_simulation, TfxSim0Pass, TfxSim2Pass, TfxSim3Pass};
 use self::tonemapping_pass::TonemappingPass;
@@ -50,15 +63,21 @@ pub struct RenderGraph {
   /// 1 per swapchain image
   present_fbos: Vec<vk::Framebuffer>,
   rg_resources: Option<RenderGraphResources>,
+  /// If TAA result from the previous frame can be used as a history
+  taa_history_valid: bool,
 
   // passes
   shadow_map_pass: ShadowMapPass,
+  shadow_moments_pass: ShadowMomentsPass,
+  shadow_moments_blur_pass: BlurPass,
   sss_depth_pass: SSSDepthPass,
   sss_blur_pass: SSSBlurPass,
   forward_pass: ForwardPass,
   tfx_forward_pass: TfxForwardPass,
   tfx_ppll_build_pass: TfxPpllBuildPass,
   tfx_ppll_resolve_pass: TfxPpllResolvePass,
+  tfx_wboit_accumulate_pass: TfxWboitAccumulatePass,
+  tfx_wboit_resolve_pass: TfxWboitResolvePass,
   tfx_depth_only_pass: TfxDepthOnlyPass,
   tfx_sim0: TfxSim0Pass,
   tfx_sim2: TfxSim2Pass,
@@ -66,6 +85,10 @@ pub struct RenderGraph {
   linear_depth_pass: LinearDepthPass,
   ssao_pass: SSAOPass,
   ssao_blur_pass: BlurPass,
+  taa_pass: TaaPass,
+  dof_pass: DofPass,
+  auto_exposure_pass: AutoExposurePass,
+  bloom_pass: BloomPass,
   tonemapping_pass: TonemappingPass,
   present_pass: PresentPass,
 }
@@ -76,19 +99,28 @@ impl RenderGraph {
 
     // create passes
     let shadow_map_pass = ShadowMapPass::new(vk_app);
+    let shadow_moments_pass = ShadowMomentsPass::new(vk_app);
+    let shadow_moments_blur_pass = BlurPass::new(vk_app, ShadowMomentsPass::RESULT_TEXTURE_FORMAT);
     let sss_depth_pass = SSSDepthPass::new();
     let sss_blur_pass = SSSBlurPass::new(vk_app);
-    let forward_pass = ForwardPass::new(vk_app);
+    let msaa_samples = vk_app.get_supported_msaa_samples(config.msaa_samples());
+    let forward_pass = ForwardPass::new(vk_app, msaa_samples);
     let linear_depth_pass = LinearDepthPass::new(vk_app);
     let ssao_pass = SSAOPass::new(vk_app);
     let ssao_blur_pass = BlurPass::new(vk_app, SSAOPass::RESULT_TEXTURE_FORMAT);
     let tfx_forward_pass = TfxForwardPass::new(vk_app);
     let tfx_ppll_build_pass = TfxPpllBuildPass::new(vk_app);
     let tfx_ppll_resolve_pass = TfxPpllResolvePass::new(vk_app);
+    let tfx_wboit_accumulate_pass = TfxWboitAccumulatePass::new(vk_app);
+    let tfx_wboit_resolve_pass = TfxWboitResolvePass::new(vk_app);
     let tfx_depth_only_pass = TfxDepthOnlyPass::new(vk_app);
     let tfx_sim0 = TfxSim0Pass::new(vk_app);
     let tfx_sim2 = TfxSim2Pass::new(vk_app);
     let tfx_sim3 = TfxSim3Pass::new(vk_app);
+    let taa_pass = TaaPass::new(vk_app);
+    let dof_pass = DofPass::new(vk_app);
+    let auto_exposure_pass = AutoExposurePass::new(vk_app);
+    let bloom_pass = BloomPass::new(vk_app);
     let tonemapping_pass = TonemappingPass::new(vk_app);
     let present_pass = PresentPass::new(vk_app, image_format);
 
@@ -96,13 +128,18 @@ impl RenderGraph {
       per_frame_data: Vec::with_capacity(config.frames_in_flight),
       present_fbos: Vec::with_capacity(vk_app.swapchain_images_count()),
       rg_resources: None,
+      taa_history_valid: false,
       shadow_map_pass,
+      shadow_moments_pass,
+      shadow_moments_blur_pass,
       sss_depth_pass,
       sss_blur_pass,
       forward_pass,
       tfx_forward_pass,
       tfx_ppll_build_pass,
       tfx_ppll_resolve_pass,
+      tfx_wboit_accumulate_pass,
+      tfx_wboit_resolve_pass,
       tfx_depth_only_pass,
       tfx_sim0,
       tfx_sim2,
@@ -110,6 +147,10 @@ impl RenderGraph {
       linear_depth_pass,
       ssao_pass,
       ssao_blur_pass,
+      taa_pass,
+      dof_pass,
+      auto_exposure_pass,
+      bloom_pass,
       tonemapping_pass,
       present_pass,
     };
@@ -125,6 +166,10 @@ impl RenderGraph {
 
     // passes
     self.present_pass.destroy(device);
+    self.taa_pass.destroy(device);
+    self.dof_pass.destroy(device);
+    self.auto_exposure_pass.destroy(vk_app);
+    self.bloom_pass.destroy(device);
     self.tonemapping_pass.destroy(device);
     self.ssao_pass.destroy(vk_app);
     self.ssao_blur_pass.destroy(device);
@@ -132,6 +177,8 @@ impl RenderGraph {
     self.tfx_forward_pass.destroy(vk_app);
     self.tfx_ppll_build_pass.destroy(vk_app);
     self.tfx_ppll_resolve_pass.destroy(vk_app);
+    self.tfx_wboit_accumulate_pass.destroy(vk_app);
+    self.tfx_wboit_resolve_pass.destroy(vk_app);
     self.tfx_depth_only_pass.destroy(device);
     self.tfx_sim0.destroy(device);
     self.tfx_sim2.destroy(device);
@@ -140,6 +187,8 @@ impl RenderGraph {
     self.sss_depth_pass.destroy();
     self.sss_blur_pass.destroy(device);
     self.shadow_map_pass.destroy(device);
+    self.shadow_moments_pass.destroy(device);
+    self.shadow_moments_blur_pass.destroy(device);
 
     self.rg_resources.as_mut().map(|res| res.destroy(vk_app));
 
@@ -169,15 +218,34 @@ impl RenderGraph {
     let frame_idx = timer.frame_idx();
     let frame_in_flight_id: FrameInFlightId = (frame_idx % (config.frames_in_flight as u64)) as _;
 
+    // render scale, MSAA or shadow technique could have changed in the UI
+    self.update_render_targets(vk_app, config);
+    self.update_vsm_resources(vk_app, config);
+    let render_size = config.get_render_size(vk_app.window_size());
+
     // sync between frames
     let frame_data = &self.per_frame_data[frame_in_flight_id];
     self.wait_for_previous_frame_in_flight(vk_app, frame_data);
+    if config.postfx.auto_exposure {
+      config.postfx.auto_exposure_stats =
+        AutoExposurePass::read_stats(vk_app, &frame_data.auto_exposure_readback_buffer);
+    }
+
+    // TAA jitters the projection, must happen before any uniforms are written
+    let use_taa = config.postfx.use_taa();
+    let jitter = either!(use_taa, TaaPass::get_jitter(frame_idx), Vec2::ZERO);
+    scene.camera.set_jitter(jitter, render_size);
 
     // update per-frame uniforms
     let config_vk_buffer = &frame_data.config_uniform_buffer;
     update_config_uniform_buffer(vk_app, config, timer, scene, config_vk_buffer);
-    update_model_uniform_buffers(config, scene, frame_in_flight_id);
+    let lights_vk_buffer = &frame_data.lights_buffer;
+    let shadow_atlas = ShadowAtlas::new(config, &scene.bounding_box());
+    let sss_frustum = SSSDepthPass::get_frustum(config, scene);
+    update_lights_buffer(config, &shadow_atlas, lights_vk_buffer);
+    update_model_uniform_buffers(scene, frame_in_flight_id);
     update_tfx_uniform_buffers(config, scene, frame_in_flight_id);
+    scene.camera.store_previous_frame_matrices();
 
     // acquire next swapchain image
     let swapchain_image = vk_app.acquire_next_swapchain_image(frame_data);
@@ -197,8 +265,10 @@ impl RenderGraph {
       config: RefCell::new(config),
       scene: RefCell::new(scene),
       command_buffer: cmd_buf,
-      size: vk_app.window_size(),
+      size: render_size,
       config_buffer: config_vk_buffer,
+      lights_buffer: lights_vk_buffer,
+      shadow_atlas: &shadow_atlas,
       window,
       timer,
       profiler: RefCell::new(profiler),
@@ -212,26 +282,51 @@ impl RenderGraph {
     execute_tfx_simulation(&pass_ctx, &self.tfx_sim0, &self.tfx_sim2, &self.tfx_sim3);
 
     // shadow map generate pass
-    self.shadow_map_pass.execute::<ShadowMapPass>(
-      &pass_ctx,
-      &mut res.shadow_map_pass,
-      &pass_ctx.config.borrow().shadows.shadow_source,
-      true,
-    );
+    self
+      .shadow_map_pass
+      .execute_atlas(&pass_ctx, &mut res.shadow_map_pass, &shadow_atlas);
+
+    // variance shadow maps - convert depth to moments and blur them.
+    // VSM requires moments, other techniques use the raw depth
+    let shadow_tex = match res.vsm.as_mut() {
+      Some(vsm) => {
+        self.shadow_moments_pass.execute(
+          &pass_ctx,
+          &mut vsm.shadow_moments_pass,
+          &mut res.shadow_map_pass.depth_tex,
+        );
+        let moments_size = vsm.shadow_moments_pass.moments_tex.size();
+        self.shadow_moments_blur_pass.execute(
+          &pass_ctx,
+          "VSM",
+          &mut vsm.shadow_moments_blur_fbo0,
+          &mut vsm.shadow_moments_blur_fbo1,
+          &mut vsm.shadow_moments_pass.moments_tex,
+          &mut vsm.shadow_moments_ping_result_tex,
+          moments_size,
+          &mut res.shadow_map_pass.depth_tex, // not used, see `u_depth_max_dist`
+          pass_ctx.config.borrow().shadows.vsm_blur_radius,
+          f32::MAX, // no depth-aware rejection
+          pass_ctx.config.borrow().shadows.vsm_blur_gauss_sigma,
+        );
+        &mut vsm.shadow_moments_pass.moments_tex
+      }
+      None => &mut res.shadow_map_pass.depth_tex,
+    };
 
     // sss forward scatter depth map generate pass
     self.sss_depth_pass.execute(
       &pass_ctx,
       &mut res.sss_depth_pass,
       &self.shadow_map_pass,
-      &pass_ctx.config.borrow().sss_forward_scatter.source,
+      &sss_frustum,
     );
 
     // forward rendering
     self.forward_pass.execute(
       &pass_ctx,
       &mut res.forward_pass,
-      &mut res.shadow_map_pass.depth_tex,
+      shadow_tex,
       &mut res.sss_depth_pass.depth_tex,
       &mut res.ssao_pass.ssao_tex,
     );
@@ -274,17 +369,39 @@ impl RenderGraph {
         &pass_ctx,
         &mut res.tfx_ppll_build_pass,
         &mut res.tfx_ppll_resolve_pass,
-        res.tfx_depth_only_pass,
-        &mut res.forward_pass.depth_stencil_tex,
-        &mut res.forward_pass.diffuse_tex,
-        &mut res.ssao_pass.ssao_tex,
-        &mut res.shadow_map_pass.depth_tex,
+        &mut TfxRenderTargets {
+          depth_stencil_tex: &mut res.forward_pass.depth_stencil_tex,
+          depth_only_fbo: res.tfx_depth_only_pass,
+          forward_color_tex: &mut res.forward_pass.diffuse_tex,
+          forward_normal_tex: &mut res.forward_pass.normals_tex,
+          forward_velocity_tex: &mut res.forward_pass.velocity_tex,
+          ao_texture: &mut res.ssao_pass.ssao_tex,
+          shadow_map_texture: shadow_tex,
+        },
+      );
+    } else if pass_ctx.config.borrow().is_hair_using_wboit() {
+      execute_tfx_wboit(
+        &self.tfx_wboit_accumulate_pass,
+        &self.tfx_wboit_resolve_pass,
+        &self.tfx_depth_only_pass,
+        &pass_ctx,
+        &mut res.tfx_wboit_accumulate_pass,
+        &mut res.tfx_wboit_resolve_pass,
+        &mut TfxRenderTargets {
+          depth_stencil_tex: &mut res.forward_pass.depth_stencil_tex,
+          depth_only_fbo: res.tfx_depth_only_pass,
+          forward_color_tex: &mut res.forward_pass.diffuse_tex,
+          forward_normal_tex: &mut res.forward_pass.normals_tex,
+          forward_velocity_tex: &mut res.forward_pass.velocity_tex,
+          ao_texture: &mut res.ssao_pass.ssao_tex,
+          shadow_map_texture: shadow_tex,
+        },
       );
     } else {
       self.tfx_forward_pass.execute(
         &pass_ctx,
         &mut res.forward_pass,
-        &mut res.shadow_map_pass.depth_tex,
+        shadow_tex,
         &mut res.ssao_pass.ssao_tex,
       );
     }
@@ -314,18 +431,78 @@ impl RenderGraph {
       &mut res.ssao_blur_fbo1,
       &mut res.ssao_pass.ssao_tex,
       &mut res.ssao_ping_result_tex,
-      pass_ctx.config.borrow().get_ssao_viewport_size(),
+      pass_ctx.config.borrow().get_ssao_viewport_size(render_size),
       &mut res.linear_depth_pass.linear_depth_tex,
       pass_ctx.config.borrow().ssao.blur_radius,
       pass_ctx.config.borrow().ssao.blur_max_depth_distance,
       pass_ctx.config.borrow().ssao.blur_gauss_sigma,
     );
 
+    // temporal anti-aliasing. Ping-pong between 2 framebuffers,
+    // the one written in previous frame is the history
+    let (taa_fbo, taa_history_fbo) = either!(
+      frame_idx.is_multiple_of(2),
+      (&mut res.taa_pass_fbo0, &mut res.taa_pass_fbo1),
+      (&mut res.taa_pass_fbo1, &mut res.taa_pass_fbo0)
+    );
+    if use_taa {
+      let history_tex = either!(
+        self.taa_history_valid,
+        Some(&mut taa_history_fbo.result_tex),
+        None
+      );
+      self.taa_pass.execute(
+        &pass_ctx,
+        taa_fbo,
+        history_tex,
+        &mut res.forward_pass.diffuse_tex,
+        &mut res.forward_pass.velocity_tex,
+        &mut res.linear_depth_pass.linear_depth_tex,
+      );
+    }
+    self.taa_history_valid = use_taa;
+    let tonemapping_source = either!(
+      use_taa,
+      &mut taa_fbo.result_tex,
+      &mut res.forward_pass.diffuse_tex
+    );
+
+    // depth of field
+    let tonemapping_source = if pass_ctx.config.borrow().postfx.use_dof {
+      self.dof_pass.execute(
+        &pass_ctx,
+        &mut res.dof_pass,
+        tonemapping_source,
+        &mut res.linear_depth_pass.linear_depth_tex,
+      );
+      &mut res.dof_pass.result_tex
+    } else {
+      tonemapping_source
+    };
+
+    // bloom
+    if pass_ctx.config.borrow().postfx.use_bloom {
+      self
+        .bloom_pass
+        .execute(&pass_ctx, &mut res.bloom_pass, tonemapping_source);
+    }
+
+    // eye adaptation
+    if pass_ctx.config.borrow().postfx.auto_exposure {
+      self.auto_exposure_pass.execute(
+        &pass_ctx,
+        tonemapping_source,
+        &frame_data.auto_exposure_readback_buffer,
+      );
+    }
+
     // color grading + tonemapping
     self.tonemapping_pass.execute(
       &pass_ctx,
       &mut res.tonemapping_pass,
-      &mut res.forward_pass.diffuse_tex,
+      tonemapping_source,
+      &self.auto_exposure_pass.exposure_buffer,
+      res.bloom_pass.result_tex(),
     );
 
     // final pass to render output to OS window framebuffer
@@ -390,6 +567,94 @@ impl RenderGraph {
     profiler.end_frame(device);
   }
 
+  /// Linear depth (positive distance from the camera) of the last rendered frame
+  /// under the cursor. Waits till GPU is idle, so do not use every frame.
+  pub fn read_linear_depth(&self, vk_app: &VkCtx, cursor: Vec2) -> f32 {
+    let res = self
+      .rg_resources
+      .as_ref()
+      .expect("RenderGraph resources were not initialized before reading depth");
+    let window_size = vk_app.window_size();
+    let render_size = res.render_size;
+    let scale = vec2(
+      render_size.width as f32 / window_size.width as f32,
+      render_size.height as f32 / window_size.height as f32,
+    );
+    let texel = (cursor * scale).floor();
+    let x = (texel.x.max(0.0) as u32).min(render_size.width - 1);
+    let y = (texel.y.max(0.0) as u32).min(render_size.height - 1);
+
+    let tex = &res.linear_depth_pass.linear_depth_tex;
+    let bytes = vk_app.read_texture_texel(tex, 4, x, y);
+    let depth = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    -depth // stored as negative values
+  }
+
+  /// Recreate render targets if render scale or MSAA settings have changed.
+  /// Waits for the GPU to finish all work, so it should happen only on user request.
+  fn update_render_targets(&mut self, vk_app: &VkCtx, config: &Config) {
+    let render_size = config.get_render_size(vk_app.window_size());
+    let msaa_samples = vk_app.get_supported_msaa_samples(config.msaa_samples());
+    let msaa_changed = self.forward_pass.msaa_samples != msaa_samples;
+    let size_changed = self
+      .rg_resources
+      .as_ref()
+      .is_none_or(|res| res.render_size != render_size);
+    if !msaa_changed && !size_changed {
+      return;
+    }
+
+    let device = vk_app.vk_device();
+    unsafe {
+      device
+        .device_wait_idle()
+        .expect("Failed device_wait_idle() before recreating render targets");
+
+      if msaa_changed {
+        self.forward_pass.destroy(vk_app);
+        self.forward_pass = ForwardPass::new(vk_app, msaa_samples);
+      }
+      if let Some(res) = self.rg_resources.as_mut() {
+        res.destroy(vk_app);
+      }
+    }
+    self.rg_resources = Some(RenderGraphResources::new(vk_app, config, self));
+    self.taa_history_valid = false;
+  }
+
+  /// Allocate or free VSM resources if the shadow technique has changed.
+  /// Waits for the GPU to finish all work, so it should happen only on user request.
+  fn update_vsm_resources(&mut self, vk_app: &VkCtx, config: &Config) {
+    let is_vsm = config.shadows.is_vsm();
+    let vsm_changed = self
+      .rg_resources
+      .as_ref()
+      .is_some_and(|res| res.vsm.is_some() != is_vsm);
+    if !vsm_changed {
+      return;
+    }
+
+    let vsm = if is_vsm {
+      Some(VsmResources::new(vk_app, config, self))
+    } else {
+      None
+    };
+    let res = self
+      .rg_resources
+      .as_mut()
+      .expect("RenderGraph resources were not initialized before updating VSM resources");
+    unsafe {
+      vk_app
+        .vk_device()
+        .device_wait_idle()
+        .expect("Failed device_wait_idle() before recreating VSM resources");
+      if let Some(old_vsm) = res.vsm.as_mut() {
+        old_vsm.destroy(vk_app);
+      }
+    }
+    res.vsm = vsm;
+  }
+
   /// `vkWaitForFences`
   fn wait_for_previous_frame_in_flight(&self, vk_app: &VkCtx, frame_data: &FrameData) {
     let device = vk_app.vk_device();
@@ -409,9 +674,15 @@ impl RenderGraph {
 
     (0..frames_in_flight).for_each(|frame_id| {
       let config_uniform_buffer = allocate_config_uniform_buffer(vk_app, frame_id);
-      self
-        .per_frame_data
-        .push(FrameData::new(vk_app, config_uniform_buffer));
+      let lights_buffer = allocate_lights_buffer(vk_app, frame_id);
+      let auto_exposure_readback_buffer =
+        AutoExposurePass::create_readback_buffer(vk_app, frame_id);
+      self.per_frame_data.push(FrameData::new(
+        vk_app,
+        config_uniform_buffer,
+        lights_buffer,
+        auto_exposure_readback_buffer,
+      ));
     });
   }
 
@@ -467,25 +738,42 @@ fn update_config_uniform_buffer(
   scene: &World,
   vk_buffer: &VkBuffer,
 ) {
-  let camera = &scene.camera;
-  let data = GlobalConfigUBO::new(vk_app, config, timer, camera);
+  let data = GlobalConfigUBO::new(vk_app, config, timer, scene);
   let data_bytes = bytemuck::bytes_of(&data);
   vk_buffer.write_to_mapped(data_bytes);
 }
 
-fn update_model_uniform_buffers(
-  config: &Config,
-  scene: &World,
-  frame_in_flight_id: FrameInFlightId,
-) {
+fn allocate_lights_buffer(vk_app: &VkCtx, frame_id: usize) -> VkBuffer {
+  let size = LightsSSBO::get_buffer_size();
+  vk_app.create_buffer_empty(
+    format!("lights_buffer_{}", frame_id),
+    size,
+    vk::BufferUsageFlags::STORAGE_BUFFER,
+    VkMemoryPreference::GpuMappable,
+  )
+}
+
+fn update_lights_buffer(config: &Config, shadow_atlas: &ShadowAtlas, vk_buffer: &VkBuffer) {
+  let data = LightsSSBO::new(config, shadow_atlas);
+  let data_bytes = bytemuck::bytes_of(&data);
+  vk_buffer.write_to_mapped(data_bytes);
+}
+
+fn update_model_uniform_buffers(scene: &World, frame_in_flight_id: FrameInFlightId) {
   let camera = &scene.camera;
   scene.entities.iter().for_each(|entity| {
-    entity.update_ubo_data(frame_in_flight_id, config, camera);
+    entity.update_ubo_data(frame_in_flight_id, camera);
   });
 }
 
-fn update_tfx_uniform_buffers(config: &Config, scene: &World, frame_in_flight_id: FrameInFlightId) {
-  scene.tressfx_objects.iter().for_each(|entity| {
+fn update_tfx_uniform_buffers(
+  config: &Config,
+  scene: &mut World,
+  frame_in_flight_id: FrameInFlightId,
+) {
+  let camera = &scene.camera;
+  scene.tressfx_objects.iter_mut().for_each(|entity| {
+    entity.update_lod(camera);
     entity.update_params_uniform_buffer(frame_in_flight_id, config);
   });
 }
@@ -9,6 +9,10 @@ pub struct FrameData {
   pub command_buffer: vk::CommandBuffer,
   /// Refreshed once every frame. Contains e.g. all config settings, camera data
   pub config_uniform_buffer: VkBuffer,
+  /// Refreshed once every frame. See `LightGPUData`
+  pub lights_buffer: VkBuffer,
+  /// Copy of the auto exposure result. Read on CPU after the frame has finished
+  pub auto_exposure_readback_buffer: VkBuffer,
 
   // SYNC
   pub queue_submit_finished_fence: vk::Fence,
@@ -18,13 +22,20 @@ pub struct FrameData {
 }
 
 impl FrameData {
-  pub fn new(vk_app: &VkCtx, config_uniform_buffer: VkBuffer) -> Self {
+  pub fn new(
+    vk_app: &VkCtx,
+    config_uniform_buffer: VkBuffer,
+    lights_buffer: VkBuffer,
+    auto_exposure_readback_buffer: VkBuffer,
+  ) -> Self {
     let device = vk_app.vk_device();
     let command_buffer = create_command_buffer(device, vk_app.command_pool);
 
     Self {
       command_buffer,
       config_uniform_buffer,
+      lights_buffer,
+      auto_exposure_readback_buffer,
       queue_submit_finished_fence: create_fence(device),
       acquire_semaphore: create_semaphore(device),
       rendering_complete_semaphore: create_semaphore(device),
@@ -36,6 +47,8 @@ impl FrameData {
     let allocator = &vk_app.allocator;
 
     self.config_uniform_buffer.delete(allocator);
+    self.lights_buffer.delete(allocator);
+    self.auto_exposure_readback_buffer.delete(allocator);
     device.destroy_fence(self.queue_submit_finished_fence, None);
     device.destroy_semaphore(self.acquire_semaphore, None);
     device.destroy_semaphore(self.rendering_complete_semaphore, None);
@@ -1,14 +1,12 @@
 use bytemuck;
-use glam::{vec4, Mat4, Vec3, Vec4};
+use glam::{vec4, Mat4, Vec4};
 
 use crate::{
   app_timer::AppTimer,
-  config::{ColorGradingProp, Config, LightAmbient, LightCfg, SSAOConfig},
-  render_graph::{
-    shadow_map_pass::ShadowMapPass, sss_depth_pass::SSSDepthPass, tfx_render::TfxPpllBuildPass,
-  },
-  scene::Camera,
-  utils::{into_vec4, mint3_into_vec4, spherical_to_cartesian_dgr},
+  config::{ColorGradingProp, Config, LightAmbient, SSAOConfig},
+  render_graph::{sss_depth_pass::SSSDepthPass, tfx_render::TfxPpllBuildPass},
+  scene::World,
+  utils::{into_vec4, mint3_into_vec4},
   vk_ctx::VkCtx,
 };
 
@@ -22,15 +20,13 @@ pub struct GlobalConfigUBO {
   pub u_projection_mat: Mat4,
   pub u_inv_projection_mat: Mat4, // inverse projection matrix
   pub u_view_projection_mat: Mat4,
-  pub u_tfx_hair_settings: Vec4, // [hairDisplayMode, u_tfxLinkedListPoolSize, g_GravityMagnitude, g_TimeStep]
-  pub u_tfx_wind: Vec4,          // [windDir.xyz, windStrength]
-  pub u_tfx_shape: Vec4, // [Sim0.Verlet damping, Sim2.LSC local stiffness, Sim0.GSC global stiffness, Sim0.GSC global range.]
-  pub u_tfx_constraints: Vec4, // [Sim3.Length Constraints iterations, Sim3.Length stiffness,-,-]
+  pub u_prev_view_projection_mat: Mat4, // previous frame, no jitter
+  pub u_tfx_hair_settings: Vec4, // [hairDisplayMode, u_tfxLinkedListPoolSize, u_tfxMinPixelWidth, g_TimeStep]
+  pub u_tfx_wind: Vec4, // [windDir.xyz, windStrength] - debug only, simulation uses per-object settings
 
   // AO + Shadow
-  pub u_shadow_matrix_vp: Mat4,
   pub u_shadow_radius_and_bias: Vec4, // [u_shadowRadiusForwardShading, u_shadowBiasForwardShading, u_shadowRadiusTfx, u_shadowBiasTfx]
-  pub u_shadow_caster_position: Vec4, // [position.xyz, u_shadowsTechnique]
+  pub u_shadow_settings: Vec4, // [u_shadowsTechnique, u_vsmLightBleedingReduction, u_vsmMinVariance, -]
   pub u_ao_settings: Vec4, // (u_aoStrength, u_aoExp, showDebugPositions+u_maxShadowContribution, gamma)
   // sss
   pub u_sss_settings: Vec4, // [u_sssPosition, u_sssFarPlane]
@@ -38,20 +34,26 @@ pub struct GlobalConfigUBO {
   pub u_sss_blur: Vec4, // [u_sssWidth, u_sssStrength, u_sssFovy+u_sssFollowSurface, -]
   // Lights
   pub u_light_ambient: Vec4,
-  pub u_light0_position: Vec3,
-  pub u_light0_color: Vec4,
-  pub u_light1_position: Vec3,
-  pub u_light1_color: Vec4,
-  pub u_light2_position: Vec3,
-  pub u_light2_color: Vec4,
+  pub u_lights_settings: Vec4, // [u_lightCount, -, -, -]
+  pub u_env_settings: Vec4,    // [u_envEnabled, u_envIntensity, u_envRotation, u_skyboxBlur]
+  pub u_env_irradiance_sh: [Vec4; 9],
   // SSAO
   pub u_ssao: Vec4,  // [u_noiseScale.xy, u_radius, u_bias]
   pub u_ssao2: Vec4, // [u_kernelSize, u_linear_depth_preview_range.yz, -]
   // FXAA
   pub u_fxaa_settings: Vec4,
+  // TAA
+  pub u_taa_settings: Vec4, // [u_taaJitter.xy, u_taaHistoryWeight, u_taaNeighbourhoodClamp]
+  pub u_render_scale_settings: Vec4, // [u_windowSize.xy, u_renderScale, -]
   // Color correction
   pub u_tonemapping: Vec4,
   pub u_tonemapping2: Vec4,
+  pub u_auto_exposure: Vec4, // [u_autoExposureEnabled, u_autoExposureMinEV, u_autoExposureMaxEV, u_autoExposureSpeed]
+  pub u_auto_exposure2: Vec4, // [u_exposureCompensation, u_deltaTime, -, -]
+  pub u_bloom_settings: Vec4, // [u_bloomThreshold, u_bloomSoftKnee, u_bloomIntensity, -]
+  pub u_lens_settings: Vec4, // [u_vignetteStrength, u_vignetteRadius, u_chromaticAberration, u_filmGrain]
+  pub u_lens_settings2: Vec4, // [u_filmGrainSeed, -, -, -]
+  pub u_dof_settings: Vec4,  // [u_dofFocusDistance, u_dofCocScale, u_dofMaxCoc, -]
   // TONEMAPPING
   pub u_color_saturation: Vec4, // general
   pub u_color_contrast: Vec4,
@@ -91,22 +93,19 @@ fn encode_flag_in_value_sign(flag: bool, value: f32) -> f32 {
 }
 
 impl GlobalConfigUBO {
-  pub fn new(
-    vk_app: &VkCtx,
-    config: &Config,
-    timer: &AppTimer,
-    camera: &Camera,
-  ) -> GlobalConfigUBO {
-    let vp = vk_app.window_size();
+  pub fn new(vk_app: &VkCtx, config: &Config, timer: &AppTimer, scene: &World) -> GlobalConfigUBO {
+    let camera = &scene.camera;
+    let window_size = vk_app.window_size();
+    let vp = config.get_render_size(window_size);
     let cam_cfg = &config.camera;
     let cam_pos = camera.position();
     let postfx = &config.postfx;
     let color_grading = &postfx.color_grading;
     let shadows = &config.shadows;
-    let shadow_pos = shadows.shadow_source.position();
     let sss_frw = &config.sss_forward_scatter;
     let sss_frw_pos = sss_frw.source.position();
-    let ssao_vp = config.get_ssao_viewport_size();
+    let sss_frustum = SSSDepthPass::get_frustum(config, scene);
+    let ssao_vp = config.get_ssao_viewport_size(vp);
 
     GlobalConfigUBO {
       u_camera_position_and_display_mode: vec4(
@@ -122,43 +121,30 @@ impl GlobalConfigUBO {
         cam_cfg.z_far,
       ),
       u_view_mat: *camera.view_matrix(),
-      u_projection_mat: *camera.perspective_matrix(),
-      u_inv_projection_mat: camera.perspective_matrix().inverse(),
-      u_view_projection_mat: camera.view_projection_matrix(),
+      u_projection_mat: *camera.jittered_perspective_matrix(),
+      u_inv_projection_mat: camera.jittered_perspective_matrix().inverse(),
+      u_view_projection_mat: camera.jittered_view_projection_matrix(),
+      u_prev_view_projection_mat: *camera.prev_view_projection_matrix(),
       u_tfx_hair_settings: vec4(
         config.get_hair_display_mode() as f32,
-        TfxPpllBuildPass::get_ppll_data_nodes_count(config.get_viewport_size()) as f32,
-        config.tfx_simulation.gravity,
+        TfxPpllBuildPass::get_ppll_data_nodes_count(vp) as f32,
+        config.get_hair_min_pixel_width(),
         timer.delta_time_s(),
       ),
-      u_tfx_wind: into_vec4(
-        config.tfx_simulation.wind_position(),
-        config.tfx_simulation.wind_strength,
-      ),
-      u_tfx_shape: vec4(
-        config.tfx_simulation.verlet_integration_damping,
-        config.tfx_simulation.local_stiffness,
-        config.tfx_simulation.global_stiffness,
-        config.tfx_simulation.global_stiffness_range,
-      ),
-      u_tfx_constraints: vec4(
-        config.tfx_simulation.length_constraint_iterations as f32,
-        config.tfx_simulation.length_stiffness,
-        0.0,
-        0.0,
-      ),
+      u_tfx_wind: debug_wind(scene),
       // shadows:
-      u_shadow_matrix_vp: ShadowMapPass::get_light_shadow_mvp(
-        &shadows.shadow_source,
-        Mat4::IDENTITY,
-      ),
       u_shadow_radius_and_bias: vec4(
         shadows.blur_radius as _,
         shadows.bias,
         shadows.blur_radius_tfx as _,
         shadows.bias_hair_tfx,
       ),
-      u_shadow_caster_position: into_vec4(shadow_pos, shadows.shadow_technique as _),
+      u_shadow_settings: vec4(
+        shadows.shadow_technique as _,
+        shadows.vsm_light_bleeding_reduction,
+        shadows.vsm_min_variance,
+        0.0,
+      ),
       u_ao_settings: Vec4::new(
         config.ssao.ao_strength,
         config.ssao.ao_exp,
@@ -170,9 +156,9 @@ impl GlobalConfigUBO {
         sss_frw_pos.x,
         sss_frw_pos.y,
         sss_frw_pos.z,
-        sss_frw.source.projection.far,
+        sss_frustum.depth_range,
       ),
-      u_sss_matrix_vp: SSSDepthPass::get_sss_forward_mvp(&sss_frw.source, Mat4::IDENTITY),
+      u_sss_matrix_vp: sss_frustum.view_projection,
       u_sss_blur: vec4(
         config.sss_blur.blur_width,
         config.sss_blur.blur_strength,
@@ -184,12 +170,9 @@ impl GlobalConfigUBO {
       ),
       // lights
       u_light_ambient: light_ambient(&config.light_ambient),
-      u_light0_position: light_pos(&config.light0),
-      u_light0_color: light_color(&config.light0),
-      u_light1_position: light_pos(&config.light1),
-      u_light1_color: light_color(&config.light1),
-      u_light2_position: light_pos(&config.light2),
-      u_light2_color: light_color(&config.light2),
+      u_lights_settings: vec4(config.get_light_count() as f32, 0.0, 0.0, 0.0),
+      u_env_settings: env_settings(config, scene),
+      u_env_irradiance_sh: env_irradiance_sh(scene),
       // SSAO
       u_ssao: vec4(
         (ssao_vp.width / SSAOConfig::RNG_VECTOR_TEXTURE_SIZE) as _,
@@ -211,6 +194,23 @@ impl GlobalConfigUBO {
         postfx.edge_threshold_min,
         postfx.fxaa_luma_gamma,
       ),
+      // TAA
+      u_taa_settings: vec4(
+        camera.jitter().x,
+        camera.jitter().y,
+        postfx.taa_history_weight,
+        if postfx.taa_neighbourhood_clamp {
+          1.0
+        } else {
+          0.0
+        },
+      ),
+      u_render_scale_settings: vec4(
+        window_size.width as f32,
+        window_size.height as f32,
+        config.render_scale(),
+        0.0,
+      ),
       // Color correction
       u_tonemapping: vec4(
         postfx.exposure,
@@ -224,6 +224,32 @@ impl GlobalConfigUBO {
         color_grading.shadows_max,
         color_grading.highlights_min,
       ),
+      u_auto_exposure: vec4(
+        if postfx.auto_exposure { 1.0 } else { 0.0 },
+        postfx.auto_exposure_min_ev,
+        postfx.auto_exposure_max_ev,
+        postfx.auto_exposure_speed,
+      ),
+      u_auto_exposure2: vec4(postfx.exposure_compensation, timer.delta_time_s(), 0.0, 0.0),
+      u_bloom_settings: vec4(
+        postfx.bloom_threshold,
+        postfx.bloom_soft_knee,
+        value_if_enabled(postfx.use_bloom, postfx.bloom_intensity),
+        0.0,
+      ),
+      u_lens_settings: vec4(
+        value_if_enabled(postfx.use_vignette, postfx.vignette_strength),
+        postfx.vignette_radius,
+        value_if_enabled(postfx.use_chromatic_aberration, postfx.chromatic_aberration),
+        value_if_enabled(postfx.use_film_grain, postfx.film_grain),
+      ),
+      u_lens_settings2: vec4((timer.frame_idx() % 1000) as f32, 0.0, 0.0, 0.0),
+      u_dof_settings: vec4(
+        postfx.dof_focus_distance,
+        postfx.dof_coc_scale(vp.height),
+        postfx.dof_max_coc,
+        0.0,
+      ),
       // TONEMAPPING
       u_color_saturation: pack_color_grading_prop(&color_grading.global.saturation), // general
       u_color_contrast: pack_color_grading_prop(&color_grading.global.contrast),
@@ -254,18 +280,52 @@ impl GlobalConfigUBO {
   }
 }
 
+/// Wind of the first simulated TressFX object. Only used to show the debug wind source.
+fn debug_wind(scene: &World) -> Vec4 {
+  let simulated = scene
+    .tressfx_objects
+    .iter()
+    .find(|tfx| tfx.simulation.enabled);
+  match simulated {
+    Some(tfx) => into_vec4(tfx.simulation.wind_position(), tfx.simulation.wind_strength),
+    None => Vec4::ZERO,
+  }
+}
+
 fn light_ambient(light: &LightAmbient) -> Vec4 {
   mint3_into_vec4(light.color, light.energy)
 }
 
-fn light_color(light: &LightCfg) -> Vec4 {
-  mint3_into_vec4(light.color, light.energy)
+fn env_settings(config: &Config, scene: &World) -> Vec4 {
+  let env = &config.environment;
+  let is_enabled = env.enabled && scene.environment.is_some();
+  vec4(
+    if is_enabled { 1.0 } else { 0.0 },
+    env.intensity,
+    env.rotation.to_radians(),
+    env.skybox_blur,
+  )
 }
 
-fn light_pos(light: &LightCfg) -> Vec3 {
-  spherical_to_cartesian_dgr(light.pos_phi, light.pos_theta, light.pos_distance)
+fn env_irradiance_sh(scene: &World) -> [Vec4; 9] {
+  let mut result = [Vec4::ZERO; 9];
+  if let Some(env) = &scene.environment {
+    for (i, coef) in env.irradiance_sh.iter().enumerate() {
+      result[i] = into_vec4(*coef, 0.0);
+    }
+  }
+  result
 }
 
 fn pack_color_grading_prop(prop: &ColorGradingProp) -> Vec4 {
   mint3_into_vec4(prop.color, prop.value)
 }
+
+/// Shader treats 0 as 'effect disabled'
+fn value_if_enabled(enabled: bool, value: f32) -> f32 {
+  if enabled {
+    value
+  } else {
+    0.0
+  }
+}
@@ -0,0 +1,98 @@
+use std::mem::size_of;
+
+use bytemuck;
+use glam::{vec4, Mat4, Vec4};
+
+use crate::{
+  config::{Config, LightCfg},
+  utils::{into_vec4, mint3_into_vec4},
+};
+
+use super::{ShadowAtlas, ShadowAtlasSlot};
+
+/// Single light, as stored in the lights SSBO. Must match `LightGPUData` in `_lights.glsl`.
+#[derive(Copy, Clone, Debug)] // , bytemuck::Zeroable, bytemuck::Pod
+#[repr(C)]
+pub struct LightGPUData {
+  pub position_and_type: Vec4,    // [position.xyz, type]
+  pub direction_and_radius: Vec4, // [direction.xyz, radius]
+  pub color: Vec4,                // [color.rgb, intensity]
+  pub spot_and_falloff: Vec4,     // [cos(innerAngle), cos(outerAngle), falloff, shadowSlot or -1]
+}
+
+unsafe impl bytemuck::Zeroable for LightGPUData {}
+unsafe impl bytemuck::Pod for LightGPUData {}
+
+impl LightGPUData {
+  pub fn new(light: &LightCfg, shadow_slot: Option<usize>) -> Self {
+    let outer_angle = light.spot_outer_angle.max(0.0);
+    let inner_angle = light.spot_inner_angle.clamp(0.0, outer_angle);
+    let shadow_slot = shadow_slot.map(|s| s as f32).unwrap_or(-1.0);
+
+    Self {
+      position_and_type: into_vec4(light.position(), light.light_type as f32),
+      direction_and_radius: into_vec4(light.direction(), light.radius),
+      color: mint3_into_vec4(light.color, light.energy),
+      spot_and_falloff: vec4(
+        inner_angle.to_radians().cos(),
+        outer_angle.to_radians().cos(),
+        light.falloff,
+        shadow_slot,
+      ),
+    }
+  }
+}
+
+/// Single region of the shadow atlas. Must match `ShadowSlotGPUData` in `_lights.glsl`.
+#[derive(Copy, Clone, Debug)] // , bytemuck::Zeroable, bytemuck::Pod
+#[repr(C)]
+pub struct ShadowSlotGPUData {
+  pub view_projection: Mat4,
+  pub atlas_rect: Vec4, // [uvOffset.xy, uvScale.xy]
+}
+
+unsafe impl bytemuck::Zeroable for ShadowSlotGPUData {}
+unsafe impl bytemuck::Pod for ShadowSlotGPUData {}
+
+/// Content of the lights SSBO. Must match `LightsBuffer` in `_lights.glsl`.
+/// Light count is part of `GlobalConfigUBO`.
+#[derive(Copy, Clone, Debug)] // , bytemuck::Zeroable, bytemuck::Pod
+#[repr(C)]
+pub struct LightsSSBO {
+  pub lights: [LightGPUData; Config::MAX_LIGHTS],
+  pub shadow_slots: [ShadowSlotGPUData; ShadowAtlas::MAX_SLOTS],
+}
+
+unsafe impl bytemuck::Zeroable for LightsSSBO {}
+unsafe impl bytemuck::Pod for LightsSSBO {}
+
+impl LightsSSBO {
+  pub fn new(config: &Config, shadow_atlas: &ShadowAtlas) -> Self {
+    let mut data: Self = bytemuck::Zeroable::zeroed();
+
+    let lights = config.lights.iter().take(Config::MAX_LIGHTS);
+    for (i, light) in lights.enumerate() {
+      let shadow_slot = shadow_atlas.light_slots.get(i).copied().flatten();
+      data.lights[i] = LightGPUData::new(light, shadow_slot);
+    }
+
+    for (i, slot) in shadow_atlas.slots.iter().enumerate() {
+      data.shadow_slots[i] = ShadowSlotGPUData::new(shadow_atlas, slot);
+    }
+
+    data
+  }
+
+  pub fn get_buffer_size() -> usize {
+    size_of::<LightsSSBO>()
+  }
+}
+
+impl ShadowSlotGPUData {
+  fn new(shadow_atlas: &ShadowAtlas, slot: &ShadowAtlasSlot) -> Self {
+    Self {
+      view_projection: slot.view_projection,
+      atlas_rect: shadow_atlas.get_uv_rect(slot),
+    }
+  }
+}
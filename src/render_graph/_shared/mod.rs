@@ -1,15 +1,21 @@
 mod forward_model_ubo;
 mod frame_data;
 mod global_config_ubo;
+mod lights_ssbo;
 mod pass_exec_context;
 mod render_graph_resources;
 mod renderable_vertex;
+mod shadow_atlas;
+mod shadow_frustum;
 mod tfx_params_ubo;
 
 pub use self::forward_model_ubo::*;
 pub use self::frame_data::*;
 pub use self::global_config_ubo::*;
+pub use self::lights_ssbo::*;
 pub use self::pass_exec_context::*;
 pub use self::render_graph_resources::*;
 pub use self::renderable_vertex::*;
+pub use self::shadow_atlas::*;
+pub use self::shadow_frustum::*;
 pub use self::tfx_params_ubo::*;
@@ -15,6 +15,8 @@ use crate::{
   },
 };
 
+use super::ShadowAtlas;
+
 /// All the kitchen sink that we might want to use in the render pass.
 /// Created so we do not have to provide it all one-by-one.
 pub struct PassExecContext<'a> {
@@ -23,8 +25,13 @@ pub struct PassExecContext<'a> {
   pub config: RefCell<&'a mut Config>, // `mut` cause UI
   pub scene: RefCell<&'a mut World>,   // `mut` cause UI
   pub command_buffer: vk::CommandBuffer,
+  /// Size of the render targets. Window size scaled by `Config::render_scale()`
   pub size: vk::Extent2D,
   pub config_buffer: &'a VkBuffer,
+  /// See `LightGPUData`
+  pub lights_buffer: &'a VkBuffer,
+  /// Shadow map slots assigned to lights this frame
+  pub shadow_atlas: &'a ShadowAtlas,
   pub window: &'a winit::window::Window,
   pub timer: &'a AppTimer,
   /// Use `RefCell` to allow both mutable and const borrow regardles if `self` is mutable.
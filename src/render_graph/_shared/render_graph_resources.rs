@@ -2,22 +2,34 @@ use ash;
 use ash::vk;
 
 use crate::config::Config;
+use crate::render_graph::bloom_pass::BloomPassFramebuffer;
 use crate::render_graph::blur_pass::BlurFramebuffer;
+use crate::render_graph::dof_pass::DofPassFramebuffer;
 use crate::render_graph::forward_pass::{ForwardPass, ForwardPassFramebuffer};
 use crate::render_graph::linear_depth_pass::LinearDepthPassFramebuffer;
 use crate::render_graph::shadow_map_pass::{ShadowMapPass, ShadowMapPassFramebuffer};
+use crate::render_graph::shadow_moments_pass::{ShadowMomentsPass, ShadowMomentsPassFramebuffer};
 use crate::render_graph::ssao_pass::{SSAOPass, SSAOPassFramebuffer};
 use crate::render_graph::sss_blur_pass::{SSSBlurFramebuffer, SSSBlurPass};
 use crate::render_graph::sss_depth_pass::SSSDepthPassFramebuffer;
-use crate::render_graph::tfx_render::{TfxPpllBuildPassFramebuffer, TfxPpllResolvePassFramebuffer};
+use crate::render_graph::taa_pass::TaaPassFramebuffer;
+use crate::render_graph::tfx_render::{
+  TfxPpllBuildPassFramebuffer, TfxPpllResolvePassFramebuffer, TfxWboitAccumulatePassFramebuffer,
+  TfxWboitResolvePassFramebuffer,
+};
 use crate::render_graph::tonemapping_pass::TonemappingPassFramebuffer;
 use crate::render_graph::RenderGraph;
 use crate::vk_ctx::VkCtx;
 use crate::vk_utils::VkTexture;
 
 pub struct RenderGraphResources {
+  /// Size of all screen-sized render targets. See `Config::get_render_size()`
+  pub render_size: vk::Extent2D,
+
   // framebuffers
   pub shadow_map_pass: ShadowMapPassFramebuffer,
+  /// Only if VSM is enabled. See `VsmResources`
+  pub vsm: Option<VsmResources>,
   pub sss_depth_pass: SSSDepthPassFramebuffer,
   pub sss_blur_fbo0: SSSBlurFramebuffer,
   pub sss_blur_fbo1: SSSBlurFramebuffer,
@@ -26,10 +38,17 @@ pub struct RenderGraphResources {
   pub forward_pass: ForwardPassFramebuffer,
   pub tfx_ppll_build_pass: TfxPpllBuildPassFramebuffer,
   pub tfx_ppll_resolve_pass: TfxPpllResolvePassFramebuffer,
+  pub tfx_wboit_accumulate_pass: TfxWboitAccumulatePassFramebuffer,
+  pub tfx_wboit_resolve_pass: TfxWboitResolvePassFramebuffer,
   pub tfx_depth_only_pass: vk::Framebuffer,
   pub linear_depth_pass: LinearDepthPassFramebuffer,
   pub ssao_pass: SSAOPassFramebuffer,
   pub tonemapping_pass: TonemappingPassFramebuffer,
+  /// TAA - ping-pong, one is the history for the other
+  pub taa_pass_fbo0: TaaPassFramebuffer,
+  pub taa_pass_fbo1: TaaPassFramebuffer,
+  pub dof_pass: DofPassFramebuffer,
+  pub bloom_pass: BloomPassFramebuffer,
 
   // misc
   /// SSS - first result attachment in ping-pong
@@ -40,8 +59,9 @@ pub struct RenderGraphResources {
 
 impl RenderGraphResources {
   pub fn new(vk_app: &VkCtx, config: &Config, rg: &RenderGraph) -> Self {
-    let window_size = &vk_app.window_size();
-    let ssao_result_size = config.get_ssao_viewport_size();
+    let render_size = config.get_render_size(vk_app.window_size());
+    let window_size = &render_size;
+    let ssao_result_size = config.get_ssao_viewport_size(render_size);
 
     // textures
     let sss_ping_result_tex = ForwardPass::create_diffuse_attachment_tex::<SSSBlurPass>(
@@ -55,7 +75,12 @@ impl RenderGraphResources {
     // shadow + shadow-like SSS
     let shadow_map_pass = rg
       .shadow_map_pass
-      .create_framebuffer::<ShadowMapPass>(vk_app, config.shadows.shadowmap_size);
+      .create_framebuffer::<ShadowMapPass>(vk_app, config.shadows.atlas_size);
+    let vsm = if config.shadows.is_vsm() {
+      Some(VsmResources::new(vk_app, config, rg))
+    } else {
+      None
+    };
     let sss_depth_pass = rg.sss_depth_pass.create_framebuffer(
       vk_app,
       &rg.shadow_map_pass,
@@ -73,10 +98,23 @@ impl RenderGraphResources {
       &forward_pass.diffuse_tex,
       &forward_pass.normals_tex,
     );
+    let tfx_wboit_accumulate_pass = rg.tfx_wboit_accumulate_pass.create_framebuffer(
+      vk_app,
+      &forward_pass.depth_stencil_tex,
+      &forward_pass.normals_tex,
+    );
+    let tfx_wboit_resolve_pass = rg.tfx_wboit_resolve_pass.create_framebuffer(
+      vk_app,
+      &forward_pass.depth_stencil_tex,
+      &forward_pass.diffuse_tex,
+      &forward_pass.normals_tex,
+    );
 
-    let tfx_depth_only_pass = rg
-      .tfx_depth_only_pass
-      .create_framebuffer(vk_app, &forward_pass.depth_stencil_tex);
+    let tfx_depth_only_pass = rg.tfx_depth_only_pass.create_framebuffer(
+      vk_app,
+      &forward_pass.depth_stencil_tex,
+      &forward_pass.velocity_tex,
+    );
     // sss blur
     let sss_blur_fbo0 = rg.sss_blur_pass.create_framebuffer(
       vk_app,
@@ -98,23 +136,38 @@ impl RenderGraphResources {
     let ssao_blur_fbo1 = rg
       .ssao_blur_pass
       .create_framebuffer(vk_app, &ssao_pass.ssao_tex);
+    // taa
+    let taa_pass_fbo0 = rg.taa_pass.create_framebuffer(vk_app, window_size);
+    let taa_pass_fbo1 = rg.taa_pass.create_framebuffer(vk_app, window_size);
+    // depth of field
+    let dof_pass = rg.dof_pass.create_framebuffer(vk_app, window_size);
+    // bloom
+    let bloom_pass = rg.bloom_pass.create_framebuffer(vk_app, window_size);
     // tonemap
     let tonemapping_pass = rg.tonemapping_pass.create_framebuffer(vk_app, window_size);
 
     Self {
+      render_size,
       // fbos
       shadow_map_pass,
+      vsm,
       sss_depth_pass,
       sss_blur_fbo0,
       sss_blur_fbo1,
       forward_pass,
       tfx_ppll_build_pass,
       tfx_ppll_resolve_pass,
+      tfx_wboit_accumulate_pass,
+      tfx_wboit_resolve_pass,
       tfx_depth_only_pass,
       linear_depth_pass,
       ssao_pass,
       ssao_blur_fbo0,
       ssao_blur_fbo1,
+      taa_pass_fbo0,
+      taa_pass_fbo1,
+      dof_pass,
+      bloom_pass,
       tonemapping_pass,
       // textures
       sss_ping_result_tex,
@@ -128,6 +181,9 @@ impl RenderGraphResources {
 
     // passes framebuffers
     self.shadow_map_pass.destroy(vk_app);
+    if let Some(vsm) = self.vsm.as_mut() {
+      vsm.destroy(vk_app);
+    }
     self.sss_depth_pass.destroy(vk_app);
     self.sss_blur_fbo0.destroy(vk_app);
     self.sss_blur_fbo1.destroy(vk_app);
@@ -137,8 +193,14 @@ impl RenderGraphResources {
     device.destroy_framebuffer(self.tfx_depth_only_pass, None);
     self.tfx_ppll_build_pass.destroy(vk_app);
     self.tfx_ppll_resolve_pass.destroy(vk_app);
+    self.tfx_wboit_accumulate_pass.destroy(vk_app);
+    self.tfx_wboit_resolve_pass.destroy(vk_app);
     self.linear_depth_pass.destroy(vk_app);
     self.ssao_pass.destroy(vk_app);
+    self.taa_pass_fbo0.destroy(vk_app);
+    self.taa_pass_fbo1.destroy(vk_app);
+    self.dof_pass.destroy(vk_app);
+    self.bloom_pass.destroy(vk_app);
     self.tonemapping_pass.destroy(vk_app);
 
     // misc
@@ -146,3 +208,49 @@ impl RenderGraphResources {
     self.ssao_ping_result_tex.delete(device, allocator);
   }
 }
+
+/// Variance shadow maps. Moments textures are as big as the shadow atlas,
+/// so they are allocated only when VSM is the selected shadow technique.
+pub struct VsmResources {
+  pub shadow_moments_pass: ShadowMomentsPassFramebuffer,
+  pub shadow_moments_blur_fbo0: BlurFramebuffer,
+  pub shadow_moments_blur_fbo1: BlurFramebuffer,
+  /// First result attachment in ping-pong
+  pub shadow_moments_ping_result_tex: VkTexture,
+}
+
+impl VsmResources {
+  pub fn new(vk_app: &VkCtx, config: &Config, rg: &RenderGraph) -> Self {
+    let atlas_size = config.shadows.atlas_size;
+    let shadow_moments_ping_result_tex =
+      ShadowMomentsPass::create_result_texture(vk_app, atlas_size, true);
+    let shadow_moments_pass = rg
+      .shadow_moments_pass
+      .create_framebuffer(vk_app, atlas_size);
+    let shadow_moments_blur_fbo0 = rg
+      .shadow_moments_blur_pass
+      .create_framebuffer(vk_app, &shadow_moments_ping_result_tex);
+    let shadow_moments_blur_fbo1 = rg
+      .shadow_moments_blur_pass
+      .create_framebuffer(vk_app, &shadow_moments_pass.moments_tex);
+
+    Self {
+      shadow_moments_pass,
+      shadow_moments_blur_fbo0,
+      shadow_moments_blur_fbo1,
+      shadow_moments_ping_result_tex,
+    }
+  }
+
+  pub unsafe fn destroy(&mut self, vk_app: &VkCtx) {
+    let device = vk_app.vk_device();
+    let allocator = &vk_app.allocator;
+
+    self.shadow_moments_pass.destroy(vk_app);
+    self.shadow_moments_blur_fbo0.destroy(vk_app);
+    self.shadow_moments_blur_fbo1.destroy(vk_app);
+    self
+      .shadow_moments_ping_result_tex
+      .delete(device, allocator);
+  }
+}
@@ -0,0 +1,206 @@
+use ash::vk;
+use glam::{vec4, Mat4, Vec3, Vec4};
+
+use crate::config::{Config, LightCfg, ShadowsConfig};
+use crate::scene::BoundingBox;
+
+use super::shadow_frustum::get_up_vector;
+use super::ShadowFrustum;
+
+/// Near plane for perspective (spot and point light) shadows
+const PERSPECTIVE_NEAR: f32 = 0.05;
+/// Directional light's shadow takes `N x N` tiles
+const DIRECTIONAL_TILES: u32 = 2;
+/// Order must match `getShadowSlotIdx()` in `_lights.glsl`
+const CUBE_FACES: [Vec3; 6] = [
+  Vec3::X,
+  Vec3::NEG_X,
+  Vec3::Y,
+  Vec3::NEG_Y,
+  Vec3::Z,
+  Vec3::NEG_Z,
+];
+
+/// Part of the shadow atlas rendered from a single point of view.
+pub struct ShadowAtlasSlot {
+  pub view_projection: Mat4,
+  /// Used to expand hair strands towards the light
+  pub eye: Vec3,
+  /// In pixels
+  pub rect: vk::Rect2D,
+}
+
+/// Shadow maps of all shadow-casting lights packed into a single depth texture.
+/// Texture is a grid of `ShadowsConfig::ATLAS_TILES_PER_ROW^2` square tiles:
+/// - directional light - orthographic projection, `2x2` tiles,
+/// - spot light - perspective projection, 1 tile,
+/// - point light - cube map, 6 tiles (one for each face).
+///
+/// Recalculated every frame from `Config::lights`. Directional lights are fitted to the scene bounds.
+pub struct ShadowAtlas {
+  pub slots: Vec<ShadowAtlasSlot>,
+  /// Index of the first slot for each light in `Config::lights`.
+  /// None if the light does not cast shadows or there was no space left.
+  pub light_slots: Vec<Option<usize>>,
+  atlas_size: u32,
+}
+
+impl ShadowAtlas {
+  /// Must match `MAX_SHADOW_SLOTS` in `_lights.glsl`
+  pub const MAX_SLOTS: usize =
+    (ShadowsConfig::ATLAS_TILES_PER_ROW * ShadowsConfig::ATLAS_TILES_PER_ROW) as usize;
+
+  pub fn new(config: &Config, scene_bounds: &BoundingBox) -> Self {
+    let lights = &config.lights[..config.get_light_count()];
+    let mut atlas = Self {
+      slots: Vec::with_capacity(Self::MAX_SLOTS),
+      light_slots: vec![None; lights.len()],
+      atlas_size: config.shadows.atlas_size,
+    };
+    let mut tiles = TileAllocator::new();
+
+    // Directional lights first, they need aligned blocks of tiles.
+    let (directional, other): (Vec<_>, Vec<_>) = lights
+      .iter()
+      .enumerate()
+      .filter(|(_, light)| light.casts_shadow)
+      .partition(|(_, light)| light.is_directional());
+
+    for (light_idx, light) in directional {
+      if let Some(tile) = tiles.allocate_block(DIRECTIONAL_TILES) {
+        let slot_size = atlas.tile_size() * DIRECTIONAL_TILES;
+        let frustum = get_directional_frustum(config, light, scene_bounds, slot_size);
+        atlas.light_slots[light_idx] = Some(atlas.slots.len());
+        atlas.push_slot(
+          frustum.view_projection,
+          frustum.eye,
+          tile,
+          DIRECTIONAL_TILES,
+        );
+      }
+    }
+
+    for (light_idx, light) in other {
+      let faces: Vec<Vec3> = if light.is_point() {
+        CUBE_FACES.to_vec()
+      } else {
+        vec![light.direction()]
+      };
+      if tiles.free_count() < faces.len() {
+        continue;
+      }
+
+      atlas.light_slots[light_idx] = Some(atlas.slots.len());
+      let fov = if light.is_point() {
+        90.0f32.to_radians()
+      } else {
+        (light.spot_outer_angle * 2.0)
+          .clamp(1.0, 170.0)
+          .to_radians()
+      };
+      let projection = Mat4::perspective_rh(fov, 1.0, PERSPECTIVE_NEAR, light.radius.max(0.1));
+      let eye = light.position();
+      for dir in faces {
+        let tile = tiles.allocate_block(1).unwrap();
+        let view = Mat4::look_at_rh(eye, eye + dir, get_up_vector(dir));
+        atlas.push_slot(projection * view, eye, tile, 1);
+      }
+    }
+
+    atlas
+  }
+
+  fn push_slot(&mut self, view_projection: Mat4, eye: Vec3, tile: (u32, u32), tiles_count: u32) {
+    let tile_size = self.tile_size();
+    let size = tile_size * tiles_count;
+    self.slots.push(ShadowAtlasSlot {
+      view_projection,
+      eye,
+      rect: vk::Rect2D {
+        offset: vk::Offset2D {
+          x: (tile.0 * tile_size) as _,
+          y: (tile.1 * tile_size) as _,
+        },
+        extent: vk::Extent2D {
+          width: size,
+          height: size,
+        },
+      },
+    });
+  }
+
+  fn tile_size(&self) -> u32 {
+    self.atlas_size / ShadowsConfig::ATLAS_TILES_PER_ROW
+  }
+
+  /// Slot's region in the atlas texture as `[uvOffset.xy, uvScale.xy]`
+  pub fn get_uv_rect(&self, slot: &ShadowAtlasSlot) -> Vec4 {
+    let size = self.atlas_size as f32;
+    vec4(
+      slot.rect.offset.x as f32 / size,
+      slot.rect.offset.y as f32 / size,
+      slot.rect.extent.width as f32 / size,
+      slot.rect.extent.height as f32 / size,
+    )
+  }
+}
+
+fn get_directional_frustum(
+  config: &Config,
+  light: &LightCfg,
+  scene_bounds: &BoundingBox,
+  slot_size: u32,
+) -> ShadowFrustum {
+  let shadows = &config.shadows;
+  let dir = light.direction();
+  if shadows.fit_to_scene {
+    ShadowFrustum::fit_to_bounds(scene_bounds, dir, slot_size)
+  } else {
+    let target = light.look_at_target;
+    let eye = target - dir * shadows.directional_distance;
+    ShadowFrustum::from_projection(eye, target, &shadows.directional_projection)
+  }
+}
+
+/// Tracks which tiles of the atlas are already taken.
+struct TileAllocator {
+  taken: Vec<bool>,
+}
+
+impl TileAllocator {
+  fn new() -> Self {
+    Self {
+      taken: vec![false; ShadowAtlas::MAX_SLOTS],
+    }
+  }
+
+  fn free_count(&self) -> usize {
+    self.taken.iter().filter(|t| !**t).count()
+  }
+
+  /// Find free `size x size` block of tiles aligned to `size`.
+  /// @return top left tile `(x, y)`
+  fn allocate_block(&mut self, size: u32) -> Option<(u32, u32)> {
+    let per_row = ShadowsConfig::ATLAS_TILES_PER_ROW;
+    let tile_idx = |x: u32, y: u32| (y * per_row + x) as usize;
+
+    for y in (0..per_row).step_by(size as usize) {
+      for x in (0..per_row).step_by(size as usize) {
+        let is_free = (0..size)
+          .flat_map(|dy| (0..size).map(move |dx| (x + dx, y + dy)))
+          .all(|(tx, ty)| !self.taken[tile_idx(tx, ty)]);
+        if !is_free {
+          continue;
+        }
+
+        for dy in 0..size {
+          for dx in 0..size {
+            self.taken[tile_idx(x + dx, y + dy)] = true;
+          }
+        }
+        return Some((x, y));
+      }
+    }
+    None
+  }
+}
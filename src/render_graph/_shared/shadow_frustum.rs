@@ -0,0 +1,96 @@
+use glam::{vec3, Mat4, Vec3};
+
+use crate::config::{ShadowLightProjection, ShadowSourceCfg};
+use crate::scene::BoundingBox;
+
+/// Fitted frustum size is rounded up to one of `2^(n / SIZE_STEPS_PER_OCTAVE)`.
+/// Otherwise the texel size would change every frame as the hair moves.
+const SIZE_STEPS_PER_OCTAVE: f32 = 4.0;
+/// Make sure the scene is not clipped by texel snapping
+const SIZE_PADDING: f32 = 1.02;
+/// Near/far planes are snapped to multiples of `frustum size / DEPTH_STEPS`
+const DEPTH_STEPS: f32 = 16.0;
+
+/// Orthographic camera used to render depth maps from the light's point of view.
+pub struct ShadowFrustum {
+  pub view_projection: Mat4,
+  /// Position of the shadow camera. Used to expand hair strands towards the light
+  pub eye: Vec3,
+  /// Distance between near and far planes
+  pub depth_range: f32,
+}
+
+impl ShadowFrustum {
+  /// Manually placed camera with fixed projection box
+  pub fn from_projection(eye: Vec3, target: Vec3, projection: &ShadowLightProjection) -> Self {
+    let dir = (target - eye).normalize_or_zero();
+    let view = Mat4::look_at_rh(eye, target, get_up_vector(dir));
+    let p = projection;
+    let projection = Mat4::orthographic_rh(p.left, p.right, p.bottom, p.top, p.near, p.far);
+    Self {
+      view_projection: projection * view,
+      eye,
+      depth_range: p.far - p.near,
+    }
+  }
+
+  pub fn from_shadow_source(source: &ShadowSourceCfg) -> Self {
+    Self::from_projection(source.position(), source.look_at_target, &source.projection)
+  }
+
+  /// Tightest orthographic projection that contains whole `bounds` when looking along `dir`.
+  ///
+  /// Frustum size is quantized and its position is snapped to the texel grid (in light space),
+  /// so the shadow does not shimmer when the camera or hair moves.
+  pub fn fit_to_bounds(bounds: &BoundingBox, dir: Vec3, texture_size: u32) -> Self {
+    // rotation only, so that snapping is not affected by the eye position
+    let light_view = Mat4::look_at_rh(Vec3::ZERO, dir, get_up_vector(dir));
+    let (mut min, mut max) = (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN));
+    for corner in bounds.corners() {
+      let p = light_view.transform_point3(corner);
+      min = min.min(p);
+      max = max.max(p);
+    }
+
+    let size = ((max.x - min.x).max(max.y - min.y) * SIZE_PADDING).max(0.01);
+    let size = 2.0f32.powf((size.log2() * SIZE_STEPS_PER_OCTAVE).ceil() / SIZE_STEPS_PER_OCTAVE);
+    let half_size = size / 2.0;
+
+    let texel_size = size / texture_size.max(1) as f32;
+    let snap = |v: f32| (v / texel_size).floor() * texel_size;
+    let center_x = snap((min.x + max.x) / 2.0);
+    let center_y = snap((min.y + max.y) / 2.0);
+
+    // camera looks towards -Z, so `max.z` is closest to the light
+    let depth_step = size / DEPTH_STEPS;
+    let near_z = (max.z / depth_step).ceil() * depth_step + depth_step;
+    let far_z = (min.z / depth_step).floor() * depth_step - depth_step;
+
+    let projection = Mat4::orthographic_rh(
+      center_x - half_size,
+      center_x + half_size,
+      center_y - half_size,
+      center_y + half_size,
+      -near_z,
+      -far_z,
+    );
+    let eye = light_view
+      .inverse()
+      .transform_point3(vec3(center_x, center_y, near_z));
+
+    Self {
+      view_projection: projection * light_view,
+      eye,
+      depth_range: near_z - far_z,
+    }
+  }
+}
+
+/// `look_at` breaks if up vector is parallel to the view direction
+pub(super) fn get_up_vector(dir: Vec3) -> Vec3 {
+  if dir.y.abs() > 0.99 {
+    Vec3::Z
+  } else {
+    Vec3::Y
+  }
+}
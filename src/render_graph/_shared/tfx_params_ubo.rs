@@ -15,11 +15,19 @@ pub struct TfxParamsUBO {
   // geometry
   pub u_geometry: Vec4, // [u_thin_tip, u_fiber_radius, u_follow_hair_spread_root, u_follow_hair_spread_tip]
   pub u_center_of_gravity: Vec4, // [cog.xyz, -]
+  pub u_follow_hairs: Vec4, // [uint u_followHairMode, u_followHairClumpStrength, u_followHairClumpShape, -]
+  pub u_strand_modifiers: Vec4, // [u_curlRadius, u_curlFrequency, u_frizz, u_lengthVariation]
+  pub u_selected_strand: Vec4, // [uint strandId, uint instanceId, bool isSelected, -]
+  pub u_lod: Vec4,          // [lodFollowHairs, -, -, -]
   // material
   pub u_albedo: Vec4,    // [u_albedo.rgb, -]
   pub u_specular1: Vec4, // [u_specularColor1.rgb, u_specular_power1]
   pub u_specular2: Vec4, // [u_specularColor1.rgb, u_specular_power2]
   pub u_material: Vec4, // [u_primaryShift, u_secondaryShift, u_specularStrength1, u_specularStrength2]
+  // simulation
+  pub u_sim_wind: Vec4,        // [windDir.xyz, windStrength]
+  pub u_sim_shape: Vec4, // [Sim0.Verlet damping, Sim2.LSC local stiffness, Sim0.GSC global stiffness, Sim0.GSC global range.]
+  pub u_sim_constraints: Vec4, // [Sim3.Length Constraints iterations, Sim3.Length stiffness, g_GravityMagnitude, -]
 }
 
 unsafe impl bytemuck::Zeroable for TfxParamsUBO {}
@@ -28,22 +36,42 @@ unsafe impl bytemuck::Pod for TfxParamsUBO {}
 impl TfxParamsUBO {
   pub fn new(_config: &Config, tfx: &TfxObject) -> Self {
     let mat = &tfx.material;
+    let sim = &tfx.simulation;
+    let modifiers = &tfx.strand_modifiers;
+    let lod = &tfx.lod;
 
     Self {
       u_model_matrix: tfx.model_matrix,
       u_general_settings: vec4(
-        mat.opacity,
+        (mat.opacity * lod.opacity_scale).min(1.0),
         tfx.num_vertices_per_strand as f32,
         mat.ao_strength,
         mat.ao_exp,
       ),
       u_geometry: vec4(
         1.0 - tfx.thin_tip,
-        tfx.fiber_radius,
+        tfx.fiber_radius * lod.width_scale,
         tfx.follow_hair_spread_root,
         tfx.follow_hair_spread_tip,
       ),
       u_center_of_gravity: into_vec4(tfx.center_of_gravity, 0.0),
+      u_follow_hairs: vec4(
+        tfx.follow_hair_mode as f32,
+        tfx.follow_hair_clump_strength,
+        tfx.follow_hair_clump_shape,
+        0.0,
+      ),
+      u_strand_modifiers: vec4(
+        modifiers.curl_radius,
+        modifiers.curl_frequency,
+        modifiers.frizz,
+        modifiers.length_variation,
+      ),
+      u_selected_strand: match tfx.strand_inspector.selection {
+        Some(s) => vec4(s.strand_id as f32, s.instance_id as f32, 1.0, 0.0),
+        None => Vec4::ZERO,
+      },
+      u_lod: vec4(lod.follow_hairs, 0.0, 0.0, 0.0),
       u_albedo: mint3_into_vec4(mat.albedo, 0.0),
       u_specular1: mint3_into_vec4(mat.specular_color1, mat.specular_power1),
       u_specular2: mint3_into_vec4(mat.specular_color2, mat.specular_power2),
@@ -53,6 +81,20 @@ impl TfxParamsUBO {
         mat.specular_strength1,
         mat.specular_strength2,
       ),
+      // simulation
+      u_sim_wind: into_vec4(sim.wind_position(), sim.wind_strength),
+      u_sim_shape: vec4(
+        sim.verlet_integration_damping,
+        sim.local_stiffness,
+        sim.global_stiffness,
+        sim.global_stiffness_range,
+      ),
+      u_sim_constraints: vec4(
+        sim.length_constraint_iterations as f32,
+        sim.length_stiffness,
+        sim.gravity,
+        0.0,
+      ),
     }
   }
 }
@@ -0,0 +1,313 @@
+use ash::vk;
+use log::info;
+
+use crate::config::AutoExposureStats;
+use crate::utils::get_simple_type_name;
+use crate::vk_ctx::VkCtx;
+use crate::vk_utils::*;
+
+use super::PassExecContext;
+
+const BINDING_INDEX_CONFIG_UBO: u32 = 0;
+const BINDING_INDEX_SOURCE: u32 = 1;
+const BINDING_INDEX_HISTOGRAM: u32 = 2;
+const BINDING_INDEX_EXPOSURE: u32 = 3;
+
+const SHADER_PATH_HISTOGRAM: &str = "./assets/shaders-compiled/auto_exposure_histogram.comp.spv";
+const SHADER_PATH_AVERAGE: &str = "./assets/shaders-compiled/auto_exposure_average.comp.spv";
+
+/// Change this in `_auto_exposure.glsl` too
+const HISTOGRAM_BINS: usize = 256;
+/// Change this in `_auto_exposure.glsl` too
+const THREAD_GROUP_SIZE: u32 = 16;
+/// `[exposure, adaptedLuminance, targetLuminance, pixelCount]` followed by histogram
+const EXPOSURE_HEADER_BYTES: usize = 4 * 4;
+const EXPOSURE_BUFFER_BYTES: usize = EXPOSURE_HEADER_BYTES + HISTOGRAM_BINS * 4;
+
+/// Eye adaptation.
+///
+/// 1. Build luminance histogram of the HDR image.
+/// 2. Average the histogram and smoothly adapt to it over time.
+///
+/// Result is stored in `exposure_buffer` and read by `TonemappingPass`.
+/// Buffers are not dependent on render size, so adaptation state
+/// survives recreating render targets.
+pub struct AutoExposurePass {
+  histogram_pipeline: vk::Pipeline,
+  average_pipeline: vk::Pipeline,
+  pipeline_layout: vk::PipelineLayout,
+  uniforms_layout: vk::DescriptorSetLayout,
+  /// Cleared after every use by the average step
+  histogram_buffer: VkBuffer,
+  /// See `_auto_exposure.glsl` for the layout
+  pub exposure_buffer: VkBuffer,
+}
+
+impl AutoExposurePass {
+  pub fn new(vk_app: &VkCtx) -> Self {
+    info!("Creating {}", get_simple_type_name::<Self>());
+    let device = vk_app.vk_device();
+    let pipeline_cache = &vk_app.pipeline_cache;
+
+    let uniforms_desc = Self::get_uniforms_layout();
+    let uniforms_layout = create_push_descriptor_layout(device, uniforms_desc);
+    let pipeline_layout = create_pipeline_layout(device, &[uniforms_layout], &[]);
+    let histogram_pipeline = create_compute_pipeline(
+      device,
+      pipeline_cache,
+      &pipeline_layout,
+      SHADER_PATH_HISTOGRAM,
+    );
+    let average_pipeline = create_compute_pipeline(
+      device,
+      pipeline_cache,
+      &pipeline_layout,
+      SHADER_PATH_AVERAGE,
+    );
+
+    let histogram_buffer = vk_app.create_buffer_from_data(
+      format!("{}.histogram", get_simple_type_name::<Self>()),
+      &[0u8; HISTOGRAM_BINS * 4],
+      vk::BufferUsageFlags::STORAGE_BUFFER,
+    );
+    let exposure_buffer = vk_app.create_buffer_from_data(
+      format!("{}.exposure", get_simple_type_name::<Self>()),
+      &[0u8; EXPOSURE_BUFFER_BYTES],
+      vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::TRANSFER_SRC,
+    );
+
+    Self {
+      histogram_pipeline,
+      average_pipeline,
+      pipeline_layout,
+      uniforms_layout,
+      histogram_buffer,
+      exposure_buffer,
+    }
+  }
+
+  pub unsafe fn destroy(&mut self, vk_app: &VkCtx) {
+    let device = vk_app.vk_device();
+    let allocator = &vk_app.allocator;
+
+    device.destroy_descriptor_set_layout(self.uniforms_layout, None);
+    device.destroy_pipeline_layout(self.pipeline_layout, None);
+    device.destroy_pipeline(self.histogram_pipeline, None);
+    device.destroy_pipeline(self.average_pipeline, None);
+    self.histogram_buffer.delete(allocator);
+    self.exposure_buffer.delete(allocator);
+  }
+
+  fn get_uniforms_layout() -> Vec<vk::DescriptorSetLayoutBinding> {
+    vec![
+      create_ubo_binding(BINDING_INDEX_CONFIG_UBO, vk::ShaderStageFlags::COMPUTE),
+      create_texture_binding(BINDING_INDEX_SOURCE, vk::ShaderStageFlags::COMPUTE),
+      create_ssbo_binding(BINDING_INDEX_HISTOGRAM, vk::ShaderStageFlags::COMPUTE),
+      create_ssbo_binding(BINDING_INDEX_EXPOSURE, vk::ShaderStageFlags::COMPUTE),
+    ]
+  }
+
+  /// Buffer used to read the result back to CPU. Needs 1 per frame in flight.
+  pub fn create_readback_buffer(vk_app: &VkCtx, frame_id: usize) -> VkBuffer {
+    vk_app.create_buffer_empty(
+      format!("auto_exposure_readback_{}", frame_id),
+      EXPOSURE_BUFFER_BYTES,
+      vk::BufferUsageFlags::TRANSFER_DST,
+      VkMemoryPreference::CpuReadback,
+    )
+  }
+
+  /// Parse content of the readback buffer. Only valid after the frame that wrote it has finished.
+  pub fn read_stats(vk_app: &VkCtx, readback_buffer: &VkBuffer) -> AutoExposureStats {
+    let mut bytes = vec![0u8; EXPOSURE_BUFFER_BYTES];
+    // memory might not be HOST_COHERENT
+    readback_buffer.invalidate_mapped(&vk_app.allocator);
+    readback_buffer.read_from_mapped(&mut bytes);
+    let read_u32 = |offset: usize| {
+      let mut buf = [0u8; 4];
+      buf.copy_from_slice(&bytes[offset..offset + 4]);
+      u32::from_le_bytes(buf)
+    };
+    let read_f32 = |offset: usize| f32::from_bits(read_u32(offset));
+
+    let pixel_count = read_u32(12).max(1) as f32;
+    let histogram = (0..HISTOGRAM_BINS)
+      .map(|i| read_u32(EXPOSURE_HEADER_BYTES + i * 4) as f32 / pixel_count)
+      .collect();
+
+    AutoExposureStats {
+      exposure: read_f32(0),
+      adapted_luminance: read_f32(4),
+      target_luminance: read_f32(8),
+      histogram,
+    }
+  }
+
+  pub fn execute(
+    &self,
+    exec_ctx: &PassExecContext,
+    source_tex: &mut VkTexture,
+    readback_buffer: &VkBuffer,
+  ) {
+    let vk_app = exec_ctx.vk_app;
+    let command_buffer = exec_ctx.command_buffer;
+    let device = vk_app.vk_device();
+    let size = exec_ctx.size;
+    let pass_name = &get_simple_type_name::<Self>();
+
+    unsafe {
+      self.cmd_resource_barriers(device, &command_buffer, source_tex);
+
+      let scope_id = exec_ctx.cmd_begin_scope(pass_name);
+
+      // histogram
+      device.cmd_bind_pipeline(
+        command_buffer,
+        vk::PipelineBindPoint::COMPUTE,
+        self.histogram_pipeline,
+      );
+      self.bind_uniforms(exec_ctx, source_tex);
+      let group_count_x = size.width.div_ceil(THREAD_GROUP_SIZE);
+      let group_count_y = size.height.div_ceil(THREAD_GROUP_SIZE);
+      device.cmd_dispatch(command_buffer, group_count_x, group_count_y, 1);
+
+      cmd_storage_resource_barrier(
+        device,
+        command_buffer,
+        VkStorageResourceBarrier {
+          previous_op: (
+            vk::PipelineStageFlags2::COMPUTE_SHADER,
+            vk::AccessFlags2::SHADER_WRITE,
+          ),
+          next_op: (
+            vk::PipelineStageFlags2::COMPUTE_SHADER,
+            vk::AccessFlags2::SHADER_READ | vk::AccessFlags2::SHADER_WRITE,
+          ),
+        },
+      );
+
+      // average + adapt
+      device.cmd_bind_pipeline(
+        command_buffer,
+        vk::PipelineBindPoint::COMPUTE,
+        self.average_pipeline,
+      );
+      self.bind_uniforms(exec_ctx, source_tex);
+      device.cmd_dispatch(command_buffer, 1, 1, 1);
+
+      exec_ctx.cmd_end_scope(scope_id);
+
+      // tonemapping reads the result. Also copy it for the UI
+      cmd_storage_resource_barrier(
+        device,
+        command_buffer,
+        VkStorageResourceBarrier {
+          previous_op: (
+            vk::PipelineStageFlags2::COMPUTE_SHADER,
+            vk::AccessFlags2::SHADER_WRITE,
+          ),
+          next_op: (
+            vk::PipelineStageFlags2::FRAGMENT_SHADER | vk::PipelineStageFlags2::COPY,
+            vk::AccessFlags2::SHADER_READ | vk::AccessFlags2::TRANSFER_READ,
+          ),
+        },
+      );
+      let mem_region = vk::BufferCopy::builder()
+        .size(EXPOSURE_BUFFER_BYTES as u64)
+        .build();
+      device.cmd_copy_buffer(
+        command_buffer,
+        self.exposure_buffer.buffer,
+        readback_buffer.buffer,
+        &[mem_region],
+      );
+      cmd_storage_resource_barrier(
+        device,
+        command_buffer,
+        VkStorageResourceBarrier {
+          previous_op: (
+            vk::PipelineStageFlags2::COPY,
+            vk::AccessFlags2::TRANSFER_WRITE,
+          ),
+          next_op: (vk::PipelineStageFlags2::HOST, vk::AccessFlags2::HOST_READ),
+        },
+      );
+    }
+  }
+
+  unsafe fn bind_uniforms(&self, exec_ctx: &PassExecContext, source_tex: &VkTexture) {
+    let vk_app = exec_ctx.vk_app;
+    let resouce_binder = exec_ctx.create_resouce_binder(self.pipeline_layout);
+
+    let uniform_resouces = [
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::UBO,
+        binding: BINDING_INDEX_CONFIG_UBO,
+        buffer: exec_ctx.config_buffer,
+      },
+      BindableResource::Texture {
+        binding: BINDING_INDEX_SOURCE,
+        texture: source_tex,
+        image_view: None,
+        sampler: vk_app.default_texture_sampler_nearest,
+      },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: BINDING_INDEX_HISTOGRAM,
+        buffer: &self.histogram_buffer,
+      },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: BINDING_INDEX_EXPOSURE,
+        buffer: &self.exposure_buffer,
+      },
+    ];
+    bind_resources_to_descriptors_compute(&resouce_binder, 0, &uniform_resouces);
+  }
+
+  unsafe fn cmd_resource_barriers(
+    &self,
+    device: &ash::Device,
+    command_buffer: &vk::CommandBuffer,
+    source_tex: &mut VkTexture,
+  ) {
+    // previous frame's tonemapping and readback copy
+    cmd_storage_resource_barrier(
+      device,
+      *command_buffer,
+      VkStorageResourceBarrier {
+        previous_op: (
+          vk::PipelineStageFlags2::FRAGMENT_SHADER | vk::PipelineStageFlags2::COPY,
+          vk::AccessFlags2::SHADER_READ | vk::AccessFlags2::TRANSFER_READ,
+        ),
+        next_op: (
+          vk::PipelineStageFlags2::COMPUTE_SHADER,
+          vk::AccessFlags2::SHADER_READ | vk::AccessFlags2::SHADER_WRITE,
+        ),
+      },
+    );
+
+    // Layout transition assumes the texture is read in a fragment shader.
+    // Chain it to the compute stage
+    VkTexture::cmd_transition_attachments_for_read_barrier(
+      device,
+      *command_buffer,
+      &mut [source_tex],
+    );
+    cmd_storage_resource_barrier(
+      device,
+      *command_buffer,
+      VkStorageResourceBarrier {
+        previous_op: (
+          vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+            | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+          vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+        ),
+        next_op: (
+          vk::PipelineStageFlags2::COMPUTE_SHADER,
+          vk::AccessFlags2::SHADER_SAMPLED_READ,
+        ),
+      },
+    );
+  }
+}
@@ -0,0 +1,400 @@
+use std::mem::size_of;
+
+use ash::vk;
+use glam::{vec2, Vec2};
+use log::info;
+
+use crate::utils::get_simple_type_name;
+use crate::vk_ctx::VkCtx;
+use crate::vk_utils::*;
+
+use super::PassExecContext;
+
+const BINDING_INDEX_CONFIG_UBO: u32 = 0;
+const BINDING_INDEX_SOURCE: u32 = 1;
+
+/// 16-bit float supports blending on every GPU
+const RESULT_TEXTURE_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+const COLOR_ATTACHMENT_COUNT: usize = 1;
+/// Each mip is half the size of the previous one. First mip is half of the render size
+const MIP_COUNT: usize = 6;
+const SHADER_PATHS_DOWNSAMPLE: (&str, &str) = (
+  "./assets/shaders-compiled/fullscreen_quad.vert.spv",
+  "./assets/shaders-compiled/bloom_downsample.frag.spv",
+);
+const SHADER_PATHS_UPSAMPLE: (&str, &str) = (
+  "./assets/shaders-compiled/fullscreen_quad.vert.spv",
+  "./assets/shaders-compiled/bloom_upsample.frag.spv",
+);
+
+#[derive(Clone, Copy)]
+enum BloomStep {
+  /// Write to the next (smaller) mip, overrides previous content
+  Downsample,
+  /// Additively blend into the previous (bigger) mip
+  Upsample,
+}
+
+/// Bloom using dual filtering on a mip chain.
+///
+/// 1. Downsample - first step extracts the bright pixels (threshold), each next one halves the size.
+/// 2. Upsample - from the smallest mip, additively blend into the bigger one.
+///
+/// Result (first mip) is added to the HDR color in `TonemappingPass`.
+///
+/// https://community.arm.com/cfs-file/__key/communityserver-blogs-components-weblogfiles/00-00-00-20-66/siggraph2015_2D00_mmg_2D00_marius_2D00_notes.pdf
+pub struct BloomPass {
+  /// Overrides previous content
+  render_pass_downsample: vk::RenderPass,
+  /// Blends with previous content
+  render_pass_upsample: vk::RenderPass,
+  pipeline_downsample: vk::Pipeline,
+  pipeline_upsample: vk::Pipeline,
+  pipeline_layout: vk::PipelineLayout,
+  uniforms_layout: vk::DescriptorSetLayout,
+}
+
+impl BloomPass {
+  pub fn new(vk_app: &VkCtx) -> Self {
+    info!("Creating {}", get_simple_type_name::<Self>());
+    let device = vk_app.vk_device();
+    let pipeline_cache = &vk_app.pipeline_cache;
+
+    let render_pass_downsample = Self::create_render_pass(device, vk::AttachmentLoadOp::DONT_CARE);
+    let render_pass_upsample = Self::create_render_pass(device, vk::AttachmentLoadOp::LOAD);
+    let uniforms_desc = Self::get_uniforms_layout();
+    let push_constant_ranges = Self::get_push_constant_layout();
+    let uniforms_layout = create_push_descriptor_layout(device, uniforms_desc);
+    let pipeline_layout =
+      create_pipeline_layout(device, &[uniforms_layout], &[push_constant_ranges]);
+    let pipeline_downsample = Self::create_pipeline(
+      device,
+      pipeline_cache,
+      &render_pass_downsample,
+      &pipeline_layout,
+      SHADER_PATHS_DOWNSAMPLE,
+      false,
+    );
+    let pipeline_upsample = Self::create_pipeline(
+      device,
+      pipeline_cache,
+      &render_pass_upsample,
+      &pipeline_layout,
+      SHADER_PATHS_UPSAMPLE,
+      true,
+    );
+
+    Self {
+      render_pass_downsample,
+      render_pass_upsample,
+      pipeline_downsample,
+      pipeline_upsample,
+      pipeline_layout,
+      uniforms_layout,
+    }
+  }
+
+  pub unsafe fn destroy(&self, device: &ash::Device) {
+    device.destroy_render_pass(self.render_pass_downsample, None);
+    device.destroy_render_pass(self.render_pass_upsample, None);
+    device.destroy_descriptor_set_layout(self.uniforms_layout, None);
+    device.destroy_pipeline_layout(self.pipeline_layout, None);
+    device.destroy_pipeline(self.pipeline_downsample, None);
+    device.destroy_pipeline(self.pipeline_upsample, None);
+  }
+
+  fn create_render_pass(device: &ash::Device, load_op: vk::AttachmentLoadOp) -> vk::RenderPass {
+    let color_attachment = create_color_attachment(
+      0,
+      RESULT_TEXTURE_FORMAT,
+      load_op,
+      vk::AttachmentStoreOp::STORE,
+    );
+
+    unsafe { create_render_pass_from_attachments(device, None, &[color_attachment]) }
+  }
+
+  fn get_uniforms_layout() -> Vec<vk::DescriptorSetLayoutBinding> {
+    vec![
+      create_ubo_binding(BINDING_INDEX_CONFIG_UBO, vk::ShaderStageFlags::FRAGMENT),
+      create_texture_binding(BINDING_INDEX_SOURCE, vk::ShaderStageFlags::FRAGMENT),
+    ]
+  }
+
+  fn get_push_constant_layout() -> vk::PushConstantRange {
+    vk::PushConstantRange::builder()
+      .offset(0)
+      .size(size_of::<BloomPassPushConstants>() as _)
+      .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+      .build()
+  }
+
+  fn create_pipeline(
+    device: &ash::Device,
+    pipeline_cache: &vk::PipelineCache,
+    render_pass: &vk::RenderPass,
+    pipeline_layout: &vk::PipelineLayout,
+    shader_paths: (&str, &str),
+    additive_blend: bool,
+  ) -> vk::Pipeline {
+    let vertex_desc = ps_vertex_empty();
+
+    create_pipeline_with_defaults(
+      device,
+      render_pass,
+      pipeline_layout,
+      shader_paths,
+      vertex_desc,
+      COLOR_ATTACHMENT_COUNT,
+      |builder| {
+        if !additive_blend {
+          let pipeline_create_info = builder.build();
+          return create_pipeline(device, pipeline_cache, pipeline_create_info);
+        }
+
+        let blend_additive = vk::PipelineColorBlendAttachmentState::builder()
+          .color_write_mask(vk::ColorComponentFlags::RGBA)
+          .blend_enable(true)
+          .color_blend_op(vk::BlendOp::ADD)
+          .src_color_blend_factor(vk::BlendFactor::ONE) // shader output
+          .dst_color_blend_factor(vk::BlendFactor::ONE) // existing value on destination attachment
+          .alpha_blend_op(vk::BlendOp::ADD)
+          .src_alpha_blend_factor(vk::BlendFactor::ZERO) // shader output
+          .dst_alpha_blend_factor(vk::BlendFactor::ONE) // existing value on destination attachment
+          .build();
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+          .attachments(&[blend_additive])
+          .build();
+
+        let pipeline_create_info = builder.color_blend_state(&color_blend_state).build();
+        create_pipeline(device, pipeline_cache, pipeline_create_info)
+      },
+    )
+  }
+
+  /// `size` - render size. First mip is half of it
+  pub fn create_framebuffer(&self, vk_app: &VkCtx, size: &vk::Extent2D) -> BloomPassFramebuffer {
+    let device = vk_app.vk_device();
+
+    let mips = (0..MIP_COUNT)
+      .map(|mip_idx| {
+        let mip_size = vk::Extent2D {
+          width: (size.width >> (mip_idx + 1)).max(1),
+          height: (size.height >> (mip_idx + 1)).max(1),
+        };
+        let tex = vk_app.create_attachment::<Self>(
+          &format!("mip{}", mip_idx),
+          RESULT_TEXTURE_FORMAT,
+          mip_size,
+        );
+        // render passes differ only in load op, so both are compatible with the fbo
+        let fbo = create_framebuffer(
+          device,
+          self.render_pass_downsample,
+          &[tex.image_view()],
+          &mip_size,
+        );
+        BloomMipFramebuffer { tex, fbo }
+      })
+      .collect();
+
+    BloomPassFramebuffer { mips }
+  }
+
+  pub fn execute(
+    &self,
+    exec_ctx: &PassExecContext,
+    framebuffer: &mut BloomPassFramebuffer,
+    source_tex: &mut VkTexture,
+  ) {
+    let radius = exec_ctx.config.borrow().postfx.bloom_radius;
+    let mips = &mut framebuffer.mips;
+
+    // downsample
+    for mip_idx in 0..mips.len() {
+      let (prev_mips, next_mips) = mips.split_at_mut(mip_idx);
+      let target = &mut next_mips[0];
+      let is_first_pass = mip_idx == 0;
+      let source = match prev_mips.last_mut() {
+        Some(prev_mip) => &mut prev_mip.tex,
+        None => &mut *source_tex,
+      };
+      self.execute_single_step(
+        exec_ctx,
+        BloomStep::Downsample,
+        mip_idx,
+        target,
+        source,
+        &BloomPassPushConstants {
+          u_source_texel_size: texel_size(source),
+          u_is_first_pass: if is_first_pass { 1.0 } else { 0.0 },
+          u_radius: radius,
+        },
+      );
+    }
+
+    // upsample
+    for mip_idx in (0..mips.len() - 1).rev() {
+      let (prev_mips, next_mips) = mips.split_at_mut(mip_idx + 1);
+      let target = &mut prev_mips[mip_idx];
+      let source = &mut next_mips[0].tex;
+      self.execute_single_step(
+        exec_ctx,
+        BloomStep::Upsample,
+        mip_idx,
+        target,
+        source,
+        &BloomPassPushConstants {
+          u_source_texel_size: texel_size(source),
+          u_is_first_pass: 0.0,
+          u_radius: radius,
+        },
+      );
+    }
+  }
+
+  fn execute_single_step(
+    &self,
+    exec_ctx: &PassExecContext,
+    step: BloomStep,
+    mip_idx: usize,
+    target: &mut BloomMipFramebuffer,
+    source_tex: &mut VkTexture,
+    params: &BloomPassPushConstants,
+  ) {
+    let vk_app = exec_ctx.vk_app;
+    let command_buffer = exec_ctx.command_buffer;
+    let device = vk_app.vk_device();
+    let size = target.tex.size();
+    let (step_name, render_pass, pipeline) = match step {
+      BloomStep::Downsample => (
+        "down",
+        &self.render_pass_downsample,
+        &self.pipeline_downsample,
+      ),
+      BloomStep::Upsample => ("up", &self.render_pass_upsample, &self.pipeline_upsample),
+    };
+    let pass_name = &format!(
+      "{}.{}{}",
+      get_simple_type_name::<Self>(),
+      step_name,
+      mip_idx
+    );
+
+    unsafe {
+      self.cmd_resource_barriers(device, &command_buffer, &mut target.tex, source_tex);
+
+      // start render pass
+      let scope_id = exec_ctx.cmd_begin_scope(pass_name);
+      exec_ctx.cmd_start_render_pass(render_pass, pipeline, &target.fbo, &size, &[]);
+
+      // bind uniforms (do not move this)
+      self.bind_uniforms(exec_ctx, params, source_tex);
+
+      // draw calls
+      cmd_draw_fullscreen_triangle(device, &command_buffer);
+
+      // end
+      exec_ctx.cmd_end_render_pass(scope_id);
+    }
+  }
+
+  unsafe fn bind_uniforms(
+    &self,
+    exec_ctx: &PassExecContext,
+    push_constants: &BloomPassPushConstants,
+    source_tex: &mut VkTexture,
+  ) {
+    let vk_app = exec_ctx.vk_app;
+    let command_buffer = exec_ctx.command_buffer;
+    let device = vk_app.vk_device();
+    let resouce_binder = exec_ctx.create_resouce_binder(self.pipeline_layout);
+
+    let uniform_resouces = [
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::UBO,
+        binding: BINDING_INDEX_CONFIG_UBO,
+        buffer: exec_ctx.config_buffer,
+      },
+      BindableResource::Texture {
+        binding: BINDING_INDEX_SOURCE,
+        texture: source_tex,
+        image_view: None,
+        sampler: vk_app.default_texture_sampler_linear,
+      },
+    ];
+    bind_resources_to_descriptors_graphic(&resouce_binder, 0, &uniform_resouces);
+
+    // push constants
+    let push_constants_bytes = bytemuck::bytes_of(push_constants);
+    device.cmd_push_constants(
+      command_buffer,
+      self.pipeline_layout,
+      vk::ShaderStageFlags::FRAGMENT,
+      0,
+      push_constants_bytes,
+    );
+  }
+
+  unsafe fn cmd_resource_barriers(
+    &self,
+    device: &ash::Device,
+    command_buffer: &vk::CommandBuffer,
+    result_tex: &mut VkTexture, // write
+    source_tex: &mut VkTexture, // read
+  ) {
+    VkTexture::cmd_transition_attachments_for_read_barrier(
+      device,
+      *command_buffer,
+      &mut [source_tex],
+    );
+
+    VkTexture::cmd_transition_attachments_for_write_barrier(
+      device,
+      *command_buffer,
+      &mut [result_tex],
+    );
+  }
+}
+
+fn texel_size(tex: &VkTexture) -> Vec2 {
+  let size = tex.size();
+  vec2(1.0 / size.width as f32, 1.0 / size.height as f32)
+}
+
+pub struct BloomMipFramebuffer {
+  pub tex: VkTexture,
+  pub fbo: vk::Framebuffer,
+}
+
+pub struct BloomPassFramebuffer {
+  /// Mip 0 has the final result
+  pub mips: Vec<BloomMipFramebuffer>,
+}
+
+impl BloomPassFramebuffer {
+  pub fn result_tex(&mut self) -> &mut VkTexture {
+    &mut self.mips[0].tex
+  }
+
+  pub unsafe fn destroy(&mut self, vk_app: &VkCtx) {
+    let device = vk_app.vk_device();
+    let allocator = &vk_app.allocator;
+
+    self.mips.iter_mut().for_each(|mip| {
+      device.destroy_framebuffer(mip.fbo, None);
+      mip.tex.delete(device, allocator);
+    });
+  }
+}
+
+#[derive(Copy, Clone, Debug)] // , bytemuck::Zeroable, bytemuck::Pod
+#[repr(C)]
+struct BloomPassPushConstants {
+  pub u_source_texel_size: Vec2,
+  pub u_is_first_pass: f32,
+  pub u_radius: f32,
+}
+
+unsafe impl bytemuck::Zeroable for BloomPassPushConstants {}
+unsafe impl bytemuck::Pod for BloomPassPushConstants {}
@@ -0,0 +1,300 @@
+use ash::vk;
+use log::info;
+
+use crate::utils::get_simple_type_name;
+use crate::vk_ctx::VkCtx;
+use crate::vk_utils::*;
+
+use super::forward_pass::ForwardPass;
+use super::PassExecContext;
+
+const BINDING_INDEX_CONFIG_UBO: u32 = 0;
+const BINDING_INDEX_LINEAR_DEPTH: u32 = 1;
+const BINDING_INDEX_COLOR: u32 = 2;
+const BINDING_INDEX_COC: u32 = 3;
+
+/// Signed, so foreground and background can be told apart
+const COC_TEXTURE_FORMAT: vk::Format = vk::Format::R16_SFLOAT;
+const RESULT_TEXTURE_FORMAT: vk::Format = ForwardPass::DIFFUSE_TEXTURE_FORMAT;
+const COLOR_ATTACHMENT_COUNT: usize = 1;
+const SHADER_PATHS_COC: (&str, &str) = (
+  "./assets/shaders-compiled/fullscreen_quad.vert.spv",
+  "./assets/shaders-compiled/dof_coc.frag.spv",
+);
+const SHADER_PATHS_BLUR: (&str, &str) = (
+  "./assets/shaders-compiled/fullscreen_quad.vert.spv",
+  "./assets/shaders-compiled/dof.frag.spv",
+);
+
+/// Depth of field. Runs in HDR, before tonemapping.
+///
+/// 1. CoC - circle of confusion from the thin lens model (focus distance,
+///    f-stop, focal length). See `PostFxCfg::dof_coc_scale()`.
+/// 2. Blur - single pass scatter-as-gather bokeh.
+///
+/// https://developer.nvidia.com/gpugems/gpugems/part-iv-image-processing/chapter-23-depth-field-survey-techniques
+/// http://blog.tuxedolabs.com/2018/05/04/bokeh-depth-of-field-in-single-pass.html
+pub struct DofPass {
+  render_pass_coc: vk::RenderPass,
+  render_pass_blur: vk::RenderPass,
+  pipeline_coc: vk::Pipeline,
+  pipeline_blur: vk::Pipeline,
+  pipeline_layout: vk::PipelineLayout,
+  uniforms_layout: vk::DescriptorSetLayout,
+}
+
+impl DofPass {
+  pub fn new(vk_app: &VkCtx) -> Self {
+    info!("Creating {}", get_simple_type_name::<Self>());
+    let device = vk_app.vk_device();
+    let pipeline_cache = &vk_app.pipeline_cache;
+
+    let render_pass_coc = Self::create_render_pass(device, COC_TEXTURE_FORMAT);
+    let render_pass_blur = Self::create_render_pass(device, RESULT_TEXTURE_FORMAT);
+    let uniforms_desc = Self::get_uniforms_layout();
+    let uniforms_layout = create_push_descriptor_layout(device, uniforms_desc);
+    let pipeline_layout = create_pipeline_layout(device, &[uniforms_layout], &[]);
+    let pipeline_coc = Self::create_pipeline(
+      device,
+      pipeline_cache,
+      &render_pass_coc,
+      &pipeline_layout,
+      SHADER_PATHS_COC,
+    );
+    let pipeline_blur = Self::create_pipeline(
+      device,
+      pipeline_cache,
+      &render_pass_blur,
+      &pipeline_layout,
+      SHADER_PATHS_BLUR,
+    );
+
+    Self {
+      render_pass_coc,
+      render_pass_blur,
+      pipeline_coc,
+      pipeline_blur,
+      pipeline_layout,
+      uniforms_layout,
+    }
+  }
+
+  pub unsafe fn destroy(&self, device: &ash::Device) {
+    device.destroy_render_pass(self.render_pass_coc, None);
+    device.destroy_render_pass(self.render_pass_blur, None);
+    device.destroy_descriptor_set_layout(self.uniforms_layout, None);
+    device.destroy_pipeline_layout(self.pipeline_layout, None);
+    device.destroy_pipeline(self.pipeline_coc, None);
+    device.destroy_pipeline(self.pipeline_blur, None);
+  }
+
+  fn create_render_pass(device: &ash::Device, format: vk::Format) -> vk::RenderPass {
+    let color_attachment = create_color_attachment(
+      0,
+      format,
+      vk::AttachmentLoadOp::DONT_CARE, // we override every pixel regardless
+      vk::AttachmentStoreOp::STORE,
+    );
+
+    unsafe { create_render_pass_from_attachments(device, None, &[color_attachment]) }
+  }
+
+  /// Both steps share the layout, each binds only the resources it uses
+  fn get_uniforms_layout() -> Vec<vk::DescriptorSetLayoutBinding> {
+    vec![
+      create_ubo_binding(BINDING_INDEX_CONFIG_UBO, vk::ShaderStageFlags::FRAGMENT),
+      create_texture_binding(BINDING_INDEX_LINEAR_DEPTH, vk::ShaderStageFlags::FRAGMENT),
+      create_texture_binding(BINDING_INDEX_COLOR, vk::ShaderStageFlags::FRAGMENT),
+      create_texture_binding(BINDING_INDEX_COC, vk::ShaderStageFlags::FRAGMENT),
+    ]
+  }
+
+  fn create_pipeline(
+    device: &ash::Device,
+    pipeline_cache: &vk::PipelineCache,
+    render_pass: &vk::RenderPass,
+    pipeline_layout: &vk::PipelineLayout,
+    shader_paths: (&str, &str),
+  ) -> vk::Pipeline {
+    let vertex_desc = ps_vertex_empty();
+
+    create_pipeline_with_defaults(
+      device,
+      render_pass,
+      pipeline_layout,
+      shader_paths,
+      vertex_desc,
+      COLOR_ATTACHMENT_COUNT,
+      |builder| {
+        let pipeline_create_info = builder.build();
+        create_pipeline(device, pipeline_cache, pipeline_create_info)
+      },
+    )
+  }
+
+  pub fn create_framebuffer(&self, vk_app: &VkCtx, size: &vk::Extent2D) -> DofPassFramebuffer {
+    let device = vk_app.vk_device();
+
+    let coc_tex = vk_app.create_attachment::<Self>("coc", COC_TEXTURE_FORMAT, *size);
+    let coc_fbo = create_framebuffer(device, self.render_pass_coc, &[coc_tex.image_view()], size);
+
+    let result_tex = vk_app.create_attachment::<Self>("result", RESULT_TEXTURE_FORMAT, *size);
+    let result_fbo = create_framebuffer(
+      device,
+      self.render_pass_blur,
+      &[result_tex.image_view()],
+      size,
+    );
+
+    DofPassFramebuffer {
+      coc_tex,
+      coc_fbo,
+      result_tex,
+      result_fbo,
+    }
+  }
+
+  pub fn execute(
+    &self,
+    exec_ctx: &PassExecContext,
+    framebuffer: &mut DofPassFramebuffer,
+    color_tex: &mut VkTexture,
+    linear_depth_tex: &mut VkTexture,
+  ) {
+    let vk_app = exec_ctx.vk_app;
+    let command_buffer = exec_ctx.command_buffer;
+    let device = vk_app.vk_device();
+    let size = exec_ctx.size;
+    let pass_name = &get_simple_type_name::<Self>();
+
+    unsafe {
+      // circle of confusion
+      self.cmd_resource_barriers(
+        device,
+        &command_buffer,
+        &mut framebuffer.coc_tex,
+        &mut [linear_depth_tex],
+      );
+      let scope_id = exec_ctx.cmd_begin_scope(&format!("{}.coc", pass_name));
+      exec_ctx.cmd_start_render_pass(
+        &self.render_pass_coc,
+        &self.pipeline_coc,
+        &framebuffer.coc_fbo,
+        &size,
+        &[],
+      );
+      self.bind_uniforms_coc(exec_ctx, linear_depth_tex);
+      cmd_draw_fullscreen_triangle(device, &command_buffer);
+      exec_ctx.cmd_end_render_pass(scope_id);
+
+      // blur
+      self.cmd_resource_barriers(
+        device,
+        &command_buffer,
+        &mut framebuffer.result_tex,
+        &mut [color_tex, &mut framebuffer.coc_tex],
+      );
+      let scope_id = exec_ctx.cmd_begin_scope(&format!("{}.blur", pass_name));
+      exec_ctx.cmd_start_render_pass(
+        &self.render_pass_blur,
+        &self.pipeline_blur,
+        &framebuffer.result_fbo,
+        &size,
+        &[],
+      );
+      self.bind_uniforms_blur(exec_ctx, color_tex, &framebuffer.coc_tex);
+      cmd_draw_fullscreen_triangle(device, &command_buffer);
+      exec_ctx.cmd_end_render_pass(scope_id);
+    }
+  }
+
+  unsafe fn bind_uniforms_coc(&self, exec_ctx: &PassExecContext, linear_depth_tex: &VkTexture) {
+    let vk_app = exec_ctx.vk_app;
+    let resouce_binder = exec_ctx.create_resouce_binder(self.pipeline_layout);
+
+    let uniform_resouces = [
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::UBO,
+        binding: BINDING_INDEX_CONFIG_UBO,
+        buffer: exec_ctx.config_buffer,
+      },
+      BindableResource::Texture {
+        binding: BINDING_INDEX_LINEAR_DEPTH,
+        texture: linear_depth_tex,
+        image_view: None,
+        sampler: vk_app.default_texture_sampler_nearest,
+      },
+    ];
+    bind_resources_to_descriptors_graphic(&resouce_binder, 0, &uniform_resouces);
+  }
+
+  unsafe fn bind_uniforms_blur(
+    &self,
+    exec_ctx: &PassExecContext,
+    color_tex: &VkTexture,
+    coc_tex: &VkTexture,
+  ) {
+    let vk_app = exec_ctx.vk_app;
+    let resouce_binder = exec_ctx.create_resouce_binder(self.pipeline_layout);
+
+    let uniform_resouces = [
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::UBO,
+        binding: BINDING_INDEX_CONFIG_UBO,
+        buffer: exec_ctx.config_buffer,
+      },
+      BindableResource::Texture {
+        binding: BINDING_INDEX_COLOR,
+        texture: color_tex,
+        image_view: None,
+        sampler: vk_app.default_texture_sampler_linear,
+      },
+      BindableResource::Texture {
+        binding: BINDING_INDEX_COC,
+        texture: coc_tex,
+        image_view: None,
+        sampler: vk_app.default_texture_sampler_nearest,
+      },
+    ];
+    bind_resources_to_descriptors_graphic(&resouce_binder, 0, &uniform_resouces);
+  }
+
+  unsafe fn cmd_resource_barriers(
+    &self,
+    device: &ash::Device,
+    command_buffer: &vk::CommandBuffer,
+    result_tex: &mut VkTexture,             // write
+    source_textures: &mut [&mut VkTexture], // read
+  ) {
+    VkTexture::cmd_transition_attachments_for_read_barrier(
+      device,
+      *command_buffer,
+      source_textures,
+    );
+
+    VkTexture::cmd_transition_attachments_for_write_barrier(
+      device,
+      *command_buffer,
+      &mut [result_tex],
+    );
+  }
+}
+
+pub struct DofPassFramebuffer {
+  pub coc_tex: VkTexture,
+  pub coc_fbo: vk::Framebuffer,
+  pub result_tex: VkTexture,
+  pub result_fbo: vk::Framebuffer,
+}
+
+impl DofPassFramebuffer {
+  pub unsafe fn destroy(&mut self, vk_app: &VkCtx) {
+    let device = vk_app.vk_device();
+    let allocator = &vk_app.allocator;
+
+    device.destroy_framebuffer(self.coc_fbo, None);
+    device.destroy_framebuffer(self.result_fbo, None);
+    self.coc_tex.delete(device, allocator);
+    self.result_tex.delete(device, allocator);
+  }
+}
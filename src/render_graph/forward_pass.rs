@@ -3,8 +3,9 @@ use ash::vk;
 use log::info;
 
 use crate::config::Config;
+use crate::either;
 use crate::render_graph::_shared::RenderableVertex;
-use crate::scene::WorldEntity;
+use crate::scene::{EnvironmentMap, WorldEntity};
 use crate::utils::get_simple_type_name;
 use crate::vk_ctx::VkCtx;
 use crate::vk_utils::*;
@@ -19,56 +20,104 @@ const BINDING_INDEX_HAIR_SHADOW_TEXTURE: u32 = 4;
 const BINDING_INDEX_SHADOW_MAP: u32 = 5;
 const BINDING_INDEX_SSS_DEPTH_MAP: u32 = 6;
 const BINDING_INDEX_AO_TEX: u32 = 7;
+const BINDING_INDEX_LIGHTS_SSBO: u32 = 8;
+const BINDING_INDEX_ENVIRONMENT_TEX: u32 = 9;
 
 const SHADER_PATHS: (&str, &str) = (
   "./assets/shaders-compiled/forward.vert.spv",
   "./assets/shaders-compiled/forward.frag.spv",
 );
+const SKYBOX_SHADER_PATHS: (&str, &str) = (
+  "./assets/shaders-compiled/skybox.vert.spv",
+  "./assets/shaders-compiled/skybox.frag.spv",
+);
 
 // TODO [LOW] ATM attachment data is split into create_framebuffer, render_pass, execute (cause clear color). Unify.
 //      Or create RenderPass abstract class that will get some attachment desc and calc most of things
 
-/// Render scene objects (not hair). Outputs `diffuse.rgb`, `normal.rgb` (packed) and `depth/stencil`.
-/// Sets `SKIN` stencil flag.
+/// Render scene objects (not hair). Outputs `diffuse.rgb`, `normal.rgb` (packed), `velocity.xy` and `depth/stencil`.
+/// Sets `SKIN` stencil flag. If there is an environment map, it is drawn as the background.
 ///
 /// Output is different for some special debug modes. E.g. shadow debug mode outputs shadow
 /// preview - exact same values that are used in normal rendering path.
+///
+/// With MSAA, renders into multisampled attachments that are resolved at the end of the pass.
+/// Depth and stencil are resolved too, so hair passes can use them as usual.
 pub struct ForwardPass {
+  /// Single sampled. Used to create `ForwardPassFramebuffer.fbo`
   render_pass: vk::RenderPass,
+  /// Only if MSAA is enabled. Pipelines are created for this render pass then
+  msaa_render_pass: Option<vk::RenderPass>,
+  pub msaa_samples: vk::SampleCountFlags,
   pipeline: vk::Pipeline,
+  /// Uses same `pipeline_layout`
+  skybox_pipeline: vk::Pipeline,
   pipeline_layout: vk::PipelineLayout,
   uniforms_layout: vk::DescriptorSetLayout,
   /// When shader expects e.g. specular texture, but object does not have one.
   /// This texture is only for binding, please do not rely on any value inside.
   /// Has format `VkTexture::RAW_DATA_TEXTURE_FORMAT`.
   dummy_data_texture: VkTexture,
+  /// Same as `dummy_data_texture`, but for when there is no environment map.
+  /// Has format `EnvironmentMap::TEXTURE_FORMAT`.
+  dummy_environment_texture: VkTexture,
 }
 
 impl ForwardPass {
   pub const DEPTH_TEXTURE_FORMAT: vk::Format = vk::Format::D24_UNORM_S8_UINT;
   pub const DIFFUSE_TEXTURE_FORMAT: vk::Format = vk::Format::R32G32B32A32_SFLOAT;
   pub const NORMALS_TEXTURE_FORMAT: vk::Format = vk::Format::R8G8B8A8_UINT;
-  pub const COLOR_ATTACHMENT_COUNT: usize = 2;
-
-  pub fn new(vk_app: &VkCtx) -> Self {
-    info!("Creating {}", get_simple_type_name::<Self>());
+  pub const VELOCITY_TEXTURE_FORMAT: vk::Format = vk::Format::R16G16_SFLOAT;
+  pub const COLOR_ATTACHMENT_COUNT: usize = 3;
+
+  /// Use `vk::SampleCountFlags::TYPE_1` to disable MSAA
+  pub fn new(vk_app: &VkCtx, msaa_samples: vk::SampleCountFlags) -> Self {
+    info!(
+      "Creating {} (MSAA: {:?})",
+      get_simple_type_name::<Self>(),
+      msaa_samples
+    );
     let device = vk_app.vk_device();
     let pipeline_cache = &vk_app.pipeline_cache;
 
     let render_pass = Self::create_render_pass(device, vk::AttachmentLoadOp::CLEAR);
+    let msaa_render_pass = either!(
+      msaa_samples == vk::SampleCountFlags::TYPE_1,
+      None,
+      Some(Self::create_msaa_render_pass(device, msaa_samples))
+    );
+    let pipeline_render_pass = msaa_render_pass.unwrap_or(render_pass);
     let uniforms_desc = Self::get_uniforms_layout();
     let uniforms_layout = create_push_descriptor_layout(device, uniforms_desc);
     let pipeline_layout = create_pipeline_layout(device, &[uniforms_layout], &[]);
-    let pipeline = Self::create_pipeline(device, pipeline_cache, &render_pass, &pipeline_layout);
+    let pipeline = Self::create_pipeline(
+      device,
+      pipeline_cache,
+      &pipeline_render_pass,
+      &pipeline_layout,
+      msaa_samples,
+    );
+    let skybox_pipeline = Self::create_skybox_pipeline(
+      device,
+      pipeline_cache,
+      &pipeline_render_pass,
+      &pipeline_layout,
+      msaa_samples,
+    );
 
     let dummy_data_texture = Self::create_dummy_texture(vk_app);
+    let dummy_environment_texture = Self::create_dummy_environment_texture(vk_app);
 
     Self {
       render_pass,
+      msaa_render_pass,
+      msaa_samples,
       pipeline,
+      skybox_pipeline,
       pipeline_layout,
       uniforms_layout,
       dummy_data_texture,
+      dummy_environment_texture,
     }
   }
 
@@ -76,14 +125,39 @@ impl ForwardPass {
     let device = vk_app.vk_device();
     let allocator = &vk_app.allocator;
     device.destroy_render_pass(self.render_pass, None);
+    if let Some(msaa_render_pass) = self.msaa_render_pass {
+      device.destroy_render_pass(msaa_render_pass, None);
+    }
     device.destroy_descriptor_set_layout(self.uniforms_layout, None);
     device.destroy_pipeline_layout(self.pipeline_layout, None);
     device.destroy_pipeline(self.pipeline, None);
+    device.destroy_pipeline(self.skybox_pipeline, None);
     self.dummy_data_texture.delete(device, allocator);
+    self.dummy_environment_texture.delete(device, allocator);
   }
 
   /// Define render pass to compile shader against
   pub fn create_render_pass(device: &ash::Device, load_op: vk::AttachmentLoadOp) -> vk::RenderPass {
+    let (depth_attachment, color_attachments) = Self::create_attachments(load_op);
+    unsafe {
+      create_render_pass_from_attachments(device, Some(depth_attachment), &color_attachments)
+    }
+  }
+
+  fn create_msaa_render_pass(
+    device: &ash::Device,
+    msaa_samples: vk::SampleCountFlags,
+  ) -> vk::RenderPass {
+    let (depth_attachment, color_attachments) =
+      Self::create_attachments(vk::AttachmentLoadOp::CLEAR);
+    unsafe {
+      create_render_pass_multisampled(device, msaa_samples, depth_attachment, &color_attachments)
+    }
+  }
+
+  fn create_attachments(
+    load_op: vk::AttachmentLoadOp,
+  ) -> (AttachmentDefinition, [AttachmentDefinition; 3]) {
     // TODO [LOW] check if render pass can auto convert attachment layouts after execution? The `final_layout` param
     let depth_attachment = create_depth_stencil_attachment(
       0,
@@ -106,14 +180,17 @@ impl ForwardPass {
       load_op,
       vk::AttachmentStoreOp::STORE,
     );
+    let velocity_attachment = create_color_attachment(
+      3,
+      Self::VELOCITY_TEXTURE_FORMAT,
+      load_op,
+      vk::AttachmentStoreOp::STORE,
+    );
 
-    unsafe {
-      create_render_pass_from_attachments(
-        device,
-        Some(depth_attachment),
-        &[color_attachment, normals_attachment],
-      )
-    }
+    (
+      depth_attachment,
+      [color_attachment, normals_attachment, velocity_attachment],
+    )
   }
 
   fn get_uniforms_layout() -> Vec<vk::DescriptorSetLayoutBinding> {
@@ -141,6 +218,11 @@ impl ForwardPass {
       create_texture_binding(BINDING_INDEX_SHADOW_MAP, vk::ShaderStageFlags::FRAGMENT),
       create_texture_binding(BINDING_INDEX_SSS_DEPTH_MAP, vk::ShaderStageFlags::FRAGMENT),
       create_texture_binding(BINDING_INDEX_AO_TEX, vk::ShaderStageFlags::FRAGMENT),
+      create_ssbo_binding(BINDING_INDEX_LIGHTS_SSBO, vk::ShaderStageFlags::FRAGMENT),
+      create_texture_binding(
+        BINDING_INDEX_ENVIRONMENT_TEX,
+        vk::ShaderStageFlags::FRAGMENT,
+      ),
     ]
   }
 
@@ -149,6 +231,7 @@ impl ForwardPass {
     pipeline_cache: &vk::PipelineCache,
     render_pass: &vk::RenderPass,
     pipeline_layout: &vk::PipelineLayout,
+    msaa_samples: vk::SampleCountFlags,
   ) -> vk::Pipeline {
     let vertex_desc = RenderableVertex::get_vertex_description();
 
@@ -171,8 +254,48 @@ impl ForwardPass {
           .front(stencil_write_skin)
           .back(stencil_write_skin)
           .build();
+        let multisample = ps_multisample(msaa_samples);
 
-        let pipeline_create_info = builder.depth_stencil_state(&depth_stencil).build();
+        let pipeline_create_info = builder
+          .depth_stencil_state(&depth_stencil)
+          .multisample_state(&multisample)
+          .build();
+        create_pipeline(device, pipeline_cache, pipeline_create_info)
+      },
+    )
+  }
+
+  /// Fullscreen triangle at the far plane, only touches pixels without any mesh
+  fn create_skybox_pipeline(
+    device: &ash::Device,
+    pipeline_cache: &vk::PipelineCache,
+    render_pass: &vk::RenderPass,
+    pipeline_layout: &vk::PipelineLayout,
+    msaa_samples: vk::SampleCountFlags,
+  ) -> vk::Pipeline {
+    let vertex_desc = ps_vertex_empty();
+
+    create_pipeline_with_defaults(
+      device,
+      render_pass,
+      pipeline_layout,
+      SKYBOX_SHADER_PATHS,
+      vertex_desc,
+      Self::COLOR_ATTACHMENT_COUNT,
+      |builder| {
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::builder()
+          .depth_test_enable(true)
+          .depth_write_enable(false)
+          .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
+          .depth_bounds_test_enable(false)
+          .stencil_test_enable(false)
+          .build();
+        let multisample = ps_multisample(msaa_samples);
+
+        let pipeline_create_info = builder
+          .depth_stencil_state(&depth_stencil)
+          .multisample_state(&multisample)
+          .build();
         create_pipeline(device, pipeline_cache, pipeline_create_info)
       },
     )
@@ -196,6 +319,8 @@ impl ForwardPass {
 
     let normals_tex =
       vk_app.create_attachment::<Self>("normal", Self::NORMALS_TEXTURE_FORMAT, *size);
+    let velocity_tex =
+      vk_app.create_attachment::<Self>("velocity", Self::VELOCITY_TEXTURE_FORMAT, *size);
 
     let fbo = create_framebuffer(
       device,
@@ -204,6 +329,7 @@ impl ForwardPass {
         depth_stencil_tex.image_view(),
         diffuse_tex.image_view(),
         normals_tex.image_view(),
+        velocity_tex.image_view(),
       ],
       &size,
     );
@@ -211,11 +337,64 @@ impl ForwardPass {
     let depth_image_view =
       depth_stencil_tex.create_extra_image_view(device, vk::ImageAspectFlags::DEPTH);
 
+    let msaa = self.msaa_render_pass.map(|msaa_render_pass| {
+      self.create_msaa_framebuffer(
+        vk_app,
+        msaa_render_pass,
+        size,
+        &[
+          depth_stencil_tex.image_view(),
+          diffuse_tex.image_view(),
+          normals_tex.image_view(),
+          velocity_tex.image_view(),
+        ],
+      )
+    });
+
     ForwardPassFramebuffer {
       depth_stencil_tex,
       depth_image_view,
       diffuse_tex,
       normals_tex,
+      velocity_tex,
+      fbo,
+      msaa,
+    }
+  }
+
+  /// `resolve_image_views` - same order as in `ForwardPassFramebuffer.fbo`
+  fn create_msaa_framebuffer(
+    &self,
+    vk_app: &VkCtx,
+    msaa_render_pass: vk::RenderPass,
+    size: &vk::Extent2D,
+    resolve_image_views: &[vk::ImageView],
+  ) -> ForwardPassMsaaFramebuffer {
+    let device = vk_app.vk_device();
+    let samples = self.msaa_samples;
+    let create_tex = |name: &str, format: vk::Format| {
+      vk_app.create_attachment_multisampled::<Self>(name, format, *size, samples)
+    };
+
+    let depth_stencil_tex = create_tex("depth_msaa", Self::DEPTH_TEXTURE_FORMAT);
+    let diffuse_tex = create_tex("diffuse_msaa", Self::DIFFUSE_TEXTURE_FORMAT);
+    let normals_tex = create_tex("normal_msaa", Self::NORMALS_TEXTURE_FORMAT);
+    let velocity_tex = create_tex("velocity_msaa", Self::VELOCITY_TEXTURE_FORMAT);
+
+    let mut image_views = vec![
+      depth_stencil_tex.image_view(),
+      diffuse_tex.image_view(),
+      normals_tex.image_view(),
+      velocity_tex.image_view(),
+    ];
+    image_views.extend_from_slice(resolve_image_views);
+    let fbo = create_framebuffer(device, msaa_render_pass, &image_views, &size);
+
+    ForwardPassMsaaFramebuffer {
+      depth_stencil_tex,
+      diffuse_tex,
+      normals_tex,
+      velocity_tex,
       fbo,
     }
   }
@@ -239,8 +418,14 @@ impl ForwardPass {
       config.clear_depth_stencil(),
       config.clear_color(),
       config.clear_normals(),
+      config.clear_velocity(),
     ];
 
+    let (render_pass, fbo) = match (&framebuffer.msaa, self.msaa_render_pass) {
+      (Some(msaa), Some(msaa_render_pass)) => (msaa_render_pass, msaa.fbo),
+      _ => (self.render_pass, framebuffer.fbo),
+    };
+
     // TODO [LOW] no need to rerecord every frame TBH. Everything can be controlled by uniforms etc.
     unsafe {
       self.cmd_resource_barriers(
@@ -254,28 +439,29 @@ impl ForwardPass {
 
       // start render pass
       let scope_id = exec_ctx.cmd_begin_scope(pass_name);
-      exec_ctx.cmd_start_render_pass(
-        &self.render_pass,
-        &self.pipeline,
-        &framebuffer.fbo,
-        &size,
-        &clear_values,
-      );
+      exec_ctx.cmd_start_render_pass(&render_pass, &self.pipeline, &fbo, &size, &clear_values);
 
       // draw calls
       let scene = &*exec_ctx.scene.borrow();
+      let environment_texture = self.get_environment_texture(&scene.environment);
       for entity in &scene.entities {
         self.bind_entity_ubos(
           exec_ctx,
           shadow_map_texture,
           sss_depth_texture,
           ao_texture,
+          environment_texture,
           entity,
         );
         entity.cmd_bind_mesh_buffers(device, command_buffer);
         entity.cmd_draw_mesh(device, command_buffer);
       }
 
+      // skybox last, so that most of the pixels are rejected by depth test
+      if scene.environment.is_some() && config.environment.show_skybox {
+        self.cmd_draw_skybox(exec_ctx, environment_texture);
+      }
+
       // end
       exec_ctx.cmd_end_render_pass(scope_id);
     }
@@ -302,9 +488,23 @@ impl ForwardPass {
       &mut [
         &mut framebuffer.diffuse_tex,
         &mut framebuffer.normals_tex,
+        &mut framebuffer.velocity_tex,
         &mut framebuffer.depth_stencil_tex,
       ],
     );
+
+    if let Some(msaa) = framebuffer.msaa.as_mut() {
+      VkTexture::cmd_transition_attachments_for_write_barrier(
+        device,
+        *command_buffer,
+        &mut [
+          &mut msaa.diffuse_tex,
+          &mut msaa.normals_tex,
+          &mut msaa.velocity_tex,
+          &mut msaa.depth_stencil_tex,
+        ],
+      );
+    }
   }
 
   unsafe fn bind_entity_ubos(
@@ -313,6 +513,7 @@ impl ForwardPass {
     shadow_map_texture: &mut VkTexture,
     sss_depth_texture: &mut VkTexture,
     ao_texture: &mut VkTexture,
+    environment_texture: &VkTexture,
     entity: &WorldEntity,
   ) {
     let vk_app = exec_ctx.vk_app;
@@ -373,12 +574,63 @@ impl ForwardPass {
         image_view: None,
         sampler: vk_app.default_texture_sampler_linear,
       },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: BINDING_INDEX_LIGHTS_SSBO,
+        buffer: exec_ctx.lights_buffer,
+      },
+      BindableResource::Texture {
+        binding: BINDING_INDEX_ENVIRONMENT_TEX,
+        texture: environment_texture,
+        image_view: None,
+        sampler: vk_app.environment_map_sampler,
+      },
     ];
 
     let resouce_binder = exec_ctx.create_resouce_binder(self.pipeline_layout);
     bind_resources_to_descriptors_graphic(&resouce_binder, 0, &uniform_resouces);
   }
 
+  unsafe fn cmd_draw_skybox(&self, exec_ctx: &PassExecContext, environment_texture: &VkTexture) {
+    let vk_app = exec_ctx.vk_app;
+    let device = vk_app.vk_device();
+    let command_buffer = exec_ctx.command_buffer;
+
+    device.cmd_bind_pipeline(
+      command_buffer,
+      vk::PipelineBindPoint::GRAPHICS,
+      self.skybox_pipeline,
+    );
+
+    let uniform_resouces = [
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::UBO,
+        binding: BINDING_INDEX_CONFIG_UBO,
+        buffer: exec_ctx.config_buffer,
+      },
+      BindableResource::Texture {
+        binding: BINDING_INDEX_ENVIRONMENT_TEX,
+        texture: environment_texture,
+        image_view: None,
+        sampler: vk_app.environment_map_sampler,
+      },
+    ];
+    let resouce_binder = exec_ctx.create_resouce_binder(self.pipeline_layout);
+    bind_resources_to_descriptors_graphic(&resouce_binder, 0, &uniform_resouces);
+
+    cmd_draw_fullscreen_triangle(device, &command_buffer);
+  }
+
+  fn get_environment_texture<'a>(
+    &'a self,
+    environment: &'a Option<EnvironmentMap>,
+  ) -> &'a VkTexture {
+    environment
+      .as_ref()
+      .map(|env| &env.specular_tex)
+      .unwrap_or(&self.dummy_environment_texture)
+  }
+
   fn create_dummy_texture(vk_app: &VkCtx) -> VkTexture {
     vk_app.create_texture_empty(
       "ForwardPassDummyDataTex".to_string(),
@@ -393,6 +645,21 @@ impl ForwardPass {
       vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
     )
   }
+
+  fn create_dummy_environment_texture(vk_app: &VkCtx) -> VkTexture {
+    vk_app.create_texture_empty(
+      "ForwardPassDummyEnvironmentTex".to_string(),
+      vk::Extent2D {
+        width: 1,
+        height: 1,
+      },
+      EnvironmentMap::TEXTURE_FORMAT,
+      vk::ImageTiling::OPTIMAL,
+      vk::ImageUsageFlags::SAMPLED,
+      VkMemoryPreference::GpuOnly,
+      vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+    )
+  }
 }
 
 pub struct ForwardPassFramebuffer {
@@ -401,7 +668,11 @@ pub struct ForwardPassFramebuffer {
   pub depth_image_view: vk::ImageView,
   pub diffuse_tex: VkTexture,
   pub normals_tex: VkTexture,
+  /// Screen space movement since the previous frame (in NDC). Used by TAA
+  pub velocity_tex: VkTexture,
+  /// Single sampled. If MSAA is enabled, used only by the hair passes
   pub fbo: vk::Framebuffer,
+  pub msaa: Option<ForwardPassMsaaFramebuffer>,
 }
 
 impl ForwardPassFramebuffer {
@@ -414,5 +685,32 @@ impl ForwardPassFramebuffer {
     self.depth_stencil_tex.delete(device, allocator);
     self.diffuse_tex.delete(device, allocator);
     self.normals_tex.delete(device, allocator);
+    self.velocity_tex.delete(device, allocator);
+    if let Some(msaa) = self.msaa.as_mut() {
+      msaa.destroy(vk_app);
+    }
+  }
+}
+
+/// Multisampled attachments, resolved into `ForwardPassFramebuffer` textures.
+pub struct ForwardPassMsaaFramebuffer {
+  pub depth_stencil_tex: VkTexture,
+  pub diffuse_tex: VkTexture,
+  pub normals_tex: VkTexture,
+  pub velocity_tex: VkTexture,
+  /// Multisampled attachments followed by the resolve ones
+  pub fbo: vk::Framebuffer,
+}
+
+impl ForwardPassMsaaFramebuffer {
+  pub unsafe fn destroy(&mut self, vk_app: &VkCtx) {
+    let device = vk_app.vk_device();
+    let allocator = &vk_app.allocator;
+
+    device.destroy_framebuffer(self.fbo, None);
+    self.depth_stencil_tex.delete(device, allocator);
+    self.diffuse_tex.delete(device, allocator);
+    self.normals_tex.delete(device, allocator);
+    self.velocity_tex.delete(device, allocator);
   }
 }
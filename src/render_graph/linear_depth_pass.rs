@@ -106,7 +106,7 @@ impl LinearDepthPass {
     let device = vk_app.vk_device();
 
     let linear_depth_tex =
-      vk_app.create_attachment::<Self>("linear_depth", RESULT_TEXTURE_FORMAT, *size);
+      vk_app.create_readable_attachment::<Self>("linear_depth", RESULT_TEXTURE_FORMAT, *size);
 
     let fbo = create_framebuffer(
       device,
@@ -18,6 +18,7 @@ const BINDING_INDEX_DEPTH: u32 = 4;
 const BINDING_INDEX_SHADOW_MAP: u32 = 5;
 const BINDING_INDEX_FORWARD_PASS_RESULT: u32 = 6;
 const BINDING_INDEX_LINEAR_DEPTH: u32 = 7;
+const BINDING_INDEX_LIGHTS_SSBO: u32 = 8;
 
 const COLOR_ATTACHMENT_COUNT: usize = 1;
 const SHADER_PATHS: (&str, &str) = (
@@ -34,6 +35,7 @@ pub struct PresentPass {
 
 /// Render to OS window framebuffer. Handles debug modes (e.g. shadow factor, normals).
 /// Can shows debug positions of lights, shadow and SSS sources.
+/// Resamples the result if render scale is not 1.0.
 impl PresentPass {
   pub fn new(vk_app: &VkCtx, image_format: vk::Format) -> Self {
     info!("Creating {}", get_simple_type_name::<Self>());
@@ -94,6 +96,7 @@ impl PresentPass {
         vk::ShaderStageFlags::FRAGMENT,
       ),
       create_texture_binding(BINDING_INDEX_LINEAR_DEPTH, vk::ShaderStageFlags::FRAGMENT),
+      create_ssbo_binding(BINDING_INDEX_LIGHTS_SSBO, vk::ShaderStageFlags::FRAGMENT),
     ]
   }
 
@@ -146,7 +149,8 @@ impl PresentPass {
     let vk_app = exec_ctx.vk_app;
     let command_buffer = exec_ctx.command_buffer;
     let device = vk_app.vk_device();
-    let size = exec_ctx.size;
+    // `exec_ctx.size` is render size, we write to the window
+    let size = vk_app.window_size();
     let clear_values = exec_ctx.config.borrow().clear_swapchain_color();
     let pass_name = &get_simple_type_name::<Self>();
 
@@ -260,6 +264,11 @@ impl PresentPass {
         image_view: None,
         sampler: vk_app.default_texture_sampler_nearest,
       },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: BINDING_INDEX_LIGHTS_SSBO,
+        buffer: exec_ctx.lights_buffer,
+      },
     ];
     bind_resources_to_descriptors_graphic(&resouce_binder, 0, &uniform_resouces);
   }
@@ -5,14 +5,13 @@ use ash::vk;
 use glam::{vec4, Mat4, Vec3, Vec4};
 use log::info;
 
-use crate::config::ShadowSourceCfg;
 use crate::render_graph::tfx_render::TfxForwardPass;
-use crate::scene::{Camera, TfxObject};
+use crate::scene::TfxObject;
 use crate::utils::get_simple_type_name;
 use crate::vk_ctx::VkCtx;
 use crate::vk_utils::*;
 
-use super::{PassExecContext, RenderableVertex};
+use super::{PassExecContext, RenderableVertex, ShadowAtlas, ShadowAtlasSlot, ShadowFrustum};
 
 const DEPTH_TEXTURE_FORMAT: vk::Format = vk::Format::D32_SFLOAT;
 const COLOR_ATTACHMENT_COUNT: usize = 1;
@@ -27,6 +26,8 @@ const SHADER_PATHS_TRESSFX_HAIR: (&str, &str) = (
 
 /// Render depth map from the point of view of `ShadowSource`. Quite flexible honestly,
 /// many other effects might want to reuse it (e.g. SSS depth pass).
+///
+/// For lights, all shadow maps are rendered into a single `ShadowAtlas` texture.
 pub struct ShadowMapPass {
   render_pass: vk::RenderPass,
   pipeline_meshes: vk::Pipeline,
@@ -114,6 +115,10 @@ impl ShadowMapPass {
         TfxForwardPass::BINDING_INDEX_TANGENTS_SSBO,
         vk::ShaderStageFlags::VERTEX,
       ),
+      create_ssbo_binding(
+        TfxForwardPass::BINDING_INDEX_FOLLOW_HAIR_NEIGHBOURS_SSBO,
+        vk::ShaderStageFlags::VERTEX,
+      ),
       create_ubo_binding(
         TfxForwardPass::BINDING_INDEX_TFX_PARAMS_UBO,
         vk::ShaderStageFlags::VERTEX,
@@ -213,12 +218,41 @@ impl ShadowMapPass {
     ShadowMapPassFramebuffer { depth_tex, fbo }
   }
 
+  /// Render all slots of the shadow atlas (shadow-casting lights)
+  pub fn execute_atlas(
+    &self,
+    exec_ctx: &PassExecContext,
+    framebuffer: &mut ShadowMapPassFramebuffer,
+    shadow_atlas: &ShadowAtlas,
+  ) -> () {
+    self.execute_slots::<Self>(exec_ctx, framebuffer, &shadow_atlas.slots, true);
+  }
+
+  /// Render single depth map that fills the whole framebuffer.
   /// - @param `PassType` - use when using ShadowPass as impl. detail of other passes. Unfortunately, we cannot default to `Self`
   pub fn execute<PassType>(
     &self,
     exec_ctx: &PassExecContext,
     framebuffer: &mut ShadowMapPassFramebuffer,
-    shadow_source: &ShadowSourceCfg,
+    frustum: &ShadowFrustum,
+    render_hair: bool,
+  ) -> () {
+    let slot = ShadowAtlasSlot {
+      view_projection: frustum.view_projection,
+      eye: frustum.eye,
+      rect: size_to_rect_vk(&vk::Extent2D {
+        width: framebuffer.depth_tex.width,
+        height: framebuffer.depth_tex.height,
+      }),
+    };
+    self.execute_slots::<PassType>(exec_ctx, framebuffer, &[slot], render_hair);
+  }
+
+  fn execute_slots<PassType>(
+    &self,
+    exec_ctx: &PassExecContext,
+    framebuffer: &mut ShadowMapPassFramebuffer,
+    slots: &[ShadowAtlasSlot],
     render_hair: bool,
   ) -> () {
     let vk_app = exec_ctx.vk_app;
@@ -241,7 +275,7 @@ impl ShadowMapPass {
     unsafe {
       self.cmd_resource_barriers(device, &command_buffer, framebuffer);
 
-      // start render pass
+      // start render pass. Clears the whole texture, even unused slots
       let scope_id = exec_ctx.cmd_begin_scope(pass_name);
       exec_ctx.cmd_start_render_pass(
         &self.render_pass,
@@ -251,42 +285,52 @@ impl ShadowMapPass {
         &[clear_depth],
       );
 
-      // draw meshes
       let scene = exec_ctx.scene.borrow();
-      for entity in &scene.entities {
-        self.bind_push_constants(
-          exec_ctx,
-          shadow_source,
-          &entity.model_matrix,
-          1.0,
-          shadow_source.position(),
-          size,
-        );
-        entity.cmd_bind_mesh_buffers(device, command_buffer);
-        entity.cmd_draw_mesh(device, command_buffer);
-      }
-
-      // draw hair
-      if render_hair {
-        // switch pipeline
+      for slot in slots {
+        // only render to this slot's part of the texture
+        let viewport = create_viewport_for_rect(&slot.rect);
+        device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+        device.cmd_set_scissor(command_buffer, 0, &[slot.rect]);
         device.cmd_bind_pipeline(
           command_buffer,
           vk::PipelineBindPoint::GRAPHICS,
-          self.pipeline_hair,
+          self.pipeline_meshes,
         );
-        // draw hair commands
-        for entity in &scene.tressfx_objects {
-          self.bind_hair_ubos(exec_ctx, entity);
+
+        // draw meshes
+        for entity in &scene.entities {
           self.bind_push_constants(
             exec_ctx,
-            shadow_source,
-            &entity.model_matrix,
-            config.shadows.hair_tfx_radius_multipler,
-            shadow_source.position(),
-            size,
+            slot.view_projection * entity.model_matrix,
+            1.0,
+            slot.eye,
+            slot.rect.extent,
           );
+          entity.cmd_bind_mesh_buffers(device, command_buffer);
           entity.cmd_draw_mesh(device, command_buffer);
         }
+
+        // draw hair
+        if render_hair {
+          // switch pipeline
+          device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            self.pipeline_hair,
+          );
+          // draw hair commands. Hair positions are already in world space
+          for entity in &scene.tressfx_objects {
+            self.bind_hair_ubos(exec_ctx, entity);
+            self.bind_push_constants(
+              exec_ctx,
+              slot.view_projection,
+              config.shadows.hair_tfx_radius_multipler,
+              slot.eye,
+              slot.rect.extent,
+            );
+            entity.cmd_draw_mesh(device, command_buffer);
+          }
+        }
       }
 
       // end
@@ -310,8 +354,7 @@ impl ShadowMapPass {
   unsafe fn bind_push_constants(
     &self,
     exec_ctx: &PassExecContext,
-    source: &ShadowSourceCfg,
-    model_matrix: &Mat4,
+    mvp: Mat4,
     hair_fiber_radius: f32,
     camera_position: Vec3,
     shadowmap_size: vk::Extent2D,
@@ -322,7 +365,7 @@ impl ShadowMapPass {
 
     // push constants
     let push_constants = ShadowMapPassPushConstants {
-      mvp: Self::get_light_shadow_mvp(source, *model_matrix),
+      mvp,
       camera_position: vec4(
         camera_position.x,
         camera_position.y,
@@ -365,6 +408,11 @@ impl ShadowMapPass {
         binding: TfxForwardPass::BINDING_INDEX_TANGENTS_SSBO,
         buffer: &entity.tangents_buffer,
       },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: TfxForwardPass::BINDING_INDEX_FOLLOW_HAIR_NEIGHBOURS_SSBO,
+        buffer: &entity.follow_hair_neighbours_buffer,
+      },
       BindableResource::Buffer {
         usage: BindableBufferUsage::UBO,
         binding: TfxForwardPass::BINDING_INDEX_TFX_PARAMS_UBO,
@@ -375,12 +423,6 @@ impl ShadowMapPass {
     let resouce_binder = exec_ctx.create_resouce_binder(self.pipeline_layout_hair);
     bind_resources_to_descriptors_graphic(&resouce_binder, 0, &uniform_resouces);
   }
-
-  pub fn get_light_shadow_mvp(source: &ShadowSourceCfg, model_matrix: Mat4) -> Mat4 {
-    let v_mat = get_depth_view_matrix(source);
-    let p_mat = get_depth_projection_matrix(source);
-    Camera::calc_model_view_projection_matrix(&model_matrix, &v_mat, &p_mat)
-  }
 }
 
 pub struct ShadowMapPassFramebuffer {
@@ -409,16 +451,3 @@ struct ShadowMapPassPushConstants {
 
 unsafe impl bytemuck::Zeroable for ShadowMapPassPushConstants {}
 unsafe impl bytemuck::Pod for ShadowMapPassPushConstants {}
-
-///////////////////////////////
-// matrices calc
-
-fn get_depth_view_matrix(source: &ShadowSourceCfg) -> Mat4 {
-  return Mat4::look_at_rh(source.position(), source.look_at_target, Vec3::Y);
-}
-
-fn get_depth_projection_matrix(source: &ShadowSourceCfg) -> Mat4 {
-  // this is for directional light, all rays are parallel
-  let dpm = &source.projection;
-  Mat4::orthographic_rh(dpm.left, dpm.right, dpm.bottom, dpm.top, dpm.near, dpm.far)
-}
@@ -0,0 +1,216 @@
+use ash;
+use ash::vk;
+use log::info;
+
+use crate::utils::get_simple_type_name;
+use crate::vk_ctx::VkCtx;
+use crate::{either, vk_utils::*};
+
+use super::PassExecContext;
+
+const BINDING_INDEX_CONFIG_UBO: u32 = 0;
+const BINDING_INDEX_SHADOW_ATLAS: u32 = 1;
+
+const COLOR_ATTACHMENT_COUNT: usize = 1;
+const SHADER_PATHS: (&str, &str) = (
+  "./assets/shaders-compiled/fullscreen_quad.vert.spv",
+  "./assets/shaders-compiled/shadow_moments.frag.spv",
+);
+
+/// Variance shadow maps: convert shadow atlas depth into moments `(depth, depth^2)`.
+/// Unlike depth, moments can be filtered, so the result is blurred with `BlurPass` afterwards.
+///
+/// Blur does not know about atlas slots, so there might be slight bleeding between them.
+pub struct ShadowMomentsPass {
+  render_pass: vk::RenderPass,
+  pipeline: vk::Pipeline,
+  pipeline_layout: vk::PipelineLayout,
+  uniforms_layout: vk::DescriptorSetLayout,
+}
+
+impl ShadowMomentsPass {
+  pub const RESULT_TEXTURE_FORMAT: vk::Format = vk::Format::R32G32_SFLOAT;
+
+  pub fn new(vk_app: &VkCtx) -> Self {
+    info!("Creating {}", get_simple_type_name::<Self>());
+    let device = vk_app.vk_device();
+    let pipeline_cache = &vk_app.pipeline_cache;
+
+    let render_pass = Self::create_render_pass(device);
+    let uniforms_desc = Self::get_uniforms_layout();
+    let uniforms_layout = create_push_descriptor_layout(device, uniforms_desc);
+    let pipeline_layout = create_pipeline_layout(device, &[uniforms_layout], &[]);
+    let pipeline = Self::create_pipeline(device, pipeline_cache, &render_pass, &pipeline_layout);
+
+    Self {
+      render_pass,
+      pipeline,
+      pipeline_layout,
+      uniforms_layout,
+    }
+  }
+
+  pub unsafe fn destroy(&self, device: &ash::Device) {
+    device.destroy_render_pass(self.render_pass, None);
+    device.destroy_descriptor_set_layout(self.uniforms_layout, None);
+    device.destroy_pipeline_layout(self.pipeline_layout, None);
+    device.destroy_pipeline(self.pipeline, None);
+  }
+
+  fn create_render_pass(device: &ash::Device) -> vk::RenderPass {
+    let color_attachment = create_color_attachment(
+      0,
+      Self::RESULT_TEXTURE_FORMAT,
+      vk::AttachmentLoadOp::DONT_CARE, // we override every pixel regardless
+      vk::AttachmentStoreOp::STORE,
+    );
+
+    unsafe { create_render_pass_from_attachments(device, None, &[color_attachment]) }
+  }
+
+  fn get_uniforms_layout() -> Vec<vk::DescriptorSetLayoutBinding> {
+    vec![
+      create_ubo_binding(BINDING_INDEX_CONFIG_UBO, vk::ShaderStageFlags::FRAGMENT),
+      create_texture_binding(BINDING_INDEX_SHADOW_ATLAS, vk::ShaderStageFlags::FRAGMENT),
+    ]
+  }
+
+  fn create_pipeline(
+    device: &ash::Device,
+    pipeline_cache: &vk::PipelineCache,
+    render_pass: &vk::RenderPass,
+    pipeline_layout: &vk::PipelineLayout,
+  ) -> vk::Pipeline {
+    let vertex_desc = ps_vertex_empty();
+
+    create_pipeline_with_defaults(
+      device,
+      render_pass,
+      pipeline_layout,
+      SHADER_PATHS,
+      vertex_desc,
+      COLOR_ATTACHMENT_COUNT,
+      |builder| {
+        let pipeline_create_info = builder.build();
+        create_pipeline(device, pipeline_cache, pipeline_create_info)
+      },
+    )
+  }
+
+  /// - `is_ping_pass` - temporary texture for the blur
+  pub fn create_result_texture(vk_app: &VkCtx, size_px: u32, is_ping_pass: bool) -> VkTexture {
+    let name = either!(is_ping_pass, "moments_blur_tmp", "moments");
+    let size = vk::Extent2D {
+      width: size_px,
+      height: size_px,
+    };
+    vk_app.create_attachment::<Self>(name, Self::RESULT_TEXTURE_FORMAT, size)
+  }
+
+  pub fn create_framebuffer(&self, vk_app: &VkCtx, size_px: u32) -> ShadowMomentsPassFramebuffer {
+    let device = vk_app.vk_device();
+
+    let moments_tex = Self::create_result_texture(vk_app, size_px, false);
+
+    let fbo = create_framebuffer(
+      device,
+      self.render_pass,
+      &[moments_tex.image_view()],
+      &moments_tex.size(),
+    );
+
+    ShadowMomentsPassFramebuffer { moments_tex, fbo }
+  }
+
+  pub fn execute(
+    &self,
+    exec_ctx: &PassExecContext,
+    framebuffer: &mut ShadowMomentsPassFramebuffer,
+    shadow_atlas_tex: &mut VkTexture,
+  ) -> () {
+    let vk_app = exec_ctx.vk_app;
+    let command_buffer = exec_ctx.command_buffer;
+    let device = vk_app.vk_device();
+    let pass_name = &get_simple_type_name::<Self>();
+    let size = framebuffer.moments_tex.size();
+
+    unsafe {
+      self.cmd_resource_barriers(device, &command_buffer, framebuffer, shadow_atlas_tex);
+
+      // start render pass
+      let scope_id = exec_ctx.cmd_begin_scope(pass_name);
+      exec_ctx.cmd_start_render_pass(
+        &self.render_pass,
+        &self.pipeline,
+        &framebuffer.fbo,
+        &size,
+        &[],
+      );
+
+      // bind uniforms (do not move this)
+      self.bind_uniforms(exec_ctx, shadow_atlas_tex);
+
+      // draw calls
+      cmd_draw_fullscreen_triangle(device, &command_buffer);
+
+      // end
+      exec_ctx.cmd_end_render_pass(scope_id);
+    }
+  }
+
+  unsafe fn cmd_resource_barriers(
+    &self,
+    device: &ash::Device,
+    command_buffer: &vk::CommandBuffer,
+    framebuffer: &mut ShadowMomentsPassFramebuffer,
+    shadow_atlas_tex: &mut VkTexture,
+  ) {
+    VkTexture::cmd_transition_attachments_for_read_barrier(
+      device,
+      *command_buffer,
+      &mut [shadow_atlas_tex],
+    );
+
+    VkTexture::cmd_transition_attachments_for_write_barrier(
+      device,
+      *command_buffer,
+      &mut [&mut framebuffer.moments_tex],
+    );
+  }
+
+  unsafe fn bind_uniforms(&self, exec_ctx: &PassExecContext, shadow_atlas_tex: &mut VkTexture) {
+    let vk_app = exec_ctx.vk_app;
+    let resouce_binder = exec_ctx.create_resouce_binder(self.pipeline_layout);
+
+    let uniform_resouces = [
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::UBO,
+        binding: BINDING_INDEX_CONFIG_UBO,
+        buffer: exec_ctx.config_buffer,
+      },
+      BindableResource::Texture {
+        binding: BINDING_INDEX_SHADOW_ATLAS,
+        texture: shadow_atlas_tex,
+        image_view: None,
+        sampler: vk_app.default_texture_sampler_nearest,
+      },
+    ];
+    bind_resources_to_descriptors_graphic(&resouce_binder, 0, &uniform_resouces);
+  }
+}
+
+pub struct ShadowMomentsPassFramebuffer {
+  /// `(depth, depth^2)` for each texel of the shadow atlas
+  pub moments_tex: VkTexture,
+  pub fbo: vk::Framebuffer,
+}
+
+impl ShadowMomentsPassFramebuffer {
+  pub unsafe fn destroy(&mut self, vk_app: &VkCtx) {
+    let device = vk_app.vk_device();
+    let allocator = &vk_app.allocator;
+
+    device.destroy_framebuffer(self.fbo, None);
+    self.moments_tex.delete(device, allocator);
+  }
+}
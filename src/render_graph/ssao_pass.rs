@@ -151,7 +151,7 @@ impl SSAOPass {
     let command_buffer = exec_ctx.command_buffer;
     let device = vk_app.vk_device();
     let config = exec_ctx.config.borrow();
-    let size = config.get_ssao_viewport_size();
+    let size = config.get_ssao_viewport_size(exec_ctx.size);
     let pass_name = &get_simple_type_name::<Self>();
 
     unsafe {
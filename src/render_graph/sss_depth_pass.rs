@@ -1,12 +1,12 @@
-use glam::Mat4;
 use log::info;
 
-use crate::config::ShadowSourceCfg;
+use crate::config::Config;
+use crate::scene::World;
 use crate::utils::get_simple_type_name;
 use crate::vk_ctx::VkCtx;
 
 use super::shadow_map_pass::{ShadowMapPass, ShadowMapPassFramebuffer};
-use super::PassExecContext;
+use super::{PassExecContext, ShadowFrustum};
 
 pub type SSSDepthPassFramebuffer = ShadowMapPassFramebuffer;
 
@@ -39,12 +39,19 @@ impl SSSDepthPass {
     exec_ctx: &PassExecContext,
     framebuffer: &mut SSSDepthPassFramebuffer,
     shadow_pass: &ShadowMapPass,
-    source: &ShadowSourceCfg,
+    frustum: &ShadowFrustum,
   ) -> () {
-    shadow_pass.execute::<Self>(exec_ctx, framebuffer, source, false);
+    shadow_pass.execute::<Self>(exec_ctx, framebuffer, frustum, false);
   }
 
-  pub fn get_sss_forward_mvp(source: &ShadowSourceCfg, model_matrix: Mat4) -> Mat4 {
-    ShadowMapPass::get_light_shadow_mvp(source, model_matrix)
+  /// Projection used for SSS depth map. Either manual or fitted to the scene bounds.
+  pub fn get_frustum(config: &Config, scene: &World) -> ShadowFrustum {
+    let sss = &config.sss_forward_scatter;
+    if config.shadows.fit_to_scene {
+      let dir = (sss.source.look_at_target - sss.source.position()).normalize_or_zero();
+      ShadowFrustum::fit_to_bounds(&scene.bounding_box(), dir, sss.depthmap_size)
+    } else {
+      ShadowFrustum::from_shadow_source(&sss.source)
+    }
   }
 }
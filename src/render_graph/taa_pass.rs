@@ -0,0 +1,284 @@
+use ash::vk;
+use glam::{vec2, Vec2};
+use log::info;
+
+use crate::app_timer::FrameIdx;
+use crate::utils::get_simple_type_name;
+use crate::vk_ctx::VkCtx;
+use crate::vk_utils::*;
+
+use super::forward_pass::ForwardPass;
+use super::PassExecContext;
+
+const BINDING_INDEX_CONFIG_UBO: u32 = 0;
+const BINDING_INDEX_COLOR: u32 = 1;
+const BINDING_INDEX_HISTORY: u32 = 2;
+const BINDING_INDEX_VELOCITY: u32 = 3;
+const BINDING_INDEX_LINEAR_DEPTH: u32 = 4;
+
+const RESULT_TEXTURE_FORMAT: vk::Format = ForwardPass::DIFFUSE_TEXTURE_FORMAT;
+const COLOR_ATTACHMENT_COUNT: usize = 1;
+const SHADER_PATHS: (&str, &str) = (
+  "./assets/shaders-compiled/fullscreen_quad.vert.spv",
+  "./assets/shaders-compiled/taa.frag.spv",
+);
+/// Length of the jitter sequence
+const JITTER_SAMPLES: u64 = 8;
+
+/// Temporal anti-aliasing. Blends current (jittered) frame with
+/// the reprojected result of the previous frame. Uses 2 framebuffers
+/// (ping-pong), each frame reads the other one's result as history.
+pub struct TaaPass {
+  render_pass: vk::RenderPass,
+  pipeline: vk::Pipeline,
+  pipeline_layout: vk::PipelineLayout,
+  uniforms_layout: vk::DescriptorSetLayout,
+}
+
+impl TaaPass {
+  pub fn new(vk_app: &VkCtx) -> Self {
+    info!("Creating {}", get_simple_type_name::<Self>());
+    let device = vk_app.vk_device();
+    let pipeline_cache = &vk_app.pipeline_cache;
+
+    let render_pass = Self::create_render_pass(device);
+    let uniforms_desc = Self::get_uniforms_layout();
+    let uniforms_layout = create_push_descriptor_layout(device, uniforms_desc);
+    let pipeline_layout = create_pipeline_layout(device, &[uniforms_layout], &[]);
+    let pipeline = Self::create_pipeline(device, pipeline_cache, &render_pass, &pipeline_layout);
+
+    Self {
+      render_pass,
+      pipeline,
+      pipeline_layout,
+      uniforms_layout,
+    }
+  }
+
+  pub unsafe fn destroy(&self, device: &ash::Device) {
+    device.destroy_render_pass(self.render_pass, None);
+    device.destroy_descriptor_set_layout(self.uniforms_layout, None);
+    device.destroy_pipeline_layout(self.pipeline_layout, None);
+    device.destroy_pipeline(self.pipeline, None);
+  }
+
+  /// Subpixel offset (in pixels, `[-0.5, 0.5]`) of the projection for the frame.
+  /// Halton(2, 3) sequence.
+  pub fn get_jitter(frame_idx: FrameIdx) -> Vec2 {
+    let idx = (frame_idx % JITTER_SAMPLES) + 1;
+    vec2(halton(idx, 2) - 0.5, halton(idx, 3) - 0.5)
+  }
+
+  fn create_render_pass(device: &ash::Device) -> vk::RenderPass {
+    let color_attachment = create_color_attachment(
+      0,
+      RESULT_TEXTURE_FORMAT,
+      vk::AttachmentLoadOp::DONT_CARE, // we override every pixel regardless
+      vk::AttachmentStoreOp::STORE,
+    );
+
+    unsafe { create_render_pass_from_attachments(device, None, &[color_attachment]) }
+  }
+
+  fn get_uniforms_layout() -> Vec<vk::DescriptorSetLayoutBinding> {
+    vec![
+      create_ubo_binding(BINDING_INDEX_CONFIG_UBO, vk::ShaderStageFlags::FRAGMENT),
+      create_texture_binding(BINDING_INDEX_COLOR, vk::ShaderStageFlags::FRAGMENT),
+      create_texture_binding(BINDING_INDEX_HISTORY, vk::ShaderStageFlags::FRAGMENT),
+      create_texture_binding(BINDING_INDEX_VELOCITY, vk::ShaderStageFlags::FRAGMENT),
+      create_texture_binding(BINDING_INDEX_LINEAR_DEPTH, vk::ShaderStageFlags::FRAGMENT),
+    ]
+  }
+
+  fn create_pipeline(
+    device: &ash::Device,
+    pipeline_cache: &vk::PipelineCache,
+    render_pass: &vk::RenderPass,
+    pipeline_layout: &vk::PipelineLayout,
+  ) -> vk::Pipeline {
+    let vertex_desc = ps_vertex_empty();
+
+    create_pipeline_with_defaults(
+      device,
+      render_pass,
+      pipeline_layout,
+      SHADER_PATHS,
+      vertex_desc,
+      COLOR_ATTACHMENT_COUNT,
+      |builder| {
+        let pipeline_create_info = builder.build();
+        create_pipeline(device, pipeline_cache, pipeline_create_info)
+      },
+    )
+  }
+
+  pub fn create_framebuffer(&self, vk_app: &VkCtx, size: &vk::Extent2D) -> TaaPassFramebuffer {
+    let device = vk_app.vk_device();
+
+    let result_tex = vk_app.create_attachment::<Self>("result", RESULT_TEXTURE_FORMAT, *size);
+
+    let fbo = create_framebuffer(device, self.render_pass, &[result_tex.image_view()], size);
+
+    TaaPassFramebuffer { result_tex, fbo }
+  }
+
+  /// If `history_tex` is `None` (e.g. TAA was just enabled),
+  /// current frame is used as a history.
+  pub fn execute(
+    &self,
+    exec_ctx: &PassExecContext,
+    framebuffer: &mut TaaPassFramebuffer,
+    mut history_tex: Option<&mut VkTexture>,
+    color_tex: &mut VkTexture,
+    velocity_tex: &mut VkTexture,
+    linear_depth_tex: &mut VkTexture,
+  ) {
+    let vk_app = exec_ctx.vk_app;
+    let command_buffer = exec_ctx.command_buffer;
+    let device = vk_app.vk_device();
+    let size = exec_ctx.size;
+    let pass_name = &get_simple_type_name::<Self>();
+
+    unsafe {
+      self.cmd_resource_barriers(
+        device,
+        &command_buffer,
+        framebuffer,
+        color_tex,
+        velocity_tex,
+        linear_depth_tex,
+      );
+      if let Some(history_tex) = history_tex.as_deref_mut() {
+        VkTexture::cmd_transition_attachments_for_read_barrier(
+          device,
+          command_buffer,
+          &mut [history_tex],
+        );
+      }
+
+      // start render pass
+      let scope_id = exec_ctx.cmd_begin_scope(pass_name);
+      exec_ctx.cmd_start_render_pass(
+        &self.render_pass,
+        &self.pipeline,
+        &framebuffer.fbo,
+        &size,
+        &[],
+      );
+
+      // bind uniforms (do not move this)
+      self.bind_uniforms(
+        exec_ctx,
+        history_tex,
+        color_tex,
+        velocity_tex,
+        linear_depth_tex,
+      );
+
+      // draw calls
+      cmd_draw_fullscreen_triangle(device, &command_buffer);
+
+      // end
+      exec_ctx.cmd_end_render_pass(scope_id);
+    }
+  }
+
+  unsafe fn bind_uniforms(
+    &self,
+    exec_ctx: &PassExecContext,
+    history_tex: Option<&mut VkTexture>,
+    color_tex: &mut VkTexture,
+    velocity_tex: &mut VkTexture,
+    linear_depth_tex: &mut VkTexture,
+  ) {
+    let vk_app = exec_ctx.vk_app;
+    let resouce_binder = exec_ctx.create_resouce_binder(self.pipeline_layout);
+    let history_tex: &VkTexture = match history_tex {
+      Some(tex) => tex,
+      None => color_tex,
+    };
+
+    let uniform_resouces = [
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::UBO,
+        binding: BINDING_INDEX_CONFIG_UBO,
+        buffer: exec_ctx.config_buffer,
+      },
+      BindableResource::Texture {
+        binding: BINDING_INDEX_COLOR,
+        texture: color_tex,
+        image_view: None,
+        sampler: vk_app.default_texture_sampler_nearest,
+      },
+      BindableResource::Texture {
+        binding: BINDING_INDEX_HISTORY,
+        texture: history_tex,
+        image_view: None,
+        sampler: vk_app.default_texture_sampler_linear,
+      },
+      BindableResource::Texture {
+        binding: BINDING_INDEX_VELOCITY,
+        texture: velocity_tex,
+        image_view: None,
+        sampler: vk_app.default_texture_sampler_nearest,
+      },
+      BindableResource::Texture {
+        binding: BINDING_INDEX_LINEAR_DEPTH,
+        texture: linear_depth_tex,
+        image_view: None,
+        sampler: vk_app.default_texture_sampler_nearest,
+      },
+    ];
+    bind_resources_to_descriptors_graphic(&resouce_binder, 0, &uniform_resouces);
+  }
+
+  unsafe fn cmd_resource_barriers(
+    &self,
+    device: &ash::Device,
+    command_buffer: &vk::CommandBuffer,
+    framebuffer: &mut TaaPassFramebuffer,
+    color_tex: &mut VkTexture,
+    velocity_tex: &mut VkTexture,
+    linear_depth_tex: &mut VkTexture,
+  ) {
+    VkTexture::cmd_transition_attachments_for_read_barrier(
+      device,
+      *command_buffer,
+      &mut [color_tex, velocity_tex, linear_depth_tex],
+    );
+
+    VkTexture::cmd_transition_attachments_for_write_barrier(
+      device,
+      *command_buffer,
+      &mut [&mut framebuffer.result_tex],
+    );
+  }
+}
+
+/// https://en.wikipedia.org/wiki/Halton_sequence
+fn halton(mut idx: u64, base: u64) -> f32 {
+  let mut f = 1.0f32;
+  let mut result = 0.0f32;
+  while idx > 0 {
+    f /= base as f32;
+    result += f * (idx % base) as f32;
+    idx /= base;
+  }
+  result
+}
+
+pub struct TaaPassFramebuffer {
+  /// Also used as a history for the next frame
+  pub result_tex: VkTexture,
+  pub fbo: vk::Framebuffer,
+}
+
+impl TaaPassFramebuffer {
+  pub unsafe fn destroy(&mut self, vk_app: &VkCtx) {
+    let device = vk_app.vk_device();
+    let allocator = &vk_app.allocator;
+
+    device.destroy_framebuffer(self.fbo, None);
+    self.result_tex.delete(device, allocator);
+  }
+}
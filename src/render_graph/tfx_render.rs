@@ -5,6 +5,8 @@ mod tfx_depth_only_pass;
 mod tfx_forward_pass;
 mod tfx_ppll_build_pass;
 mod tfx_ppll_resolve_pass;
+mod tfx_wboit_accumulate_pass;
+mod tfx_wboit_resolve_pass;
 
 use crate::vk_utils::VkTexture;
 
@@ -12,9 +14,23 @@ pub use self::tfx_depth_only_pass::*;
 pub use self::tfx_forward_pass::*;
 pub use self::tfx_ppll_build_pass::*;
 pub use self::tfx_ppll_resolve_pass::*;
+pub use self::tfx_wboit_accumulate_pass::*;
+pub use self::tfx_wboit_resolve_pass::*;
 
 use super::PassExecContext;
 
+/// Scene render targets used by the hair passes, regardless of the OIT technique.
+pub struct TfxRenderTargets<'a> {
+  pub depth_stencil_tex: &'a mut VkTexture,
+  /// See `TfxDepthOnlyPass`
+  pub depth_only_fbo: vk::Framebuffer,
+  pub forward_color_tex: &'a mut VkTexture,
+  pub forward_normal_tex: &'a mut VkTexture,
+  pub forward_velocity_tex: &'a mut VkTexture,
+  pub ao_texture: &'a mut VkTexture,
+  pub shadow_map_texture: &'a mut VkTexture,
+}
+
 /// Normally you would render all TressFX objects in PPLL build phase,
 /// then run PPLL resolve only once. In resolve step, the material data
 /// can be retrieved by:
@@ -35,25 +51,21 @@ pub fn execute_tfx_ppll(
   pass_ctx: &PassExecContext,
   fbo_build: &mut TfxPpllBuildPassFramebuffer,
   fbo_resolve: &mut TfxPpllResolvePassFramebuffer,
-  fbo_depth_only: vk::Framebuffer,
-  depth_stencil_tex: &mut VkTexture,
-  forward_color_tex: &mut VkTexture,
-  ao_texture: &mut VkTexture,
-  shadow_map_texture: &mut VkTexture,
+  targets: &mut TfxRenderTargets,
 ) {
   let scene = pass_ctx.scene.borrow();
   for entity in &scene.tressfx_objects {
-    tfx_ppll_build_pass.execute(pass_ctx, fbo_build, depth_stencil_tex, entity);
+    tfx_ppll_build_pass.execute(pass_ctx, fbo_build, targets.depth_stencil_tex, entity);
 
     tfx_ppll_resolve_pass.execute(
       pass_ctx,
       fbo_resolve,
-      depth_stencil_tex,
-      forward_color_tex,
+      targets.depth_stencil_tex,
+      targets.forward_color_tex,
       &mut fbo_build.head_pointers_image,
       &mut fbo_build.ppll_data,
-      ao_texture,
-      shadow_map_texture,
+      targets.ao_texture,
+      targets.shadow_map_texture,
       entity,
     );
 
@@ -63,6 +75,48 @@ pub fn execute_tfx_ppll(
     //     regardless of their depth.
     // - resolve pass - discard pixels that do not pass stencil test (huge optimization)
     // This means that depth buffer is never written to. Fix this mistake here.
-    tfx_depth_only_pass.execute(pass_ctx, fbo_depth_only, depth_stencil_tex, entity);
+    tfx_depth_only_pass.execute(
+      pass_ctx,
+      targets.depth_only_fbo,
+      targets.depth_stencil_tex,
+      targets.forward_velocity_tex,
+      entity,
+    );
+  }
+}
+
+/// Weighted Blended Order-Independent Transparency. Same per-object
+/// structure as `execute_tfx_ppll()`, but with fixed memory cost
+/// (2 textures instead of linked list) and no per-pixel sorting.
+pub fn execute_tfx_wboit(
+  tfx_wboit_accumulate_pass: &TfxWboitAccumulatePass,
+  tfx_wboit_resolve_pass: &TfxWboitResolvePass,
+  tfx_depth_only_pass: &TfxDepthOnlyPass,
+  pass_ctx: &PassExecContext,
+  fbo_accumulate: &mut TfxWboitAccumulatePassFramebuffer,
+  fbo_resolve: &mut TfxWboitResolvePassFramebuffer,
+  targets: &mut TfxRenderTargets,
+) {
+  let scene = pass_ctx.scene.borrow();
+  for entity in &scene.tressfx_objects {
+    tfx_wboit_accumulate_pass.execute(pass_ctx, fbo_accumulate, targets, entity);
+
+    tfx_wboit_resolve_pass.execute(
+      pass_ctx,
+      fbo_resolve,
+      fbo_accumulate,
+      targets.depth_stencil_tex,
+      targets.forward_color_tex,
+      entity,
+    );
+
+    // Accumulate pass does not write depth, see `execute_tfx_ppll()`
+    tfx_depth_only_pass.execute(
+      pass_ctx,
+      targets.depth_only_fbo,
+      targets.depth_stencil_tex,
+      targets.forward_velocity_tex,
+      entity,
+    );
   }
 }
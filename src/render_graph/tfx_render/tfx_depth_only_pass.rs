@@ -11,14 +11,15 @@ use crate::vk_utils::*;
 
 use super::PassExecContext;
 
-const COLOR_ATTACHMENT_COUNT: usize = 0;
+const COLOR_ATTACHMENT_COUNT: usize = 1;
 const SHADER_PATHS: (&str, &str) = (
   // Entire new vertex shader because Vulkan complains other passes vertex shader outputs are not used.
   "./assets/shaders-compiled/tfx_depth_only.vert.spv",
-  "./assets/shaders-compiled/shadow_map_gen.frag.spv",
+  "./assets/shaders-compiled/tfx_depth_only.frag.spv",
 );
 
 /// Same as `ShadowMapPass`, but hair only and preserves current depth value.
+/// Also writes hair velocity, as PPLL and WBOIT do not output it themselves.
 pub struct TfxDepthOnlyPass {
   render_pass: vk::RenderPass,
   pipeline: vk::Pipeline,
@@ -65,7 +66,16 @@ impl TfxDepthOnlyPass {
       vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
     );
 
-    unsafe { create_render_pass_from_attachments(device, Some(depth_attachment), &[]) }
+    let velocity_attachment = create_color_attachment(
+      1,
+      ForwardPass::VELOCITY_TEXTURE_FORMAT,
+      vk::AttachmentLoadOp::LOAD,
+      vk::AttachmentStoreOp::STORE,
+    );
+
+    unsafe {
+      create_render_pass_from_attachments(device, Some(depth_attachment), &[velocity_attachment])
+    }
   }
 
   fn get_uniforms_layout_hair() -> Vec<vk::DescriptorSetLayoutBinding> {
@@ -82,10 +92,18 @@ impl TfxDepthOnlyPass {
         TfxForwardPass::BINDING_INDEX_TANGENTS_SSBO,
         vk::ShaderStageFlags::VERTEX,
       ),
+      create_ssbo_binding(
+        TfxForwardPass::BINDING_INDEX_FOLLOW_HAIR_NEIGHBOURS_SSBO,
+        vk::ShaderStageFlags::VERTEX,
+      ),
       create_ubo_binding(
         TfxForwardPass::BINDING_INDEX_TFX_PARAMS_UBO,
         vk::ShaderStageFlags::VERTEX,
       ),
+      create_ssbo_binding(
+        TfxForwardPass::BINDING_INDEX_PREV_POSITIONS_SSBO,
+        vk::ShaderStageFlags::VERTEX,
+      ),
     ]
   }
 
@@ -132,12 +150,17 @@ impl TfxDepthOnlyPass {
     )
   }
 
-  pub fn create_framebuffer(&self, vk_app: &VkCtx, depth_tex: &VkTexture) -> vk::Framebuffer {
+  pub fn create_framebuffer(
+    &self,
+    vk_app: &VkCtx,
+    depth_tex: &VkTexture,
+    velocity_tex: &VkTexture,
+  ) -> vk::Framebuffer {
     let device = vk_app.vk_device();
     create_framebuffer(
       device,
       self.render_pass,
-      &[depth_tex.image_view()],
+      &[depth_tex.image_view(), velocity_tex.image_view()],
       &depth_tex.size(),
     )
   }
@@ -147,6 +170,7 @@ impl TfxDepthOnlyPass {
     exec_ctx: &PassExecContext,
     fbo: vk::Framebuffer,
     depth_tex: &mut VkTexture,
+    velocity_tex: &mut VkTexture,
     entity: &TfxObject,
   ) -> () {
     let vk_app = exec_ctx.vk_app;
@@ -155,7 +179,7 @@ impl TfxDepthOnlyPass {
     let pass_name = &create_per_object_pass_name::<Self>(&entity.name);
 
     unsafe {
-      self.cmd_resource_barriers(device, &command_buffer, depth_tex);
+      self.cmd_resource_barriers(device, &command_buffer, depth_tex, velocity_tex);
 
       // start render pass
       let scope_id = exec_ctx.cmd_begin_scope(pass_name);
@@ -181,11 +205,12 @@ impl TfxDepthOnlyPass {
     device: &ash::Device,
     command_buffer: &vk::CommandBuffer,
     depth_tex: &mut VkTexture,
+    velocity_tex: &mut VkTexture,
   ) {
     VkTexture::cmd_transition_attachments_for_write_barrier(
       device,
       *command_buffer,
-      &mut [depth_tex],
+      &mut [depth_tex, velocity_tex],
     );
   }
 
@@ -201,17 +226,27 @@ impl TfxDepthOnlyPass {
       BindableResource::Buffer {
         usage: BindableBufferUsage::SSBO,
         binding: TfxForwardPass::BINDING_INDEX_POSITIONS_SSBO,
-        buffer: &entity.get_current_position_buffer(exec_ctx.timer.frame_idx()),
+        buffer: entity.get_current_position_buffer(exec_ctx.timer.frame_idx()),
       },
       BindableResource::Buffer {
         usage: BindableBufferUsage::SSBO,
         binding: TfxForwardPass::BINDING_INDEX_TANGENTS_SSBO,
         buffer: &entity.tangents_buffer,
       },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: TfxForwardPass::BINDING_INDEX_FOLLOW_HAIR_NEIGHBOURS_SSBO,
+        buffer: &entity.follow_hair_neighbours_buffer,
+      },
       BindableResource::Buffer {
         usage: BindableBufferUsage::UBO,
         binding: TfxForwardPass::BINDING_INDEX_TFX_PARAMS_UBO,
-        buffer: &entity.get_tfx_params_ubo_buffer(exec_ctx.frame_in_flight_id),
+        buffer: entity.get_tfx_params_ubo_buffer(exec_ctx.frame_in_flight_id),
+      },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: TfxForwardPass::BINDING_INDEX_PREV_POSITIONS_SSBO,
+        buffer: entity.get_previous_position_buffer(exec_ctx.timer.frame_idx()),
       },
     ];
 
@@ -33,6 +33,11 @@ impl TfxForwardPass {
   pub const BINDING_INDEX_TFX_PARAMS_UBO: u32 = 3;
   pub const BINDING_INDEX_SHADOW_MAP: u32 = 4;
   pub const BINDING_INDEX_AO_TEX: u32 = 5;
+  /// Hardcoded in `_tfx_vertex_resolve.glsl`, same for all hair passes
+  pub const BINDING_INDEX_FOLLOW_HAIR_NEIGHBOURS_SSBO: u32 = 7;
+  pub const BINDING_INDEX_LIGHTS_SSBO: u32 = 8;
+  /// Hardcoded in `_tfx_velocity.glsl`, same for all hair passes
+  pub const BINDING_INDEX_PREV_POSITIONS_SSBO: u32 = 9;
 
   pub fn new(vk_app: &VkCtx) -> Self {
     info!("Creating {}", get_simple_type_name::<Self>());
@@ -75,6 +80,14 @@ impl TfxForwardPass {
         Self::BINDING_INDEX_TANGENTS_SSBO,
         vk::ShaderStageFlags::VERTEX,
       ),
+      create_ssbo_binding(
+        Self::BINDING_INDEX_FOLLOW_HAIR_NEIGHBOURS_SSBO,
+        vk::ShaderStageFlags::VERTEX,
+      ),
+      create_ssbo_binding(
+        Self::BINDING_INDEX_PREV_POSITIONS_SSBO,
+        vk::ShaderStageFlags::VERTEX,
+      ),
       create_ubo_binding(
         Self::BINDING_INDEX_TFX_PARAMS_UBO,
         vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
@@ -84,6 +97,10 @@ impl TfxForwardPass {
         vk::ShaderStageFlags::FRAGMENT,
       ),
       create_texture_binding(Self::BINDING_INDEX_AO_TEX, vk::ShaderStageFlags::FRAGMENT),
+      create_ssbo_binding(
+        Self::BINDING_INDEX_LIGHTS_SSBO,
+        vk::ShaderStageFlags::FRAGMENT,
+      ),
     ]
   }
 
@@ -176,6 +193,7 @@ impl TfxForwardPass {
       &mut [
         &mut framebuffer.diffuse_tex,
         &mut framebuffer.normals_tex,
+        &mut framebuffer.velocity_tex,
         &mut framebuffer.depth_stencil_tex,
       ],
     );
@@ -200,30 +218,45 @@ impl TfxForwardPass {
       BindableResource::Buffer {
         usage: BindableBufferUsage::SSBO,
         binding: Self::BINDING_INDEX_POSITIONS_SSBO,
-        buffer: &entity.get_current_position_buffer(exec_ctx.timer.frame_idx()),
+        buffer: entity.get_current_position_buffer(exec_ctx.timer.frame_idx()),
       },
       BindableResource::Buffer {
         usage: BindableBufferUsage::SSBO,
         binding: Self::BINDING_INDEX_TANGENTS_SSBO,
         buffer: &entity.tangents_buffer,
       },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: Self::BINDING_INDEX_FOLLOW_HAIR_NEIGHBOURS_SSBO,
+        buffer: &entity.follow_hair_neighbours_buffer,
+      },
       BindableResource::Buffer {
         usage: BindableBufferUsage::UBO,
         binding: Self::BINDING_INDEX_TFX_PARAMS_UBO,
-        buffer: &entity.get_tfx_params_ubo_buffer(exec_ctx.frame_in_flight_id),
+        buffer: entity.get_tfx_params_ubo_buffer(exec_ctx.frame_in_flight_id),
       },
       BindableResource::Texture {
         binding: Self::BINDING_INDEX_SHADOW_MAP,
-        texture: &shadow_map_texture,
+        texture: shadow_map_texture,
         image_view: None,
         sampler: vk_app.default_texture_sampler_nearest,
       },
       BindableResource::Texture {
         binding: Self::BINDING_INDEX_AO_TEX,
-        texture: &ao_texture,
+        texture: ao_texture,
         image_view: None,
         sampler: vk_app.default_texture_sampler_linear,
       },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: Self::BINDING_INDEX_LIGHTS_SSBO,
+        buffer: exec_ctx.lights_buffer,
+      },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: Self::BINDING_INDEX_PREV_POSITIONS_SSBO,
+        buffer: entity.get_previous_position_buffer(exec_ctx.timer.frame_idx()),
+      },
     ];
 
     let resouce_binder = exec_ctx.create_resouce_binder(self.pipeline_layout);
@@ -47,6 +47,7 @@ impl TfxPpllBuildPass {
   const BINDING_INDEX_HEAD_POINTERS_IMAGE: u32 = 4;
   const BINDING_INDEX_DATA_BUFFER: u32 = 5;
   const BINDING_INDEX_NEXT_FREE_ENTRY_ATOMIC: u32 = 6;
+  const BINDING_INDEX_FOLLOW_HAIR_NEIGHBOURS_SSBO: u32 = 7;
 
   const COLOR_ATTACHMENT_COUNT: usize = 0;
 
@@ -105,6 +106,10 @@ impl TfxPpllBuildPass {
         Self::BINDING_INDEX_TANGENTS_SSBO,
         vk::ShaderStageFlags::VERTEX,
       ),
+      create_ssbo_binding(
+        Self::BINDING_INDEX_FOLLOW_HAIR_NEIGHBOURS_SSBO,
+        vk::ShaderStageFlags::VERTEX,
+      ),
       create_ubo_binding(
         Self::BINDING_INDEX_TFX_PARAMS_UBO,
         vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
@@ -377,6 +382,11 @@ impl TfxPpllBuildPass {
         binding: Self::BINDING_INDEX_TANGENTS_SSBO,
         buffer: &entity.tangents_buffer,
       },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: Self::BINDING_INDEX_FOLLOW_HAIR_NEIGHBOURS_SSBO,
+        buffer: &entity.follow_hair_neighbours_buffer,
+      },
       BindableResource::Buffer {
         usage: BindableBufferUsage::UBO,
         binding: Self::BINDING_INDEX_TFX_PARAMS_UBO,
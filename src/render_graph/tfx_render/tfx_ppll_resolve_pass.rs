@@ -1,5 +1,6 @@
 use ash;
 use ash::vk;
+use bytemuck;
 use log::info;
 
 use crate::config::Config;
@@ -17,9 +18,14 @@ const SHADER_PATHS: (&str, &str) = (
 );
 
 /// https://github.com/SaschaWillems/Vulkan/blob/master/examples/oit/oit.cpp#L610
+///
+/// K-buffer size and tail mode are specialization constants. Pipeline for every
+/// combination is created upfront, so they can be switched at runtime without
+/// waiting for the GPU.
 pub struct TfxPpllResolvePass {
   render_pass: vk::RenderPass,
-  pipeline: vk::Pipeline,
+  /// Indexed by `get_pipeline_idx()`
+  pipelines: Vec<vk::Pipeline>,
   pipeline_layout: vk::PipelineLayout,
   uniforms_layout: vk::DescriptorSetLayout,
 }
@@ -31,6 +37,10 @@ impl TfxPpllResolvePass {
   const BINDING_INDEX_TFX_PARAMS_UBO: u32 = 3;
   const BINDING_INDEX_AO_TEX: u32 = 4;
   const BINDING_INDEX_SHADOW_MAP: u32 = 5;
+  const BINDING_INDEX_LIGHTS_SSBO: u32 = 6; // Must match shader
+
+  /// See `HairPPLLTailMode`
+  const TAIL_MODES_COUNT: usize = 3;
 
   pub fn new(vk_app: &VkCtx) -> Self {
     info!("Creating TfxPpllResolvePass");
@@ -41,11 +51,26 @@ impl TfxPpllResolvePass {
     let uniforms_desc = Self::get_uniforms_layout();
     let uniforms_layout = create_push_descriptor_layout(device, uniforms_desc);
     let pipeline_layout = create_pipeline_layout(device, &[uniforms_layout], &[]);
-    let pipeline = Self::create_pipeline(device, pipeline_cache, &render_pass, &pipeline_layout);
+    let pipelines = Config::PPLL_KBUFFER_SIZES
+      .iter()
+      .flat_map(|kbuffer_size| {
+        (0..Self::TAIL_MODES_COUNT).map(move |tail_mode| (*kbuffer_size, tail_mode as u32))
+      })
+      .map(|(kbuffer_size, tail_mode)| {
+        Self::create_pipeline(
+          device,
+          pipeline_cache,
+          &render_pass,
+          &pipeline_layout,
+          kbuffer_size,
+          tail_mode,
+        )
+      })
+      .collect();
 
     Self {
       render_pass,
-      pipeline,
+      pipelines,
       pipeline_layout,
       uniforms_layout,
     }
@@ -56,7 +81,17 @@ impl TfxPpllResolvePass {
     device.destroy_render_pass(self.render_pass, None);
     device.destroy_descriptor_set_layout(self.uniforms_layout, None);
     device.destroy_pipeline_layout(self.pipeline_layout, None);
-    device.destroy_pipeline(self.pipeline, None);
+    for pipeline in &self.pipelines {
+      device.destroy_pipeline(*pipeline, None);
+    }
+  }
+
+  fn get_pipeline_idx(config: &Config) -> usize {
+    let kbuffer_size_idx = config
+      .hair_ppll_kbuffer_size_idx
+      .min(Config::PPLL_KBUFFER_SIZES.len() - 1);
+    let tail_mode = config.hair_ppll_tail_mode.min(Self::TAIL_MODES_COUNT - 1);
+    kbuffer_size_idx * Self::TAIL_MODES_COUNT + tail_mode
   }
 
   fn get_uniforms_layout() -> Vec<vk::DescriptorSetLayoutBinding> {
@@ -82,6 +117,10 @@ impl TfxPpllResolvePass {
         Self::BINDING_INDEX_SHADOW_MAP,
         vk::ShaderStageFlags::FRAGMENT,
       ),
+      create_ssbo_binding(
+        Self::BINDING_INDEX_LIGHTS_SSBO,
+        vk::ShaderStageFlags::FRAGMENT,
+      ),
     ]
   }
 
@@ -91,14 +130,27 @@ impl TfxPpllResolvePass {
     pipeline_cache: &vk::PipelineCache,
     render_pass: &vk::RenderPass,
     pipeline_layout: &vk::PipelineLayout,
+    kbuffer_size: u32,
+    tail_mode: u32,
   ) -> vk::Pipeline {
     let vertex_desc = ps_vertex_empty();
 
-    create_pipeline_with_defaults(
+    // Must match `constant_id` in `tfx_ppll_resolve.frag.glsl`
+    let specialization_values: [u32; 2] = [kbuffer_size, tail_mode];
+    let specialization_entries = create_specialization_map_entries(specialization_values.len());
+    let specialization_info = vk::SpecializationInfo::builder()
+      .map_entries(&specialization_entries)
+      .data(bytemuck::cast_slice(&specialization_values))
+      .build();
+
+    create_pipeline_with_defaults_specialized(
       device,
       render_pass,
       pipeline_layout,
-      SHADER_PATHS,
+      SpecializedShaders {
+        paths: SHADER_PATHS,
+        fs_specialization: Some(&specialization_info),
+      },
       vertex_desc,
       ForwardPass::COLOR_ATTACHMENT_COUNT,
       |builder| {
@@ -182,6 +234,7 @@ impl TfxPpllResolvePass {
     let device = vk_app.vk_device();
     let size = exec_ctx.size;
     let pass_name = &create_per_object_pass_name::<Self>(&entity.name);
+    let pipeline = self.pipelines[Self::get_pipeline_idx(&exec_ctx.config.borrow())];
 
     unsafe {
       self.cmd_resource_barriers(
@@ -195,13 +248,7 @@ impl TfxPpllResolvePass {
 
       // start render pass
       let scope_id = exec_ctx.cmd_begin_scope(pass_name);
-      exec_ctx.cmd_start_render_pass(
-        &self.render_pass,
-        &self.pipeline,
-        &framebuffer.fbo,
-        &size,
-        &[],
-      );
+      exec_ctx.cmd_start_render_pass(&self.render_pass, &pipeline, &framebuffer.fbo, &size, &[]);
 
       // bind uniforms (do not move this)
       self.bind_uniforms(
@@ -305,6 +352,11 @@ impl TfxPpllResolvePass {
         image_view: None,
         sampler: vk_app.default_texture_sampler_nearest,
       },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: Self::BINDING_INDEX_LIGHTS_SSBO,
+        buffer: exec_ctx.lights_buffer,
+      },
     ];
     bind_resources_to_descriptors_graphic(&resouce_binder, 0, &uniform_resouces);
   }
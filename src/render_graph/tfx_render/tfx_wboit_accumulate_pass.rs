@@ -0,0 +1,422 @@
+use ash::vk;
+use log::info;
+
+use crate::config::Config;
+use crate::render_graph::forward_pass::ForwardPass;
+use crate::scene::TfxObject;
+use crate::utils::create_per_object_pass_name;
+use crate::vk_ctx::VkCtx;
+use crate::vk_utils::*;
+
+use crate::render_graph::PassExecContext;
+
+use super::TfxRenderTargets;
+
+const SHADER_PATHS: (&str, &str) = (
+  "./assets/shaders-compiled/tfx_forward.vert.spv",
+  "./assets/shaders-compiled/tfx_wboit_accumulate.frag.spv",
+);
+
+/// First step of Weighted Blended Order-Independent Transparency.
+/// Every hair fragment is shaded and added to the accumulation texture with
+/// a depth-based weight. Revealage texture stores product of `(1 - alpha)`.
+/// Neither depends on the draw order, so no sorting (or per pixel lists) is needed.
+/// Sets `HAIR` stencil flag.
+///
+/// Much cheaper than PPLL, but the closest strands are not resolved exactly.
+/// http://jcgt.org/published/0002/02/09/
+pub struct TfxWboitAccumulatePass {
+  render_pass: vk::RenderPass,
+  pipeline: vk::Pipeline,
+  pipeline_layout: vk::PipelineLayout,
+  uniforms_layout: vk::DescriptorSetLayout,
+}
+
+impl TfxWboitAccumulatePass {
+  pub const ACCUMULATION_TEXTURE_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+  pub const REVEALAGE_TEXTURE_FORMAT: vk::Format = vk::Format::R16_SFLOAT;
+
+  const BINDING_INDEX_CONFIG_UBO: u32 = 0;
+  const BINDING_INDEX_POSITIONS_SSBO: u32 = 1;
+  const BINDING_INDEX_TANGENTS_SSBO: u32 = 2;
+  const BINDING_INDEX_TFX_PARAMS_UBO: u32 = 3;
+  const BINDING_INDEX_SHADOW_MAP: u32 = 4;
+  const BINDING_INDEX_AO_TEX: u32 = 5;
+  const BINDING_INDEX_FOLLOW_HAIR_NEIGHBOURS_SSBO: u32 = 7;
+  const BINDING_INDEX_LIGHTS_SSBO: u32 = 8;
+  /// Hardcoded in `_tfx_velocity.glsl`, same for all hair passes
+  const BINDING_INDEX_PREV_POSITIONS_SSBO: u32 = 9;
+
+  /// accumulation, revealage, normals
+  const COLOR_ATTACHMENT_COUNT: usize = 3;
+
+  pub fn new(vk_app: &VkCtx) -> Self {
+    info!("Creating TfxWboitAccumulatePass");
+    let device = vk_app.vk_device();
+    let pipeline_cache = &vk_app.pipeline_cache;
+
+    let render_pass = Self::create_render_pass(device);
+    let uniforms_desc = Self::get_uniforms_layout();
+    let uniforms_layout = create_push_descriptor_layout(device, uniforms_desc);
+    let pipeline_layout = create_pipeline_layout(device, &[uniforms_layout], &[]);
+    let pipeline = Self::create_pipeline(device, pipeline_cache, &render_pass, &pipeline_layout);
+
+    Self {
+      render_pass,
+      pipeline,
+      pipeline_layout,
+      uniforms_layout,
+    }
+  }
+
+  pub unsafe fn destroy(&mut self, vk_app: &VkCtx) {
+    let device = vk_app.vk_device();
+    device.destroy_render_pass(self.render_pass, None);
+    device.destroy_descriptor_set_layout(self.uniforms_layout, None);
+    device.destroy_pipeline_layout(self.pipeline_layout, None);
+    device.destroy_pipeline(self.pipeline, None);
+  }
+
+  fn create_render_pass(device: &ash::Device) -> vk::RenderPass {
+    let depth_attachment = create_depth_stencil_attachment(
+      0,
+      ForwardPass::DEPTH_TEXTURE_FORMAT,
+      vk::AttachmentLoadOp::LOAD,   // depth_load_op
+      vk::AttachmentStoreOp::STORE, // depth_store_op
+      vk::AttachmentLoadOp::LOAD,   // stencil_load_op
+      vk::AttachmentStoreOp::STORE, // stencil_store_op
+      vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+    );
+    let accumulation_attachment = create_color_attachment(
+      1,
+      Self::ACCUMULATION_TEXTURE_FORMAT,
+      vk::AttachmentLoadOp::CLEAR,
+      vk::AttachmentStoreOp::STORE,
+    );
+    let revealage_attachment = create_color_attachment(
+      2,
+      Self::REVEALAGE_TEXTURE_FORMAT,
+      vk::AttachmentLoadOp::CLEAR,
+      vk::AttachmentStoreOp::STORE,
+    );
+    let normals_attachment = create_color_attachment(
+      3,
+      ForwardPass::NORMALS_TEXTURE_FORMAT,
+      vk::AttachmentLoadOp::LOAD,
+      vk::AttachmentStoreOp::STORE,
+    );
+
+    unsafe {
+      create_render_pass_from_attachments(
+        device,
+        Some(depth_attachment),
+        &[
+          accumulation_attachment,
+          revealage_attachment,
+          normals_attachment,
+        ],
+      )
+    }
+  }
+
+  fn get_uniforms_layout() -> Vec<vk::DescriptorSetLayoutBinding> {
+    vec![
+      create_ubo_binding(
+        Self::BINDING_INDEX_CONFIG_UBO,
+        vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+      ),
+      create_ssbo_binding(
+        Self::BINDING_INDEX_POSITIONS_SSBO,
+        vk::ShaderStageFlags::VERTEX,
+      ),
+      create_ssbo_binding(
+        Self::BINDING_INDEX_TANGENTS_SSBO,
+        vk::ShaderStageFlags::VERTEX,
+      ),
+      create_ssbo_binding(
+        Self::BINDING_INDEX_FOLLOW_HAIR_NEIGHBOURS_SSBO,
+        vk::ShaderStageFlags::VERTEX,
+      ),
+      create_ssbo_binding(
+        Self::BINDING_INDEX_PREV_POSITIONS_SSBO,
+        vk::ShaderStageFlags::VERTEX,
+      ),
+      create_ubo_binding(
+        Self::BINDING_INDEX_TFX_PARAMS_UBO,
+        vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+      ),
+      create_texture_binding(
+        Self::BINDING_INDEX_SHADOW_MAP,
+        vk::ShaderStageFlags::FRAGMENT,
+      ),
+      create_texture_binding(Self::BINDING_INDEX_AO_TEX, vk::ShaderStageFlags::FRAGMENT),
+      create_ssbo_binding(
+        Self::BINDING_INDEX_LIGHTS_SSBO,
+        vk::ShaderStageFlags::FRAGMENT,
+      ),
+    ]
+  }
+
+  fn create_pipeline(
+    device: &ash::Device,
+    pipeline_cache: &vk::PipelineCache,
+    render_pass: &vk::RenderPass,
+    pipeline_layout: &vk::PipelineLayout,
+  ) -> vk::Pipeline {
+    let vertex_desc = ps_vertex_empty();
+
+    create_pipeline_with_defaults(
+      device,
+      render_pass,
+      pipeline_layout,
+      SHADER_PATHS,
+      vertex_desc,
+      Self::COLOR_ATTACHMENT_COUNT,
+      |builder| {
+        // Depth write disabled, all fragments in front of the opaque objects contribute.
+        // Depth is written later in `TfxDepthOnlyPass`.
+        let stencil_write_hair = ps_stencil_write_if_depth_passed(Config::STENCIL_BIT_HAIR, true);
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::builder()
+          .depth_test_enable(true)
+          .depth_write_enable(false)
+          .depth_compare_op(vk::CompareOp::LESS)
+          .depth_bounds_test_enable(false)
+          .stencil_test_enable(true)
+          .front(stencil_write_hair)
+          .back(stencil_write_hair)
+          .build();
+
+        // sum of weighted colors
+        let blend_accumulation = vk::PipelineColorBlendAttachmentState::builder()
+          .color_write_mask(vk::ColorComponentFlags::RGBA)
+          .blend_enable(true)
+          .color_blend_op(vk::BlendOp::ADD)
+          .src_color_blend_factor(vk::BlendFactor::ONE) // shader output
+          .dst_color_blend_factor(vk::BlendFactor::ONE) // existing value on destination attachment
+          .alpha_blend_op(vk::BlendOp::ADD)
+          .src_alpha_blend_factor(vk::BlendFactor::ONE) // shader output
+          .dst_alpha_blend_factor(vk::BlendFactor::ONE) // existing value on destination attachment
+          .build();
+        // product of `(1 - alpha)`
+        let blend_revealage = vk::PipelineColorBlendAttachmentState::builder()
+          .color_write_mask(vk::ColorComponentFlags::R)
+          .blend_enable(true)
+          .color_blend_op(vk::BlendOp::ADD)
+          .src_color_blend_factor(vk::BlendFactor::ZERO) // shader output
+          .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_COLOR) // existing value on destination attachment
+          .alpha_blend_op(vk::BlendOp::ADD)
+          .src_alpha_blend_factor(vk::BlendFactor::ZERO) // shader output
+          .dst_alpha_blend_factor(vk::BlendFactor::ONE) // existing value on destination attachment
+          .build();
+        let blend_normals = ps_color_attachment_override(vk::ColorComponentFlags::RGBA);
+
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+          .attachments(&[blend_accumulation, blend_revealage, blend_normals])
+          .build();
+
+        let pipeline_create_info = builder
+          .depth_stencil_state(&depth_stencil)
+          .color_blend_state(&color_blend_state)
+          .build();
+        create_pipeline(device, pipeline_cache, pipeline_create_info)
+      },
+    )
+  }
+
+  pub fn create_framebuffer(
+    &self,
+    vk_app: &VkCtx,
+    depth_stencil_tex: &VkTexture,
+    forward_normal_tex: &VkTexture,
+  ) -> TfxWboitAccumulatePassFramebuffer {
+    let device = vk_app.vk_device();
+    let size = depth_stencil_tex.size();
+
+    let accumulation_tex =
+      vk_app.create_attachment::<Self>("accumulation", Self::ACCUMULATION_TEXTURE_FORMAT, size);
+    let revealage_tex =
+      vk_app.create_attachment::<Self>("revealage", Self::REVEALAGE_TEXTURE_FORMAT, size);
+
+    let fbo = create_framebuffer(
+      device,
+      self.render_pass,
+      &[
+        depth_stencil_tex.image_view(),
+        accumulation_tex.image_view(),
+        revealage_tex.image_view(),
+        forward_normal_tex.image_view(),
+      ],
+      &size,
+    );
+
+    TfxWboitAccumulatePassFramebuffer {
+      fbo,
+      accumulation_tex,
+      revealage_tex,
+    }
+  }
+
+  pub fn execute(
+    &self,
+    exec_ctx: &PassExecContext,
+    framebuffer: &mut TfxWboitAccumulatePassFramebuffer,
+    targets: &mut TfxRenderTargets,
+    entity: &TfxObject,
+  ) {
+    let vk_app = exec_ctx.vk_app;
+    let command_buffer = exec_ctx.command_buffer;
+    let size = exec_ctx.size;
+    let device = vk_app.vk_device();
+    let pass_name = &create_per_object_pass_name::<Self>(&entity.name);
+
+    // clear values for depth and normals are ignored (`AttachmentLoadOp::LOAD`)
+    let clear_values = [
+      vk::ClearValue::default(),
+      vk::ClearValue {
+        color: vk::ClearColorValue {
+          float32: [0.0, 0.0, 0.0, 0.0],
+        },
+      },
+      vk::ClearValue {
+        color: vk::ClearColorValue {
+          float32: [1.0, 1.0, 1.0, 1.0],
+        },
+      },
+      vk::ClearValue::default(),
+    ];
+
+    unsafe {
+      self.cmd_resource_barriers(device, &command_buffer, framebuffer, targets);
+
+      // start render pass
+      let scope_id = exec_ctx.cmd_begin_scope(pass_name);
+      exec_ctx.cmd_start_render_pass(
+        &self.render_pass,
+        &self.pipeline,
+        &framebuffer.fbo,
+        &size,
+        &clear_values,
+      );
+
+      // draw calls
+      self.bind_entity_ubos(
+        exec_ctx,
+        entity,
+        targets.shadow_map_texture,
+        targets.ao_texture,
+      );
+      entity.cmd_draw_mesh(device, command_buffer);
+
+      // end
+      exec_ctx.cmd_end_render_pass(scope_id);
+    }
+  }
+
+  unsafe fn cmd_resource_barriers(
+    &self,
+    device: &ash::Device,
+    command_buffer: &vk::CommandBuffer,
+    framebuffer: &mut TfxWboitAccumulatePassFramebuffer,
+    targets: &mut TfxRenderTargets,
+  ) {
+    VkTexture::cmd_transition_attachments_for_read_barrier(
+      device,
+      *command_buffer,
+      &mut [targets.shadow_map_texture, targets.ao_texture],
+    );
+
+    VkTexture::cmd_transition_attachments_for_write_barrier(
+      device,
+      *command_buffer,
+      &mut [
+        targets.depth_stencil_tex,
+        &mut framebuffer.accumulation_tex,
+        &mut framebuffer.revealage_tex,
+        targets.forward_normal_tex,
+      ],
+    );
+  }
+
+  unsafe fn bind_entity_ubos(
+    &self,
+    exec_ctx: &PassExecContext,
+    entity: &TfxObject,
+    shadow_map_texture: &VkTexture,
+    ao_texture: &VkTexture,
+  ) {
+    let vk_app = exec_ctx.vk_app;
+    let config_buffer = exec_ctx.config_buffer;
+
+    let uniform_resouces = [
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::UBO,
+        binding: Self::BINDING_INDEX_CONFIG_UBO,
+        buffer: config_buffer,
+      },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: Self::BINDING_INDEX_POSITIONS_SSBO,
+        buffer: entity.get_current_position_buffer(exec_ctx.timer.frame_idx()),
+      },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: Self::BINDING_INDEX_TANGENTS_SSBO,
+        buffer: &entity.tangents_buffer,
+      },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: Self::BINDING_INDEX_FOLLOW_HAIR_NEIGHBOURS_SSBO,
+        buffer: &entity.follow_hair_neighbours_buffer,
+      },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::UBO,
+        binding: Self::BINDING_INDEX_TFX_PARAMS_UBO,
+        buffer: entity.get_tfx_params_ubo_buffer(exec_ctx.frame_in_flight_id),
+      },
+      BindableResource::Texture {
+        binding: Self::BINDING_INDEX_SHADOW_MAP,
+        texture: shadow_map_texture,
+        image_view: None,
+        sampler: vk_app.default_texture_sampler_nearest,
+      },
+      BindableResource::Texture {
+        binding: Self::BINDING_INDEX_AO_TEX,
+        texture: ao_texture,
+        image_view: None,
+        sampler: vk_app.default_texture_sampler_linear,
+      },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: Self::BINDING_INDEX_LIGHTS_SSBO,
+        buffer: exec_ctx.lights_buffer,
+      },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: Self::BINDING_INDEX_PREV_POSITIONS_SSBO,
+        buffer: entity.get_previous_position_buffer(exec_ctx.timer.frame_idx()),
+      },
+    ];
+
+    let resouce_binder = exec_ctx.create_resouce_binder(self.pipeline_layout);
+    bind_resources_to_descriptors_graphic(&resouce_binder, 0, &uniform_resouces);
+  }
+}
+
+pub struct TfxWboitAccumulatePassFramebuffer {
+  pub fbo: vk::Framebuffer,
+  /// `[sum(color * alpha * weight), sum(alpha * weight)]`
+  pub accumulation_tex: VkTexture,
+  /// `product(1 - alpha)`
+  pub revealage_tex: VkTexture,
+}
+
+impl TfxWboitAccumulatePassFramebuffer {
+  pub unsafe fn destroy(&mut self, vk_app: &VkCtx) {
+    let device = vk_app.vk_device();
+    let allocator = &vk_app.allocator;
+
+    device.destroy_framebuffer(self.fbo, None);
+    self.accumulation_tex.delete(device, allocator);
+    self.revealage_tex.delete(device, allocator);
+  }
+}
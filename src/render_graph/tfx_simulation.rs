@@ -32,13 +32,16 @@ pub fn execute_tfx_simulation(
   tfx_sim3: &TfxSim3Pass,
 ) {
   let scene = pass_ctx.scene.borrow();
-  let local_shape_iterations = pass_ctx
-    .config
-    .borrow()
-    .tfx_simulation
-    .local_stiffness_iterations;
 
   for entity in &scene.tressfx_objects {
+    if !entity.simulation.enabled {
+      continue; // static hair group, e.g. eyelashes
+    }
+    if entity.simulation_cache.is_playback() {
+      continue; // positions are provided by the cache
+    }
+    let local_shape_iterations = entity.simulation.local_stiffness_iterations;
+
     cmd_barrier_prepare_for_simulation(pass_ctx.vk_app.vk_device(), pass_ctx.command_buffer);
 
     tfx_sim0.execute(pass_ctx, entity);
@@ -35,6 +35,8 @@ impl TfxSim0Pass {
   const BINDING_INDEX_POSITIONS_PREV: u32 = 2;
   const BINDING_INDEX_POSITIONS_PREV_PREV: u32 = 3;
   const BINDING_INDEX_POSITIONS_INITIAL: u32 = 4;
+  const BINDING_INDEX_TFX_PARAMS_UBO: u32 = 5;
+  const BINDING_INDEX_VERTEX_WEIGHTS: u32 = 6;
 
   pub fn new(vk_app: &VkCtx) -> Self {
     info!("Creating {}", get_simple_type_name::<Self>());
@@ -65,6 +67,14 @@ impl TfxSim0Pass {
         Self::BINDING_INDEX_CONFIG_UBO,
         vk::ShaderStageFlags::COMPUTE,
       ),
+      create_ubo_binding(
+        Self::BINDING_INDEX_TFX_PARAMS_UBO,
+        vk::ShaderStageFlags::COMPUTE,
+      ),
+      create_ssbo_binding(
+        Self::BINDING_INDEX_VERTEX_WEIGHTS,
+        vk::ShaderStageFlags::COMPUTE,
+      ),
       create_ssbo_binding(Self::BINDING_INDEX_POSITIONS, vk::ShaderStageFlags::COMPUTE),
       create_ssbo_binding(
         Self::BINDING_INDEX_POSITIONS_PREV,
@@ -120,6 +130,16 @@ impl TfxSim0Pass {
         binding: Self::BINDING_INDEX_CONFIG_UBO,
         buffer: config_buffer,
       },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::UBO,
+        binding: Self::BINDING_INDEX_TFX_PARAMS_UBO,
+        buffer: entity.get_tfx_params_ubo_buffer(exec_ctx.frame_in_flight_id),
+      },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: Self::BINDING_INDEX_VERTEX_WEIGHTS,
+        buffer: &entity.vertex_weights_buffer,
+      },
       BindableResource::Buffer {
         usage: BindableBufferUsage::SSBO,
         binding: Self::BINDING_INDEX_POSITIONS,
@@ -32,6 +32,8 @@ impl TfxSim2Pass {
   const BINDING_INDEX_CONFIG_UBO: u32 = 0;
   const BINDING_INDEX_POSITIONS: u32 = 1;
   const BINDING_INDEX_POSITIONS_INITIAL: u32 = 2;
+  const BINDING_INDEX_TFX_PARAMS_UBO: u32 = 3;
+  const BINDING_INDEX_VERTEX_WEIGHTS: u32 = 4;
 
   pub fn new(vk_app: &VkCtx) -> Self {
     info!("Creating {}", get_simple_type_name::<Self>());
@@ -62,6 +64,14 @@ impl TfxSim2Pass {
         Self::BINDING_INDEX_CONFIG_UBO,
         vk::ShaderStageFlags::COMPUTE,
       ),
+      create_ubo_binding(
+        Self::BINDING_INDEX_TFX_PARAMS_UBO,
+        vk::ShaderStageFlags::COMPUTE,
+      ),
+      create_ssbo_binding(
+        Self::BINDING_INDEX_VERTEX_WEIGHTS,
+        vk::ShaderStageFlags::COMPUTE,
+      ),
       create_ssbo_binding(Self::BINDING_INDEX_POSITIONS, vk::ShaderStageFlags::COMPUTE),
       create_ssbo_binding(
         Self::BINDING_INDEX_POSITIONS_INITIAL,
@@ -108,6 +118,16 @@ impl TfxSim2Pass {
         binding: Self::BINDING_INDEX_CONFIG_UBO,
         buffer: config_buffer,
       },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::UBO,
+        binding: Self::BINDING_INDEX_TFX_PARAMS_UBO,
+        buffer: entity.get_tfx_params_ubo_buffer(exec_ctx.frame_in_flight_id),
+      },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: Self::BINDING_INDEX_VERTEX_WEIGHTS,
+        buffer: &entity.vertex_weights_buffer,
+      },
       BindableResource::Buffer {
         usage: BindableBufferUsage::SSBO,
         binding: Self::BINDING_INDEX_POSITIONS,
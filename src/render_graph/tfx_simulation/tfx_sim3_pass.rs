@@ -36,6 +36,8 @@ impl TfxSim3Pass {
   const BINDING_INDEX_POSITIONS_PREV: u32 = 2;
   const BINDING_INDEX_POSITIONS_INITIAL: u32 = 3;
   const BINDING_INDEX_TANGENTS: u32 = 4;
+  const BINDING_INDEX_TFX_PARAMS_UBO: u32 = 5;
+  const BINDING_INDEX_VERTEX_WEIGHTS: u32 = 6;
 
   pub fn new(vk_app: &VkCtx) -> Self {
     info!("Creating {}", get_simple_type_name::<Self>());
@@ -68,6 +70,14 @@ impl TfxSim3Pass {
         Self::BINDING_INDEX_CONFIG_UBO,
         vk::ShaderStageFlags::COMPUTE,
       ),
+      create_ubo_binding(
+        Self::BINDING_INDEX_TFX_PARAMS_UBO,
+        vk::ShaderStageFlags::COMPUTE,
+      ),
+      create_ssbo_binding(
+        Self::BINDING_INDEX_VERTEX_WEIGHTS,
+        vk::ShaderStageFlags::COMPUTE,
+      ),
       create_ssbo_binding(Self::BINDING_INDEX_POSITIONS, vk::ShaderStageFlags::COMPUTE),
       create_ssbo_binding(
         Self::BINDING_INDEX_POSITIONS_PREV,
@@ -127,6 +137,16 @@ impl TfxSim3Pass {
         binding: Self::BINDING_INDEX_CONFIG_UBO,
         buffer: config_buffer,
       },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::UBO,
+        binding: Self::BINDING_INDEX_TFX_PARAMS_UBO,
+        buffer: entity.get_tfx_params_ubo_buffer(exec_ctx.frame_in_flight_id),
+      },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: Self::BINDING_INDEX_VERTEX_WEIGHTS,
+        buffer: &entity.vertex_weights_buffer,
+      },
       BindableResource::Buffer {
         usage: BindableBufferUsage::SSBO,
         binding: Self::BINDING_INDEX_POSITIONS,
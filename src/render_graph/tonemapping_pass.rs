@@ -10,6 +10,8 @@ use super::PassExecContext;
 
 const BINDING_INDEX_CONFIG_UBO: u32 = 0;
 const BINDING_INDEX_PREVIOUS_RESULT: u32 = 1;
+const BINDING_INDEX_EXPOSURE: u32 = 2;
+const BINDING_INDEX_BLOOM: u32 = 3;
 
 const RESULT_TEXTURE_FORMAT: vk::Format = vk::Format::R8G8B8A8_UINT;
 const COLOR_ATTACHMENT_COUNT: usize = 1;
@@ -19,8 +21,11 @@ const SHADER_PATHS: (&str, &str) = (
 );
 
 /// Does:
+/// - bloom (from `BloomPass`) and chromatic aberration
+/// - exposure (manual or from `AutoExposurePass`)
 /// - dithering
 /// - color grading
+/// - vignette and film grain
 /// - outputs luma in alpha channel (later used for e.g. FXAA contrast detection)
 pub struct TonemappingPass {
   render_pass: vk::RenderPass,
@@ -74,6 +79,8 @@ impl TonemappingPass {
         BINDING_INDEX_PREVIOUS_RESULT,
         vk::ShaderStageFlags::FRAGMENT,
       ),
+      create_ssbo_binding(BINDING_INDEX_EXPOSURE, vk::ShaderStageFlags::FRAGMENT),
+      create_texture_binding(BINDING_INDEX_BLOOM, vk::ShaderStageFlags::FRAGMENT),
     ]
   }
 
@@ -127,6 +134,8 @@ impl TonemappingPass {
     exec_ctx: &PassExecContext,
     framebuffer: &mut TonemappingPassFramebuffer,
     previous_result: &mut VkTexture,
+    exposure_buffer: &VkBuffer,
+    bloom_tex: &mut VkTexture,
   ) -> () {
     let vk_app = exec_ctx.vk_app;
     let command_buffer = exec_ctx.command_buffer;
@@ -135,7 +144,13 @@ impl TonemappingPass {
     let pass_name = &get_simple_type_name::<Self>();
 
     unsafe {
-      self.cmd_resource_barriers(device, &command_buffer, framebuffer, previous_result);
+      self.cmd_resource_barriers(
+        device,
+        &command_buffer,
+        framebuffer,
+        previous_result,
+        bloom_tex,
+      );
 
       // start render pass
       let scope_id = exec_ctx.cmd_begin_scope(pass_name);
@@ -148,7 +163,7 @@ impl TonemappingPass {
       );
 
       // bind uniforms (do not move this)
-      self.bind_uniforms(exec_ctx, previous_result);
+      self.bind_uniforms(exec_ctx, previous_result, exposure_buffer, bloom_tex);
 
       // draw calls
       cmd_draw_fullscreen_triangle(device, &command_buffer);
@@ -158,7 +173,13 @@ impl TonemappingPass {
     }
   }
 
-  unsafe fn bind_uniforms(&self, exec_ctx: &PassExecContext, previous_result: &mut VkTexture) {
+  unsafe fn bind_uniforms(
+    &self,
+    exec_ctx: &PassExecContext,
+    previous_result: &mut VkTexture,
+    exposure_buffer: &VkBuffer,
+    bloom_tex: &mut VkTexture,
+  ) {
     let vk_app = exec_ctx.vk_app;
     let resouce_binder = exec_ctx.create_resouce_binder(self.pipeline_layout);
 
@@ -174,6 +195,17 @@ impl TonemappingPass {
         image_view: None,
         sampler: vk_app.default_texture_sampler_nearest,
       },
+      BindableResource::Buffer {
+        usage: BindableBufferUsage::SSBO,
+        binding: BINDING_INDEX_EXPOSURE,
+        buffer: exposure_buffer,
+      },
+      BindableResource::Texture {
+        binding: BINDING_INDEX_BLOOM,
+        texture: bloom_tex,
+        image_view: None,
+        sampler: vk_app.default_texture_sampler_linear,
+      },
     ];
     bind_resources_to_descriptors_graphic(&resouce_binder, 0, &uniform_resouces);
   }
@@ -184,11 +216,12 @@ impl TonemappingPass {
     command_buffer: &vk::CommandBuffer,
     framebuffer: &mut TonemappingPassFramebuffer,
     previous_result: &mut VkTexture,
+    bloom_tex: &mut VkTexture,
   ) {
     VkTexture::cmd_transition_attachments_for_read_barrier(
       device,
       *command_buffer,
-      &mut [previous_result],
+      &mut [previous_result, bloom_tex],
     );
 
     VkTexture::cmd_transition_attachments_for_write_barrier(
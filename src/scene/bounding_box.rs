@@ -4,7 +4,8 @@ use glam::{vec3, vec4, Mat4, Vec3};
 
 use crate::render_graph::RenderableVertex;
 
-/// More for debug and info than for actuall use
+/// Used for debug info and to fit shadow frustums
+#[derive(Copy, Clone, Debug)]
 pub struct BoundingBox {
   /// In world space
   pub min: Vec3,
@@ -31,6 +32,34 @@ impl BoundingBox {
     }
   }
 
+  pub fn from_sphere(center: Vec3, radius: f32) -> Self {
+    Self {
+      min: center - Vec3::splat(radius),
+      max: center + Vec3::splat(radius),
+    }
+  }
+
+  pub fn union(&self, other: &BoundingBox) -> Self {
+    Self {
+      min: self.min.min(other.min),
+      max: self.max.max(other.max),
+    }
+  }
+
+  pub fn corners(&self) -> [Vec3; 8] {
+    let (a, b) = (self.min, self.max);
+    [
+      vec3(a.x, a.y, a.z),
+      vec3(b.x, a.y, a.z),
+      vec3(a.x, b.y, a.z),
+      vec3(b.x, b.y, a.z),
+      vec3(a.x, a.y, b.z),
+      vec3(b.x, a.y, b.z),
+      vec3(a.x, b.y, b.z),
+      vec3(b.x, b.y, b.z),
+    ]
+  }
+
   pub fn center(&self) -> Vec3 {
     (self.min + self.max) / 2.0
   }
@@ -1,5 +1,5 @@
 use ash::vk;
-use glam::{vec3, vec4, Mat4, Vec3, Vec4Swizzles};
+use glam::{vec2, vec3, vec4, Mat4, Vec2, Vec3, Vec4Swizzles};
 
 use crate::config::Config;
 
@@ -9,7 +9,14 @@ const WHEEL_SENSITIVITY: f32 = 0.5;
 
 pub struct Camera {
   view_matrix: Mat4,
+  /// Without jitter
   perspective_matrix: Mat4,
+  /// `perspective_matrix` moved by `jitter`. Used for rendering
+  jittered_perspective_matrix: Mat4,
+  /// Subpixel offset of the projection (in NDC). Used by TAA
+  jitter: Vec2,
+  /// Unjittered view projection matrix from the previous frame. Used for motion vectors
+  prev_view_projection_matrix: Mat4,
   position: Vec3,
   /// in radians
   rotation_yaw: f32,
@@ -24,21 +31,26 @@ impl Camera {
     let rotation_yaw = cam_cfg.rotation.x.to_radians();
     let rotation_pitch = cam_cfg.rotation.y.to_radians();
     let aspect_ratio: f32 = window_size.width as f32 / window_size.height as f32;
+    let view_matrix = calc_view_matrix(position, rotation_yaw, rotation_pitch);
+    // https://matthewwellings.com/blog/the-new-vulkan-coordinate-system/
+    // https://www.saschawillems.de/blog/2019/03/29/flipping-the-vulkan-viewport/
+    // though glam does have fixes already implemented
+    let perspective_matrix = Mat4::perspective_rh(
+      cam_cfg.fov_dgr.to_radians(),
+      aspect_ratio,
+      cam_cfg.z_near,
+      cam_cfg.z_far,
+    );
 
     Camera {
       position,
       rotation_yaw,
       rotation_pitch,
-      view_matrix: calc_view_matrix(position, rotation_yaw, rotation_pitch),
-      // https://matthewwellings.com/blog/the-new-vulkan-coordinate-system/
-      // https://www.saschawillems.de/blog/2019/03/29/flipping-the-vulkan-viewport/
-      // though glam does have fixes already implemented
-      perspective_matrix: Mat4::perspective_rh(
-        cam_cfg.fov_dgr.to_radians(),
-        aspect_ratio,
-        cam_cfg.z_near,
-        cam_cfg.z_far,
-      ),
+      view_matrix,
+      perspective_matrix,
+      jittered_perspective_matrix: perspective_matrix,
+      jitter: Vec2::ZERO,
+      prev_view_projection_matrix: perspective_matrix * view_matrix,
     }
   }
 
@@ -50,20 +62,71 @@ impl Camera {
     &self.view_matrix
   }
 
+  /// Without jitter. Use for anything that is not rendering e.g. LOD, culling, picking
   pub fn perspective_matrix(&self) -> &Mat4 {
     &self.perspective_matrix
   }
 
-  #[allow(dead_code)]
+  /// Includes TAA jitter. Use only for rendering
+  pub fn jittered_perspective_matrix(&self) -> &Mat4 {
+    &self.jittered_perspective_matrix
+  }
+
+  /// Without jitter
   pub fn view_projection_matrix(&self) -> Mat4 {
-    let v = self.view_matrix();
-    let p = self.perspective_matrix();
-    p.mul_mat4(&v)
+    self.perspective_matrix.mul_mat4(&self.view_matrix)
+  }
+
+  /// Includes TAA jitter. Use only for rendering
+  pub fn jittered_view_projection_matrix(&self) -> Mat4 {
+    self.jittered_perspective_matrix.mul_mat4(&self.view_matrix)
+  }
+
+  pub fn prev_view_projection_matrix(&self) -> &Mat4 {
+    &self.prev_view_projection_matrix
+  }
+
+  /// In NDC
+  pub fn jitter(&self) -> Vec2 {
+    self.jitter
+  }
+
+  /// Move the projection by a subpixel offset. `jitter_px` is in pixels,
+  /// use `Vec2::ZERO` to disable.
+  pub fn set_jitter(&mut self, jitter_px: Vec2, viewport_size: vk::Extent2D) {
+    self.jitter = vec2(
+      2.0 * jitter_px.x / viewport_size.width as f32,
+      2.0 * jitter_px.y / viewport_size.height as f32,
+    );
+    // `clip.w = -view.z`, so subtract to move NDC by `+jitter`
+    let mut p = self.perspective_matrix;
+    p.z_axis.x -= self.jitter.x;
+    p.z_axis.y -= self.jitter.y;
+    self.jittered_perspective_matrix = p;
+  }
+
+  /// Call after all per-frame uniforms were written
+  pub fn store_previous_frame_matrices(&mut self) {
+    self.prev_view_projection_matrix = self.view_projection_matrix();
+  }
+
+  /// World space ray from the camera through the pixel.
+  /// - `cursor` is in pixels, from the top left corner
+  /// - returns `(origin, normalized direction)`
+  pub fn get_cursor_ray(&self, cursor: Vec2, viewport_size: vk::Extent2D) -> (Vec3, Vec3) {
+    // viewport is flipped, see `create_viewport()`
+    let ndc_x = 2.0 * cursor.x / viewport_size.width as f32 - 1.0;
+    let ndc_y = 1.0 - 2.0 * cursor.y / viewport_size.height as f32;
+    let inv_vp = self.view_projection_matrix().inverse();
+    let near = inv_vp.project_point3(vec3(ndc_x, ndc_y, 0.0));
+    let far = inv_vp.project_point3(vec3(ndc_x, ndc_y, 1.0));
+    (self.position, (far - near).normalize())
   }
 
-  pub fn model_view_projection_matrix(&self, model_matrix: Mat4) -> Mat4 {
+  /// Includes TAA jitter. Use only for rendering
+  pub fn jittered_model_view_projection_matrix(&self, model_matrix: Mat4) -> Mat4 {
     let v = self.view_matrix();
-    let p = self.perspective_matrix();
+    let p = self.jittered_perspective_matrix();
     let m = model_matrix;
     Self::calc_model_view_projection_matrix(&m, v, p)
   }
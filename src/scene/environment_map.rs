@@ -0,0 +1,322 @@
+use std::f32::consts::PI;
+use std::io;
+use std::path::Path;
+
+use ash::vk;
+use glam::{vec2, vec3, Vec2, Vec3};
+use log::info;
+
+use crate::vk_ctx::VkCtx;
+use crate::vk_utils::VkTexture;
+
+use super::hdr_file::{load_hdr_file, HdrImage};
+
+/// Larger environment maps are downsampled to this width
+const BASE_WIDTH: u32 = 512;
+/// Samples per texel when prefiltering specular mips
+const PREFILTER_SAMPLE_COUNT: u32 = 64;
+/// Irradiance is low frequency, no need to integrate the full size image
+const IRRADIANCE_SOURCE_WIDTH: u32 = 64;
+
+/// Image-based lighting from an equirectangular HDR image. Everything is precomputed at load time:
+/// - `specular_tex` - mip `n` is the environment prefiltered with GGX lobe
+///   for `roughness = n / (mip_levels - 1)`. Mip 0 is used as a skybox.
+/// - `irradiance_sh` - diffuse irradiance as 2nd order spherical harmonics.
+///
+/// Direction to UV mapping must match `envDirectionToUv()` in `_ibl.glsl`.
+pub struct EnvironmentMap {
+  pub name: String,
+  /// `R32G32B32A32_SFLOAT`, equirectangular
+  pub specular_tex: VkTexture,
+  /// Already convolved with the cosine lobe and divided by PI.
+  /// Basis order must match `sampleEnvIrradiance()` in `_ibl.glsl`.
+  pub irradiance_sh: [Vec3; 9],
+}
+
+impl EnvironmentMap {
+  pub const SPECULAR_MIP_LEVELS: usize = 6;
+  pub const TEXTURE_FORMAT: vk::Format = vk::Format::R32G32B32A32_SFLOAT;
+
+  pub fn from_file(vk_ctx: &VkCtx, path: &Path) -> io::Result<Self> {
+    let image = EquirectImage::from_hdr(load_hdr_file(path)?);
+    let name = path.file_stem().map_or("environment".to_string(), |s| {
+      s.to_string_lossy().to_string()
+    });
+
+    // box-filtered pyramid, used as source for both specular and diffuse
+    let mut pyramid = vec![image];
+    while pyramid.last().unwrap().width > BASE_WIDTH {
+      let next = pyramid.last().unwrap().downsample();
+      pyramid = vec![next];
+    }
+    while pyramid.last().unwrap().width > 1 {
+      let next = pyramid.last().unwrap().downsample();
+      pyramid.push(next);
+    }
+
+    info!(
+      "Prefiltering environment map '{}' ({}x{}, {} mips)",
+      name,
+      pyramid[0].width,
+      pyramid[0].height,
+      Self::SPECULAR_MIP_LEVELS
+    );
+    let mip_count = Self::SPECULAR_MIP_LEVELS.min(pyramid.len());
+    let mips: Vec<Vec<u8>> = (0..mip_count)
+      .map(|mip| {
+        let roughness = mip as f32 / (mip_count - 1).max(1) as f32;
+        prefilter_specular(&pyramid, mip, roughness).to_bytes()
+      })
+      .collect();
+
+    let sh_source = pyramid
+      .iter()
+      .find(|img| img.width <= IRRADIANCE_SOURCE_WIDTH)
+      .unwrap_or(&pyramid[0]);
+    let irradiance_sh = project_irradiance_sh(sh_source);
+
+    let size = vk::Extent2D {
+      width: pyramid[0].width,
+      height: pyramid[0].height,
+    };
+    let specular_tex = vk_ctx.create_texture_from_mips_data(
+      format!("{}_env_specular", name),
+      size,
+      Self::TEXTURE_FORMAT,
+      &mips,
+    );
+
+    Ok(Self {
+      name,
+      specular_tex,
+      irradiance_sh,
+    })
+  }
+
+  pub unsafe fn destroy(&mut self, device: &ash::Device, allocator: &vma::Allocator) {
+    self.specular_tex.delete(device, allocator);
+  }
+}
+
+/// Must match `envDirectionToUv()` in `_ibl.glsl`
+fn direction_to_uv(dir: Vec3) -> Vec2 {
+  vec2(
+    0.5 + dir.z.atan2(dir.x) / (2.0 * PI),
+    dir.y.clamp(-1.0, 1.0).acos() / PI,
+  )
+}
+
+fn uv_to_direction(uv: Vec2) -> Vec3 {
+  let phi = (uv.x - 0.5) * 2.0 * PI;
+  let theta = uv.y * PI;
+  vec3(
+    theta.sin() * phi.cos(),
+    theta.cos(),
+    theta.sin() * phi.sin(),
+  )
+}
+
+struct EquirectImage {
+  width: u32,
+  height: u32,
+  pixels: Vec<Vec3>,
+}
+
+impl EquirectImage {
+  fn from_hdr(image: HdrImage) -> Self {
+    Self {
+      width: image.width,
+      height: image.height,
+      pixels: image.pixels,
+    }
+  }
+
+  /// Wraps horizontally, clamps vertically
+  fn texel(&self, x: i32, y: i32) -> Vec3 {
+    let x = x.rem_euclid(self.width as i32) as u32;
+    let y = y.clamp(0, self.height as i32 - 1) as u32;
+    self.pixels[(y * self.width + x) as usize]
+  }
+
+  fn texel_direction(&self, x: u32, y: u32) -> Vec3 {
+    let uv = vec2(
+      (x as f32 + 0.5) / self.width as f32,
+      (y as f32 + 0.5) / self.height as f32,
+    );
+    uv_to_direction(uv)
+  }
+
+  /// Bilinear
+  fn sample(&self, dir: Vec3) -> Vec3 {
+    let uv = direction_to_uv(dir);
+    let x = uv.x * self.width as f32 - 0.5;
+    let y = uv.y * self.height as f32 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i32, y0 as i32);
+    let top = self.texel(x0, y0).lerp(self.texel(x0 + 1, y0), tx);
+    let bottom = self.texel(x0, y0 + 1).lerp(self.texel(x0 + 1, y0 + 1), tx);
+    top.lerp(bottom, ty)
+  }
+
+  /// Half size, 2x2 box filter. Same size as the next mip.
+  fn downsample(&self) -> Self {
+    let width = (self.width / 2).max(1);
+    let height = (self.height / 2).max(1);
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+      for x in 0..width {
+        let (sx, sy) = ((x * 2) as i32, (y * 2) as i32);
+        let sum = self.texel(sx, sy)
+          + self.texel(sx + 1, sy)
+          + self.texel(sx, sy + 1)
+          + self.texel(sx + 1, sy + 1);
+        pixels.push(sum / 4.0);
+      }
+    }
+    Self {
+      width,
+      height,
+      pixels,
+    }
+  }
+
+  /// `R32G32B32A32_SFLOAT`
+  fn to_bytes(&self) -> Vec<u8> {
+    let size = vk::Extent2D {
+      width: self.width,
+      height: self.height,
+    };
+    VkTexture::create_texture_bytes(size, |_, _, idx| {
+      let p = self.pixels[idx as usize];
+      vec![p.x, p.y, p.z, 1.0]
+    })
+  }
+}
+
+/// GGX importance sampling with `N = V = R` assumption ('Real Shading in Unreal Engine 4').
+/// Each sample reads from the pyramid level based on sample's solid angle
+/// ('GPU-Based Importance Sampling', GPU Gems 3, chapter 20).
+fn prefilter_specular(pyramid: &[EquirectImage], mip: usize, roughness: f32) -> EquirectImage {
+  let base = &pyramid[0];
+  let width = (base.width >> mip).max(1);
+  let height = (base.height >> mip).max(1);
+  if mip == 0 {
+    return EquirectImage {
+      width,
+      height,
+      pixels: base.pixels.clone(),
+    };
+  }
+
+  let a = roughness * roughness;
+  let base_texel_solid_angle = 4.0 * PI / (base.width * base.height) as f32;
+  let target = EquirectImage {
+    width,
+    height,
+    pixels: Vec::new(),
+  };
+
+  let mut pixels = Vec::with_capacity((width * height) as usize);
+  for y in 0..height {
+    for x in 0..width {
+      let n = target.texel_direction(x, y);
+      let (tangent, bitangent) = n.any_orthonormal_pair();
+      let mut sum = Vec3::ZERO;
+      let mut weight_sum = 0.0;
+
+      for i in 0..PREFILTER_SAMPLE_COUNT {
+        let xi = hammersley(i, PREFILTER_SAMPLE_COUNT);
+        let phi = 2.0 * PI * xi.x;
+        let cos_theta = ((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y)).sqrt();
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let h =
+          tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + n * cos_theta;
+        let l = 2.0 * n.dot(h) * h - n;
+        let n_dot_l = n.dot(l);
+        if n_dot_l <= 0.0 {
+          continue;
+        }
+
+        // pdf of the sample, with N = V = H the D * NdotH / (4 * VdotH) simplifies to D / 4
+        let n_dot_h = cos_theta;
+        let d_denom = n_dot_h * n_dot_h * (a * a - 1.0) + 1.0;
+        let d = (a * a) / (PI * d_denom * d_denom).max(0.0001);
+        let pdf = d / 4.0;
+        let sample_solid_angle = 1.0 / (PREFILTER_SAMPLE_COUNT as f32 * pdf + 0.0001);
+        let lod = 0.5 * (sample_solid_angle / base_texel_solid_angle).log2() + 1.0;
+        let level = (lod.round().max(0.0) as usize).min(pyramid.len() - 1);
+
+        sum += pyramid[level].sample(l) * n_dot_l;
+        weight_sum += n_dot_l;
+      }
+
+      pixels.push(sum / weight_sum.max(0.0001));
+    }
+  }
+
+  EquirectImage {
+    width,
+    height,
+    pixels,
+  }
+}
+
+fn hammersley(i: u32, count: u32) -> Vec2 {
+  let radical_inverse = i.reverse_bits() as f32 * 2.328_306_4e-10; // / 0x100000000
+  vec2(i as f32 / count as f32, radical_inverse)
+}
+
+/// Must match `sampleEnvIrradiance()` in `_ibl.glsl`
+fn sh9_basis(dir: Vec3) -> [f32; 9] {
+  let Vec3 { x, y, z } = dir;
+  [
+    0.282095,
+    0.488603 * y,
+    0.488603 * z,
+    0.488603 * x,
+    1.092548 * x * y,
+    1.092548 * y * z,
+    0.315392 * (3.0 * z * z - 1.0),
+    1.092548 * x * z,
+    0.546274 * (x * x - y * y),
+  ]
+}
+
+/// 'An Efficient Representation for Irradiance Environment Maps' - Ramamoorthi, Hanrahan.
+/// Result is radiance projected into SH, convolved with the cosine lobe and divided by PI,
+/// so that diffuse is just `albedo * irradiance`.
+fn project_irradiance_sh(image: &EquirectImage) -> [Vec3; 9] {
+  // cosine lobe convolution (PI, 2PI/3, PI/4) divided by PI
+  const BAND_FACTORS: [f32; 9] = [
+    1.0,
+    2.0 / 3.0,
+    2.0 / 3.0,
+    2.0 / 3.0,
+    0.25,
+    0.25,
+    0.25,
+    0.25,
+    0.25,
+  ];
+  let mut coefficients = [Vec3::ZERO; 9];
+  let texel_angle = (2.0 * PI / image.width as f32) * (PI / image.height as f32);
+
+  for y in 0..image.height {
+    let theta = (y as f32 + 0.5) / image.height as f32 * PI;
+    let solid_angle = texel_angle * theta.sin();
+    for x in 0..image.width {
+      let dir = image.texel_direction(x, y);
+      let radiance = image.pixels[(y * image.width + x) as usize];
+      let basis = sh9_basis(dir);
+      for i in 0..9 {
+        coefficients[i] += radiance * (basis[i] * solid_angle);
+      }
+    }
+  }
+
+  for i in 0..9 {
+    coefficients[i] *= BAND_FACTORS[i];
+  }
+  coefficients
+}
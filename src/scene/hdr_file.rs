@@ -0,0 +1,300 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use glam::{vec3, Vec3};
+use log::{info, trace};
+
+/// Decoded Radiance `.hdr` (RGBE) image. First row is the top of the image.
+pub struct HdrImage {
+  pub width: u32,
+  pub height: u32,
+  pub pixels: Vec<Vec3>,
+}
+
+/// Load Radiance `.hdr` file. Only the standard `-Y height +X width` orientation
+/// is supported. Scanlines can be either flat or new-style run-length encoded.
+/// OpenEXR is not supported, `AppCli` rejects such files.
+///
+/// * http://radsite.lbl.gov/radiance/refer/filefmts.pdf
+/// * https://www.graphics.cornell.edu/~bjw/rgbe.html
+pub fn load_hdr_file(path: &Path) -> io::Result<HdrImage> {
+  info!("Loading HDR image from '{}'", path.display());
+  let file = File::open(path)?;
+  let mut reader = BufReader::new(file);
+
+  // header
+  let mut line = String::new();
+  let mut is_first_line = true;
+  loop {
+    line.clear();
+    let bytes_read = reader.read_line(&mut line)?;
+    let header_line = line.trim();
+    if bytes_read == 0 || header_line.is_empty() {
+      break;
+    }
+    trace!("HDR header: {}", header_line);
+    if is_first_line && !header_line.starts_with("#?") {
+      return Err(invalid_data("not a Radiance .hdr file".to_string()));
+    }
+    if header_line.starts_with("FORMAT=") && header_line != "FORMAT=32-bit_rle_rgbe" {
+      return Err(invalid_data(format!(
+        "unsupported format '{}'",
+        header_line
+      )));
+    }
+    is_first_line = false;
+  }
+
+  // resolution
+  line.clear();
+  reader.read_line(&mut line)?;
+  let (width, height) = parse_resolution(&line).ok_or_else(|| {
+    invalid_data(format!(
+      "unsupported resolution line '{}', expected '-Y <height> +X <width>'",
+      line.trim()
+    ))
+  })?;
+
+  // pixels
+  let mut data: Vec<u8> = Vec::new();
+  reader.read_to_end(&mut data)?;
+  let (width_px, height_px) = (width as usize, height as usize);
+  // checked before allocating, corrupted header could declare anything
+  let pixel_cnt = width_px.checked_mul(height_px);
+  let min_data_len = min_scanline_bytes(width_px).and_then(|len| len.checked_mul(height_px));
+  let (pixel_cnt, scanline_len) = match (pixel_cnt, min_data_len) {
+    (Some(pixel_cnt), Some(min_len)) if min_len <= data.len() => (pixel_cnt, width_px * 4),
+    _ => {
+      return Err(invalid_data(format!(
+        "resolution {}x{} does not fit in {} bytes of pixel data",
+        width,
+        height,
+        data.len()
+      )))
+    }
+  };
+  let mut pixels: Vec<Vec3> = Vec::with_capacity(pixel_cnt);
+  let mut offset = 0usize;
+  let mut scanline = vec![0u8; scanline_len];
+  for _ in 0..height {
+    offset = read_scanline(&data, offset, width_px, &mut scanline)?;
+    for rgbe in scanline.chunks_exact(4) {
+      pixels.push(rgbe_to_vec3(rgbe));
+    }
+  }
+
+  info!("Loaded HDR image {}x{}", width, height);
+  Ok(HdrImage {
+    width,
+    height,
+    pixels,
+  })
+}
+
+fn invalid_data(msg: String) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// @return `(width, height)`
+fn parse_resolution(line: &str) -> Option<(u32, u32)> {
+  let resolution: Vec<&str> = line.split_whitespace().collect();
+  if resolution.len() != 4 || resolution[0] != "-Y" || resolution[2] != "+X" {
+    return None;
+  }
+  let height: u32 = resolution[1].parse().ok()?;
+  let width: u32 = resolution[3].parse().ok()?;
+  if width == 0 || height == 0 {
+    return None;
+  }
+  Some((width, height))
+}
+
+/// Smallest possible size of the encoded scanline. Run-length encoded scanline
+/// stores at most 127 values per 2 byte run, for each of 4 channels.
+fn min_scanline_bytes(width: usize) -> Option<usize> {
+  if is_rle_width(width) {
+    Some(4 + 4 * 2 * width.div_ceil(127))
+  } else {
+    width.checked_mul(4)
+  }
+}
+
+fn is_rle_width(width: usize) -> bool {
+  (8..0x7fff).contains(&width)
+}
+
+/// Fills `scanline` with `[r,g,b,e]` values.
+/// @return offset of the next scanline
+fn read_scanline(
+  data: &[u8],
+  offset: usize,
+  width: usize,
+  scanline: &mut [u8],
+) -> io::Result<usize> {
+  let read_byte = |idx: usize| -> io::Result<u8> {
+    data.get(idx).copied().ok_or_else(|| {
+      io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "unexpected end of file when reading pixels",
+      )
+    })
+  };
+
+  let is_rle = is_rle_width(width)
+    && read_byte(offset)? == 2
+    && read_byte(offset + 1)? == 2
+    && (read_byte(offset + 2)? & 0x80) == 0;
+  if !is_rle {
+    let len = width * 4;
+    let flat = data.get(offset..offset + len).ok_or_else(|| {
+      io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "unexpected end of file when reading pixels",
+      )
+    })?;
+    scanline.copy_from_slice(flat);
+    return Ok(offset + len);
+  }
+
+  let encoded_width = ((read_byte(offset + 2)? as usize) << 8) | read_byte(offset + 3)? as usize;
+  if encoded_width != width {
+    return Err(invalid_data(format!(
+      "invalid scanline width {}, expected {}",
+      encoded_width, width
+    )));
+  }
+
+  // each channel is encoded separately
+  let mut offset = offset + 4;
+  for channel in 0..4 {
+    let mut x = 0;
+    while x < width {
+      let count = read_byte(offset)? as usize;
+      offset += 1;
+      if count > 128 {
+        // run of the same value
+        let count = count - 128;
+        let value = read_byte(offset)?;
+        offset += 1;
+        for i in 0..count.min(width - x) {
+          scanline[(x + i) * 4 + channel] = value;
+        }
+        x += count;
+      } else {
+        // raw values
+        if count == 0 {
+          return Err(invalid_data(
+            "invalid run-length encoding, zero length run".to_string(),
+          ));
+        }
+        for i in 0..count {
+          let value = read_byte(offset + i)?;
+          if x + i < width {
+            scanline[(x + i) * 4 + channel] = value;
+          }
+        }
+        offset += count;
+        x += count;
+      }
+    }
+  }
+  Ok(offset)
+}
+
+fn rgbe_to_vec3(rgbe: &[u8]) -> Vec3 {
+  let exponent = rgbe[3];
+  if exponent == 0 {
+    return Vec3::ZERO;
+  }
+  let f = 2.0f32.powi(exponent as i32 - (128 + 8));
+  vec3(rgbe[0] as f32, rgbe[1] as f32, rgbe[2] as f32) * f
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const WIDTH: usize = 8;
+
+  fn expected_scanline() -> Vec<u8> {
+    (0..WIDTH)
+      .flat_map(|x| [x as u8, 10, 20 + x as u8, 128])
+      .collect()
+  }
+
+  /// Channels: r - raw values, g - single run, b - raw values, e - two runs
+  fn rle_scanline() -> Vec<u8> {
+    let mut data = vec![2, 2, 0, WIDTH as u8];
+    data.push(WIDTH as u8);
+    data.extend(0..WIDTH as u8);
+    data.extend([128 + WIDTH as u8, 10]);
+    data.push(WIDTH as u8);
+    data.extend(20..20 + WIDTH as u8);
+    data.extend([128 + 3, 128, 128 + 5, 128]);
+    data
+  }
+
+  #[test]
+  fn read_flat_scanline() {
+    // width below 8 is never run-length encoded
+    let data: Vec<u8> = (0..16).collect();
+    let mut scanline = vec![0u8; 4 * 4];
+    let offset = read_scanline(&data, 0, 4, &mut scanline).unwrap();
+    assert_eq!(offset, 16);
+    assert_eq!(scanline, data);
+  }
+
+  #[test]
+  fn read_rle_scanline() {
+    let data = rle_scanline();
+    let mut scanline = vec![0u8; WIDTH * 4];
+    let offset = read_scanline(&data, 0, WIDTH, &mut scanline).unwrap();
+    assert_eq!(offset, data.len());
+    assert_eq!(scanline, expected_scanline());
+  }
+
+  #[test]
+  fn read_scanline_rejects_truncated_data() {
+    let flat: Vec<u8> = (0..15).collect();
+    let mut scanline = vec![0u8; 4 * 4];
+    let err = read_scanline(&flat, 0, 4, &mut scanline).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+    let rle = rle_scanline();
+    let mut scanline = vec![0u8; WIDTH * 4];
+    for len in 4..rle.len() {
+      let err = read_scanline(&rle[..len], 0, WIDTH, &mut scanline).unwrap_err();
+      assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof, "length {}", len);
+    }
+  }
+
+  #[test]
+  fn read_rle_scanline_rejects_invalid_data() {
+    let mut scanline = vec![0u8; WIDTH * 4];
+
+    let mut wrong_width = rle_scanline();
+    wrong_width[3] = WIDTH as u8 + 1;
+    let err = read_scanline(&wrong_width, 0, WIDTH, &mut scanline).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let mut zero_run = rle_scanline();
+    zero_run[4] = 0;
+    let err = read_scanline(&zero_run, 0, WIDTH, &mut scanline).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+  }
+
+  #[test]
+  fn load_hdr_rejects_resolution_larger_than_data() {
+    let path = std::env::temp_dir().join(format!("hdr-file-{}-huge.hdr", std::process::id()));
+    let mut content =
+      b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 4000000000 +X 4000000000\n".to_vec();
+    content.extend([0u8; 64]);
+    std::fs::write(&path, content).unwrap();
+
+    let result = load_hdr_file(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(result.err().unwrap().kind(), io::ErrorKind::InvalidData);
+  }
+}
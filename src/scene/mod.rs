@@ -1,14 +1,16 @@
 use std::path::Path;
 
 use ash::vk;
-use glam::vec4;
 use glam::Mat4;
 use glam::Vec2;
 use glam::Vec3;
+use glam::Vec4;
+use log::error;
 use log::info;
 use log::trace;
 use tobj;
 
+use crate::app_cli::AppCli;
 use crate::config::Config;
 use crate::render_graph::RenderableVertex;
 use crate::vk_ctx::VkCtx;
@@ -16,6 +18,7 @@ use crate::vk_utils::*;
 
 pub use self::bounding_box::*;
 pub use self::camera::*;
+pub use self::environment_map::*;
 pub use self::material::*;
 pub use self::tressfx::*;
 pub use self::world::*;
@@ -23,12 +26,14 @@ pub use self::world_entity::*;
 
 mod bounding_box;
 mod camera;
+mod environment_map;
+mod hdr_file;
 mod material;
 mod tressfx;
 mod world;
 mod world_entity;
 
-pub fn load_scene(vk_ctx: &VkCtx, config: &Config) -> World {
+pub fn load_scene(vk_ctx: &VkCtx, config: &Config, app_cli: &AppCli) -> World {
   let scale = config.model_scale;
   let frames_in_flight = config.frames_in_flight;
   let model_matrix = Mat4::from_scale(Vec3::new(scale, scale, scale));
@@ -36,27 +41,107 @@ pub fn load_scene(vk_ctx: &VkCtx, config: &Config) -> World {
   let sintel_eyes = load_sintel_eyes(vk_ctx, frames_in_flight, model_matrix);
 
   // tressfx
-  let sintel_tfx_file = load_tressfx_file(std::path::Path::new(
-    "./assets/sintel_lite_v2_1/GEO-sintel_hair_emit.002-sintel_hair.tfx",
-  ));
-  let mut sintel_hair = TfxObject::from_file(
+  let tressfx_objects = load_sintel_hair_groups(vk_ctx, config, app_cli, model_matrix);
+
+  // image based lighting
+  let environment =
+    app_cli.environment_map.as_ref().and_then(|path| {
+      match EnvironmentMap::from_file(vk_ctx, Path::new(path)) {
+        Ok(env) => Some(env),
+        Err(err) => {
+          error!("Failed to load environment map '{}': {}", path, err);
+          None
+        }
+      }
+    });
+
+  World {
+    camera: Camera::new(config, vk_ctx.window_size()),
+    entities: vec![sintel, sintel_eyes],
+    tressfx_objects,
+    environment,
+  }
+}
+
+/// Main hair asset + additional groups from command line. Each group is a separate `TfxObject`
+/// with it's own material, follow hairs, simulation settings and colliders. Main asset uses
+/// Sintel's head colliders, additional groups use the ones provided on the command line.
+fn load_sintel_hair_groups(
+  vk_ctx: &VkCtx,
+  config: &Config,
+  app_cli: &AppCli,
+  model_matrix: Mat4,
+) -> Vec<TfxObject> {
+  let hair_asset = app_cli
+    .hair_asset
+    .as_deref()
+    .unwrap_or("./assets/sintel_lite_v2_1/GEO-sintel_hair_emit.002-sintel_hair.tfx");
+  let mut main_hair = load_hair_group(
     vk_ctx,
     config,
+    app_cli,
     "sintel_hair",
+    hair_asset,
     model_matrix,
-    &sintel_tfx_file,
   );
-  sintel_hair.center_of_gravity.y = 9.0; // just below the eyes
-  sintel_hair.collision_capsule0 = vec4(0.0, 36.65, -1.3, 9.27);
-  sintel_hair.collision_capsule1 = vec4(0.0, 35.81, 2.38, 8.97);
-  sintel_hair.collision_capsule2 = vec4(0.0, 26.56, 2.75, 8.89);
-  sintel_hair.scale_debug_use_only = scale;
+  main_hair.center_of_gravity = SINTEL_HEAD_CENTER_OF_GRAVITY;
+  set_collision_capsules(&mut main_hair, &SINTEL_HEAD_COLLISION_CAPSULES);
+  let mut groups = vec![main_hair];
 
-  World {
-    camera: Camera::new(config, vk_ctx.window_size()),
-    entities: vec![sintel, sintel_eyes],
-    tressfx_objects: vec![sintel_hair],
+  for group in &app_cli.hair_groups {
+    let path = Path::new(&group.file_path);
+    let file_stem = path.file_stem().map_or("hair_group".to_string(), |s| {
+      s.to_string_lossy().to_string()
+    });
+    // names are used for profiler scopes and UI ids, so they have to be unique
+    let name = format!("{}_{}", file_stem, groups.len());
+    let mut tfx = load_hair_group(
+      vk_ctx,
+      config,
+      app_cli,
+      &name,
+      &group.file_path,
+      model_matrix,
+    );
+    tfx.simulation.enabled = group.simulated;
+    // if not provided, normals point away from the center of the group
+    tfx.center_of_gravity = group.center_of_gravity.unwrap_or(tfx.bounding_sphere.0);
+    set_collision_capsules(&mut tfx, &group.collision_capsules);
+    groups.push(tfx);
   }
+
+  groups
+}
+
+/// Model space. Just below the eyes
+const SINTEL_HEAD_CENTER_OF_GRAVITY: Vec3 = Vec3::new(0.0, 9.0, 0.0);
+/// Model space, `[center.xyz, radius]`
+const SINTEL_HEAD_COLLISION_CAPSULES: [Vec4; 3] = [
+  Vec4::new(0.0, 36.65, -1.3, 9.27),
+  Vec4::new(0.0, 35.81, 2.38, 8.97),
+  Vec4::new(0.0, 26.56, 2.75, 8.89),
+];
+
+fn load_hair_group(
+  vk_ctx: &VkCtx,
+  config: &Config,
+  app_cli: &AppCli,
+  name: &str,
+  file_path: &str,
+  model_matrix: Mat4,
+) -> TfxObject {
+  let tfx_file = try_load_hair_file(Path::new(file_path), app_cli.short_strand_policy())
+    .unwrap_or_else(|err| panic!("Failed to load hair asset '{}': {}", file_path, err));
+  TfxObject::from_file(vk_ctx, config, name, model_matrix, &tfx_file)
+}
+
+/// Capsules that are not provided are disabled (radius 0)
+fn set_collision_capsules(tfx: &mut TfxObject, capsules: &[Vec4]) {
+  let get_capsule = |i: usize| capsules.get(i).copied().unwrap_or(Vec4::ZERO);
+  tfx.collision_capsule0 = get_capsule(0);
+  tfx.collision_capsule1 = get_capsule(1);
+  tfx.collision_capsule2 = get_capsule(2);
+  tfx.collision_capsule3 = get_capsule(3);
 }
 
 fn load_sintel(vk_ctx: &VkCtx, frames_in_flight: usize, model_matrix: Mat4) -> WorldEntity {
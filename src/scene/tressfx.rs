@@ -1,9 +1,28 @@
+mod tfx_export;
 mod tfx_file_data;
+mod tfx_file_import;
 mod tfx_file_load;
+mod tfx_file_save;
+mod tfx_follow_hairs;
+mod tfx_groom;
+mod tfx_lod;
 mod tfx_material;
 mod tfx_object;
+mod tfx_simulation;
+mod tfx_simulation_cache;
+mod tfx_strand_inspector;
+mod tfx_strand_modifiers;
 
+pub use tfx_export::*;
 pub use tfx_file_data::*;
-pub use tfx_file_load::*;
+pub use tfx_file_import::*;
+pub use tfx_file_save::*;
+pub use tfx_follow_hairs::*;
+pub use tfx_groom::*;
+pub use tfx_lod::*;
 pub use tfx_material::*;
 pub use tfx_object::*;
+pub use tfx_simulation::*;
+pub use tfx_simulation_cache::*;
+pub use tfx_strand_inspector::*;
+pub use tfx_strand_modifiers::*;
@@ -0,0 +1,195 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use glam::Vec4;
+use log::info;
+
+use super::{get_follow_hair_position, save_tressfx_file, TfxFileData, TfxObject};
+
+/// File format is determined by extension.
+pub enum TfxExportFormat {
+  /// TressFX `.tfx`
+  Tfx,
+  /// Wavefront `.obj` with each strand as a polyline (`l` element)
+  ObjPolylines,
+  /// Cem Yuksel's `.hair`: http://www.cemyuksel.com/research/hairmodels/
+  CyHair,
+}
+
+impl TfxExportFormat {
+  pub fn from_path(path: &Path) -> Option<Self> {
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    match ext.as_str() {
+      "tfx" => Some(Self::Tfx),
+      "obj" => Some(Self::ObjPolylines),
+      "hair" => Some(Self::CyHair),
+      _ => None,
+    }
+  }
+}
+
+pub struct TfxExportSettings {
+  /// Format is based on the extension. See `TfxExportFormat`
+  pub file_path: String,
+  /// Export also procedurally generated follow hairs, not only guide strands
+  pub include_follow_hairs: bool,
+  /// Export requires GPU readback, so it's done after the frame
+  pub export_next_frame: bool,
+}
+
+impl TfxExportSettings {
+  pub fn new(name: &str) -> Self {
+    Self {
+      file_path: format!("./{}_export.obj", name),
+      include_follow_hairs: false,
+      export_next_frame: false,
+    }
+  }
+}
+
+/// Strands in world space. All strands have the same vertex count.
+pub struct TfxExportStrands {
+  pub num_vertices_per_strand: u32,
+  /// `[position.xyz, w from simulation]` for each vertex
+  pub positions: Vec<Vec4>,
+}
+
+impl TfxExportStrands {
+  pub fn num_strands(&self) -> u32 {
+    self.positions.len() as u32 / self.num_vertices_per_strand
+  }
+
+  /// Same transforms as the vertex shader: `model_matrix` and follow hairs.
+  /// See `get_follow_hair_position()`. `TfxStrandModifiers` are render-only and are not exported.
+  ///
+  /// @param positions raw simulation positions, `FLOAT4[numHairStrands * numVerticesPerStrand]`
+  pub fn from_simulation(tfx: &TfxObject, positions: &[f32], include_follow_hairs: bool) -> Self {
+    let verts_per_strand = tfx.num_vertices_per_strand;
+    let instance_count = if include_follow_hairs {
+      tfx.follow_hairs
+    } else {
+      1
+    };
+    let mut result = Vec::with_capacity((tfx.vertex_count() * instance_count) as _);
+
+    for instance_id in 0..instance_count {
+      for strand_id in 0..tfx.num_hair_strands {
+        for vertex_id in 0..verts_per_strand {
+          let idx = ((strand_id * verts_per_strand + vertex_id) * 4) as usize;
+          let w = positions[idx + 3];
+          let pos = get_follow_hair_position(tfx, positions, instance_id, strand_id, vertex_id);
+          result.push(pos.extend(w));
+        }
+      }
+    }
+
+    Self {
+      num_vertices_per_strand: verts_per_strand,
+      positions: result,
+    }
+  }
+}
+
+/// Write strands to a file. Format is based on file extension.
+pub fn export_strands(path: &Path, strands: &TfxExportStrands) -> std::io::Result<()> {
+  info!(
+    "Exporting {} hair strands to '{}'",
+    strands.num_strands(),
+    path.to_string_lossy()
+  );
+
+  match TfxExportFormat::from_path(path) {
+    Some(TfxExportFormat::Tfx) => save_tressfx_file(path, &to_tfx_file_data(strands)),
+    Some(TfxExportFormat::ObjPolylines) => write_obj_polylines(path, strands),
+    Some(TfxExportFormat::CyHair) => write_cyhair(path, strands),
+    None => Err(std::io::Error::new(
+      std::io::ErrorKind::InvalidInput,
+      format!(
+        "Unsupported hair export format for '{}'. Expected .tfx, .obj or .hair",
+        path.to_string_lossy()
+      ),
+    )),
+  }
+}
+
+fn to_tfx_file_data(strands: &TfxExportStrands) -> TfxFileData {
+  let raw_vertex_positions = strands
+    .positions
+    .iter()
+    .flat_map(|p| p.to_array())
+    .collect::<Vec<_>>();
+
+  TfxFileData {
+    version: 4.0,
+    num_hair_strands: strands.num_strands(),
+    num_vertices_per_strand: strands.num_vertices_per_strand,
+    offset_vertex_position: 0,
+    offset_strand_uv: 0,
+    offset_vertex_uv: 0,
+    offset_strand_thickness: 0,
+    offset_vertex_color: 0,
+    raw_vertex_positions,
+    raw_vertex_weights: Vec::new(),
+  }
+}
+
+fn write_obj_polylines(path: &Path, strands: &TfxExportStrands) -> std::io::Result<()> {
+  let file = File::create(path)?;
+  let mut w = BufWriter::new(file);
+
+  writeln!(w, "# Exported from rs-tressfx")?;
+  writeln!(
+    w,
+    "# {} strands, {} vertices each",
+    strands.num_strands(),
+    strands.num_vertices_per_strand
+  )?;
+  for p in &strands.positions {
+    writeln!(w, "v {} {} {}", p.x, p.y, p.z)?;
+  }
+
+  let verts_per_strand = strands.num_vertices_per_strand as usize;
+  for strand_id in 0..(strands.num_strands() as usize) {
+    write!(w, "l")?;
+    for vertex_id in 0..verts_per_strand {
+      // OBJ indices start at 1
+      write!(w, " {}", strand_id * verts_per_strand + vertex_id + 1)?;
+    }
+    writeln!(w)?;
+  }
+
+  w.flush()
+}
+
+/// Header is 128 bytes. We always use the same segment count for every strand,
+/// so there is no segments array. Only the points array is written.
+fn write_cyhair(path: &Path, strands: &TfxExportStrands) -> std::io::Result<()> {
+  const FLAG_HAS_POINTS: u32 = 1 << 1;
+  const FILE_INFO_SIZE: usize = 88;
+
+  let file = File::create(path)?;
+  let mut w = BufWriter::new(file);
+
+  let default_segments = strands.num_vertices_per_strand - 1;
+  w.write_all(b"HAIR")?;
+  w.write_all(&strands.num_strands().to_le_bytes())?;
+  w.write_all(&(strands.positions.len() as u32).to_le_bytes())?;
+  w.write_all(&FLAG_HAS_POINTS.to_le_bytes())?;
+  w.write_all(&default_segments.to_le_bytes())?;
+  w.write_all(&1.0f32.to_le_bytes())?; // default thickness
+  w.write_all(&0.0f32.to_le_bytes())?; // default transparency
+  for _ in 0..3 {
+    w.write_all(&1.0f32.to_le_bytes())?; // default color
+  }
+  let mut file_info = [0u8; FILE_INFO_SIZE];
+  let info = b"Exported from rs-tressfx";
+  file_info[..info.len()].copy_from_slice(info);
+  w.write_all(&file_info)?;
+
+  for p in &strands.positions {
+    w.write_all(bytemuck::cast_slice(&p.truncate().to_array()))?;
+  }
+
+  w.flush()
+}
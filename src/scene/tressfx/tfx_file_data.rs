@@ -5,6 +5,7 @@ use glam::{vec3, Vec3};
 /// ### Offsets
 /// Offset values are in bytes, aligned on 8 bytes boundaries,
 /// and relative to beginning of the .tfx file. **ALL ARE UNSIGNED INTS!**
+#[derive(Clone)]
 pub struct TfxFileData {
   /// Specifies TressFX version number
   pub version: f32,
@@ -34,13 +35,34 @@ pub struct TfxFileData {
 
   /// vertex positions - ready to shove onto GPU
   pub raw_vertex_positions: Vec<f32>,
+  /// Per-vertex simulation weights - ready to shove onto GPU. Layout for each vertex:
+  /// `[global stiffness multiplier, local stiffness multiplier, -, pinned]`.
+  ///
+  /// Read from a separate `.tfxweights` file, defaults otherwise.
+  /// Vertex with `pinned > 0.5` is not affected by the simulation.
+  pub raw_vertex_weights: Vec<f32>,
 }
 
 impl TfxFileData {
+  /// `TressFXTFXFileHeader` is 8 values followed by `unsigned int reserved[32]`.
+  pub const HEADER_SIZE: u32 = 8 * 4 + 32 * 4;
+  /// Weights used if the asset does not provide them.
+  pub const DEFAULT_VERTEX_WEIGHTS: [f32; 4] = [1.0, 1.0, 1.0, 0.0];
+
   pub fn total_vertices(&self) -> u32 {
     self.num_vertices_per_strand * self.num_hair_strands
   }
 
+  pub fn fill_default_vertex_weights(&mut self) {
+    let total_vertices = self.total_vertices() as usize;
+    self.raw_vertex_weights.clear();
+    for _ in 0..total_vertices {
+      self
+        .raw_vertex_weights
+        .extend_from_slice(&Self::DEFAULT_VERTEX_WEIGHTS);
+    }
+  }
+
   pub fn get_vertex_pos(&self, idx: usize) -> Vec3 {
     vec3(
       self.raw_vertex_positions[idx * 4],
@@ -49,6 +71,13 @@ impl TfxFileData {
       // self.raw_vertex_positions[idx * 4 + 3],
     )
   }
+
+  /// Keeps `w` (used by the simulation) as-is
+  pub fn set_vertex_pos(&mut self, idx: usize, pos: Vec3) {
+    self.raw_vertex_positions[idx * 4] = pos.x;
+    self.raw_vertex_positions[idx * 4 + 1] = pos.y;
+    self.raw_vertex_positions[idx * 4 + 2] = pos.z;
+  }
 }
 
 impl Display for TfxFileData {
@@ -0,0 +1,576 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use glam::Vec3;
+use log::{info, trace, warn};
+
+use super::tfx_file_data::TfxFileData;
+use super::tfx_file_load::{
+  invalid_data, try_load_tressfx_file, try_read_float_array, try_read_uint,
+};
+
+/// Same as required by `try_load_tressfx_file()`. Simulation work scheduling depends on it.
+pub const IMPORT_VERTICES_PER_STRAND: usize = 32;
+
+/// What to do with strands that have less points than `IMPORT_VERTICES_PER_STRAND`.
+pub enum TfxShortStrandPolicy {
+  /// Skip the strand
+  Drop,
+  /// Insert new points along the strand (arc-length). Shape does not change.
+  Upsample,
+}
+
+/// Load hair asset. Format is based on extension:
+/// - `.tfx` - TressFX, loaded as-is,
+/// - `.hair` - Cem Yuksel's format: http://www.cemyuksel.com/research/hairmodels/
+/// - `.obj` - polylines (`l` elements). Other elements are ignored.
+///
+/// Strands from `.hair` and `.obj` are resampled to `IMPORT_VERTICES_PER_STRAND`
+/// points with uniform arc-length spacing. Strands with less than 2 points are always dropped.
+///
+/// Unreadable or invalid file is an error, the caller decides if it's fatal.
+pub fn try_load_hair_file(
+  path: &Path,
+  short_strands: TfxShortStrandPolicy,
+) -> io::Result<TfxFileData> {
+  let ext = path
+    .extension()
+    .map(|e| e.to_string_lossy().to_lowercase())
+    .unwrap_or_default();
+
+  let strands = match ext.as_str() {
+    "tfx" => return try_load_tressfx_file(path),
+    "hair" => load_cyhair_file(path)?,
+    "obj" => load_obj_polylines_file(path)?,
+    _ => {
+      return Err(invalid_data(
+        "unsupported format, expected .tfx, .hair or .obj".to_string(),
+      ))
+    }
+  };
+
+  let tfx_data = resample_strands(&strands, IMPORT_VERTICES_PER_STRAND, short_strands);
+  if tfx_data.num_hair_strands == 0 {
+    return Err(invalid_data(
+      "file does not contain any usable strands".to_string(),
+    ));
+  }
+  trace!("{}", tfx_data);
+  Ok(tfx_data)
+}
+
+/// Header is 128 bytes, followed by optional arrays (based on flags):
+/// segments `u16[hairCount]`, points `FLOAT3[pointCount]`, thickness, transparency, colors.
+/// Strand has `segments + 1` points. We only need points.
+///
+/// Counts from the header are validated against the file size before anything is allocated.
+fn load_cyhair_file(path: &Path) -> io::Result<Vec<Vec<Vec3>>> {
+  const FLAG_HAS_SEGMENTS: u32 = 1 << 0;
+  const FLAG_HAS_POINTS: u32 = 1 << 1;
+  const HEADER_SIZE: u64 = 128;
+
+  info!(
+    "Loading Cem Yuksel hair asset from '{}'",
+    path.to_string_lossy()
+  );
+
+  let file = File::open(path)?;
+  let file_size = file.metadata()?.len();
+  let mut r = BufReader::new(file);
+
+  let mut magic = [0u8; 4];
+  r.read_exact(&mut magic)?;
+  if &magic != b"HAIR" {
+    return Err(invalid_data("not a .hair file".to_string()));
+  }
+  let hair_count = try_read_uint(&mut r)? as u64;
+  let point_count = try_read_uint(&mut r)? as u64;
+  let flags = try_read_uint(&mut r)?;
+  let default_segments = try_read_uint(&mut r)? as u64;
+  if flags & FLAG_HAS_POINTS == 0 {
+    return Err(invalid_data(
+      "file does not contain hair points".to_string(),
+    ));
+  }
+
+  // each strand has at least 1 point, so `hair_count` is bounded too
+  let has_segments = flags & FLAG_HAS_SEGMENTS != 0;
+  let segments_bytes = if has_segments { hair_count * 2 } else { 0 };
+  let points_bytes = point_count * 3 * std::mem::size_of::<f32>() as u64;
+  let data_bytes = file_size.saturating_sub(HEADER_SIZE);
+  if hair_count > point_count || segments_bytes + points_bytes > data_bytes {
+    return Err(invalid_data(format!(
+      "header declares {} strands and {} points, which does not fit in {} bytes of data",
+      hair_count, point_count, data_bytes
+    )));
+  }
+  let hair_count = hair_count as usize;
+  let point_count = point_count as usize;
+
+  r.seek(SeekFrom::Start(HEADER_SIZE))?;
+  let segments: Vec<usize> = if has_segments {
+    let mut bytes = vec![0u8; hair_count * 2];
+    r.read_exact(&mut bytes)?;
+    bytes
+      .chunks_exact(2)
+      .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+      .collect()
+  } else {
+    vec![default_segments as usize; hair_count]
+  };
+
+  let mut coords: Vec<f32> = Vec::with_capacity(point_count * 3);
+  try_read_float_array(
+    &mut r,
+    &mut coords,
+    HEADER_SIZE + segments_bytes,
+    point_count * 3,
+  )?;
+  let points: Vec<Vec3> = coords
+    .chunks_exact(3)
+    .map(|p| Vec3::new(p[0], p[1], p[2]))
+    .collect();
+
+  let mut strands = Vec::with_capacity(hair_count);
+  let mut offset = 0;
+  for segment_count in segments {
+    let end = offset + segment_count + 1;
+    if end > points.len() {
+      return Err(invalid_data(
+        "file has less points than declared by segments".to_string(),
+      ));
+    }
+    strands.push(points[offset..end].to_vec());
+    offset = end;
+  }
+  Ok(strands)
+}
+
+/// Each `l` element is a strand. Consecutive single-segment `l` elements that continue
+/// previous one (e.g. `l 1 2`, `l 2 3` - how Blender exports curves as edges) are merged.
+/// Full polylines are never merged, even if they share an end vertex.
+fn load_obj_polylines_file(path: &Path) -> io::Result<Vec<Vec<Vec3>>> {
+  info!("Loading OBJ hair curves from '{}'", path.to_string_lossy());
+
+  let file = File::open(path)?;
+  let r = BufReader::new(file);
+
+  let mut vertices: Vec<Vec3> = Vec::new();
+  let mut lines: Vec<Vec<usize>> = Vec::new();
+  let mut prev_was_edge = false;
+
+  for (line_idx, line) in r.lines().enumerate() {
+    let line = line?;
+    let mut tokens = line.split_whitespace();
+    let element = tokens.next();
+    let is_edge = match element {
+      Some("v") => {
+        let coords: Vec<f32> = tokens.take(3).filter_map(|t| t.parse().ok()).collect();
+        if coords.len() != 3 {
+          return Err(invalid_data(format!(
+            "invalid vertex at line {}",
+            line_idx + 1
+          )));
+        }
+        vertices.push(Vec3::new(coords[0], coords[1], coords[2]));
+        false
+      }
+      Some("l") => {
+        let indices = tokens
+          .map(|t| {
+            parse_obj_index(t, vertices.len()).ok_or_else(|| {
+              invalid_data(format!(
+                "invalid line element index '{}' at line {}",
+                t,
+                line_idx + 1
+              ))
+            })
+          })
+          .collect::<io::Result<Vec<usize>>>()?;
+        let is_edge = indices.len() == 2;
+        let continues_previous = match (lines.last(), indices.first()) {
+          (Some(prev), Some(first)) => prev_was_edge && is_edge && prev.last() == Some(first),
+          _ => false,
+        };
+        if continues_previous {
+          lines.last_mut().unwrap().extend_from_slice(&indices[1..]);
+        } else {
+          lines.push(indices);
+        }
+        is_edge
+      }
+      // comments and empty lines do not break the edge chain
+      None | Some("#") => prev_was_edge,
+      _ => false,
+    };
+    prev_was_edge = is_edge;
+  }
+
+  Ok(
+    lines
+      .iter()
+      .map(|indices| indices.iter().map(|idx| vertices[*idx]).collect())
+      .collect(),
+  )
+}
+
+/// OBJ indices start at 1. Negative values are relative to the last vertex.
+/// `l` elements can also have texture coordinates: `v/vt`.
+/// Returns `None` for `0` and for indices outside of already declared vertices.
+fn parse_obj_index(token: &str, vertex_count: usize) -> Option<usize> {
+  let idx: i64 = token.split('/').next()?.parse().ok()?;
+  let idx = match idx {
+    0 => return None,
+    i if i < 0 => vertex_count as i64 + i,
+    i => i - 1,
+  };
+  (0..vertex_count as i64)
+    .contains(&idx)
+    .then_some(idx as usize)
+}
+
+/// Relative strand length change after resampling that is still considered fine.
+/// More means the original strands had sharp bends between the samples.
+const RESAMPLE_LENGTH_TOLERANCE: f32 = 0.01;
+
+/// Resample every strand to `vertices_per_strand` points evenly spaced along the strand.
+/// Warns if the resampled strands differ in length from the originals by more than `RESAMPLE_LENGTH_TOLERANCE`.
+fn resample_strands(
+  strands: &[Vec<Vec3>],
+  vertices_per_strand: usize,
+  short_strands: TfxShortStrandPolicy,
+) -> TfxFileData {
+  let mut raw_vertex_positions = Vec::with_capacity(strands.len() * vertices_per_strand * 4);
+  let mut num_hair_strands = 0;
+  let mut dropped_strands = 0;
+  let mut max_length_error = 0.0f32;
+  let mut inaccurate_strands = 0;
+
+  for strand in strands {
+    let is_short = strand.len() < vertices_per_strand;
+    let can_upsample = matches!(short_strands, TfxShortStrandPolicy::Upsample);
+    let length = get_strand_length(strand);
+    if strand.len() < 2 || length <= f32::EPSILON || (is_short && !can_upsample) {
+      dropped_strands += 1;
+      continue;
+    }
+
+    let resampled = resample_strand(strand, vertices_per_strand);
+    let resampled_length = get_strand_length(&resampled);
+    let length_error = (resampled_length - length).abs() / length;
+    max_length_error = max_length_error.max(length_error);
+    if length_error > RESAMPLE_LENGTH_TOLERANCE {
+      inaccurate_strands += 1;
+    }
+
+    for (i, p) in resampled.iter().enumerate() {
+      // root is not movable
+      let w = if i == 0 { 0.0 } else { 1.0 };
+      raw_vertex_positions.extend_from_slice(&[p.x, p.y, p.z, w]);
+    }
+    num_hair_strands += 1;
+  }
+
+  if dropped_strands > 0 {
+    warn!(
+      "Dropped {} of {} hair strands (too short or less than {} points)",
+      dropped_strands,
+      strands.len(),
+      vertices_per_strand
+    );
+  }
+  info!(
+    "Resampled {} strands to {} vertices each. Max strand length error: {:.3}%",
+    num_hair_strands,
+    vertices_per_strand,
+    max_length_error * 100.0
+  );
+  if inaccurate_strands > 0 {
+    warn!(
+      "{} of {} resampled strands changed length by more than {:.1}%. Source strands have more detail than {} vertices can represent",
+      inaccurate_strands,
+      num_hair_strands,
+      RESAMPLE_LENGTH_TOLERANCE * 100.0,
+      vertices_per_strand
+    );
+  }
+
+  let mut tfx_data = TfxFileData {
+    version: 4.0,
+    num_hair_strands,
+    num_vertices_per_strand: vertices_per_strand as _,
+    offset_vertex_position: 0,
+    offset_strand_uv: 0,
+    offset_vertex_uv: 0,
+    offset_strand_thickness: 0,
+    offset_vertex_color: 0,
+    raw_vertex_positions,
+    raw_vertex_weights: Vec::new(),
+  };
+  tfx_data.fill_default_vertex_weights();
+  tfx_data
+}
+
+fn get_strand_length(strand: &[Vec3]) -> f32 {
+  strand.windows(2).map(|w| w[0].distance(w[1])).sum()
+}
+
+/// Arc-length parametrization. Points lie on the original polyline, so
+/// the resampled strand is slightly shorter if the original was curved between samples.
+pub fn resample_strand(strand: &[Vec3], point_count: usize) -> Vec<Vec3> {
+  let length = get_strand_length(strand);
+  let segment_length = length / (point_count - 1) as f32;
+
+  let mut result = Vec::with_capacity(point_count);
+  result.push(strand[0]);
+
+  let mut src_idx = 0;
+  let mut src_dist = 0.0; // distance along the strand to `strand[src_idx]`
+  for i in 1..(point_count - 1) {
+    let target_dist = segment_length * i as f32;
+    while src_idx < strand.len() - 2 {
+      let next_dist = src_dist + strand[src_idx].distance(strand[src_idx + 1]);
+      if next_dist >= target_dist {
+        break;
+      }
+      src_dist = next_dist;
+      src_idx += 1;
+    }
+
+    let a = strand[src_idx];
+    let b = strand[src_idx + 1];
+    let src_segment_length = a.distance(b);
+    let t = if src_segment_length > 0.0 {
+      ((target_dist - src_dist) / src_segment_length).clamp(0.0, 1.0)
+    } else {
+      0.0
+    };
+    result.push(a.lerp(b, t));
+  }
+
+  result.push(strand[strand.len() - 1]);
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Write;
+  use std::path::PathBuf;
+
+  use super::*;
+
+  fn write_temp_file(name: &str, content: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("tfx-import-{}-{}", std::process::id(), name));
+    File::create(&path).unwrap().write_all(content).unwrap();
+    path
+  }
+
+  /// Quarter circle, so resampling has to cut corners
+  fn curved_strand(point_count: usize) -> Vec<Vec3> {
+    (0..point_count)
+      .map(|i| {
+        let angle = std::f32::consts::FRAC_PI_2 * i as f32 / (point_count - 1) as f32;
+        Vec3::new(angle.cos(), angle.sin(), 0.0)
+      })
+      .collect()
+  }
+
+  fn straight_strand(point_count: usize) -> Vec<Vec3> {
+    (0..point_count)
+      .map(|i| Vec3::new(0.0, -(i as f32) * 0.5, 1.0))
+      .collect()
+  }
+
+  fn get_imported_strand_lengths(data: &TfxFileData) -> Vec<f32> {
+    let vertices_per_strand = data.num_vertices_per_strand as usize;
+    data
+      .raw_vertex_positions
+      .chunks_exact(vertices_per_strand * 4)
+      .map(|strand| {
+        let points: Vec<Vec3> = strand
+          .chunks_exact(4)
+          .map(|p| Vec3::new(p[0], p[1], p[2]))
+          .collect();
+        get_strand_length(&points)
+      })
+      .collect()
+  }
+
+  fn assert_length_close(actual: f32, expected: f32) {
+    assert!(
+      (actual - expected).abs() / expected <= RESAMPLE_LENGTH_TOLERANCE,
+      "Expected strand length {}, got {}",
+      expected,
+      actual
+    );
+  }
+
+  #[test]
+  fn resample_strand_preserves_length() {
+    for strand in [straight_strand(7), curved_strand(100), curved_strand(5)] {
+      for point_count in [4, 32, 64] {
+        let resampled = resample_strand(&strand, point_count);
+        assert_eq!(resampled.len(), point_count);
+        assert_eq!(resampled[0], strand[0]);
+        assert_eq!(resampled[point_count - 1], strand[strand.len() - 1]);
+        if strand.len() <= point_count {
+          // upsampling only inserts points on existing segments
+          assert_length_close(get_strand_length(&resampled), get_strand_length(&strand));
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn resample_strand_spacing_is_uniform() {
+    let resampled = resample_strand(&curved_strand(200), 32);
+    let expected = get_strand_length(&resampled) / 31.0;
+    for w in resampled.windows(2) {
+      assert!((w[0].distance(w[1]) - expected).abs() < expected * 0.01);
+    }
+  }
+
+  #[test]
+  fn short_strands_policy() {
+    let strands = vec![straight_strand(8), straight_strand(40), straight_strand(1)];
+    let data = resample_strands(&strands, 32, TfxShortStrandPolicy::Drop);
+    assert_eq!(data.num_hair_strands, 1);
+    let data = resample_strands(&strands, 32, TfxShortStrandPolicy::Upsample);
+    assert_eq!(data.num_hair_strands, 2);
+    assert_eq!(data.raw_vertex_positions.len(), 2 * 32 * 4);
+  }
+
+  fn cyhair_bytes(strands: &[Vec<Vec3>]) -> Vec<u8> {
+    let point_count: usize = strands.iter().map(|s| s.len()).sum();
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"HAIR");
+    bytes.extend_from_slice(&(strands.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&(point_count as u32).to_le_bytes());
+    bytes.extend_from_slice(&0b11u32.to_le_bytes()); // segments + points
+    bytes.extend_from_slice(&0u32.to_le_bytes()); // default segments
+    bytes.resize(128, 0);
+    for strand in strands {
+      bytes.extend_from_slice(&((strand.len() - 1) as u16).to_le_bytes());
+    }
+    for p in strands.iter().flatten() {
+      for v in p.to_array() {
+        bytes.extend_from_slice(&v.to_le_bytes());
+      }
+    }
+    bytes
+  }
+
+  #[test]
+  fn load_cyhair_round_trip() {
+    let strands = vec![straight_strand(5), curved_strand(64)];
+    let path = write_temp_file("round-trip.hair", &cyhair_bytes(&strands));
+
+    let data = try_load_hair_file(&path, TfxShortStrandPolicy::Upsample).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(data.num_hair_strands, 2);
+    assert_eq!(
+      data.num_vertices_per_strand,
+      IMPORT_VERTICES_PER_STRAND as u32
+    );
+    let lengths = get_imported_strand_lengths(&data);
+    for (strand, length) in strands.iter().zip(lengths) {
+      assert_length_close(length, get_strand_length(strand));
+    }
+  }
+
+  #[test]
+  fn load_cyhair_rejects_invalid_counts() {
+    let strands = vec![straight_strand(5), curved_strand(64)];
+    let valid = cyhair_bytes(&strands);
+
+    let truncated = &valid[..valid.len() - 4];
+    let mut huge_point_count = valid.clone();
+    huge_point_count[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+    let mut huge_hair_count = valid.clone();
+    huge_hair_count[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    let mut too_many_segments = valid.clone();
+    too_many_segments[128..130].copy_from_slice(&1000u16.to_le_bytes());
+
+    let cases: [(&str, &[u8]); 4] = [
+      ("truncated", truncated),
+      ("huge-point-count", &huge_point_count),
+      ("huge-hair-count", &huge_hair_count),
+      ("too-many-segments", &too_many_segments),
+    ];
+    for (name, bytes) in cases {
+      let path = write_temp_file(&format!("{}.hair", name), bytes);
+      let result = load_cyhair_file(&path);
+      std::fs::remove_file(&path).unwrap();
+      let err = result
+        .err()
+        .unwrap_or_else(|| panic!("'{}' should fail", name));
+      assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{}", name);
+    }
+  }
+
+  #[test]
+  fn load_obj_round_trip() {
+    let strands = [straight_strand(5), curved_strand(64)];
+
+    let mut obj = String::new();
+    for p in strands.iter().flatten() {
+      obj += &format!("v {} {} {}\n", p.x, p.y, p.z);
+    }
+    // first strand as a single polyline, second as chain of edges (Blender)
+    obj += "l 1 2 3 4 5\n";
+    for i in 0..(strands[1].len() - 1) {
+      obj += &format!("l {} {}\n", 6 + i, 7 + i);
+    }
+    let path = write_temp_file("round-trip.obj", obj.as_bytes());
+
+    let data = try_load_hair_file(&path, TfxShortStrandPolicy::Upsample).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(data.num_hair_strands, 2);
+    let lengths = get_imported_strand_lengths(&data);
+    for (strand, length) in strands.iter().zip(lengths) {
+      assert_length_close(length, get_strand_length(strand));
+    }
+  }
+
+  #[test]
+  fn load_obj_does_not_merge_polylines() {
+    let obj = "v 0 0 0\nv 0 1 0\nv 0 2 0\nv 1 2 0\nv 1 3 0\n\
+      l 1 2 3\nl 3 4 5\n\
+      l 1 2\nl 2 3\no other\nl 3 4\n";
+    let path = write_temp_file("no-merge.obj", obj.as_bytes());
+
+    let strands = load_obj_polylines_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let strand_sizes: Vec<usize> = strands.iter().map(|s| s.len()).collect();
+    assert_eq!(strand_sizes, vec![3, 3, 3, 2]);
+  }
+
+  #[test]
+  fn load_obj_rejects_invalid_index() {
+    let obj = "v 0 0 0\nv 0 1 0\nl 1 3\n";
+    let path = write_temp_file("invalid-index.obj", obj.as_bytes());
+
+    let result = load_obj_polylines_file(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let err = result.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("line 3"), "{}", err);
+  }
+
+  #[test]
+  fn parse_obj_index_values() {
+    assert_eq!(parse_obj_index("1", 3), Some(0));
+    assert_eq!(parse_obj_index("3/7", 3), Some(2));
+    assert_eq!(parse_obj_index("-1", 3), Some(2));
+    assert_eq!(parse_obj_index("-3", 3), Some(0));
+    assert_eq!(parse_obj_index("0", 3), None);
+    assert_eq!(parse_obj_index("4", 3), None);
+    assert_eq!(parse_obj_index("-4", 3), None);
+    assert_eq!(parse_obj_index("x", 3), None);
+  }
+}
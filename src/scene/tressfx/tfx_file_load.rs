@@ -1,34 +1,49 @@
 use std::fs::File;
-use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
 
 use log::{info, trace};
 
-use crate::scene::tressfx::tfx_file_data::TfxFileData;
+use super::tfx_file_data::TfxFileData;
 
-pub fn load_tressfx_file<'a>(path: &std::path::Path) -> TfxFileData {
+/// Extension of the optional file with per-vertex simulation weights.
+/// E.g. `sintel_hair.tfx` -> `sintel_hair.tfxweights`.
+pub const VERTEX_WEIGHTS_FILE_EXTENSION: &str = "tfxweights";
+
+/// Unreadable or invalid file is an error, the caller decides if it's fatal.
+pub fn try_load_tressfx_file(path: &std::path::Path) -> io::Result<TfxFileData> {
   info!("Loading TressFX asset from '{}'", path.to_string_lossy());
 
-  let file = File::open(path).expect("Failed to open file");
+  let file = File::open(path)?;
+  let file_size = file.metadata()?.len();
   let mut r = BufReader::new(file);
 
-  let version = read_float(&mut r);
-  let num_hair_strands = read_uint(&mut r);
-  let num_vertices_per_strand = read_uint(&mut r);
-  let offset_vertex_position = read_uint(&mut r);
-  let offset_strand_uv = read_uint(&mut r);
-  let offset_vertex_uv = read_uint(&mut r);
-  let offset_strand_thickness = read_uint(&mut r);
-  let offset_vertex_color = read_uint(&mut r);
-
-  let total_vertices = num_vertices_per_strand * num_hair_strands;
-  let position_float_cnt = (total_vertices * 4) as usize;
-
-  assert!(
-    num_vertices_per_strand == 32,
-    "When loading TressFX asset '{}' the number of vertices per each strand was {}, expected 32. This is required to have optimal simulation work scheduling.",
-    path.to_string_lossy(),
-    num_vertices_per_strand
-  );
+  let version = try_read_float(&mut r)?;
+  let num_hair_strands = try_read_uint(&mut r)?;
+  let num_vertices_per_strand = try_read_uint(&mut r)?;
+  let offset_vertex_position = try_read_uint(&mut r)?;
+  let offset_strand_uv = try_read_uint(&mut r)?;
+  let offset_vertex_uv = try_read_uint(&mut r)?;
+  let offset_strand_thickness = try_read_uint(&mut r)?;
+  let offset_vertex_color = try_read_uint(&mut r)?;
+
+  if !(4..=64).contains(&num_vertices_per_strand) || !num_vertices_per_strand.is_power_of_two() {
+    return Err(invalid_data(format!(
+      "number of vertices per each strand was {}, expected power of 2 from 4 to 64",
+      num_vertices_per_strand
+    )));
+  }
+
+  // checked before allocating, corrupted header could declare anything
+  let total_vertices = num_vertices_per_strand as u64 * num_hair_strands as u64;
+  let position_float_cnt = total_vertices * 4;
+  let positions_end = offset_vertex_position as u64 + position_float_cnt * 4;
+  if positions_end > file_size {
+    return Err(invalid_data(format!(
+      "{} strands need {} bytes of positions, file has {} bytes",
+      num_hair_strands, positions_end, file_size
+    )));
+  }
+  let position_float_cnt = position_float_cnt as usize;
 
   let mut tfx_data = TfxFileData {
     version,
@@ -40,42 +55,89 @@ pub fn load_tressfx_file<'a>(path: &std::path::Path) -> TfxFileData {
     offset_strand_thickness,
     offset_vertex_color,
     raw_vertex_positions: Vec::with_capacity(position_float_cnt),
+    raw_vertex_weights: Vec::with_capacity(position_float_cnt),
   };
   trace!("{}", tfx_data);
 
   // load and coerce raw data into vectors
-  read_float_array(
+  try_read_float_array(
     &mut r,
     &mut tfx_data.raw_vertex_positions,
     tfx_data.offset_vertex_position as _,
     position_float_cnt,
+  )?;
+
+  // simulation weights. Vertex colors are actual colors, never read them as weights
+  let weights_path = path.with_extension(VERTEX_WEIGHTS_FILE_EXTENSION);
+  if weights_path.exists() {
+    load_tressfx_vertex_weights_file(&weights_path, &mut tfx_data)?;
+  } else {
+    tfx_data.fill_default_vertex_weights();
+  }
+
+  Ok(tfx_data)
+}
+
+/// Load per-vertex simulation weights from a separate file. It's a raw
+/// `FLOAT4[numHairStrands * numVerticesPerStrand]` array, same layout as vertex positions.
+/// See `TfxFileData.raw_vertex_weights` for the layout.
+pub fn load_tressfx_vertex_weights_file(
+  path: &std::path::Path,
+  tfx_data: &mut TfxFileData,
+) -> io::Result<()> {
+  info!(
+    "Loading TressFX simulation weights from '{}'",
+    path.to_string_lossy()
   );
 
-  tfx_data
+  let file = File::open(path)?;
+  let file_size = file.metadata()?.len();
+  let float_cnt = (tfx_data.total_vertices() * 4) as usize;
+  let expected_size = (float_cnt * std::mem::size_of::<f32>()) as u64;
+  if file_size != expected_size {
+    return Err(invalid_data(format!(
+      "simulation weights file '{}' has {} bytes, expected {} (FLOAT4 for each of {} vertices)",
+      path.to_string_lossy(),
+      file_size,
+      expected_size,
+      tfx_data.total_vertices()
+    )));
+  }
+
+  let mut r = BufReader::new(file);
+  tfx_data.raw_vertex_weights.clear();
+  try_read_float_array(&mut r, &mut tfx_data.raw_vertex_weights, 0, float_cnt)
 }
 
-fn read_uint<R: Read>(reader: &mut R) -> u32 {
+pub(super) fn invalid_data(msg: String) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+pub(super) fn try_read_uint<R: Read>(reader: &mut R) -> std::io::Result<u32> {
   let mut buf = [0u8; std::mem::size_of::<u32>()];
-  reader.read_exact(&mut buf).expect("Error read_uint");
-  u32::from_le_bytes(buf)
+  reader.read_exact(&mut buf)?;
+  Ok(u32::from_le_bytes(buf))
 }
 
-fn read_float<R: Read>(reader: &mut R) -> f32 {
+pub(super) fn try_read_float<R: Read>(reader: &mut R) -> std::io::Result<f32> {
   let mut buf = [0u8; std::mem::size_of::<f32>()];
-  reader.read_exact(&mut buf).expect("Error read_float");
-  f32::from_le_bytes(buf)
+  reader.read_exact(&mut buf)?;
+  Ok(f32::from_le_bytes(buf))
 }
 
-fn read_float_array<R: Read + Seek>(
+pub(super) fn try_read_float_array<R: Read + Seek>(
   reader: &mut R,
   target: &mut Vec<f32>,
   offset: u64,
   cnt: usize,
-) {
-  reader
-    .seek(SeekFrom::Start(offset))
-    .expect("Error read_float_array: seek");
-  for _ in 0..cnt {
-    target.push(read_float(reader));
-  }
+) -> std::io::Result<()> {
+  reader.seek(SeekFrom::Start(offset))?;
+  let mut bytes = vec![0u8; cnt * std::mem::size_of::<f32>()];
+  reader.read_exact(&mut bytes)?;
+  target.extend(
+    bytes
+      .chunks_exact(std::mem::size_of::<f32>())
+      .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+  );
+  Ok(())
 }
@@ -0,0 +1,56 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use log::info;
+
+use super::tfx_file_data::TfxFileData;
+use super::tfx_file_load::VERTEX_WEIGHTS_FILE_EXTENSION;
+
+/// Write `.tfx` file. Same layout as AMD's `TressFXTFXFileHeader`. Only positions are
+/// written. Simulation weights (if not default) go to a separate `.tfxweights` file.
+pub fn save_tressfx_file(path: &std::path::Path, data: &TfxFileData) -> std::io::Result<()> {
+  info!("Saving TressFX asset to '{}'", path.to_string_lossy());
+
+  let offset_vertex_position = TfxFileData::HEADER_SIZE;
+
+  let file = File::create(path)?;
+  let mut w = BufWriter::new(file);
+
+  w.write_all(&data.version.to_le_bytes())?;
+  w.write_all(&data.num_hair_strands.to_le_bytes())?;
+  w.write_all(&data.num_vertices_per_strand.to_le_bytes())?;
+  w.write_all(&offset_vertex_position.to_le_bytes())?;
+  w.write_all(&0u32.to_le_bytes())?; // offset_strand_uv
+  w.write_all(&0u32.to_le_bytes())?; // offset_vertex_uv
+  w.write_all(&0u32.to_le_bytes())?; // offset_strand_thickness
+  w.write_all(&0u32.to_le_bytes())?; // offset_vertex_color
+  w.write_all(&[0u8; 32 * 4])?; // reserved
+
+  w.write_all(bytemuck::cast_slice(&data.raw_vertex_positions))?;
+  w.flush()?;
+
+  let weights_path = path.with_extension(VERTEX_WEIGHTS_FILE_EXTENSION);
+  if has_custom_vertex_weights(data) {
+    info!(
+      "Saving TressFX simulation weights to '{}'",
+      weights_path.to_string_lossy()
+    );
+    std::fs::write(
+      &weights_path,
+      bytemuck::cast_slice(&data.raw_vertex_weights),
+    )?;
+  } else if weights_path.exists() {
+    // stale weights from the previous save would be loaded with the new strands
+    std::fs::remove_file(&weights_path)?;
+  }
+  Ok(())
+}
+
+fn has_custom_vertex_weights(data: &TfxFileData) -> bool {
+  let expected_len = data.raw_vertex_positions.len();
+  data.raw_vertex_weights.len() == expected_len
+    && data
+      .raw_vertex_weights
+      .chunks(4)
+      .any(|w| w != TfxFileData::DEFAULT_VERTEX_WEIGHTS)
+}
@@ -0,0 +1,150 @@
+use glam::{vec3, Vec3, Vec4};
+
+use super::{TfxFileData, TfxObject};
+
+/// Must match consts in `_tfx_vertex_resolve.glsl`. Must match order in UI.
+pub enum TfxFollowHairMode {
+  /// Random offset around the guide strand, controlled by spread at root and tip
+  Random = 0,
+  /// Blend between the guide strand and 2 of its nearest guide strands
+  Interpolated = 1,
+  /// Random offset at the root, pulled towards the guide strand near the tip
+  Clumped = 2,
+}
+
+/// Guide strands nearest to each guide (by root position) - `UINT4[numHairStrands]`.
+/// Used for `TfxFollowHairMode::Interpolated`.
+pub const FOLLOW_HAIR_NEIGHBOURS: usize = 4;
+
+/// Done only once at load time. For each strand does a partial select of the nearest roots,
+/// so it's `O(n^2)` instead of sorting everything.
+/// If there are not enough strands, the guide itself is used as a neighbour.
+pub fn compute_follow_hair_neighbours(data: &TfxFileData) -> Vec<[u32; FOLLOW_HAIR_NEIGHBOURS]> {
+  let verts_per_strand = data.num_vertices_per_strand as usize;
+  let roots: Vec<Vec3> = (0..(data.num_hair_strands as usize))
+    .map(|strand_id| data.get_vertex_pos(strand_id * verts_per_strand))
+    .collect();
+
+  // (distance squared, strand id). Id breaks ties, so the result does not depend on the select order
+  let cmp = |a: &(f32, usize), b: &(f32, usize)| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1));
+  let mut by_distance: Vec<(f32, usize)> = Vec::with_capacity(roots.len());
+
+  roots
+    .iter()
+    .enumerate()
+    .map(|(strand_id, root)| {
+      by_distance.clear();
+      by_distance.extend(
+        roots
+          .iter()
+          .enumerate()
+          .filter(|(other_id, _)| *other_id != strand_id)
+          .map(|(other_id, other)| (root.distance_squared(*other), other_id)),
+      );
+      let nearest_count = by_distance.len().min(FOLLOW_HAIR_NEIGHBOURS);
+      if by_distance.len() > nearest_count {
+        by_distance.select_nth_unstable_by(nearest_count - 1, cmp);
+      }
+      let nearest = &mut by_distance[..nearest_count];
+      nearest.sort_unstable_by(cmp);
+
+      let mut result = [strand_id as u32; FOLLOW_HAIR_NEIGHBOURS];
+      for (i, (_, other_id)) in nearest.iter().enumerate() {
+        result[i] = *other_id as u32;
+      }
+      result
+    })
+    .collect()
+}
+
+/// CPU version of follow hair position from `getExpandedTressFXVert()` in `_tfx_vertex_resolve.glsl`.
+///
+/// @param positions raw simulation positions, `FLOAT4[numHairStrands * numVerticesPerStrand]`
+/// @return world space position
+pub fn get_follow_hair_position(
+  tfx: &TfxObject,
+  positions: &[f32],
+  instance_id: u32,
+  strand_id: u32,
+  vertex_id: u32,
+) -> Vec3 {
+  let verts_per_strand = tfx.num_vertices_per_strand;
+  let get_position = |strand_id: u32| {
+    let idx = ((strand_id * verts_per_strand + vertex_id) * 4) as usize;
+    Vec4::from_slice(&positions[idx..idx + 4]).truncate()
+  };
+
+  let mut pos = get_position(strand_id);
+  let mode = tfx.follow_hair_mode;
+  if mode == TfxFollowHairMode::Interpolated as usize && instance_id > 0 {
+    let neighbours = tfx.follow_hair_neighbours[strand_id as usize];
+    let (n0, n1) = get_interpolation_neighbours(&neighbours, instance_id);
+    let w = get_interpolation_weights(instance_id, strand_id);
+    pos = pos * w.x + get_position(n0) * w.y + get_position(n1) * w.z;
+  }
+  let pos_world = tfx.model_matrix.transform_point3(pos);
+
+  // 1 := root, 0 := tip
+  let fraction_of_strand = 1.0 - (vertex_id as f32) / (verts_per_strand as f32);
+  pos_world + get_follow_hair_displacement(tfx, instance_id, strand_id, fraction_of_strand)
+}
+
+fn get_interpolation_neighbours(
+  neighbours: &[u32; FOLLOW_HAIR_NEIGHBOURS],
+  instance_id: u32,
+) -> (u32, u32) {
+  let n = FOLLOW_HAIR_NEIGHBOURS as u32;
+  (
+    neighbours[(instance_id % n) as usize],
+    neighbours[((instance_id + 1) % n) as usize],
+  )
+}
+
+/// `getInterpolationWeights()` from `_tfx_vertex_resolve.glsl`
+fn get_interpolation_weights(instance_id: u32, strand_id: u32) -> Vec3 {
+  let w = glsl_hash(vec3(instance_id as f32, strand_id as f32, 3.0)) + 0.05;
+  w / (w.x + w.y + w.z)
+}
+
+/// `getFollowHairDisplacement()` from `_tfx_vertex_resolve.glsl`
+fn get_follow_hair_displacement(
+  tfx: &TfxObject,
+  instance_id: u32,
+  strand_id: u32,
+  fraction_of_strand: f32,
+) -> Vec3 {
+  let mode = tfx.follow_hair_mode;
+  if instance_id == 0 || mode == TfxFollowHairMode::Interpolated as usize {
+    return Vec3::ZERO;
+  }
+
+  let root_offset = randomize_strand_pos(instance_id, strand_id, 1) * tfx.follow_hair_spread_root;
+  if mode == TfxFollowHairMode::Clumped as usize {
+    let clump =
+      (1.0 - fraction_of_strand).powf(tfx.follow_hair_clump_shape) * tfx.follow_hair_clump_strength;
+    return root_offset * (1.0 - clump);
+  }
+
+  let tip_offset = randomize_strand_pos(instance_id, strand_id, 2) * tfx.follow_hair_spread_tip;
+  tip_offset.lerp(root_offset, fraction_of_strand)
+}
+
+fn randomize_strand_pos(instance_id: u32, strand_id: u32, rng_fac: u32) -> Vec3 {
+  let seed = vec3(
+    instance_id as f32,
+    strand_id as f32,
+    (rng_fac + instance_id / 2 + instance_id / 3) as f32,
+  );
+  let v = glsl_hash(seed).normalize();
+  v * 2.0 - 1.0
+}
+
+/// `hash()` from `_utils.glsl`
+pub(super) fn glsl_hash(a: Vec3) -> Vec3 {
+  let mut a = (a * 0.8).fract();
+  a += a.dot(vec3(a.y, a.x, a.z) + 19.19);
+  let xxy = vec3(a.x, a.x, a.y);
+  let yxx = vec3(a.y, a.x, a.x);
+  let zyx = vec3(a.z, a.y, a.x);
+  ((xxy + yxx) * zyx).fract()
+}
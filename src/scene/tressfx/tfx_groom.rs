@@ -0,0 +1,427 @@
+use glam::{Mat4, Vec3};
+
+use super::{resample_strand, TfxFileData};
+
+/// Must match order in UI.
+pub enum TfxGroomTool {
+  /// Drag to bend strands under the cursor. Strand length is preserved
+  Comb = 0,
+  /// Click to cut strands at the cursor
+  Cut = 1,
+  /// Click to scale length of strands under the cursor
+  ScaleLength = 2,
+}
+
+/// Root vertices are not affected by grooming. Same as in simulation (`IsMovable()`).
+const FIRST_MOVABLE_VERTEX: usize = 2;
+
+/// In-viewer hair grooming. Edits the rest pose (`TfxObject.rest_pose`),
+/// which is then uploaded as initial positions/tangents for the simulation.
+pub struct TfxGroomSettings {
+  /// When enabled, left mouse button applies the tool instead of rotating the camera
+  pub enabled: bool,
+  /// See `TfxGroomTool`
+  pub tool: usize,
+  /// World space distance from the cursor ray
+  pub brush_radius: f32,
+  /// Comb: how much strands follow the cursor
+  pub comb_strength: f32,
+  /// ScaleLength: multiplier applied on each click
+  pub length_scale: f32,
+  pub file_path: String,
+}
+
+impl TfxGroomSettings {
+  pub fn new(name: &str) -> Self {
+    Self {
+      enabled: false,
+      tool: TfxGroomTool::Comb as _,
+      brush_radius: 1.0,
+      comb_strength: 0.8,
+      length_scale: 1.1,
+      file_path: format!("./{}_groomed.tfx", name),
+    }
+  }
+}
+
+/// World space ray from the camera through the cursor.
+#[derive(Copy, Clone)]
+pub struct TfxGroomRay {
+  pub origin: Vec3,
+  /// normalized
+  pub dir: Vec3,
+}
+
+impl TfxGroomRay {
+  pub fn at(&self, t: f32) -> Vec3 {
+    self.origin + self.dir * t
+  }
+
+  /// @return `(t, distance)` for the closest point on the ray. None if the point is behind the ray
+  pub fn closest_point(&self, p: Vec3) -> Option<(f32, f32)> {
+    let t = (p - self.origin).dot(self.dir);
+    if t < 0.0 {
+      return None;
+    }
+    Some((t, p.distance(self.at(t))))
+  }
+}
+
+/// Apply the selected tool.
+///
+/// @param prev_ray cursor ray from previous frame, used to calculate comb direction
+/// @param is_stroke_start true only on the first frame the mouse button is held.
+///   Cut and ScaleLength are applied once per click.
+/// @return number of modified strands
+pub fn apply_groom_tool(
+  settings: &TfxGroomSettings,
+  data: &mut TfxFileData,
+  model_matrix: &Mat4,
+  prev_ray: &TfxGroomRay,
+  ray: &TfxGroomRay,
+  is_stroke_start: bool,
+) -> u32 {
+  let radius = settings.brush_radius;
+  match settings.tool {
+    x if x == TfxGroomTool::Comb as usize => groom_comb(
+      data,
+      model_matrix,
+      prev_ray,
+      ray,
+      radius,
+      settings.comb_strength,
+    ),
+    x if x == TfxGroomTool::Cut as usize && is_stroke_start => {
+      groom_cut(data, model_matrix, ray, radius)
+    }
+    x if x == TfxGroomTool::ScaleLength as usize && is_stroke_start => {
+      groom_scale_length(data, model_matrix, ray, radius, settings.length_scale)
+    }
+    _ => 0,
+  }
+}
+
+/// Move vertices near `prev_ray` by the cursor movement. Afterwards, restore
+/// original segment lengths so the strand does not stretch.
+pub fn groom_comb(
+  data: &mut TfxFileData,
+  model_matrix: &Mat4,
+  prev_ray: &TfxGroomRay,
+  ray: &TfxGroomRay,
+  radius: f32,
+  strength: f32,
+) -> u32 {
+  let inv_model_matrix = model_matrix.inverse();
+  let mut modified_strands = 0;
+
+  for strand_id in 0..(data.num_hair_strands as usize) {
+    let segment_lengths = get_segment_lengths(data, strand_id);
+    let mut is_modified = false;
+
+    for vertex_id in FIRST_MOVABLE_VERTEX..(data.num_vertices_per_strand as usize) {
+      let idx = get_vertex_idx(data, strand_id, vertex_id);
+      let pos = model_matrix.transform_point3(data.get_vertex_pos(idx));
+      let (t, dist) = match prev_ray.closest_point(pos) {
+        Some(hit) if hit.1 < radius => hit,
+        _ => continue,
+      };
+
+      let falloff = 1.0 - dist / radius;
+      let delta = (ray.at(t) - prev_ray.at(t)) * falloff * strength;
+      data.set_vertex_pos(idx, inv_model_matrix.transform_point3(pos + delta));
+      is_modified = true;
+    }
+
+    if is_modified {
+      restore_segment_lengths(data, strand_id, &segment_lengths);
+      modified_strands += 1;
+    }
+  }
+
+  modified_strands
+}
+
+/// Cut each strand at the first vertex inside the brush. Strand is resampled
+/// to keep the same vertex count.
+pub fn groom_cut(
+  data: &mut TfxFileData,
+  model_matrix: &Mat4,
+  ray: &TfxGroomRay,
+  radius: f32,
+) -> u32 {
+  let verts_per_strand = data.num_vertices_per_strand as usize;
+  let mut modified_strands = 0;
+
+  for strand_id in 0..(data.num_hair_strands as usize) {
+    let cut_vertex = (FIRST_MOVABLE_VERTEX..verts_per_strand)
+      .find(|vertex_id| is_in_brush(data, model_matrix, ray, radius, strand_id, *vertex_id));
+    let cut_vertex = match cut_vertex {
+      Some(v) => v,
+      None => continue,
+    };
+
+    let kept: Vec<Vec3> = (0..=cut_vertex)
+      .map(|vertex_id| data.get_vertex_pos(get_vertex_idx(data, strand_id, vertex_id)))
+      .collect();
+    let resampled = resample_strand(&kept, verts_per_strand);
+    for (vertex_id, p) in resampled.iter().enumerate() {
+      data.set_vertex_pos(get_vertex_idx(data, strand_id, vertex_id), *p);
+    }
+    modified_strands += 1;
+  }
+
+  modified_strands
+}
+
+/// Scale every segment of strands that have at least one vertex inside the brush.
+/// Segment directions do not change.
+pub fn groom_scale_length(
+  data: &mut TfxFileData,
+  model_matrix: &Mat4,
+  ray: &TfxGroomRay,
+  radius: f32,
+  scale: f32,
+) -> u32 {
+  let verts_per_strand = data.num_vertices_per_strand as usize;
+  let mut modified_strands = 0;
+
+  for strand_id in 0..(data.num_hair_strands as usize) {
+    let is_hit = (FIRST_MOVABLE_VERTEX..verts_per_strand)
+      .any(|vertex_id| is_in_brush(data, model_matrix, ray, radius, strand_id, vertex_id));
+    if !is_hit {
+      continue;
+    }
+
+    let segment_lengths: Vec<f32> = get_segment_lengths(data, strand_id)
+      .iter()
+      .map(|l| l * scale)
+      .collect();
+    restore_segment_lengths(data, strand_id, &segment_lengths);
+    modified_strands += 1;
+  }
+
+  modified_strands
+}
+
+fn get_vertex_idx(data: &TfxFileData, strand_id: usize, vertex_id: usize) -> usize {
+  strand_id * (data.num_vertices_per_strand as usize) + vertex_id
+}
+
+fn is_in_brush(
+  data: &TfxFileData,
+  model_matrix: &Mat4,
+  ray: &TfxGroomRay,
+  radius: f32,
+  strand_id: usize,
+  vertex_id: usize,
+) -> bool {
+  let idx = get_vertex_idx(data, strand_id, vertex_id);
+  let pos = model_matrix.transform_point3(data.get_vertex_pos(idx));
+  matches!(ray.closest_point(pos), Some((_, dist)) if dist < radius)
+}
+
+/// `result[i]` is the length of segment between vertices `i` and `i+1`
+fn get_segment_lengths(data: &TfxFileData, strand_id: usize) -> Vec<f32> {
+  (1..(data.num_vertices_per_strand as usize))
+    .map(|vertex_id| {
+      let a = data.get_vertex_pos(get_vertex_idx(data, strand_id, vertex_id - 1));
+      let b = data.get_vertex_pos(get_vertex_idx(data, strand_id, vertex_id));
+      a.distance(b)
+    })
+    .collect()
+}
+
+/// Walk from the root and move each vertex along its segment to match the length.
+/// Segment directions are taken from the positions before this call, as the
+/// already moved previous vertex can overshoot the current one.
+fn restore_segment_lengths(data: &mut TfxFileData, strand_id: usize, segment_lengths: &[f32]) {
+  let mut prev_original = data.get_vertex_pos(get_vertex_idx(data, strand_id, 0));
+  for vertex_id in 1..(data.num_vertices_per_strand as usize) {
+    let idx = get_vertex_idx(data, strand_id, vertex_id);
+    let original = data.get_vertex_pos(idx);
+    let dir = (original - prev_original).normalize_or_zero();
+    let prev = data.get_vertex_pos(idx - 1);
+    data.set_vertex_pos(idx, prev + dir * segment_lengths[vertex_id - 1]);
+    prev_original = original;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const STRAND_COUNT: usize = 3;
+  const VERTS_PER_STRAND: usize = 8;
+  const STRAND_SPACING: f32 = 5.0;
+
+  /// Straight strands hanging down from `y=0`, each segment has length 1.
+  /// Strand `i` is at `x = i * STRAND_SPACING`.
+  fn create_strands() -> TfxFileData {
+    let mut raw_vertex_positions = Vec::new();
+    for strand_id in 0..STRAND_COUNT {
+      for vertex_id in 0..VERTS_PER_STRAND {
+        let w = if vertex_id == 0 { 0.0 } else { 1.0 };
+        let x = strand_id as f32 * STRAND_SPACING;
+        raw_vertex_positions.extend_from_slice(&[x, -(vertex_id as f32), 0.0, w]);
+      }
+    }
+
+    let mut data = TfxFileData {
+      version: 4.0,
+      num_hair_strands: STRAND_COUNT as _,
+      num_vertices_per_strand: VERTS_PER_STRAND as _,
+      offset_vertex_position: 0,
+      offset_strand_uv: 0,
+      offset_vertex_uv: 0,
+      offset_strand_thickness: 0,
+      offset_vertex_color: 0,
+      raw_vertex_positions,
+      raw_vertex_weights: Vec::new(),
+    };
+    data.fill_default_vertex_weights();
+    data
+  }
+
+  /// Looks along `-z` at a point on the first strand
+  fn ray_at(x: f32, y: f32) -> TfxGroomRay {
+    TfxGroomRay {
+      origin: Vec3::new(x, y, 10.0),
+      dir: Vec3::new(0.0, 0.0, -1.0),
+    }
+  }
+
+  fn get_strand(data: &TfxFileData, strand_id: usize) -> Vec<Vec3> {
+    (0..VERTS_PER_STRAND)
+      .map(|vertex_id| data.get_vertex_pos(get_vertex_idx(data, strand_id, vertex_id)))
+      .collect()
+  }
+
+  fn get_strand_length(data: &TfxFileData, strand_id: usize) -> f32 {
+    get_segment_lengths(data, strand_id).iter().sum()
+  }
+
+  fn assert_other_strands_unchanged(data: &TfxFileData, modified_strand_id: usize) {
+    let original = create_strands();
+    for strand_id in (0..STRAND_COUNT).filter(|id| *id != modified_strand_id) {
+      assert_eq!(
+        get_strand(data, strand_id),
+        get_strand(&original, strand_id)
+      );
+    }
+  }
+
+  #[test]
+  fn comb_moves_strand_and_keeps_length() {
+    let mut data = create_strands();
+    let length_before = get_strand_length(&data, 0);
+
+    let modified = groom_comb(
+      &mut data,
+      &Mat4::IDENTITY,
+      &ray_at(0.0, -5.0),
+      &ray_at(1.0, -5.0),
+      0.5,
+      1.0,
+    );
+
+    assert_eq!(modified, 1);
+    let strand = get_strand(&data, 0);
+    assert!(
+      strand[5].x > 0.5,
+      "Combed vertex did not move: {}",
+      strand[5]
+    );
+    for (vertex_id, p) in strand.iter().enumerate().take(FIRST_MOVABLE_VERTEX) {
+      assert_eq!(*p, Vec3::new(0.0, -(vertex_id as f32), 0.0));
+    }
+    for segment_length in get_segment_lengths(&data, 0) {
+      assert!((segment_length - 1.0).abs() < 1e-4);
+    }
+    assert!((get_strand_length(&data, 0) - length_before).abs() < 1e-3);
+    assert_other_strands_unchanged(&data, 0);
+  }
+
+  #[test]
+  fn comb_respects_model_matrix() {
+    let mut data = create_strands();
+    let model_matrix = Mat4::from_translation(Vec3::new(100.0, 0.0, 0.0));
+
+    let modified = groom_comb(
+      &mut data,
+      &model_matrix,
+      &ray_at(0.0, -5.0),
+      &ray_at(1.0, -5.0),
+      0.5,
+      1.0,
+    );
+    assert_eq!(modified, 0);
+
+    let modified = groom_comb(
+      &mut data,
+      &model_matrix,
+      &ray_at(100.0, -5.0),
+      &ray_at(101.0, -5.0),
+      0.5,
+      1.0,
+    );
+    assert_eq!(modified, 1);
+    assert!(get_strand(&data, 0)[5].x > 0.5);
+  }
+
+  #[test]
+  fn cut_shortens_strand() {
+    let mut data = create_strands();
+
+    let modified = groom_cut(&mut data, &Mat4::IDENTITY, &ray_at(0.0, -5.0), 0.5);
+
+    assert_eq!(modified, 1);
+    let strand = get_strand(&data, 0);
+    assert_eq!(strand.len(), VERTS_PER_STRAND);
+    assert_eq!(strand[0], Vec3::ZERO);
+    assert!((get_strand_length(&data, 0) - 5.0).abs() < 1e-4);
+    assert!((strand[VERTS_PER_STRAND - 1].y + 5.0).abs() < 1e-4);
+    assert_other_strands_unchanged(&data, 0);
+  }
+
+  #[test]
+  fn cut_ignores_root_vertices() {
+    let mut data = create_strands();
+    let modified = groom_cut(&mut data, &Mat4::IDENTITY, &ray_at(0.0, -1.0), 0.5);
+    assert_eq!(modified, 0);
+    assert_other_strands_unchanged(&data, usize::MAX);
+  }
+
+  #[test]
+  fn scale_length_keeps_shape() {
+    let mut data = create_strands();
+    let length_before = get_strand_length(&data, 0);
+
+    let modified = groom_scale_length(&mut data, &Mat4::IDENTITY, &ray_at(0.0, -5.0), 0.5, 1.5);
+
+    assert_eq!(modified, 1);
+    let strand = get_strand(&data, 0);
+    assert_eq!(strand[0], Vec3::ZERO);
+    assert!((get_strand_length(&data, 0) - length_before * 1.5).abs() < 1e-4);
+    // direction is kept
+    for p in strand {
+      assert!(p.x.abs() < 1e-5 && p.z.abs() < 1e-5);
+    }
+    assert_other_strands_unchanged(&data, 0);
+  }
+
+  #[test]
+  fn click_tools_apply_only_on_stroke_start() {
+    let mut settings = TfxGroomSettings::new("test");
+    settings.brush_radius = 0.5;
+    let ray = ray_at(0.0, -5.0);
+
+    for tool in [TfxGroomTool::Cut, TfxGroomTool::ScaleLength] {
+      settings.tool = tool as _;
+      let mut data = create_strands();
+      let modified = apply_groom_tool(&settings, &mut data, &Mat4::IDENTITY, &ray, &ray, false);
+      assert_eq!(modified, 0);
+      let modified = apply_groom_tool(&settings, &mut data, &Mat4::IDENTITY, &ray, &ray, true);
+      assert_eq!(modified, 1);
+    }
+  }
+}
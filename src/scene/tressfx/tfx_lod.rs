@@ -0,0 +1,155 @@
+use glam::{Mat4, Vec3};
+
+use super::TfxFileData;
+
+/// Automatic level of detail based on how much of the screen the hair covers.
+/// Fewer follow hairs are drawn for distant objects. To preserve coverage,
+/// remaining strands get wider and more opaque.
+///
+/// Applies to every pass that renders hair (PPLL, solid forward, depth-only, shadow map).
+pub struct TfxLodSettings {
+  pub enabled: bool,
+  /// Projected bounding sphere diameter (fraction of the screen height)
+  /// above which all follow hairs are drawn
+  pub full_detail_coverage: f32,
+  /// Projected bounding sphere diameter (fraction of the screen height)
+  /// below which only `min_follow_hairs` are drawn
+  pub min_detail_coverage: f32,
+  /// Instances drawn at lowest detail. 1 means only guide hairs
+  pub min_follow_hairs: u32,
+  /// Limit on how much strands can widen to preserve coverage.
+  /// Anything above goes into opacity instead
+  pub max_width_scale: f32,
+}
+
+impl Default for TfxLodSettings {
+  fn default() -> Self {
+    Self {
+      enabled: true,
+      full_detail_coverage: 0.5,
+      min_detail_coverage: 0.05,
+      min_follow_hairs: 1,
+      max_width_scale: 3.0,
+    }
+  }
+}
+
+/// Result of `TfxLodSettings::evaluate()`. Recalculated every frame.
+#[derive(Copy, Clone)]
+pub struct TfxLod {
+  /// Projected bounding sphere diameter as fraction of the screen height
+  pub screen_coverage: f32,
+  /// Fractional number of follow hairs. Last instance is faded (thinned)
+  /// by the fractional part for smooth transitions
+  pub follow_hairs: f32,
+  /// Multiplier for `fiber_radius`
+  pub width_scale: f32,
+  /// Multiplier for material opacity
+  pub opacity_scale: f32,
+}
+
+impl TfxLod {
+  pub fn full_detail(follow_hairs: u32) -> Self {
+    Self {
+      screen_coverage: 1.0,
+      follow_hairs: follow_hairs as f32,
+      width_scale: 1.0,
+      opacity_scale: 1.0,
+    }
+  }
+
+  /// Number of instances for the draw call
+  pub fn instance_count(&self) -> u32 {
+    self.follow_hairs.ceil() as u32
+  }
+}
+
+impl TfxLodSettings {
+  /// @param follow_hairs follow hairs at full detail
+  /// @param bounding_sphere `(center, radius)` in model space
+  pub fn evaluate(
+    &self,
+    follow_hairs: u32,
+    bounding_sphere: (Vec3, f32),
+    model_matrix: &Mat4,
+    view_matrix: &Mat4,
+    projection_matrix: &Mat4,
+  ) -> TfxLod {
+    if !self.enabled || follow_hairs <= 1 {
+      return TfxLod::full_detail(follow_hairs);
+    }
+
+    let screen_coverage = get_screen_coverage(
+      bounding_sphere,
+      model_matrix,
+      view_matrix,
+      projection_matrix,
+    );
+    let max_hairs = follow_hairs as f32;
+    let min_hairs = (self.min_follow_hairs.max(1) as f32).min(max_hairs);
+    let t = smoothstep(
+      self.min_detail_coverage,
+      self
+        .full_detail_coverage
+        .max(self.min_detail_coverage + 0.001),
+      screen_coverage,
+    );
+    let lod_follow_hairs = min_hairs + (max_hairs - min_hairs) * t;
+
+    // total coverage is roughly `strands * width * opacity`
+    let coverage_loss = max_hairs / lod_follow_hairs;
+    let width_scale = coverage_loss.min(self.max_width_scale.max(1.0));
+    let opacity_scale = coverage_loss / width_scale;
+
+    TfxLod {
+      screen_coverage,
+      follow_hairs: lod_follow_hairs,
+      width_scale,
+      opacity_scale,
+    }
+  }
+}
+
+/// Bounding sphere `(center, radius)` of all strands, in model space.
+pub fn compute_bounding_sphere(data: &TfxFileData) -> (Vec3, f32) {
+  let vertex_count = data.total_vertices() as usize;
+  let (mut min, mut max) = (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN));
+  for idx in 0..vertex_count {
+    let p = data.get_vertex_pos(idx);
+    min = min.min(p);
+    max = max.max(p);
+  }
+
+  let center = (min + max) * 0.5;
+  let radius = (0..vertex_count)
+    .map(|idx| data.get_vertex_pos(idx).distance(center))
+    .fold(0.0, f32::max);
+  (center, radius)
+}
+
+/// Projected diameter of the sphere as fraction of the screen height.
+/// 1.0 when the camera is inside the sphere.
+fn get_screen_coverage(
+  bounding_sphere: (Vec3, f32),
+  model_matrix: &Mat4,
+  view_matrix: &Mat4,
+  projection_matrix: &Mat4,
+) -> f32 {
+  let (center, radius) = bounding_sphere;
+  let center_view = view_matrix.transform_point3(model_matrix.transform_point3(center));
+  let (scale, _, _) = model_matrix.to_scale_rotation_translation();
+  let radius_world = radius * scale.max_element();
+
+  let distance = center_view.length();
+  if distance <= radius_world {
+    return 1.0;
+  }
+  // `projection_matrix.y_axis.y` is `1 / tan(fov_y / 2)`
+  let cot_half_fov = projection_matrix.y_axis.y.abs();
+  (radius_world * cot_half_fov / distance).min(1.0)
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+  let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+  t * t * (3.0 - 2.0 * t)
+}
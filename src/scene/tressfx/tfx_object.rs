@@ -8,6 +8,7 @@ use crate::{
   config::Config,
   either,
   render_graph::TfxParamsUBO,
+  scene::{BoundingBox, Camera},
   vk_ctx::VkCtx,
   vk_utils::{FrameInFlightId, VkBuffer, VkMemoryPreference, VkMemoryResource, WithSetupCmdBuffer},
 };
@@ -15,13 +16,16 @@ use crate::{
 #[allow(deprecated)]
 use crate::vk_utils::execute_full_pipeline_barrier;
 
-use super::{TfxFileData, TfxMaterial};
+use super::{
+  apply_groom_tool, compute_bounding_sphere, compute_follow_hair_neighbours, export_strands,
+  save_tressfx_file, TfxExportSettings, TfxExportStrands, TfxFileData, TfxFollowHairMode,
+  TfxGroomRay, TfxGroomSettings, TfxLod, TfxLodSettings, TfxMaterial, TfxSimulation,
+  TfxSimulationCache, TfxStrandInspector, TfxStrandModifiers, FOLLOW_HAIR_NEIGHBOURS,
+};
 
 pub struct TfxObject {
   pub name: String,
   pub model_matrix: Mat4,
-  /// used to draw collision spheres in debug mode
-  pub scale_debug_use_only: f32,
   pub center_of_gravity: Vec3,
   /// radius of each strand
   pub fiber_radius: f32,
@@ -34,9 +38,39 @@ pub struct TfxObject {
   pub follow_hair_spread_root: f32,
   /// displacement of follow hair at the tip
   pub follow_hair_spread_tip: f32,
+  /// how follow hairs are placed around guide hair. See `TfxFollowHairMode`
+  pub follow_hair_mode: usize,
+  /// `TfxFollowHairMode::Clumped` - how much follow hair is pulled towards guide at the tip
+  pub follow_hair_clump_strength: f32,
+  /// `TfxFollowHairMode::Clumped` - exponent of the clump curve. Higher values clump only near the tip
+  pub follow_hair_clump_shape: f32,
+  /// CPU copy of `follow_hair_neighbours_buffer`
+  pub follow_hair_neighbours: Vec<[u32; FOLLOW_HAIR_NEIGHBOURS]>,
+  /// curl, frizz etc.
+  pub strand_modifiers: TfxStrandModifiers,
+  /// automatic level of detail
+  pub lod_settings: TfxLodSettings,
+  /// level of detail for the current frame. See `update_lod()`
+  pub lod: TfxLod,
+  /// `(center, radius)` of the rest pose in model space. Used for LOD
+  pub bounding_sphere: (Vec3, f32),
 
   /// material
   pub material: TfxMaterial,
+  /// simulation settings
+  pub simulation: TfxSimulation,
+  /// recorded simulation run
+  pub simulation_cache: TfxSimulationCache,
+  /// export current strands to a file
+  pub export_settings: TfxExportSettings,
+  /// in-viewer grooming of `rest_pose`
+  pub groom: TfxGroomSettings,
+  /// CPU copy of the asset. Same as `initial_positions_buffer`, modified by grooming.
+  pub rest_pose: TfxFileData,
+  /// `rest_pose` was groomed, but not uploaded to GPU yet. See `upload_groomed_rest_pose()`
+  pub is_rest_pose_dirty: bool,
+  /// strand selected in the viewport
+  pub strand_inspector: TfxStrandInspector,
   /// Tfx params uploaded to GPU. Refreshed every frame (cause changes from ui etc.)
   pub tfx_params_ubo: Vec<VkBuffer>,
 
@@ -64,6 +98,10 @@ pub struct TfxObject {
   pub initial_tangents_buffer: VkBuffer,
   pub tangents_buffer: VkBuffer,
 
+  /// Per-vertex simulation weights: `[global stiffness mul, local stiffness mul, -, pinned]`.
+  pub vertex_weights_buffer: VkBuffer,
+  /// Nearest guide strands for each guide strand - `UINT4[numHairStrands]`.
+  pub follow_hair_neighbours_buffer: VkBuffer,
   /// Original positions from TressFX asset file. Used to calculate constraints, relative vectors, reset simulation state etc.
   pub initial_positions_buffer: VkBuffer,
   /// e.g. current positions - used in simulation. Rotates with other `positions_X` buffers.
@@ -84,12 +122,24 @@ impl TfxObject {
     model_matrix: Mat4,
     data: &TfxFileData,
   ) -> Self {
+    assert!(
+      data.num_vertices_per_strand == 32,
+      "TressFX object '{}' has {} vertices per each strand, expected 32. This is required to have optimal simulation work scheduling.",
+      name,
+      data.num_vertices_per_strand
+    );
+
     let initial_positions_buffer = create_positions_buffer(vk_ctx, &name, data);
     let initial_tangents_buffer = create_tangents_buffer(vk_ctx, &name, data, false);
     let tangents_buffer = create_tangents_buffer(vk_ctx, &name, data, true);
+    let vertex_weights_buffer = create_vertex_weights_buffer(vk_ctx, &name, data);
     let (index_buffer, triangle_count) = create_index_buffer(vk_ctx, &name, data);
+    let follow_hair_neighbours = compute_follow_hair_neighbours(data);
+    let follow_hair_neighbours_buffer =
+      create_follow_hair_neighbours_buffer(vk_ctx, &name, &follow_hair_neighbours);
 
     let tfx_params_ubo = allocate_params_ubo_vec(vk_ctx, config.frames_in_flight, name);
+    let follow_hairs = 15;
 
     let positions_0_buffer =
       create_simulation_positions_buffer(vk_ctx, &format!("{}.tfx_positions_0", name), data);
@@ -101,15 +151,29 @@ impl TfxObject {
     let tfx_obj = Self {
       name: name.to_string(),
       model_matrix,
-      scale_debug_use_only: 1.0,
       center_of_gravity: vec3(0.0, 0.0, 0.0),
       material: TfxMaterial::default(),
+      simulation: TfxSimulation::default(),
+      simulation_cache: TfxSimulationCache::new(name),
+      export_settings: TfxExportSettings::new(name),
+      groom: TfxGroomSettings::new(name),
+      rest_pose: data.clone(),
+      is_rest_pose_dirty: false,
+      strand_inspector: TfxStrandInspector::default(),
       // tressfx:
       fiber_radius: 0.013,
       thin_tip: 0.9,
-      follow_hairs: 15,
+      follow_hairs,
       follow_hair_spread_root: 0.3,
       follow_hair_spread_tip: 0.09,
+      follow_hair_mode: TfxFollowHairMode::Random as _,
+      follow_hair_clump_strength: 0.8,
+      follow_hair_clump_shape: 1.0,
+      follow_hair_neighbours,
+      strand_modifiers: TfxStrandModifiers::default(),
+      lod_settings: TfxLodSettings::default(),
+      lod: TfxLod::full_detail(follow_hairs),
+      bounding_sphere: compute_bounding_sphere(data),
       num_hair_strands: data.num_hair_strands,
       num_vertices_per_strand: data.num_vertices_per_strand,
       // buffers:
@@ -118,6 +182,8 @@ impl TfxObject {
       index_buffer,
       triangle_count, // closely related to `indices_buffer`
       tfx_params_ubo,
+      vertex_weights_buffer,
+      follow_hair_neighbours_buffer,
       initial_positions_buffer,
       positions_0_buffer,
       positions_1_buffer,
@@ -141,6 +207,8 @@ impl TfxObject {
     self.initial_positions_buffer.delete(allocator);
     self.tangents_buffer.delete(allocator);
     self.initial_tangents_buffer.delete(allocator);
+    self.vertex_weights_buffer.delete(allocator);
+    self.follow_hair_neighbours_buffer.delete(allocator);
     self.index_buffer.delete(allocator);
     self.tfx_params_ubo.iter_mut().for_each(|buffer| {
       buffer.delete(allocator);
@@ -164,10 +232,53 @@ impl TfxObject {
     );
 
     let index_count = self.triangle_count * 3;
-    let instance_count = self.follow_hairs;
+    let instance_count = self.lod.instance_count();
     device.cmd_draw_indexed(command_buffer, index_count, instance_count, 0, 0, 0);
   }
 
+  /// Recalculate level of detail. Call once per frame, before the uniform buffer is updated.
+  pub fn update_lod(&mut self, camera: &Camera) {
+    self.lod = self.lod_settings.evaluate(
+      self.follow_hairs,
+      self.bounding_sphere,
+      &self.model_matrix,
+      camera.view_matrix(),
+      camera.perspective_matrix(),
+    );
+  }
+
+  /// World space bounds of the hair. This is a rest pose approximation, simulated positions are
+  /// only read back on demand (picking, inspector) and are not used here. Strands can move
+  /// away from the rest pose, so the bounding sphere is enlarged by `TfxSimulation::bounds_scale`.
+  pub fn bounding_box(&self) -> BoundingBox {
+    let (center, radius) = self.world_bounding_sphere();
+    BoundingBox::from_sphere(center, radius)
+  }
+
+  /// Same as `bounding_box()`. Includes follow hairs and strand modifiers.
+  /// @return `(center, radius)` in world space
+  pub fn world_bounding_sphere(&self) -> (Vec3, f32) {
+    let (center, radius) = self.bounding_sphere;
+    let (scale, _, _) = self.model_matrix.to_scale_rotation_translation();
+    let modifiers = &self.strand_modifiers;
+    let sim_bounds_scale = if self.simulation.enabled {
+      self.simulation.bounds_scale
+    } else {
+      1.0
+    };
+    // follow hair offsets are in world space, each axis in `[-spread, spread]`
+    let follow_hair_spread = self
+      .follow_hair_spread_root
+      .max(self.follow_hair_spread_tip);
+    let margin = (follow_hair_spread + modifiers.frizz) * 3.0f32.sqrt()
+      + modifiers.curl_radius
+      + self.fiber_radius * self.lod.width_scale;
+    (
+      self.model_matrix.transform_point3(center),
+      radius * scale.max_element() * sim_bounds_scale + margin,
+    )
+  }
+
   pub fn update_params_uniform_buffer(&self, frame_in_flight_id: FrameInFlightId, config: &Config) {
     let data = TfxParamsUBO::new(config, self);
     let data_bytes = bytemuck::bytes_of(&data);
@@ -203,6 +314,121 @@ impl TfxObject {
     self.get_position_buffers(frame_idx)[0]
   }
 
+  /// Positions from the previous frame. Used to calculate hair velocity for TAA.
+  pub fn get_previous_position_buffer(&self, frame_idx: FrameIdx) -> &VkBuffer {
+    self.get_position_buffers(frame_idx)[1]
+  }
+
+  /// Read positions calculated by the simulation in the provided frame. Waits till GPU is idle.
+  pub fn read_current_positions(&self, vk_ctx: &VkCtx, frame_idx: FrameIdx) -> Vec<f32> {
+    let buffer = self.get_current_position_buffer(frame_idx);
+    let bytes = vk_ctx.read_buffer_data(buffer);
+    bytemuck::cast_slice(&bytes).to_vec()
+  }
+
+  /// Overwrite all positions buffers, as if the simulation has stopped at this state.
+  pub fn write_positions(&self, vk_ctx: &VkCtx, positions: &[f32]) {
+    let bytes = bytemuck::cast_slice(positions);
+    vk_ctx.write_buffer_data(&self.positions_0_buffer, bytes);
+    vk_ctx.write_buffer_data(&self.positions_1_buffer, bytes);
+    vk_ctx.write_buffer_data(&self.positions_2_buffer, bytes);
+  }
+
+  /// Call before the frame is rendered. If cache is in playback mode,
+  /// writes the next cached frame to position buffers.
+  pub fn update_simulation_cache_playback(&mut self, vk_ctx: &VkCtx) {
+    if let Some(frame) = self.simulation_cache.next_playback_frame() {
+      self.write_positions(vk_ctx, &self.simulation_cache.frames[frame]);
+    }
+  }
+
+  /// Call after the frame was submitted. If cache is recording,
+  /// stores the simulation result. Waits till GPU is idle.
+  pub fn update_simulation_cache_recording(&mut self, vk_ctx: &VkCtx, frame_idx: FrameIdx) {
+    if self.simulation_cache.is_recording() {
+      let positions = self.read_current_positions(vk_ctx, frame_idx);
+      self.simulation_cache.frames.push(positions);
+    }
+  }
+
+  /// Export strands from the provided frame. Waits till GPU is idle.
+  pub fn export_strands(
+    &self,
+    vk_ctx: &VkCtx,
+    frame_idx: FrameIdx,
+    path: &std::path::Path,
+    include_follow_hairs: bool,
+  ) -> std::io::Result<()> {
+    let positions = self.read_current_positions(vk_ctx, frame_idx);
+    let strands = TfxExportStrands::from_simulation(self, &positions, include_follow_hairs);
+    export_strands(path, &strands)
+  }
+
+  /// Refresh simulation state of the selected strand. Waits till GPU is idle.
+  pub fn update_strand_inspector(&mut self, vk_ctx: &VkCtx, frame_idx: FrameIdx) {
+    if self.strand_inspector.selection.is_none() {
+      return;
+    }
+    let buffers = [
+      self.get_current_position_buffer(frame_idx),
+      self.get_previous_position_buffer(frame_idx),
+    ];
+    let data = vk_ctx.read_buffers_data(&buffers);
+    let positions: &[f32] = bytemuck::cast_slice(&data[0]);
+    let prev_positions: &[f32] = bytemuck::cast_slice(&data[1]);
+    let mut inspector = std::mem::take(&mut self.strand_inspector);
+    inspector.update(self, positions, prev_positions);
+    self.strand_inspector = inspector;
+  }
+
+  /// Apply grooming tool to the rest pose. CPU only, call `upload_groomed_rest_pose()`
+  /// when the stroke ends. Uploading every frame while dragging would stall the GPU.
+  pub fn apply_groom_tool(
+    &mut self,
+    prev_ray: &TfxGroomRay,
+    ray: &TfxGroomRay,
+    is_stroke_start: bool,
+  ) {
+    let modified_strands = apply_groom_tool(
+      &self.groom,
+      &mut self.rest_pose,
+      &self.model_matrix,
+      prev_ray,
+      ray,
+      is_stroke_start,
+    );
+    if modified_strands > 0 {
+      self.is_rest_pose_dirty = true;
+    }
+  }
+
+  /// If the rest pose was groomed, upload it to GPU and reset the simulation.
+  pub fn upload_groomed_rest_pose(&mut self, vk_ctx: &VkCtx) {
+    if !self.is_rest_pose_dirty {
+      return;
+    }
+    self.is_rest_pose_dirty = false;
+
+    let positions = &self.rest_pose.raw_vertex_positions;
+    vk_ctx.write_buffer_data(
+      &self.initial_positions_buffer,
+      bytemuck::cast_slice(positions),
+    );
+    let tangents = compute_tangents(&self.rest_pose);
+    vk_ctx.write_buffer_data(
+      &self.initial_tangents_buffer,
+      bytemuck::cast_slice(&tangents),
+    );
+    self.bounding_sphere = compute_bounding_sphere(&self.rest_pose);
+    self.reset_simulation(vk_ctx);
+  }
+
+  /// Write the (possibly groomed) rest pose as `.tfx`
+  pub fn save_rest_pose(&self) -> std::io::Result<()> {
+    let path = std::path::Path::new(&self.groom.file_path);
+    save_tressfx_file(path, &self.rest_pose)
+  }
+
   pub fn vertex_count(&self) -> u32 {
     self.num_hair_strands * self.num_vertices_per_strand
   }
@@ -256,7 +482,7 @@ fn create_positions_buffer(vk_ctx: &VkCtx, name: &str, data: &TfxFileData) -> Vk
     vk_ctx,
     format!("{}.tfx_initial_positions", name),
     &data.raw_vertex_positions,
-    vk::BufferUsageFlags::TRANSFER_SRC,
+    vk::BufferUsageFlags::TRANSFER_SRC | vk::BufferUsageFlags::TRANSFER_DST,
   )
 }
 
@@ -265,7 +491,29 @@ fn create_simulation_positions_buffer(vk_ctx: &VkCtx, name: &str, data: &TfxFile
     vk_ctx,
     name.to_string(),
     &data.raw_vertex_positions,
-    vk::BufferUsageFlags::TRANSFER_DST,
+    vk::BufferUsageFlags::TRANSFER_DST | vk::BufferUsageFlags::TRANSFER_SRC,
+  )
+}
+
+fn create_vertex_weights_buffer(vk_ctx: &VkCtx, name: &str, data: &TfxFileData) -> VkBuffer {
+  create_buffer_from_float_vec(
+    vk_ctx,
+    format!("{}.tfx_vertex_weights", name),
+    &data.raw_vertex_weights,
+    vk::BufferUsageFlags::empty(),
+  )
+}
+
+fn create_follow_hair_neighbours_buffer(
+  vk_ctx: &VkCtx,
+  name: &str,
+  neighbours: &[[u32; FOLLOW_HAIR_NEIGHBOURS]],
+) -> VkBuffer {
+  let bytes = bytemuck::cast_slice(neighbours);
+  vk_ctx.create_buffer_from_data(
+    format!("{}.tfx_follow_hair_neighbours", name),
+    bytes,
+    vk::BufferUsageFlags::STORAGE_BUFFER,
   )
 }
 
@@ -275,6 +523,17 @@ fn create_tangents_buffer(
   data: &TfxFileData,
   is_used_in_sim: bool,
 ) -> VkBuffer {
+  let tangents = compute_tangents(data);
+  let nn = either!(is_used_in_sim, "tfx_tangents", "tfx_initial_tangents");
+  let usage = either!(
+    is_used_in_sim,
+    vk::BufferUsageFlags::TRANSFER_DST,
+    vk::BufferUsageFlags::TRANSFER_SRC | vk::BufferUsageFlags::TRANSFER_DST
+  );
+  create_buffer_from_float_vec(vk_ctx, format!("{}.{}", name, nn), &tangents, usage)
+}
+
+fn compute_tangents(data: &TfxFileData) -> Vec<f32> {
   let total_float_cnt = (data.total_vertices() * 4) as usize;
   let mut tangents = Vec::<f32>::with_capacity(total_float_cnt);
   for _ in 0..total_float_cnt {
@@ -320,13 +579,7 @@ fn create_tangents_buffer(
     }
   }
 
-  let nn = either!(is_used_in_sim, "tfx_tangents", "tfx_initial_tangents");
-  let usage = either!(
-    is_used_in_sim,
-    vk::BufferUsageFlags::TRANSFER_DST,
-    vk::BufferUsageFlags::TRANSFER_SRC
-  );
-  create_buffer_from_float_vec(vk_ctx, format!("{}.{}", name, nn), &tangents, usage)
+  tangents
 }
 
 fn subtract_norm(a: Vec3, b: Vec3) -> Vec3 {
@@ -2,7 +2,12 @@ use glam::Vec3;
 
 use crate::utils::spherical_to_cartesian_dgr;
 
+/// Simulation parameters. Each `TfxObject` has it's own, as e.g. eyebrows
+/// require much stiffer settings than long scalp hair.
 pub struct TfxSimulation {
+  /// Short strands (eyelashes, eyebrows) look the same without simulation.
+  /// If disabled, the strands stay in the rest pose and no compute passes are dispatched.
+  pub enabled: bool,
   pub gravity: f32,
   pub verlet_integration_damping: f32,
   pub global_stiffness: f32,
@@ -18,6 +23,11 @@ pub struct TfxSimulation {
   /// verical [dgr]
   pub wind_pos_theta: f32,
   pub wind_strength: f32,
+
+  /// Bounds of the simulated hair are not read back from the GPU. Instead, the rest pose
+  /// bounding sphere is enlarged by this factor. Increase if the hair swings far from the
+  /// rest pose (strong wind, low stiffness) and gets culled or clipped by the shadow map.
+  pub bounds_scale: f32,
 }
 
 impl TfxSimulation {
@@ -29,6 +39,7 @@ impl TfxSimulation {
 impl Default for TfxSimulation {
   fn default() -> Self {
     Self {
+      enabled: true,
       gravity: 50.0,
       verlet_integration_damping: 0.5,
       // global
@@ -45,6 +56,8 @@ impl Default for TfxSimulation {
       wind_pos_phi: 140.0,
       wind_pos_theta: 105.0,
       wind_strength: 0.0,
+
+      bounds_scale: 1.25,
     }
   }
 }
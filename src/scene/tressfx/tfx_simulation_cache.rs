@@ -0,0 +1,171 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+
+use log::info;
+
+use super::tfx_file_load::{try_read_float_array, try_read_uint};
+
+// Must match order in UI.
+pub enum TfxSimulationCacheMode {
+  /// Simulate as usual
+  Off = 0,
+  /// Simulate as usual and store the positions after each frame
+  Record = 1,
+  /// Do not simulate, copy positions from the cache instead
+  Playback = 2,
+}
+
+/// Recorded simulation run. Used to review problematic sequences frame by frame.
+///
+/// ### File format
+/// All values are little endian:
+/// - `u32` magic (`TFXC`), `u32` version
+/// - `u32` numHairStrands, `u32` numVerticesPerStrand, `u32` frameCount
+/// - `FLOAT4[numHairStrands * numVerticesPerStrand]` for each frame
+pub struct TfxSimulationCache {
+  /// See `TfxSimulationCacheMode`
+  pub mode: usize,
+  /// Raw positions, each frame has `FLOAT4[numHairStrands * numVerticesPerStrand]`
+  pub frames: Vec<Vec<f32>>,
+  pub playback_frame: usize,
+  pub playback_paused: bool,
+  pub file_path: String,
+  /// Frame that is currently in position buffers. Skip upload if nothing changed.
+  pub uploaded_frame: Option<usize>,
+}
+
+impl TfxSimulationCache {
+  const MAGIC: u32 = u32::from_le_bytes(*b"TFXC");
+  const VERSION: u32 = 1;
+
+  pub fn new(name: &str) -> Self {
+    Self {
+      mode: TfxSimulationCacheMode::Off as _,
+      frames: Vec::new(),
+      playback_frame: 0,
+      playback_paused: false,
+      file_path: format!("./{}.tfxsim", name),
+      uploaded_frame: None,
+    }
+  }
+
+  pub fn is_recording(&self) -> bool {
+    self.mode == TfxSimulationCacheMode::Record as usize
+  }
+
+  pub fn is_playback(&self) -> bool {
+    self.mode == TfxSimulationCacheMode::Playback as usize && !self.frames.is_empty()
+  }
+
+  pub fn frame_count(&self) -> usize {
+    self.frames.len()
+  }
+
+  pub fn clear(&mut self) {
+    self.frames.clear();
+    self.playback_frame = 0;
+    self.uploaded_frame = None;
+  }
+
+  /// Select which frame should be written to position buffers. Advances the playback.
+  /// @return index of the frame to upload or None if nothing has changed since last time
+  pub fn next_playback_frame(&mut self) -> Option<usize> {
+    if !self.is_playback() {
+      self.uploaded_frame = None;
+      return None;
+    }
+
+    let frame_count = self.frame_count();
+    if !self.playback_paused && self.uploaded_frame.is_some() {
+      self.playback_frame = (self.playback_frame + 1) % frame_count;
+    }
+    self.playback_frame = self.playback_frame.min(frame_count - 1);
+
+    if self.uploaded_frame == Some(self.playback_frame) {
+      return None;
+    }
+    self.uploaded_frame = Some(self.playback_frame);
+    Some(self.playback_frame)
+  }
+
+  pub fn save_to_file(
+    &self,
+    num_hair_strands: u32,
+    num_vertices_per_strand: u32,
+  ) -> std::io::Result<()> {
+    info!(
+      "Saving TressFX simulation cache ({} frames) to '{}'",
+      self.frame_count(),
+      self.file_path
+    );
+    let file = File::create(&self.file_path)?;
+    let mut w = BufWriter::new(file);
+
+    w.write_all(&Self::MAGIC.to_le_bytes())?;
+    w.write_all(&Self::VERSION.to_le_bytes())?;
+    w.write_all(&num_hair_strands.to_le_bytes())?;
+    w.write_all(&num_vertices_per_strand.to_le_bytes())?;
+    w.write_all(&(self.frame_count() as u32).to_le_bytes())?;
+    for frame in &self.frames {
+      w.write_all(bytemuck::cast_slice(frame))?;
+    }
+    w.flush()
+  }
+
+  /// Replaces current frames with content of the file. Fails if the file
+  /// was recorded for an object with different strand count or is truncated.
+  /// Current frames are kept on failure.
+  pub fn load_from_file(
+    &mut self,
+    num_hair_strands: u32,
+    num_vertices_per_strand: u32,
+  ) -> std::io::Result<()> {
+    info!("Loading TressFX simulation cache from '{}'", self.file_path);
+    let file = File::open(&self.file_path)?;
+    let file_size = file.metadata()?.len();
+    let mut r = BufReader::new(file);
+
+    let magic = try_read_uint(&mut r)?;
+    let version = try_read_uint(&mut r)?;
+    if magic != Self::MAGIC || version != Self::VERSION {
+      return Err(invalid_data_err(format!(
+        "Not a TressFX simulation cache (v{}) file",
+        Self::VERSION
+      )));
+    }
+    let file_strands = try_read_uint(&mut r)?;
+    let file_verts_per_strand = try_read_uint(&mut r)?;
+    if file_strands != num_hair_strands || file_verts_per_strand != num_vertices_per_strand {
+      return Err(invalid_data_err(format!(
+        "Cache was recorded for {} strands with {} vertices each, expected {} strands with {} vertices",
+        file_strands, file_verts_per_strand, num_hair_strands, num_vertices_per_strand
+      )));
+    }
+    let frame_count = try_read_uint(&mut r)?;
+
+    let header_size = 5 * std::mem::size_of::<u32>() as u64;
+    let float_cnt = (num_hair_strands * num_vertices_per_strand * 4) as usize;
+    let frame_size = (float_cnt * std::mem::size_of::<f32>()) as u64;
+    let expected_size = header_size + frame_count as u64 * frame_size;
+    if file_size < expected_size {
+      return Err(invalid_data_err(format!(
+        "Cache has {} bytes, expected {} for {} frames",
+        file_size, expected_size, frame_count
+      )));
+    }
+    let mut frames = Vec::with_capacity(frame_count as usize);
+    for i in 0..(frame_count as u64) {
+      let mut frame = Vec::with_capacity(float_cnt);
+      try_read_float_array(&mut r, &mut frame, header_size + i * frame_size, float_cnt)?;
+      frames.push(frame);
+    }
+
+    self.clear();
+    self.frames = frames;
+    Ok(())
+  }
+}
+
+fn invalid_data_err(msg: String) -> std::io::Error {
+  std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
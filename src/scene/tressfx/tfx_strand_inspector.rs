@@ -0,0 +1,223 @@
+use glam::{Vec3, Vec4};
+
+use super::{get_follow_hair_position, TfxObject};
+
+/// Picking tolerance grows with distance from the camera, so it's roughly constant in pixels.
+const PICK_ANGULAR_TOLERANCE: f32 = 0.005;
+
+/// Strand selected by clicking in the viewport. See `pick_strand()`.
+#[derive(Copy, Clone, PartialEq)]
+pub struct TfxStrandSelection {
+  /// Index of the guide strand
+  pub strand_id: u32,
+  /// 0 for guide strand, follow hairs are `1..follow_hairs`
+  pub instance_id: u32,
+  /// Distance along the picking ray
+  pub distance: f32,
+}
+
+impl TfxStrandSelection {
+  pub fn is_guide(&self) -> bool {
+    self.instance_id == 0
+  }
+}
+
+/// Per-vertex state of the selected strand. Always taken from the guide strand,
+/// follow hairs are not simulated.
+pub struct TfxStrandVertexInfo {
+  /// Model space position calculated by the simulation
+  pub position: Vec3,
+  /// Length of the segment from the previous vertex. 0 for the root
+  pub rest_length: f32,
+  pub current_length: f32,
+  /// Normalized direction to the next vertex (or from previous for the tip)
+  pub tangent: Vec3,
+  /// Distance travelled since previous frame
+  pub velocity: f32,
+  /// `w` of the position, 0 if the vertex is not movable
+  pub is_movable: bool,
+  /// See `TfxFileData.raw_vertex_weights`
+  pub is_pinned: bool,
+}
+
+#[derive(Default)]
+pub struct TfxStrandInspector {
+  pub selection: Option<TfxStrandSelection>,
+  /// Read simulation state every frame. Waits till GPU is idle, so it's slow.
+  pub live_update: bool,
+  pub vertices: Vec<TfxStrandVertexInfo>,
+}
+
+impl TfxStrandInspector {
+  pub fn clear(&mut self) {
+    self.selection = None;
+    self.vertices.clear();
+  }
+
+  pub fn rest_length(&self) -> f32 {
+    self.vertices.iter().map(|v| v.rest_length).sum()
+  }
+
+  pub fn current_length(&self) -> f32 {
+    self.vertices.iter().map(|v| v.current_length).sum()
+  }
+
+  pub fn max_velocity(&self) -> f32 {
+    self.vertices.iter().map(|v| v.velocity).fold(0.0, f32::max)
+  }
+
+  /// Largest `current / rest` segment length ratio
+  pub fn max_stretch(&self) -> f32 {
+    self
+      .vertices
+      .iter()
+      .filter(|v| v.rest_length > 0.0)
+      .map(|v| v.current_length / v.rest_length)
+      .fold(0.0, f32::max)
+  }
+
+  /// Refresh per-vertex state of the selected strand.
+  ///
+  /// @param positions current simulation positions, `FLOAT4[numHairStrands * numVerticesPerStrand]`
+  /// @param prev_positions simulation positions from the previous frame
+  pub fn update(&mut self, tfx: &TfxObject, positions: &[f32], prev_positions: &[f32]) {
+    let selection = match self.selection {
+      Some(s) => s,
+      None => return,
+    };
+    let verts_per_strand = tfx.num_vertices_per_strand as usize;
+    let first_vertex = selection.strand_id as usize * verts_per_strand;
+    let get_position = |positions: &[f32], vertex_id: usize| -> Vec4 {
+      let idx = (first_vertex + vertex_id) * 4;
+      Vec4::from_slice(&positions[idx..idx + 4])
+    };
+    let rest_pose = &tfx.rest_pose;
+
+    self.vertices = (0..verts_per_strand)
+      .map(|vertex_id| {
+        let pos = get_position(positions, vertex_id);
+        let prev_pos = get_position(prev_positions, vertex_id).truncate();
+        let rest_pos = rest_pose.get_vertex_pos(first_vertex + vertex_id);
+        let (rest_length, current_length) = if vertex_id == 0 {
+          (0.0, 0.0)
+        } else {
+          let rest_prev = rest_pose.get_vertex_pos(first_vertex + vertex_id - 1);
+          let current_prev = get_position(positions, vertex_id - 1).truncate();
+          (
+            rest_pos.distance(rest_prev),
+            pos.truncate().distance(current_prev),
+          )
+        };
+        let tangent = if vertex_id + 1 < verts_per_strand {
+          get_position(positions, vertex_id + 1).truncate() - pos.truncate()
+        } else {
+          pos.truncate() - get_position(positions, vertex_id - 1).truncate()
+        };
+        let weights_idx = (first_vertex + vertex_id) * 4;
+        let is_pinned = rest_pose
+          .raw_vertex_weights
+          .get(weights_idx + 3)
+          .is_some_and(|pinned| *pinned > 0.5);
+
+        TfxStrandVertexInfo {
+          position: pos.truncate(),
+          rest_length,
+          current_length,
+          tangent: tangent.normalize_or_zero(),
+          velocity: pos.truncate().distance(prev_pos),
+          is_movable: pos.w > 0.0,
+          is_pinned,
+        }
+      })
+      .collect();
+  }
+}
+
+/// Find the strand (guide or follow hair) closest to the camera along the ray.
+/// Only what is rendered can be picked: follow hairs hidden by LOD are skipped,
+/// strand width follows thin tip and LOD, and strands are shortened by length variation.
+/// Strands are tested as polylines, so curl and frizz are ignored.
+///
+/// @param positions current simulation positions, `FLOAT4[numHairStrands * numVerticesPerStrand]`
+/// @param ray_origin world space
+/// @param ray_dir world space, normalized
+pub fn pick_strand(
+  tfx: &TfxObject,
+  positions: &[f32],
+  ray_origin: Vec3,
+  ray_dir: Vec3,
+) -> Option<TfxStrandSelection> {
+  let verts_per_strand = tfx.num_vertices_per_strand;
+  let mut result: Option<TfxStrandSelection> = None;
+
+  for instance_id in 0..tfx.lod.instance_count() {
+    let fiber_radius =
+      tfx.fiber_radius * tfx.lod.width_scale * get_lod_instance_fade(tfx, instance_id);
+    for strand_id in 0..tfx.num_hair_strands {
+      let last_vertex =
+        tfx
+          .strand_modifiers
+          .get_last_vertex(instance_id, strand_id, verts_per_strand);
+      let mut prev = get_follow_hair_position(tfx, positions, instance_id, strand_id, 0);
+      for vertex_id in 1..=last_vertex {
+        let next = get_follow_hair_position(tfx, positions, instance_id, strand_id, vertex_id);
+        let hit = ray_segment_distance(ray_origin, ray_dir, prev, next);
+        prev = next;
+
+        let (t, dist) = match hit {
+          Some(hit) => hit,
+          None => continue,
+        };
+        // same as `ratio` in `getExpandedTressFXVert()`. 1 := root, 0 := tip
+        let fraction_of_strand = 1.0 - (vertex_id as f32) / (verts_per_strand as f32);
+        let thin_tip = (1.0 - tfx.thin_tip) + tfx.thin_tip * fraction_of_strand;
+        let tolerance = fiber_radius * thin_tip + t * PICK_ANGULAR_TOLERANCE;
+        let is_closer = result.is_none_or(|r| t < r.distance);
+        if dist < tolerance && is_closer {
+          result = Some(TfxStrandSelection {
+            strand_id,
+            instance_id,
+            distance: t,
+          });
+        }
+      }
+    }
+  }
+
+  result
+}
+
+/// `getLodInstanceFade()` from `_tfx_vertex_resolve.glsl`
+fn get_lod_instance_fade(tfx: &TfxObject, instance_id: u32) -> f32 {
+  (tfx.lod.follow_hairs - instance_id as f32).clamp(0.0, 1.0)
+}
+
+/// @return true if the ray hits (or starts inside) the sphere
+pub fn ray_sphere_hit(origin: Vec3, dir: Vec3, center: Vec3, radius: f32) -> bool {
+  let to_center = center - origin;
+  let t = to_center.dot(dir).max(0.0);
+  to_center.distance_squared(dir * t) <= radius * radius
+}
+
+/// Closest points between the ray and segment `ab`.
+/// @return `(t along the ray, distance)`. None if the closest point is behind the ray origin
+fn ray_segment_distance(origin: Vec3, dir: Vec3, a: Vec3, b: Vec3) -> Option<(f32, f32)> {
+  let ab = b - a;
+  let ao = origin - a;
+  let ab_len2 = ab.length_squared();
+  let d_ab = dir.dot(ab);
+  let denom = ab_len2 - d_ab * d_ab; // |dir| == 1
+
+  // parameter on the segment, clamped to its ends
+  let s = if denom > f32::EPSILON {
+    ((ab.dot(ao) - d_ab * dir.dot(ao)) / denom).clamp(0.0, 1.0)
+  } else {
+    0.0
+  };
+  let p = a + ab * s;
+  let t = (p - origin).dot(dir);
+  if t < 0.0 {
+    return None;
+  }
+  Some((t, p.distance(origin + dir * t)))
+}
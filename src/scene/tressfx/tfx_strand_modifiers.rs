@@ -0,0 +1,43 @@
+use glam::vec3;
+
+use super::glsl_hash;
+
+/// Procedural modifications applied to each rendered strand (both guide and follow hairs).
+/// Done in the vertex shader, so it's the same for every pass that renders hair
+/// (PPLL, solid forward, depth-only, shadow map). Simulation is not affected.
+///
+/// See `applyLengthVariation()` and `getStrandModifiersOffset()` in `_tfx_vertex_resolve.glsl`.
+pub struct TfxStrandModifiers {
+  /// Radius of the helix around the strand. 0 to disable
+  pub curl_radius: f32,
+  /// Number of helix turns along the whole strand
+  pub curl_frequency: f32,
+  /// Random offset for each vertex. Grows toward the tip. 0 to disable
+  pub frizz: f32,
+  /// Each strand is randomly shortened up to this fraction of its length. 0 to disable
+  pub length_variation: f32,
+}
+
+impl TfxStrandModifiers {
+  /// CPU version of `applyLengthVariation()` in `_tfx_vertex_resolve.glsl`.
+  /// @return last rendered vertex of the strand
+  pub fn get_last_vertex(&self, instance_id: u32, strand_id: u32, verts_per_strand: u32) -> u32 {
+    if self.length_variation <= 0.0 {
+      return verts_per_strand - 1;
+    }
+    let rng = glsl_hash(vec3(instance_id as f32, strand_id as f32, 4.0)).x;
+    let keep_fraction = 1.0 - rng * self.length_variation;
+    (((verts_per_strand - 1) as f32 * keep_fraction) as u32).max(1)
+  }
+}
+
+impl Default for TfxStrandModifiers {
+  fn default() -> Self {
+    Self {
+      curl_radius: 0.0,
+      curl_frequency: 6.0,
+      frizz: 0.0,
+      length_variation: 0.0,
+    }
+  }
+}
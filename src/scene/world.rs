@@ -1,12 +1,77 @@
-use super::{Camera, TfxObject, WorldEntity};
+use glam::Vec3;
+use log::info;
+
+use super::{
+  pick_strand, ray_sphere_hit, BoundingBox, Camera, EnvironmentMap, TfxObject, WorldEntity,
+};
+use crate::{app_timer::FrameIdx, vk_ctx::VkCtx, vk_utils::VkBuffer};
 
 pub struct World {
   pub camera: Camera,
   pub entities: Vec<WorldEntity>,
   pub tressfx_objects: Vec<TfxObject>,
+  /// Image based lighting. If not provided, `LightAmbient` is used instead
+  pub environment: Option<EnvironmentMap>,
 }
 
 impl World {
+  /// If true, left mouse button is used by grooming tools instead of the camera
+  pub fn is_grooming(&self) -> bool {
+    self.tressfx_objects.iter().any(|tfx| tfx.groom.enabled)
+  }
+
+  /// Bounds of all meshes and hair, in world space. Used to fit shadow frustums.
+  pub fn bounding_box(&self) -> BoundingBox {
+    let mut boxes = self
+      .entities
+      .iter()
+      .map(|entity| entity.aabb)
+      .chain(self.tressfx_objects.iter().map(|tfx| tfx.bounding_box()));
+    let first = boxes
+      .next()
+      .unwrap_or(BoundingBox::from_sphere(Vec3::ZERO, 1.0));
+    boxes.fold(first, |acc, bb| acc.union(&bb))
+  }
+
+  /// Select strand closest to the camera from all hair objects. Clicking on empty space clears the selection.
+  /// Objects are culled by their bounds, positions of the rest are read back with a single GPU wait.
+  pub fn pick_strand(&mut self, vk_ctx: &VkCtx, frame_idx: FrameIdx, origin: Vec3, dir: Vec3) {
+    let candidates: Vec<usize> = (0..self.tressfx_objects.len())
+      .filter(|i| {
+        let (center, radius) = self.tressfx_objects[*i].world_bounding_sphere();
+        ray_sphere_hit(origin, dir, center, radius)
+      })
+      .collect();
+    let buffers: Vec<&VkBuffer> = candidates
+      .iter()
+      .map(|i| self.tressfx_objects[*i].get_current_position_buffer(frame_idx))
+      .collect();
+    let positions = vk_ctx.read_buffers_data(&buffers);
+
+    let picked = candidates
+      .iter()
+      .zip(positions.iter())
+      .filter_map(|(i, bytes)| {
+        let tfx = &self.tressfx_objects[*i];
+        pick_strand(tfx, bytemuck::cast_slice(bytes), origin, dir).map(|selection| (*i, selection))
+      })
+      .min_by(|a, b| a.1.distance.total_cmp(&b.1.distance));
+
+    for (i, tfx_entity) in self.tressfx_objects.iter_mut().enumerate() {
+      match picked {
+        Some((picked_idx, selection)) if picked_idx == i => {
+          info!(
+            "Selected strand {} (instance {}) of '{}'",
+            selection.strand_id, selection.instance_id, tfx_entity.name
+          );
+          tfx_entity.strand_inspector.selection = Some(selection);
+          tfx_entity.update_strand_inspector(vk_ctx, frame_idx);
+        }
+        _ => tfx_entity.strand_inspector.clear(),
+      }
+    }
+  }
+
   pub unsafe fn destroy(&mut self, device: &ash::Device, allocator: &vma::Allocator) -> () {
     for entity in &mut self.entities {
       entity.destroy(device, allocator);
@@ -15,5 +80,9 @@ impl World {
     for entity in &mut self.tressfx_objects {
       entity.destroy(allocator);
     }
+
+    if let Some(environment) = &mut self.environment {
+      environment.destroy(device, allocator);
+    }
   }
 }
@@ -5,7 +5,6 @@ use ash::vk;
 use glam::Mat4;
 
 use crate::{
-  config::Config,
   render_graph::ForwardModelUBO,
   vk_ctx::VkCtx,
   vk_utils::{FrameInFlightId, VkBuffer, VkMemoryPreference, VkMemoryResource},
@@ -43,13 +42,8 @@ impl WorldEntity {
     &self.model_ubo[frame_in_flight_id]
   }
 
-  pub fn update_ubo_data(
-    &self,
-    frame_in_flight_id: FrameInFlightId,
-    config: &Config,
-    camera: &Camera,
-  ) {
-    let data = ForwardModelUBO::new(config, self, camera);
+  pub fn update_ubo_data(&self, frame_in_flight_id: FrameInFlightId, camera: &Camera) {
+    let data = ForwardModelUBO::new(self, camera);
     let data_bytes = bytemuck::bytes_of(&data);
     let buffer = self.get_ubo_buffer(frame_in_flight_id);
     buffer.write_to_mapped(data_bytes);
@@ -27,6 +27,10 @@ pub struct VkCtx {
   /// C'mon you will not use non-linear/nearest sampling anyway, can just create global objects..
   pub default_texture_sampler_linear: vk::Sampler,
   pub default_texture_sampler_nearest: vk::Sampler,
+  /// Linear, samples all mips. Roughness selects the prefiltered mip of `EnvironmentMap`
+  pub environment_map_sampler: vk::Sampler,
+  /// Queried once at startup, see `get_supported_msaa_sample_counts()`
+  pub supported_msaa_sample_counts: vk::SampleCountFlags,
 
   // surface
   pub surface_loader: Surface,
@@ -49,6 +53,17 @@ impl VkCtx {
     &self.device.device
   }
 
+  /// Highest MSAA sample count not above `requested`, that the device supports
+  /// for all kinds of attachments. See `supported_msaa_sample_counts`.
+  pub fn get_supported_msaa_samples(&self, requested: u32) -> vk::SampleCountFlags {
+    let supported = self.supported_msaa_sample_counts;
+    [8, 4, 2]
+      .iter()
+      .map(|&samples| vk::SampleCountFlags::from_raw(samples))
+      .find(|&samples| samples.as_raw() <= requested && supported.contains(samples))
+      .unwrap_or(vk::SampleCountFlags::TYPE_1)
+  }
+
   pub fn with_debug_loader(&self, callback: impl FnOnce(&DebugUtils)) {
     self.debug_utils.as_ref().map(|dbg| callback(&dbg.0));
   }
@@ -88,6 +103,7 @@ impl VkCtx {
     self.surface_loader.destroy_surface(self.surface_khr, None);
     device.destroy_sampler(self.default_texture_sampler_linear, None);
     device.destroy_sampler(self.default_texture_sampler_nearest, None);
+    device.destroy_sampler(self.environment_map_sampler, None);
 
     info!("VkCtx::destroy() finished. All app resources should be deleted. Only Device, Allocator and Instance remain.");
   }
@@ -62,6 +62,7 @@ pub fn vk_ctx_initialize(
   let (phys_device, queue_family_index) =
     pick_physical_device_and_queue_family_idx(&instance, &surface_loader, surface_khr);
   let (device, queue) = pick_device_and_queue(&instance, phys_device, queue_family_index);
+  let supported_msaa_sample_counts = get_supported_msaa_sample_counts(&instance, phys_device);
 
   // push descriptor set as alternative to descriptor set pools etc.
   let push_descriptor = PushDescriptor::new(&instance, &device);
@@ -114,12 +115,21 @@ pub fn vk_ctx_initialize(
     vk::Filter::LINEAR,
     vk::Filter::LINEAR,
     vk::SamplerMipmapMode::LINEAR,
+    0.0,
   );
   let sampler_nearest = create_sampler(
     &device,
     vk::Filter::NEAREST,
     vk::Filter::NEAREST,
     vk::SamplerMipmapMode::NEAREST,
+    0.0,
+  );
+  let sampler_environment_map = create_sampler(
+    &device,
+    vk::Filter::LINEAR,
+    vk::Filter::LINEAR,
+    vk::SamplerMipmapMode::LINEAR,
+    vk::LOD_CLAMP_NONE,
   );
 
   VkCtx {
@@ -148,5 +158,7 @@ pub fn vk_ctx_initialize(
     debug_utils,
     default_texture_sampler_linear: sampler_linear,
     default_texture_sampler_nearest: sampler_nearest,
+    environment_map_sampler: sampler_environment_map,
+    supported_msaa_sample_counts,
   }
 }
@@ -1,12 +1,17 @@
 use ash::vk;
 use ash::{self};
 
+use crate::either;
 use crate::utils::get_attachment_name;
 use crate::vk_utils::debug::{set_buffer_debug_label, set_texture_debug_label};
 use crate::vk_utils::{
-  get_image_aspect_from_format, VkBuffer, VkMemoryPreference, VkMemoryResource, VkTexture,
+  create_image_barrier, get_image_aspect_from_format, VkBuffer, VkMemoryPreference,
+  VkMemoryResource, VkStorageResourceBarrier, VkTexture, WithSetupCmdBuffer,
 };
 
+#[allow(deprecated)]
+use crate::vk_utils::execute_full_pipeline_barrier;
+
 use super::*;
 
 impl VkCtx {
@@ -48,6 +53,168 @@ impl VkCtx {
     buffer
   }
 
+  /// Copy content of the buffer to CPU. Waits till GPU is idle, so do not use every frame
+  /// unless You know what You are doing. Buffer requires `TRANSFER_SRC` usage.
+  pub fn read_buffer_data(&self, buffer: &VkBuffer) -> Vec<u8> {
+    self.read_buffers_data(&[buffer]).remove(0)
+  }
+
+  /// Same as `read_buffer_data()`, but waits for the GPU only once for all buffers.
+  pub fn read_buffers_data(&self, buffers: &[&VkBuffer]) -> Vec<Vec<u8>> {
+    if buffers.is_empty() {
+      return Vec::new();
+    }
+    let mut readback_buffers: Vec<VkBuffer> = buffers
+      .iter()
+      .map(|buffer| {
+        self.create_buffer_empty(
+          format!("{}-readback-buffer", buffer.get_name()),
+          buffer.size,
+          vk::BufferUsageFlags::TRANSFER_DST,
+          VkMemoryPreference::CpuReadback,
+        )
+      })
+      .collect();
+
+    self.with_setup_cb(|device, cb| unsafe {
+      // Wait for all previous writes e.g. from simulation
+      #[allow(deprecated)]
+      execute_full_pipeline_barrier(device, cb);
+
+      for (buffer, readback_buffer) in buffers.iter().zip(readback_buffers.iter()) {
+        let mem_region = ash::vk::BufferCopy::builder()
+          .dst_offset(0)
+          .src_offset(0)
+          .size(buffer.size as u64)
+          .build();
+        device.cmd_copy_buffer(cb, buffer.buffer, readback_buffer.buffer, &[mem_region]);
+      }
+
+      #[allow(deprecated)]
+      execute_full_pipeline_barrier(device, cb);
+    });
+
+    readback_buffers
+      .iter_mut()
+      .map(|readback_buffer| {
+        let mut bytes = vec![0u8; readback_buffer.size];
+        readback_buffer.invalidate_mapped(&self.allocator);
+        readback_buffer.read_from_mapped(&mut bytes);
+        unsafe { readback_buffer.delete(&self.allocator) };
+        bytes
+      })
+      .collect()
+  }
+
+  /// Overwrite content of the buffer. Waits till GPU is idle, so do not use every frame
+  /// unless You know what You are doing. Buffer requires `TRANSFER_DST` usage.
+  pub fn write_buffer_data(&self, buffer: &VkBuffer, bytes: &[u8]) {
+    let size = bytes.len();
+    let mut scratch_buffer = self.create_buffer_empty(
+      format!("{}-scratch-buffer", buffer.get_name()),
+      size,
+      vk::BufferUsageFlags::TRANSFER_SRC,
+      VkMemoryPreference::ScratchTransfer,
+    );
+    scratch_buffer.write_to_mapped(bytes);
+
+    self.with_setup_cb(|device, cb| unsafe {
+      // Previous frames might still use the buffer
+      #[allow(deprecated)]
+      execute_full_pipeline_barrier(device, cb);
+
+      let mem_region = ash::vk::BufferCopy::builder()
+        .dst_offset(0)
+        .src_offset(0)
+        .size(size as u64)
+        .build();
+      device.cmd_copy_buffer(cb, scratch_buffer.buffer, buffer.buffer, &[mem_region]);
+
+      #[allow(deprecated)]
+      execute_full_pipeline_barrier(device, cb);
+    });
+
+    unsafe { scratch_buffer.delete(&self.allocator) };
+  }
+
+  /// Copy a single texel of the texture to CPU. Waits till GPU is idle, so do not use every frame
+  /// unless You know what You are doing. Texture requires `TRANSFER_SRC` usage.
+  /// Layout of the texture is restored afterwards.
+  pub fn read_texture_texel(
+    &self,
+    texture: &VkTexture,
+    texel_bytes: usize,
+    x: u32,
+    y: u32,
+  ) -> Vec<u8> {
+    let mut readback_buffer = self.create_buffer_empty(
+      format!("{}-readback-buffer", texture.get_name()),
+      texel_bytes,
+      vk::BufferUsageFlags::TRANSFER_DST,
+      VkMemoryPreference::CpuReadback,
+    );
+    let transfer_layout = vk::ImageLayout::TRANSFER_SRC_OPTIMAL;
+    #[allow(deprecated)]
+    let barrier_to_transfer = create_image_barrier(
+      texture.image,
+      texture.aspect_flags,
+      texture.layout,
+      transfer_layout,
+      VkStorageResourceBarrier::full_pipeline_stall(),
+    );
+    #[allow(deprecated)]
+    let barrier_from_transfer = create_image_barrier(
+      texture.image,
+      texture.aspect_flags,
+      transfer_layout,
+      texture.layout,
+      VkStorageResourceBarrier::full_pipeline_stall(),
+    );
+
+    self.with_setup_cb(|device, cb| unsafe {
+      let barriers = [barrier_to_transfer];
+      let dep = vk::DependencyInfo::builder().image_memory_barriers(&barriers);
+      device.cmd_pipeline_barrier2(cb, &dep);
+
+      let region = vk::BufferImageCopy::builder()
+        .image_subresource(vk::ImageSubresourceLayers {
+          aspect_mask: texture.aspect_flags,
+          mip_level: 0,
+          base_array_layer: 0,
+          layer_count: 1,
+        })
+        .image_offset(vk::Offset3D {
+          x: x as _,
+          y: y as _,
+          z: 0,
+        })
+        .image_extent(vk::Extent3D {
+          width: 1,
+          height: 1,
+          depth: 1,
+        })
+        .build();
+      device.cmd_copy_image_to_buffer(
+        cb,
+        texture.image,
+        transfer_layout,
+        readback_buffer.buffer,
+        &[region],
+      );
+
+      let barriers = [barrier_from_transfer];
+      let dep = vk::DependencyInfo::builder().image_memory_barriers(&barriers);
+      device.cmd_pipeline_barrier2(cb, &dep);
+    });
+
+    let mut bytes = vec![0u8; texel_bytes];
+    // memory might not be HOST_COHERENT
+    readback_buffer.invalidate_mapped(&self.allocator);
+    readback_buffer.read_from_mapped(&mut bytes);
+    unsafe { readback_buffer.delete(&self.allocator) };
+    bytes
+  }
+
   fn assign_buffer_debug_label(&self, buffer: &VkBuffer) {
     self.with_debug_loader(|debug_utils_loader| {
       unsafe {
@@ -93,22 +260,91 @@ impl VkCtx {
     name: &str,
     format: vk::Format,
     size: vk::Extent2D,
+  ) -> VkTexture {
+    self.create_attachment_multisampled::<PassType>(
+      name,
+      format,
+      size,
+      vk::SampleCountFlags::TYPE_1,
+    )
+  }
+
+  /// Same as `create_attachment()`, but the content can be read on CPU
+  /// with `read_texture_texel()` e.g. DoF autofocus.
+  pub fn create_readable_attachment<PassType>(
+    &self,
+    name: &str,
+    format: vk::Format,
+    size: vk::Extent2D,
+  ) -> VkTexture {
+    self.create_attachment_with_usage::<PassType>(
+      name,
+      format,
+      size,
+      vk::SampleCountFlags::TYPE_1,
+      vk::ImageUsageFlags::TRANSFER_SRC,
+    )
+  }
+
+  /// Multisampled attachments cannot be sampled in shaders, they have to be resolved first.
+  pub fn create_attachment_multisampled<PassType>(
+    &self,
+    name: &str,
+    format: vk::Format,
+    size: vk::Extent2D,
+    samples: vk::SampleCountFlags,
+  ) -> VkTexture {
+    self.create_attachment_with_usage::<PassType>(
+      name,
+      format,
+      size,
+      samples,
+      vk::ImageUsageFlags::empty(),
+    )
+  }
+
+  fn create_attachment_with_usage<PassType>(
+    &self,
+    name: &str,
+    format: vk::Format,
+    size: vk::Extent2D,
+    samples: vk::SampleCountFlags,
+    extra_usage: vk::ImageUsageFlags,
   ) -> VkTexture {
     let aspect = get_image_aspect_from_format(format);
-    let mut usage_flags = vk::ImageUsageFlags::SAMPLED;
+    let is_multisampled = samples != vk::SampleCountFlags::TYPE_1;
+    let mut usage_flags = extra_usage
+      | either!(
+        is_multisampled,
+        vk::ImageUsageFlags::empty(),
+        vk::ImageUsageFlags::SAMPLED
+      );
     let mut initial_layout = vk::ImageLayout::PREINITIALIZED;
 
+    // not sampled, so start in a layout for writes instead
     if aspect == (vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL) {
       usage_flags |= vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT;
-      initial_layout = vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL;
+      initial_layout = either!(
+        is_multisampled,
+        vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL
+      );
     }
     if aspect == vk::ImageAspectFlags::DEPTH {
       usage_flags |= vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT;
-      initial_layout = vk::ImageLayout::DEPTH_READ_ONLY_OPTIMAL;
+      initial_layout = either!(
+        is_multisampled,
+        vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL,
+        vk::ImageLayout::DEPTH_READ_ONLY_OPTIMAL
+      );
     }
     if aspect == vk::ImageAspectFlags::COLOR {
       usage_flags |= vk::ImageUsageFlags::COLOR_ATTACHMENT;
-      initial_layout = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+      initial_layout = either!(
+        is_multisampled,
+        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+      );
     }
     assert!(
       // we have not changed from defaults - invalid!
@@ -118,15 +354,19 @@ impl VkCtx {
       aspect
     );
 
-    self.create_texture_empty(
+    let tex = VkTexture::empty_multisampled(
+      &self.vk_device(),
+      &self.allocator,
+      self,
       get_attachment_name::<PassType>(name),
       size,
       format,
-      vk::ImageTiling::OPTIMAL,
       usage_flags,
-      VkMemoryPreference::GpuOnly,
       initial_layout,
-    )
+      samples,
+    );
+    self.assign_texture_debug_label(&tex);
+    tex
   }
 
   pub fn create_texture_from_file(&self, path: &std::path::Path, format: vk::Format) -> VkTexture {
@@ -155,6 +395,26 @@ impl VkCtx {
     tex
   }
 
+  pub fn create_texture_from_mips_data(
+    &self,
+    name: String,
+    size: vk::Extent2D,
+    format: vk::Format,
+    mips_data_bytes: &[Vec<u8>],
+  ) -> VkTexture {
+    let tex = VkTexture::from_mips_data(
+      &self.vk_device(),
+      &self.allocator,
+      self,
+      name,
+      size,
+      format,
+      mips_data_bytes,
+    );
+    self.assign_texture_debug_label(&tex);
+    tex
+  }
+
   fn assign_texture_debug_label(&self, tex: &VkTexture) {
     self.with_debug_loader(|debug_utils_loader| {
       unsafe {
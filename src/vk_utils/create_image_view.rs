@@ -5,15 +5,14 @@ pub fn create_image_view(
   image: vk::Image,
   image_format: vk::Format,
   aspect_mask_flags: vk::ImageAspectFlags,
-  // base_mip_level: u32,
-  // mip_level_count: u32,
+  mip_level_count: u32,
 ) -> vk::ImageView {
   let subresource_range = vk::ImageSubresourceRange::builder()
     .aspect_mask(aspect_mask_flags)
     .base_array_layer(0)
     .layer_count(1)
-    .base_mip_level(0) // base_mip_level
-    .level_count(1) // mip_level_count
+    .base_mip_level(0)
+    .level_count(mip_level_count)
     .build();
 
   let create_info = vk::ImageViewCreateInfo::builder()
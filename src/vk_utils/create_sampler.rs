@@ -3,11 +3,14 @@ use ash::vk;
 
 /// OMG so much fluff for simple sampler.
 /// And the only thing that changes 99% of time is filtering `LINEAR`/`NEAREST`
+///
+/// Use `max_lod = 0.0` to always sample mip 0, or `vk::LOD_CLAMP_NONE` for all mips.
 pub fn create_sampler(
   device: &ash::Device,
   mag_filter: vk::Filter,
   min_filter: vk::Filter,
   mipmap_mode: vk::SamplerMipmapMode,
+  max_lod: f32,
 ) -> vk::Sampler {
   let create_info = vk::SamplerCreateInfo::builder()
     .mag_filter(mag_filter)
@@ -25,7 +28,7 @@ pub fn create_sampler(
     .mipmap_mode(mipmap_mode)
     .mip_lod_bias(0f32)
     .min_lod(0f32)
-    .max_lod(0f32)
+    .max_lod(max_lod)
     .build();
 
   unsafe {
@@ -11,3 +11,17 @@ pub fn create_viewport(size: &vk::Extent2D) -> vk::Viewport {
     ..Default::default()
   }
 }
+
+/// Same as `create_viewport()`, but only covers part of the framebuffer (e.g. shadow atlas tile)
+pub fn create_viewport_for_rect(rect: &vk::Rect2D) -> vk::Viewport {
+  let height = rect.extent.height as f32;
+  vk::Viewport {
+    x: rect.offset.x as f32,
+    y: rect.offset.y as f32 + height, // flip vulkan coord system - important!
+    width: rect.extent.width as f32,
+    height: -height, // flip vulkan coord system - important!
+    min_depth: 0f32,
+    max_depth: 1.0f32,
+    ..Default::default()
+  }
+}
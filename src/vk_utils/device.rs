@@ -202,3 +202,21 @@ pub fn pick_device_and_queue(
 
   (device, queue)
 }
+
+/// MSAA sample counts the device supports for all kinds of attachments
+/// (float/integer colors, depth and stencil).
+pub fn get_supported_msaa_sample_counts(
+  instance: &ash::Instance,
+  phys_device: vk::PhysicalDevice,
+) -> vk::SampleCountFlags {
+  let mut props_12 = vk::PhysicalDeviceVulkan12Properties::default();
+  let mut props = vk::PhysicalDeviceProperties2::builder()
+    .push_next(&mut props_12)
+    .build();
+  unsafe { instance.get_physical_device_properties2(phys_device, &mut props) };
+  let limits = props.properties.limits;
+  limits.framebuffer_color_sample_counts
+    & limits.framebuffer_depth_sample_counts
+    & limits.framebuffer_stencil_sample_counts
+    & props_12.framebuffer_integer_color_sample_counts
+}
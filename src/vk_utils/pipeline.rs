@@ -61,8 +61,44 @@ pub fn create_pipeline_with_defaults(
   color_attachment_count: usize,
   creator: impl Fn(vk::GraphicsPipelineCreateInfoBuilder) -> vk::Pipeline,
 ) -> vk::Pipeline {
-  let (module_vs, stage_vs, module_fs, stage_fs) =
-    load_render_shaders(device, shader_paths.0, shader_paths.1);
+  let shaders = SpecializedShaders {
+    paths: shader_paths,
+    fs_specialization: None,
+  };
+  create_pipeline_with_defaults_specialized(
+    device,
+    render_pass,
+    pipeline_layout,
+    shaders,
+    vertex_desc,
+    color_attachment_count,
+    creator,
+  )
+}
+
+/// Vertex and fragment shader paths, with optional values
+/// for specialization constants (`layout(constant_id = X)`) of the fragment shader.
+pub struct SpecializedShaders<'a> {
+  pub paths: (&'a str, &'a str),
+  pub fs_specialization: Option<&'a vk::SpecializationInfo>,
+}
+
+/// Same as `create_pipeline_with_defaults`, but also provides values
+/// for specialization constants of the fragment shader.
+pub fn create_pipeline_with_defaults_specialized(
+  device: &ash::Device,
+  render_pass: &vk::RenderPass,
+  pipeline_layout: &vk::PipelineLayout,
+  shaders: SpecializedShaders,
+  vertex_desc: vk::PipelineVertexInputStateCreateInfo,
+  color_attachment_count: usize,
+  creator: impl Fn(vk::GraphicsPipelineCreateInfoBuilder) -> vk::Pipeline,
+) -> vk::Pipeline {
+  let (module_vs, stage_vs, module_fs, mut stage_fs) =
+    load_render_shaders(device, shaders.paths.0, shaders.paths.1);
+  if let Some(specialization_info) = shaders.fs_specialization {
+    stage_fs.p_specialization_info = specialization_info;
+  }
 
   let dynamic_state = ps_dynamic_state(&[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR]);
 
@@ -134,6 +170,19 @@ pub fn create_compute_pipeline(
   pipeline
 }
 
+/// Entries for specialization constants with `constant_id` `0..count`.
+/// Each constant is 4 bytes (int, uint, float or bool), tightly packed in the same order.
+pub fn create_specialization_map_entries(count: usize) -> Vec<vk::SpecializationMapEntry> {
+  let constant_size = std::mem::size_of::<u32>();
+  (0..count)
+    .map(|i| vk::SpecializationMapEntry {
+      constant_id: i as u32,
+      offset: (i * constant_size) as u32,
+      size: constant_size,
+    })
+    .collect()
+}
+
 // This file contains presets for `vk::GraphicsPipelineCreateInfo`.
 // Most common options, so it's actually manageable and <100LOC every time
 
@@ -293,8 +342,13 @@ pub fn ps_stencil_compare_equal(reference: u32) -> vk::StencilOpState {
 }
 
 pub fn ps_multisample_disabled() -> vk::PipelineMultisampleStateCreateInfo {
+  ps_multisample(vk::SampleCountFlags::TYPE_1)
+}
+
+/// Has to match sample count of the render pass attachments
+pub fn ps_multisample(samples: vk::SampleCountFlags) -> vk::PipelineMultisampleStateCreateInfo {
   vk::PipelineMultisampleStateCreateInfo::builder()
-    .rasterization_samples(vk::SampleCountFlags::TYPE_1)
+    .rasterization_samples(samples)
     // fragment shader per sample? Yes, please do! Oh wait, validation layers..
     .sample_shading_enable(false)
     // other sample coverage stuff
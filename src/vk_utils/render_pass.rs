@@ -118,3 +118,120 @@ pub unsafe fn create_render_pass_from_attachments(
     .create_render_pass(&create_info, None)
     .expect("Failed creating render pass")
 }
+
+/// Same as `create_render_pass_from_attachments()`, but all attachments are multisampled.
+/// They are resolved at the end of the subpass into single sampled attachments
+/// (indices after the multisampled ones, in the same order: depth first, then colors).
+/// Multisampled values themselves are not stored.
+///
+/// Depth and stencil are resolved using the sample 0. It's always supported
+/// and averaging depth/stencil values does not make much sense anyway.
+pub unsafe fn create_render_pass_multisampled(
+  device: &ash::Device,
+  samples: vk::SampleCountFlags,
+  depth: AttachmentDefinition,
+  colors: &[AttachmentDefinition],
+) -> vk::RenderPass {
+  let ms_attachment_count = (colors.len() + 1) as u32;
+  let to_desc2 = |a: &vk::AttachmentDescription, samples: vk::SampleCountFlags| {
+    vk::AttachmentDescription2::builder()
+      .format(a.format)
+      .samples(samples)
+      .load_op(a.load_op)
+      .store_op(a.store_op)
+      .stencil_load_op(a.stencil_load_op)
+      .stencil_store_op(a.stencil_store_op)
+      .initial_layout(a.initial_layout)
+      .final_layout(a.final_layout)
+      .build()
+  };
+  let to_ref2 = |r: &vk::AttachmentReference, offset: u32, aspect: vk::ImageAspectFlags| {
+    vk::AttachmentReference2::builder()
+      .attachment(r.attachment + offset)
+      .layout(r.layout)
+      .aspect_mask(aspect)
+      .build()
+  };
+  let depth_aspect = vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL;
+
+  // multisampled attachments, followed by resolve attachments
+  let mut all_attachment_descs =
+    Vec::<vk::AttachmentDescription2>::with_capacity(2 * ms_attachment_count as usize);
+  let ms_attachments = std::iter::once(&depth).chain(colors.iter());
+  ms_attachments.clone().for_each(|a| {
+    let mut desc = to_desc2(&a.0, samples);
+    desc.store_op = vk::AttachmentStoreOp::DONT_CARE;
+    desc.stencil_store_op = vk::AttachmentStoreOp::DONT_CARE;
+    all_attachment_descs.push(desc);
+  });
+  ms_attachments.for_each(|a| {
+    let mut desc = to_desc2(&a.0, vk::SampleCountFlags::TYPE_1);
+    desc.load_op = vk::AttachmentLoadOp::DONT_CARE;
+    desc.stencil_load_op = vk::AttachmentLoadOp::DONT_CARE;
+    desc.store_op = vk::AttachmentStoreOp::STORE;
+    desc.stencil_store_op = vk::AttachmentStoreOp::STORE;
+    all_attachment_descs.push(desc);
+  });
+
+  let color_refs = colors
+    .iter()
+    .map(|a| to_ref2(&a.1, 0, vk::ImageAspectFlags::COLOR))
+    .collect::<Vec<_>>();
+  let color_resolve_refs = colors
+    .iter()
+    .map(|a| to_ref2(&a.1, ms_attachment_count, vk::ImageAspectFlags::COLOR))
+    .collect::<Vec<_>>();
+  let depth_ref = to_ref2(&depth.1, 0, depth_aspect);
+  let depth_resolve_ref = to_ref2(&depth.1, ms_attachment_count, depth_aspect);
+
+  let mut depth_resolve = vk::SubpassDescriptionDepthStencilResolve::builder()
+    .depth_resolve_mode(vk::ResolveModeFlags::SAMPLE_ZERO)
+    .stencil_resolve_mode(vk::ResolveModeFlags::SAMPLE_ZERO)
+    .depth_stencil_resolve_attachment(&depth_resolve_ref);
+  let subpass = vk::SubpassDescription2::builder()
+    .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+    .color_attachments(&color_refs)
+    .resolve_attachments(&color_resolve_refs)
+    .depth_stencil_attachment(&depth_ref)
+    .push_next(&mut depth_resolve)
+    .build();
+
+  let stages_depth =
+    vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS;
+  let stages_all = stages_depth | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT;
+  let dependency_in = vk::SubpassDependency2::builder()
+    .src_subpass(vk::SUBPASS_EXTERNAL)
+    .dst_subpass(0)
+    .src_stage_mask(stages_all)
+    .src_access_mask(vk::AccessFlags::empty())
+    .dst_stage_mask(stages_all)
+    .dst_access_mask(
+      vk::AccessFlags::COLOR_ATTACHMENT_WRITE | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+    )
+    .build();
+  // Resolve (also for depth/stencil) happens in `COLOR_ATTACHMENT_OUTPUT` stage.
+  // Barriers in `vk_texture_sync.rs` expect depth writes in fragment tests stages.
+  let dependency_out = vk::SubpassDependency2::builder()
+    .src_subpass(0)
+    .dst_subpass(vk::SUBPASS_EXTERNAL)
+    .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+    .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+    .dst_stage_mask(stages_all | vk::PipelineStageFlags::FRAGMENT_SHADER)
+    .dst_access_mask(
+      vk::AccessFlags::COLOR_ATTACHMENT_READ
+        | vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+        | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+        | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE
+        | vk::AccessFlags::SHADER_READ,
+    )
+    .build();
+
+  let create_info = vk::RenderPassCreateInfo2::builder()
+    .dependencies(&[dependency_in, dependency_out])
+    .attachments(&all_attachment_descs)
+    .subpasses(&[subpass])
+    .build();
+  device
+    .create_render_pass2(&create_info, None)
+    .expect("Failed creating multisampled render pass")
+}
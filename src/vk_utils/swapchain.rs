@@ -218,5 +218,5 @@ pub fn create_swapchain_image_view(
   image_format: vk::Format,
 ) -> vk::ImageView {
   let aspect_mask_flags = vk::ImageAspectFlags::COLOR;
-  create_image_view(device, swapchain_image, image_format, aspect_mask_flags)
+  create_image_view(device, swapchain_image, image_format, aspect_mask_flags, 1)
 }
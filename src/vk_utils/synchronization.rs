@@ -125,7 +125,7 @@ pub fn create_image_barrier(
     .subresource_range(vk::ImageSubresourceRange {
       aspect_mask,
       base_mip_level: 0,
-      level_count: 1, // vk::REMAINING_MIP_LEVELS
+      level_count: vk::REMAINING_MIP_LEVELS,
       base_array_layer: 0,
       layer_count: 1, // vk::REMAINING_ARRAY_LAYERS
     })
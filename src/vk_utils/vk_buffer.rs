@@ -121,6 +121,14 @@ impl VkBuffer {
     buffer
   }
 
+  /// Make GPU writes visible to the CPU. Call before `read_from_mapped()`,
+  /// as `CpuReadback` memory is not guaranteed to be `HOST_COHERENT`.
+  pub fn invalidate_mapped(&self, allocator: &vma::Allocator) {
+    allocator
+      .invalidate_allocation(&self.allocation, 0, vk::WHOLE_SIZE as _)
+      .expect("Failed to invalidate mapped buffer memory");
+  }
+
   pub unsafe fn delete(&mut self, allocator: &vma::Allocator) -> () {
     allocator.destroy_buffer(self.buffer, &mut self.allocation)
   }
@@ -14,6 +14,11 @@ pub enum VkMemoryPreference {
   ///
   /// Will be persistently mapped.
   ScratchTransfer,
+  /// Memory written by GPU (usually as a copy target) and then read on CPU.
+  /// Will be persistently mapped.
+  ///
+  /// Usage: reading simulation results back to CPU.
+  CpuReadback,
 }
 
 pub fn determine_gpu_allocation_info(
@@ -36,6 +41,11 @@ pub fn determine_gpu_allocation_info(
         | vma::AllocationCreateFlags::MAPPED,
       ..Default::default()
     },
+    VkMemoryPreference::CpuReadback => vma::AllocationCreateInfo {
+      usage: vma::MemoryUsage::Auto,
+      flags: vma::AllocationCreateFlags::HOST_ACCESS_RANDOM | vma::AllocationCreateFlags::MAPPED,
+      ..Default::default()
+    },
   }
 }
 
@@ -86,4 +96,17 @@ pub trait VkMemoryResource {
       panic!("Tried to write {} bytes to unmapped '{}'", size, name)
     }
   }
+
+  fn read_from_mapped(&self, bytes: &mut [u8]) {
+    let mapped_pointer = self.get_mapped_pointer();
+    let size = bytes.len();
+
+    if let Some(pointer) = mapped_pointer {
+      let slice = unsafe { std::slice::from_raw_parts(pointer.0 as *const u8, size) };
+      bytes.copy_from_slice(slice);
+    } else {
+      let name = self.get_long_name();
+      panic!("Tried to read {} bytes from unmapped '{}'", size, name)
+    }
+  }
 }
@@ -23,6 +23,8 @@ pub struct VkTexture {
   long_name: String,
   pub width: u32,
   pub height: u32,
+  /// Count of mip levels. Most textures have only 1
+  pub mip_levels: u32,
   /// Native Vulkan image
   pub image: vk::Image,
   image_view: vk::ImageView,
@@ -56,6 +58,65 @@ impl VkTexture {
     usage: vk::ImageUsageFlags,
     memory_pref: VkMemoryPreference,
     initial_layout: vk::ImageLayout,
+  ) -> Self {
+    Self::empty_with_mips(
+      device,
+      allocator,
+      with_setup_cb,
+      name,
+      size,
+      format,
+      tiling,
+      usage,
+      memory_pref,
+      initial_layout,
+      1,
+      vk::SampleCountFlags::TYPE_1,
+    )
+  }
+
+  /// Same as `VkTexture::empty()`, but with `samples` per pixel. Used as MSAA attachment.
+  pub fn empty_multisampled(
+    device: &ash::Device,
+    allocator: &vma::Allocator,
+    with_setup_cb: &impl WithSetupCmdBuffer,
+    name: String,
+    size: vk::Extent2D,
+    format: vk::Format,
+    usage: vk::ImageUsageFlags,
+    initial_layout: vk::ImageLayout,
+    samples: vk::SampleCountFlags,
+  ) -> Self {
+    Self::empty_with_mips(
+      device,
+      allocator,
+      with_setup_cb,
+      name,
+      size,
+      format,
+      vk::ImageTiling::OPTIMAL,
+      usage,
+      VkMemoryPreference::GpuOnly,
+      initial_layout,
+      1,
+      samples,
+    )
+  }
+
+  /// Same as `VkTexture::empty()`, but with `mip_levels` mips. Size of the mip `n` is `size >> n`.
+  fn empty_with_mips(
+    device: &ash::Device,
+    allocator: &vma::Allocator,
+    with_setup_cb: &impl WithSetupCmdBuffer,
+    name: String,
+    size: vk::Extent2D,
+    format: vk::Format,
+    tiling: vk::ImageTiling,
+    usage: vk::ImageUsageFlags,
+    memory_pref: VkMemoryPreference,
+    initial_layout: vk::ImageLayout,
+    mip_levels: u32,
+    samples: vk::SampleCountFlags,
   ) -> Self {
     let create_info = vk::ImageCreateInfo::builder()
       .image_type(vk::ImageType::TYPE_2D)
@@ -73,8 +134,8 @@ impl VkTexture {
       .initial_layout(vk::ImageLayout::PREINITIALIZED) // required by validation layers
       // verbose properties, but vulkan requires
       .sharing_mode(vk::SharingMode::EXCLUSIVE)
-      .samples(vk::SampleCountFlags::TYPE_1)
-      .mip_levels(1)
+      .samples(samples)
+      .mip_levels(mip_levels)
       .array_layers(1)
       .build();
 
@@ -87,12 +148,13 @@ impl VkTexture {
     let mapped_pointer = get_persistently_mapped_pointer(allocator, &allocation);
 
     let aspect = get_image_aspect_from_format(format);
-    let image_view = create_image_view(device, image, create_info.format, aspect);
+    let image_view = create_image_view(device, image, create_info.format, aspect, mip_levels);
     let mut texture = Self {
       name: name.clone(),
       long_name: get_texture_long_name(name, size.width, size.height),
       width: size.width,
       height: size.height,
+      mip_levels,
       image,
       allocation,
       mapped_pointer,
@@ -154,7 +216,7 @@ impl VkTexture {
       vk::ImageLayout::TRANSFER_DST_OPTIMAL,
     );
 
-    Self::write_initial_data(device, allocator, with_setup_cb, &pixel_bytes, &texture);
+    Self::write_initial_data(device, allocator, with_setup_cb, &pixel_bytes, &texture, 0);
     texture.set_initial_image_layout(with_setup_cb, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
     texture
   }
@@ -168,19 +230,44 @@ impl VkTexture {
     format: vk::Format,
     data_bytes: &Vec<u8>,
   ) -> Self {
-    let pixel_cnt = (size.width * size.height) as usize;
-    if data_bytes.len() % pixel_cnt != 0 {
-      panic!(
-        "Tried to create VkTexture::from_data with dimensions {}x{}px. Provided data ({} bytes) does not allign with the dimensions ({} bytes per pixel).",
-        size.width,
-        size.height,
-        data_bytes.len(),
-        (data_bytes.len() as f32) / (pixel_cnt as f32)
-      );
+    Self::from_mips_data(
+      device,
+      allocator,
+      with_setup_cb,
+      name,
+      size,
+      format,
+      std::slice::from_ref(data_bytes),
+    )
+  }
+
+  /// * `mips_data_bytes` - content of each mip level. Mip `n` has size `size >> n`.
+  pub fn from_mips_data(
+    device: &ash::Device,
+    allocator: &vma::Allocator,
+    with_setup_cb: &impl WithSetupCmdBuffer,
+    name: String,
+    size: vk::Extent2D,
+    format: vk::Format,
+    mips_data_bytes: &[Vec<u8>],
+  ) -> Self {
+    for (mip_level, data_bytes) in mips_data_bytes.iter().enumerate() {
+      let mip_size = get_mip_size(size, mip_level as _);
+      let pixel_cnt = (mip_size.width * mip_size.height) as usize;
+      if data_bytes.len() % pixel_cnt != 0 {
+        panic!(
+          "Tried to create VkTexture::from_data with dimensions {}x{}px (mip {}). Provided data ({} bytes) does not allign with the dimensions ({} bytes per pixel).",
+          mip_size.width,
+          mip_size.height,
+          mip_level,
+          data_bytes.len(),
+          (data_bytes.len() as f32) / (pixel_cnt as f32)
+        );
+      }
     }
 
     // create texture
-    let mut texture = Self::empty(
+    let mut texture = Self::empty_with_mips(
       device,
       allocator,
       with_setup_cb,
@@ -191,9 +278,20 @@ impl VkTexture {
       vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
       VkMemoryPreference::GpuOnly,
       vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+      mips_data_bytes.len() as _,
+      vk::SampleCountFlags::TYPE_1,
     );
 
-    Self::write_initial_data(device, allocator, with_setup_cb, data_bytes, &texture);
+    for (mip_level, data_bytes) in mips_data_bytes.iter().enumerate() {
+      Self::write_initial_data(
+        device,
+        allocator,
+        with_setup_cb,
+        data_bytes,
+        &texture,
+        mip_level as _,
+      );
+    }
     texture.set_initial_image_layout(with_setup_cb, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
     texture
   }
@@ -204,13 +302,15 @@ impl VkTexture {
     with_setup_cb: &impl WithSetupCmdBuffer,
     pixel_bytes: &Vec<u8>,
     dst_texture: &VkTexture,
+    mip_level: u32,
   ) {
+    let mip_size = get_mip_size(dst_texture.size(), mip_level);
     let mut scratch_texture = Self::empty(
       device,
       allocator,
       with_setup_cb,
       format!("{}-scratch-texture", dst_texture.name),
-      dst_texture.size(),
+      mip_size,
       dst_texture.format,
       vk::ImageTiling::LINEAR,
       vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_SRC,
@@ -222,20 +322,24 @@ impl VkTexture {
     // copy content
     with_setup_cb.with_setup_cb(|device, cb| unsafe {
       let offset_zero = vk::Offset3D { x: 0, y: 0, z: 0 };
-      let subresources = vk::ImageSubresourceLayers {
+      let src_subresources = vk::ImageSubresourceLayers {
         aspect_mask: dst_texture.aspect_flags, // same for both
         mip_level: 0,
         base_array_layer: 0,
         layer_count: 1,
       };
+      let dst_subresources = vk::ImageSubresourceLayers {
+        mip_level,
+        ..src_subresources
+      };
       let img_copy = ash::vk::ImageCopy::builder()
         .src_offset(offset_zero)
-        .src_subresource(subresources)
+        .src_subresource(src_subresources)
         .dst_offset(offset_zero)
-        .dst_subresource(subresources)
+        .dst_subresource(dst_subresources)
         .extent(vk::Extent3D {
-          width: dst_texture.width,
-          height: dst_texture.height,
+          width: mip_size.width,
+          height: mip_size.height,
           depth: 1,
         })
         .build();
@@ -263,7 +367,13 @@ impl VkTexture {
     device: &ash::Device,
     aspect_mask_flags: vk::ImageAspectFlags,
   ) -> vk::ImageView {
-    create_image_view(device, self.image, self.format, aspect_mask_flags)
+    create_image_view(
+      device,
+      self.image,
+      self.format,
+      aspect_mask_flags,
+      self.mip_levels,
+    )
   }
 
   pub fn is_color(&self) -> bool {
@@ -322,6 +432,14 @@ pub fn get_image_aspect_from_format(format: vk::Format) -> vk::ImageAspectFlags
   }
 }
 
+/// Size of the mip level, never less than 1px
+pub fn get_mip_size(size: vk::Extent2D, mip_level: u32) -> vk::Extent2D {
+  vk::Extent2D {
+    width: (size.width >> mip_level).max(1),
+    height: (size.height >> mip_level).max(1),
+  }
+}
+
 // Used cause vk::Format::R8G8B8_SRGB are not supported on my GPU
 fn covert_rgb_to_rgba(data_rgb: &Vec<u8>) -> Vec<u8> {
   let pixel_cnt = data_rgb.len() / 3;